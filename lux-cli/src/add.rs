@@ -43,6 +43,11 @@ pub struct Add {
     /// Project to modify.
     #[arg(short, long, visible_short_alias = 'p')]
     pub(crate) package: Option<PackageName>,
+
+    /// Optional features of the added packages to enable. {n}
+    /// Example: `lx add foo --features tls,http2`
+    #[arg(long, value_delimiter = ',')]
+    features: Vec<String>,
 }
 
 pub async fn add(data: Add, config: Config) -> Result<()> {
@@ -69,6 +74,13 @@ pub async fn add(data: Add, config: Config) -> Result<()> {
                 &config,
             )
             .await?;
+        let names = package_names(&data.package_req);
+        project
+            .add_dependency_features(
+                lua_dependency::LuaDependencyType::Regular(names.iter().collect()),
+                &data.features,
+            )
+            .await?;
     }
 
     let build_packages = data.build.unwrap_or_default();
@@ -90,6 +102,13 @@ pub async fn add(data: Add, config: Config) -> Result<()> {
                 &config,
             )
             .await?;
+        let names = package_names(&build_packages);
+        project
+            .add_dependency_features(
+                lua_dependency::LuaDependencyType::Build(names.iter().collect()),
+                &data.features,
+            )
+            .await?;
     }
 
     let test_packages = data.test.unwrap_or_default();
@@ -111,6 +130,13 @@ pub async fn add(data: Add, config: Config) -> Result<()> {
                 &config,
             )
             .await?;
+        let names = package_names(&test_packages);
+        project
+            .add_dependency_features(
+                lua_dependency::LuaDependencyType::Test(names.iter().collect()),
+                &data.features,
+            )
+            .await?;
     }
 
     if !data.package_req.is_empty() {
@@ -126,6 +152,13 @@ pub async fn add(data: Add, config: Config) -> Result<()> {
     Ok(())
 }
 
+fn package_names(packages: &[PackageReqOrGitShorthand]) -> Vec<PackageName> {
+    packages
+        .iter()
+        .map(PackageReqOrGitShorthand::package_name)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use assert_fs::{prelude::PathCopy, TempDir};
@@ -155,6 +188,7 @@ mod tests {
             force: false,
             build: Option::None,
             test: Option::None,
+            features: Vec::new(),
         };
         add(args, config.clone()).await.unwrap();
         let lockfile_path = project_root.join("lux.lock");
@@ -169,6 +203,7 @@ mod tests {
             force: false,
             build: Option::None,
             test: Option::None,
+            features: Vec::new(),
         };
         add(args, config.clone()).await.unwrap();
         let lockfile_path = project_root.join("lux.lock");
@@ -201,6 +236,7 @@ mod tests {
             force: false,
             build: Option::Some(vec!["penlight@1.5".parse().unwrap()]),
             test: Option::None,
+            features: Vec::new(),
         };
         add(args, config.clone()).await.unwrap();
         let lockfile_path = project_root.join("lux.lock");
@@ -215,6 +251,7 @@ mod tests {
             force: false,
             build: Option::Some(vec!["md5".parse().unwrap()]),
             test: Option::None,
+            features: Vec::new(),
         };
         add(args, config.clone()).await.unwrap();
         let lockfile_path = project_root.join("lux.lock");
@@ -247,6 +284,7 @@ mod tests {
             force: false,
             build: Option::None,
             test: Option::Some(vec!["penlight@1.5".parse().unwrap()]),
+            features: Vec::new(),
         };
         add(args, config.clone()).await.unwrap();
         let lockfile_path = project_root.join("lux.lock");
@@ -261,6 +299,7 @@ mod tests {
             force: false,
             build: Option::None,
             test: Option::Some(vec!["md5".parse().unwrap()]),
+            features: Vec::new(),
         };
         add(args, config.clone()).await.unwrap();
         let lockfile_path = project_root.join("lux.lock");
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use lux_lib::{operations, package::PackageSpec};
+use miette::{IntoDiagnostic, Result};
+
+#[derive(Subcommand)]
+pub enum Admin {
+    /// Generate the manifests for a directory of rockspecs and packed rocks,{n}
+    /// so that it can be served as a rocks server from any static file host.{n}
+    /// Writes `manifest`, `manifest-<lua-version>`, their `.zip` variants and an `index.html`.
+    MakeManifest(MakeManifest),
+    /// Add rockspecs or packed rocks to a rocks directory and update its manifests.
+    #[command(arg_required_else_help = true)]
+    Add(AdminAdd),
+    /// Remove rockspecs or packed rocks from a rocks directory and update its manifests.
+    #[command(arg_required_else_help = true)]
+    Remove(AdminRemove),
+}
+
+#[derive(Args)]
+pub struct MakeManifest {
+    /// The rocks directory.{n}
+    /// Defaults to the current directory.
+    dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct AdminAdd {
+    /// Rockspecs or packed rocks to add.
+    #[arg(required = true)]
+    rocks: Vec<PathBuf>,
+
+    /// The rocks directory.{n}
+    /// Defaults to the current directory.
+    #[arg(long)]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct AdminRemove {
+    /// File names of rockspecs or packed rocks to remove,{n}
+    /// or `<package>@<version>` to remove all of a package version's files.{n}
+    /// Examples:{n}
+    ///     - "foo-1.0.0-1.src.rock"{n}
+    ///     - "foo@1.0.0-1"{n}
+    #[arg(required = true)]
+    rocks: Vec<String>,
+
+    /// The rocks directory.{n}
+    /// Defaults to the current directory.
+    #[arg(long)]
+    dir: Option<PathBuf>,
+}
+
+pub fn admin(admin: Admin) -> Result<()> {
+    match admin {
+        Admin::MakeManifest(args) => {
+            let dir = rocks_dir(args.dir)?;
+            operations::MakeManifest::new(dir.clone()).make()?;
+            println!("Wrote manifests for {}", dir.display());
+        }
+        Admin::Add(args) => {
+            let dir = rocks_dir(args.dir)?;
+            for file_name in operations::AdminAdd::new(dir).rocks(args.rocks).add()? {
+                println!("Added {file_name}");
+            }
+        }
+        Admin::Remove(args) => {
+            let dir = rocks_dir(args.dir)?;
+            let (packages, files): (Vec<_>, Vec<_>) =
+                args.rocks.into_iter().partition(|rock| rock.contains('@'));
+            let packages: Vec<PackageSpec> = packages
+                .iter()
+                .map(|package| package.parse())
+                .collect::<Result<_, _>>()?;
+            for file_name in operations::AdminRemove::new(dir)
+                .files(files)
+                .packages(packages)
+                .remove()?
+            {
+                println!("Removed {file_name}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn rocks_dir(dir: Option<PathBuf>) -> Result<PathBuf> {
+    match dir {
+        Some(dir) => Ok(dir),
+        None => std::env::current_dir().into_diagnostic(),
+    }
+}
@@ -4,14 +4,14 @@ use std::time::Duration;
 
 use clap::Parser;
 use lux_cli::{
-    add, build, check, config,
+    add, admin, build, build_log, check, config,
     debug::{self, Debug},
     dist::{self, Dist},
     doc, download, exec, fetch, format, generate_rockspec, info, install, install_lua,
     install_rockspec, lint, list, outdated, pack, path, pin, progress, project, purge, remove, run,
-    run_lua, search, shell, sync, test, uninstall, unpack, update,
+    run_lua, search, shell, sync, test, uninstall, unpack, update, update_index,
     upload::{self},
-    util, vendor, which, Cli, Commands,
+    util, vendor, which, ws, Cli, Commands,
 };
 use lux_lib::{
     config::tree::RockLayoutConfig,
@@ -29,6 +29,8 @@ use tracing_subscriber::{
     Layer,
 };
 
+#[cfg(feature = "serve")]
+use lux_cli::serve;
 use lux_cli::utils::error::clap_to_miette;
 
 const DEFAULT_USER_AGENT: &str = concat!("lux/", env!("CARGO_PKG_VERSION"));
@@ -81,6 +83,7 @@ async fn main() -> Result<()> {
         .dev(Some(cli.dev))
         .extra_servers(cli.extra_servers)
         .generate_luarc(Some(!cli.no_luarc))
+        .generate_compile_commands(Some(!cli.no_compile_commands))
         .wrap_bin_scripts(Some(!cli.no_wrap_bin))
         .lua_dir(cli.lua_dir)
         .lua_version(lua_version)
@@ -93,6 +96,7 @@ async fn main() -> Result<()> {
             cli.timeout
                 .map(|duration| Duration::from_secs(duration as u64)),
         )
+        .refresh_manifests(Some(cli.refresh))
         .max_jobs(cli.max_jobs)
         .user_tree(cli.tree.clone())
         .workspace_tree(cli.tree)
@@ -100,6 +104,8 @@ async fn main() -> Result<()> {
             cli.variables
                 .map(|variables| variables.into_iter().collect()),
         )
+        .build_target(cli.target)
+        .build_sandbox(cli.sandbox.then_some(true))
         .verbose(Some(cli.verbose))
         .no_progress(Some(cli.no_progress))
         .no_prompt(Some(
@@ -165,6 +171,8 @@ async fn main() -> Result<()> {
     match cli.command {
         Commands::Check(check_args) => check::check(check_args, config).await?,
         Commands::Search(search_data) => search::search(search_data, config).await?,
+        #[cfg(feature = "serve")]
+        Commands::Serve(serve_data) => serve::serve(serve_data).await?,
         Commands::Download(download_data) => download::download(download_data, config).await?,
         Commands::Debug(debug) => match debug {
             Debug::FetchRemote(unpack_data) => fetch::fetch_remote(unpack_data, config).await?,
@@ -183,6 +191,7 @@ async fn main() -> Result<()> {
         Commands::Build(build_data) => {
             build::build(build_data, config).await?;
         }
+        Commands::BuildLog(build_log_args) => build_log::build_log(build_log_args, config).await?,
         Commands::List(list_data) => list::list_installed(list_data, config)?,
         Commands::Lua(run_lua) => run_lua::run_lua(run_lua, config).await?,
         Commands::Install(install_data) => install::install(install_data, config).await?,
@@ -197,6 +206,7 @@ async fn main() -> Result<()> {
         Commands::Exec(run_args) => exec::exec(run_args, config).await?,
         Commands::Test(test) => test::test(test, config).await?,
         Commands::Update(update_args) => update::update(update_args, config).await?,
+        Commands::UpdateIndex => update_index::update_index(config).await?,
         Commands::Info(info_data) => info::info(info_data, config).await?,
         Commands::Lint(lint_args) => lint::lint(lint_args, config).await?,
         Commands::Path(path_data) => path::path(path_data, config).await?,
@@ -204,6 +214,7 @@ async fn main() -> Result<()> {
         Commands::Unpin(pin_data) => pin::set_pinned_state(pin_data, config, Unpinned).await?,
         Commands::Upload(upload_data) => upload::upload(upload_data, config).await?,
         Commands::Add(add_data) => add::add(add_data, config).await?,
+        Commands::Admin(admin_cmd) => admin::admin(admin_cmd)?,
         Commands::Config(config_cmd) => config::config(config_cmd, config)?,
         Commands::Doc(doc_args) => doc::doc(doc_args, config).await?,
         Commands::Pack(pack_args) => pack::pack(pack_args, config).await?,
@@ -215,6 +226,7 @@ async fn main() -> Result<()> {
         Commands::GenerateRockspec(data) => generate_rockspec::generate_rockspec(data).await?,
         Commands::Shell(data) => shell::shell(data, config).await?,
         Commands::Sync(sync_args) => sync::sync(sync_args, config).await?,
+        Commands::Ws(ws_args) => ws::ws(ws_args, config).await?,
     }
     Ok(())
 }
@@ -6,7 +6,7 @@ use lux_lib::{
     package::PackageName,
     workspace::Workspace,
 };
-use miette::Result;
+use miette::{miette, Result};
 
 #[derive(Args, Default)]
 pub struct Build {
@@ -18,23 +18,84 @@ pub struct Build {
     #[arg(long)]
     only_deps: bool,
 
+    /// Rebuild the package(s), even if they are up to date.
+    #[arg(long, visible_alias = "no-incremental")]
+    force: bool,
+
     /// Package to build.
     #[arg(short, long, visible_short_alias = 'p')]
     pub(crate) package: Option<PackageName>,
+
+    /// Optional features to enable, as declared in the `[features]` table. {n}
+    /// In a workspace, features apply to the members that declare them. {n}
+    /// Example: `lx build --features tls,http2`
+    #[arg(long, value_delimiter = ',')]
+    pub(crate) features: Vec<String>,
+
+    /// Also build the package(s) twice in separate temporary trees {n}
+    /// and check that the installed files are bit-for-bit identical.
+    #[arg(long, conflicts_with = "only_deps")]
+    check_reproducible: bool,
 }
 
 /// Returns `Some` if the `only_deps` arg is set to `false`.
 pub async fn build(data: Build, config: Config) -> Result<Vec<LocalPackage>> {
     let workspace = Workspace::current_or_err()?;
     let result = operations::BuildWorkspace::new(&workspace, &config)
-        .maybe_package(data.package)
+        .maybe_package(data.package.clone())
         .no_lock(data.no_lock)
         .only_deps(data.only_deps)
+        .force(data.force)
+        .features(data.features.clone())
         .build()
         .await?;
+    if data.check_reproducible {
+        check_reproducible(&workspace, data, &config).await?;
+    }
     Ok(result)
 }
 
+async fn check_reproducible(workspace: &Workspace, data: Build, config: &Config) -> Result<()> {
+    let reports = operations::CheckReproducible::new(workspace, config)
+        .maybe_package(data.package)
+        .features(data.features)
+        .check()
+        .await?;
+    let mut unreproducible = Vec::new();
+    for report in &reports {
+        let package = report.package();
+        if report.is_reproducible() {
+            println!(
+                "{}@{} is reproducible ({})",
+                package.name(),
+                package.version(),
+                report.hashes().0
+            );
+        } else {
+            let (first, second) = report.hashes();
+            println!(
+                "{}@{} is not reproducible:\n  first build:  {first}\n  second build: {second}",
+                package.name(),
+                package.version(),
+            );
+            for difference in report.differences() {
+                println!("  - {difference}");
+            }
+            unreproducible.push(package.name().to_string());
+        }
+    }
+    if unreproducible.is_empty() {
+        Ok(())
+    } else {
+        Err(miette!(
+            help = "common causes are embedded timestamps or build paths. \
+                    Lux sets `-ffile-prefix-map` for C sources and honours `SOURCE_DATE_EPOCH`.",
+            "builds of {} are not reproducible",
+            unreproducible.join(", ")
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -0,0 +1,20 @@
+use clap::Args;
+use lux_lib::{build::build_log, config::Config, package::PackageReq};
+use miette::{miette, IntoDiagnostic, Result};
+
+#[derive(Args)]
+pub struct BuildLog {
+    /// The package to show the last build log of.{n}
+    /// If several versions match, the most recent build is shown.
+    package: PackageReq,
+}
+
+pub async fn build_log(args: BuildLog, config: Config) -> Result<()> {
+    let path = build_log::latest_build_log(&args.package, &config)
+        .into_diagnostic()?
+        .ok_or_else(|| miette!("no build log found for {}", args.package))?;
+    let content = tokio::fs::read_to_string(&path).await.into_diagnostic()?;
+    println!("# {}\n", path.display());
+    print!("{content}");
+    Ok(())
+}
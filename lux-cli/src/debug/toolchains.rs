@@ -34,16 +34,18 @@ fn print_human_readable(report: &ToolchainReport) {
     print_toolchains(report.c_compiler());
     print_toolchains(report.make());
     print_toolchains(report.cmake());
+    print_toolchains(report.meson());
     print_toolchains(report.cargo());
     print_toolchains(report.pkg_config());
 
     println!("\nSummary:");
-    let total = 5;
+    let total = 6;
 
     let found = [
         report.c_compiler(),
         report.make(),
         report.cmake(),
+        report.meson(),
         report.cargo(),
         report.pkg_config(),
     ]
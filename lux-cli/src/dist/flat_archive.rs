@@ -5,7 +5,7 @@ use std::{
 
 use clap::Args;
 use lux_lib::{
-    build::{Build, BuildBehaviour},
+    build::{reproducible, Build, BuildBehaviour},
     config::{Config, ConfigBuilder},
     lockfile::LocalPackage,
     lua_installation::LuaInstallation,
@@ -22,7 +22,7 @@ use path_slash::PathExt;
 use tempfile::{tempdir, TempDir};
 use tokio::fs::{self, File};
 use walkdir::WalkDir;
-use zip::{write::SimpleFileOptions, ZipWriter};
+use zip::ZipWriter;
 
 use crate::{
     args::{OutputFormat, PackageOrRockspec},
@@ -218,7 +218,8 @@ async fn zip_dir(src_dir: &Path, dest_file: &Path, method: &CompressionMethod) -
         .into_diagnostic()?
         .into_std()
         .await;
-    let walkdir = WalkDir::new(src_dir);
+    // Sorted, so that archives of the same tree are bit-for-bit identical.
+    let walkdir = WalkDir::new(src_dir).sort_by_file_name();
     let mut zip = ZipWriter::new(archive);
 
     let compression_method = match method {
@@ -230,14 +231,6 @@ async fn zip_dir(src_dir: &Path, dest_file: &Path, method: &CompressionMethod) -
         CompressionMethod::Lzma => zip::CompressionMethod::Lzma,
     };
 
-    #[cfg(target_family = "unix")]
-    let options = SimpleFileOptions::default()
-        .compression_method(compression_method)
-        .unix_permissions(0o755);
-
-    #[cfg(target_family = "windows")]
-    let options = SimpleFileOptions::default().compression_method(compression_method);
-
     for entry_result in walkdir.into_iter() {
         let entry = entry_result.map_err(|err| {
             miette!(
@@ -251,11 +244,16 @@ async fn zip_dir(src_dir: &Path, dest_file: &Path, method: &CompressionMethod) -
         let relative_path = path.strip_prefix(src_dir).into_diagnostic()?;
         let relative_path_str = relative_path.to_slash_lossy().to_string();
         if path.is_file() {
+            let metadata = entry.metadata().into_diagnostic()?;
+            let options = reproducible::zip_file_options(reproducible::is_executable(&metadata))
+                .compression_method(compression_method);
             zip.start_file(relative_path_str, options)
                 .into_diagnostic()?;
             let mut f = File::open(path).await.into_diagnostic()?.into_std().await;
             io::copy(&mut f, &mut zip).into_diagnostic()?;
         } else if !relative_path.as_os_str().is_empty() {
+            let options =
+                reproducible::zip_file_options(true).compression_method(compression_method);
             zip.add_directory(relative_path_str, options)
                 .into_diagnostic()?;
         }
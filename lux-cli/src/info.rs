@@ -1,67 +1,428 @@
+use std::collections::BTreeMap;
+
 use clap::Args;
+use itertools::Itertools;
 use lux_lib::{
-    config::Config, operations::Download, package::PackageReq, rockspec::Rockspec,
-    tree::InstallTree,
+    config::Config,
+    lockfile::PinnedState,
+    lua_rockspec::{ExternalDependencySpec, PerPlatform, RemoteLuaRockspec},
+    lua_version::LuaVersion,
+    operations::Download,
+    package::PackageReq,
+    remote_package_db::RemotePackageDB,
+    rockspec::{lua_dependency::LuaDependencySpec, Rockspec},
+    tree::{InstallTree, RockMatches, Tree},
+    workspace::Workspace,
 };
+use miette::{IntoDiagnostic, Result};
+use serde::Serialize;
 
-use miette::Result;
-
-use crate::workspace::current_workspace_or_user_tree;
+use crate::args::OutputFormat;
 
 #[derive(Args)]
 pub struct Info {
     package: PackageReq,
+
+    #[arg(long, default_value = "text", value_enum, ignore_case = true)]
+    output_format: OutputFormat,
+}
+
+/// The platform key for values that apply to all platforms without an override.
+const DEFAULT_PLATFORM: &str = "default";
+
+#[derive(Serialize)]
+struct PackageInfo {
+    name: String,
+    version: String,
+    summary: Option<String>,
+    description: Option<String>,
+    license: Option<String>,
+    homepage: Option<String>,
+    maintainer: Option<String>,
+    labels: Vec<String>,
+    /// The supported Lua versions
+    lua: String,
+    build_backend: Option<String>,
+    /// Dependencies by platform
+    dependencies: BTreeMap<String, Vec<String>>,
+    build_dependencies: BTreeMap<String, Vec<String>>,
+    test_dependencies: BTreeMap<String, Vec<String>>,
+    external_dependencies: BTreeMap<String, BTreeMap<String, ExternalDependency>>,
+    /// `None` if the package database could not be loaded
+    available: Option<Vec<ServerVersions>>,
+    installed: Vec<Installation>,
+    reverse_dependencies: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ExternalDependency {
+    header: Option<String>,
+    library: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ServerVersions {
+    server: String,
+    versions: Vec<AvailableVersion>,
+}
+
+#[derive(Serialize)]
+struct AvailableVersion {
+    version: String,
+    package_types: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Installation {
+    /// `user` or `project`
+    tree: String,
+    root: String,
+    version: String,
+    pinned: bool,
+    entrypoint: bool,
+    #[serde(skip)]
+    rockspec: Option<RemoteLuaRockspec>,
 }
 
 pub async fn info(data: Info, config: Config) -> Result<()> {
-    let tree = current_workspace_or_user_tree(&config)?;
+    let workspace = Workspace::current()?;
+    let workspace_tree = workspace
+        .as_ref()
+        .map(|workspace| workspace.tree(&config))
+        .transpose()?;
 
-    let rockspec = Download::new(&data.package, &config)
-        .download_rockspec()
-        .await?
-        .rockspec;
+    let installed = match &workspace_tree {
+        Some(workspace_tree) => installations("project", workspace_tree, &data.package)?,
+        None => {
+            let user_tree = config.user_tree(LuaVersion::from(&config)?.clone())?;
+            installations("user", &user_tree, &data.package)?
+        }
+    };
 
-    if tree.match_rocks(&data.package)?.is_found() {
-        println!("Currently installed in {}", tree.root().display());
-    }
+    let installed_rockspec = installed
+        .iter()
+        .filter_map(|installation| installation.rockspec.as_ref())
+        .max_by(|a, b| a.version().cmp(b.version()))
+        .cloned();
+    let (rockspec, package_db) = match installed_rockspec {
+        // Installed packages can be inspected offline,
+        // so we only list the available versions if the package database can be loaded.
+        Some(rockspec) => {
+            let package_db = RemotePackageDB::from_config(&config)
+                .await
+                .inspect_err(|err| tracing::debug!("failed to load the package database: {err}"))
+                .ok();
+            (rockspec, package_db)
+        }
+        None => {
+            let package_db = RemotePackageDB::from_config(&config).await?;
+            let rockspec = Download::new(&data.package, &config)
+                .package_db(&package_db)
+                .download_rockspec()
+                .await?
+                .rockspec;
+            (rockspec, Some(package_db))
+        }
+    };
 
-    println!("Package name: {}", rockspec.package());
-    println!("Package version: {}", rockspec.version());
-    println!();
+    let available = package_db.map(|package_db| {
+        package_db
+            .versions_by_server(rockspec.package())
+            .into_iter()
+            .map(|server| ServerVersions {
+                server: server.server_url.to_string(),
+                versions: server
+                    .versions
+                    .into_iter()
+                    .map(|(version, package_types)| AvailableVersion {
+                        version: version.to_string(),
+                        package_types: package_types.iter().map(ToString::to_string).collect(),
+                    })
+                    .collect(),
+            })
+            .collect_vec()
+    });
 
-    println!(
-        "Summary: {}",
-        rockspec
-            .description()
-            .summary
-            .as_ref()
-            .unwrap_or(&"None".to_string())
-    );
-    println!(
-        "Description: {}",
-        rockspec
-            .description()
+    let reverse_dependencies = match (&workspace, &workspace_tree) {
+        (Some(workspace), Some(workspace_tree)) => {
+            reverse_dependencies(&rockspec, workspace, workspace_tree)?
+        }
+        _ => Vec::new(),
+    };
+
+    let description = rockspec.description();
+    let info = PackageInfo {
+        name: rockspec.package().to_string(),
+        version: rockspec.version().to_string(),
+        summary: description.summary.clone(),
+        description: description
             .detailed
             .as_ref()
-            .unwrap_or(&"None".to_string())
-            .trim()
-    );
-    println!(
-        "License: {}",
-        rockspec
-            .description()
-            .license
+            .map(|detailed| detailed.trim().to_string()),
+        license: description.license.clone(),
+        homepage: description.homepage.as_ref().map(ToString::to_string),
+        maintainer: description.maintainer.clone(),
+        labels: description.labels.clone(),
+        lua: rockspec.lua().to_string(),
+        build_backend: rockspec
+            .build()
+            .current_platform()
+            .build_backend
             .as_ref()
-            .unwrap_or(&"Unknown (all rights reserved by the author)".to_string())
-    );
+            .map(ToString::to_string),
+        dependencies: dependencies_by_platform(rockspec.dependencies()),
+        build_dependencies: dependencies_by_platform(rockspec.build_dependencies()),
+        test_dependencies: dependencies_by_platform(rockspec.test_dependencies()),
+        external_dependencies: by_platform(rockspec.external_dependencies(), |dependencies| {
+            dependencies
+                .iter()
+                .map(|(name, spec)| (name.clone(), ExternalDependency::from(spec)))
+                .collect()
+        }),
+        available,
+        installed,
+        reverse_dependencies,
+    };
+
+    match data.output_format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string(&info).into_diagnostic()?);
+        }
+        OutputFormat::Text => print_info(&info),
+    }
+
+    Ok(())
+}
+
+/// The installed versions of a package in a tree.
+fn installations(kind: &str, tree: &Tree, package_req: &PackageReq) -> Result<Vec<Installation>> {
+    let ids = match tree.match_rocks(package_req)? {
+        RockMatches::NotFound(_) => return Ok(Vec::new()),
+        RockMatches::Single(id) => vec![id],
+        RockMatches::Many(ids) => ids.into_iter().collect(),
+    };
+    let lockfile = tree.lockfile()?;
+    ids.iter()
+        .filter_map(|id| lockfile.get(id))
+        .map(|package| {
+            let layout = tree.installed_rock_layout(package)?;
+            let rockspec = std::fs::read_to_string(layout.rockspec_path())
+                .ok()
+                .and_then(|content| RemoteLuaRockspec::new(&content).ok());
+            Ok(Installation {
+                tree: kind.to_string(),
+                root: tree.root().display().to_string(),
+                version: package.version().to_string(),
+                pinned: package.pinned() == PinnedState::Pinned,
+                entrypoint: lockfile.is_entrypoint(&package.id()),
+                rockspec,
+            })
+        })
+        .collect()
+}
+
+/// The workspace members and installed packages in the workspace tree that depend on the package.
+fn reverse_dependencies(
+    rockspec: &RemoteLuaRockspec,
+    workspace: &Workspace,
+    workspace_tree: &Tree,
+) -> Result<Vec<String>> {
+    let name = rockspec.package();
+    let members = workspace.members().iter().filter_map(|project| {
+        let toml = project.toml().into_local().ok()?;
+        let depends_on = |dependencies: &PerPlatform<Vec<LuaDependencySpec>>| {
+            dependencies
+                .current_platform()
+                .iter()
+                .any(|dependency| dependency.name() == name)
+        };
+        (depends_on(toml.dependencies())
+            || depends_on(toml.build_dependencies())
+            || depends_on(toml.test_dependencies()))
+        .then(|| format!("{} (workspace member)", toml.package()))
+    });
+    let lockfile = workspace_tree.lockfile()?;
+    let packages = lockfile
+        .rocks()
+        .values()
+        .filter(|package| {
+            package.dependencies().iter().any(|id| {
+                lockfile
+                    .get(id)
+                    .is_some_and(|dependency| dependency.name() == name)
+            })
+        })
+        .map(|package| format!("{} {}", package.name(), package.version()));
+    Ok(members.chain(packages).sorted().dedup().collect())
+}
+
+fn by_platform<T, U>(values: &PerPlatform<T>, f: impl Fn(&T) -> U) -> BTreeMap<String, U> {
+    std::iter::once((DEFAULT_PLATFORM.to_string(), f(values.base())))
+        .chain(
+            values
+                .per_platform()
+                .iter()
+                .map(|(platform, value)| (platform.to_string(), f(value))),
+        )
+        .collect()
+}
+
+fn dependencies_by_platform(
+    dependencies: &PerPlatform<Vec<LuaDependencySpec>>,
+) -> BTreeMap<String, Vec<String>> {
+    by_platform(dependencies, |dependencies| {
+        dependencies.iter().map(ToString::to_string).collect()
+    })
+}
+
+impl From<&ExternalDependencySpec> for ExternalDependency {
+    fn from(spec: &ExternalDependencySpec) -> Self {
+        Self {
+            header: spec
+                .header
+                .as_ref()
+                .map(|header| header.display().to_string()),
+            library: spec
+                .library
+                .as_ref()
+                .map(|library| library.display().to_string()),
+        }
+    }
+}
+
+fn print_info(info: &PackageInfo) {
+    let or = |value: &Option<String>, default: &str| {
+        value.clone().unwrap_or_else(|| default.to_string())
+    };
+
+    println!("Package name: {}", info.name);
+    println!("Package version: {}", info.version);
+    println!();
+    println!("Summary: {}", or(&info.summary, "None"));
+    println!("Description: {}", or(&info.description, "None"));
     println!(
-        "Maintainer: {}",
-        rockspec
-            .description()
-            .maintainer
-            .as_ref()
-            .unwrap_or(&"Unspecified".to_string())
+        "License: {}",
+        or(&info.license, "Unknown (all rights reserved by the author)")
     );
+    println!("Maintainer: {}", or(&info.maintainer, "Unspecified"));
+    if let Some(homepage) = &info.homepage {
+        println!("Homepage: {homepage}");
+    }
+    if !info.labels.is_empty() {
+        println!("Labels: {}", info.labels.join(", "));
+    }
+    println!("Lua: {}", info.lua);
+    println!("Build backend: {}", or(&info.build_backend, "none"));
 
-    Ok(())
+    print_dependencies("Dependencies", &info.dependencies);
+    print_dependencies("Build dependencies", &info.build_dependencies);
+    print_dependencies("Test dependencies", &info.test_dependencies);
+    let external_dependencies = info
+        .external_dependencies
+        .iter()
+        .map(|(platform, dependencies)| {
+            let dependencies = dependencies
+                .iter()
+                .sorted_by_key(|(name, _)| *name)
+                .map(|(name, spec)| {
+                    let files = spec
+                        .header
+                        .iter()
+                        .map(|header| format!("header: {header}"))
+                        .chain(spec.library.iter().map(|lib| format!("library: {lib}")))
+                        .join(", ");
+                    if files.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{name} ({files})")
+                    }
+                })
+                .collect_vec();
+            (platform.clone(), dependencies)
+        })
+        .collect();
+    print_dependencies("External dependencies", &external_dependencies);
+
+    match &info.available {
+        Some(available) if !available.is_empty() => {
+            println!();
+            println!("Available versions:");
+            for server in available {
+                println!("  {}", server.server);
+                for version in &server.versions {
+                    println!(
+                        "    {} ({})",
+                        version.version,
+                        version.package_types.join(", ")
+                    );
+                }
+            }
+        }
+        Some(_) => {}
+        None => {
+            println!();
+            println!("Available versions: unknown (the package index could not be loaded)");
+        }
+    }
+
+    println!();
+    if info.installed.is_empty() {
+        println!("Not installed");
+    } else {
+        println!("Installed:");
+        for installation in &info.installed {
+            let mut flags = Vec::new();
+            if installation.pinned {
+                flags.push("pinned");
+            }
+            if !installation.entrypoint {
+                flags.push("dependency");
+            }
+            println!(
+                "  {} in the {} tree at {}{}",
+                installation.version,
+                installation.tree,
+                installation.root,
+                if flags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", flags.join(", "))
+                }
+            );
+        }
+    }
+
+    if !info.reverse_dependencies.is_empty() {
+        println!();
+        println!("Required by:");
+        for dependent in &info.reverse_dependencies {
+            println!("  {dependent}");
+        }
+    }
+}
+
+/// Print dependencies, with the platforms that override them.
+fn print_dependencies(title: &str, dependencies: &BTreeMap<String, Vec<String>>) {
+    let empty = Vec::new();
+    let default = dependencies.get(DEFAULT_PLATFORM).unwrap_or(&empty);
+    let overrides = dependencies
+        .iter()
+        .filter(|(platform, platform_dependencies)| {
+            platform.as_str() != DEFAULT_PLATFORM && *platform_dependencies != default
+        })
+        .collect_vec();
+    if default.is_empty() && overrides.is_empty() {
+        return;
+    }
+    println!();
+    println!("{title}:");
+    for dependency in default {
+        println!("  {dependency}");
+    }
+    for (platform, platform_dependencies) in overrides {
+        println!("  [{platform}]");
+        for dependency in platform_dependencies {
+            println!("    {dependency}");
+        }
+    }
 }
@@ -10,7 +10,9 @@ use std::error::Error;
 use std::path::PathBuf;
 
 use add::Add;
+use admin::Admin;
 use build::Build;
+use build_log::BuildLog;
 use check::Check;
 use clap::{Parser, Subcommand};
 use config::ConfigCmd;
@@ -36,6 +38,8 @@ use remove::Remove;
 use run::Run;
 use run_lua::RunLua;
 use search::Search;
+#[cfg(feature = "serve")]
+use serve::Serve;
 use shell::Shell;
 use strum::IntoEnumIterator;
 use sync::SyncProject;
@@ -46,10 +50,13 @@ use upload::Upload;
 use url::Url;
 use vendor::Vendor;
 use which::Which;
+use ws::Ws;
 
 pub mod add;
+pub mod admin;
 pub mod args;
 pub mod build;
+pub mod build_log;
 pub mod check;
 pub mod config;
 pub mod debug;
@@ -77,18 +84,22 @@ pub mod remove;
 pub mod run;
 pub mod run_lua;
 pub mod search;
+#[cfg(feature = "serve")]
+pub mod serve;
 pub mod shell;
 pub mod sync;
 pub mod test;
 pub mod uninstall;
 pub mod unpack;
 pub mod update;
+pub mod update_index;
 pub mod upload;
 pub mod util;
 pub mod utils;
 pub mod vendor;
 pub mod which;
 pub mod workspace;
+pub mod ws;
 
 /// A luxurious package manager for Lua.
 #[derive(Parser)]
@@ -98,13 +109,14 @@ pub struct Cli {
     #[arg(long)]
     pub dev: bool,
 
-    /// Fetch rocks/rockspecs from this server (takes priority over config file).
-    #[arg(long, value_name = "server")]
+    /// Fetch rocks/rockspecs from this server (takes priority over config file).{n}
+    /// Can also be a `file://` URL or a path to a local rocks directory.
+    #[arg(long, value_name = "server", value_parser = lux_lib::config::parse_server_url)]
     pub server: Option<Url>,
 
     /// Fetch rocks/rockspecs from these servers in addition to the main server{n}
     /// (overrides any entries in the config file).
-    #[arg(long, value_name = "extra-server")]
+    #[arg(long, value_name = "extra-server", value_parser = lux_lib::config::parse_server_url)]
     pub extra_servers: Option<Vec<Url>>,
 
     /// Specify the luarocks server namespace to use.
@@ -154,6 +166,22 @@ pub struct Cli {
     #[arg(long, value_enum, value_name = "profile")]
     pub profile: Option<lux_lib::config::build::Profile>,
 
+    /// The target triple to compile packages for,{n}
+    /// e.g. `aarch64-unknown-linux-gnu`.{n}
+    /// Lux uses a cross C compiler and builds Lua for the target.{n}
+    /// Consider combining this with `--tree`, to keep packages{n}
+    /// built for different targets apart.{n}
+    /// Default: The host triple.
+    #[arg(long, value_name = "triple")]
+    pub target: Option<String>,
+
+    /// Run build commands in a sandbox (Linux only, requires `bwrap`).{n}
+    /// Sandboxed builds can only write to the build directory{n}
+    /// and the package's install directories, and have no network access.{n}
+    /// Per-package exceptions can be configured in `[build.sandbox.packages]`.
+    #[arg(long)]
+    pub sandbox: bool,
+
     /// Display verbose output of commands executed, enabling DEBUG logs.{n}
     /// To enable TRACE logs, set RUST_LOG=trace.
     #[arg(long)]
@@ -182,6 +210,11 @@ pub struct Cli {
     #[arg(long, value_name = "seconds")]
     pub timeout: Option<usize>,
 
+    /// Re-download the manifests of the configured servers,{n}
+    /// ignoring the cached manifests and the `manifest_ttl`.
+    #[arg(long)]
+    pub refresh: bool,
+
     /// Maximum buffer size for parallel jobs, such as downloading rockspecs and installing rocks.
     /// 0 means no limit. Default is 0.
     #[arg(long, visible_short_alias = 'j')]
@@ -192,6 +225,11 @@ pub struct Cli {
     #[arg(long)]
     pub no_luarc: bool,
 
+    /// Do not generate or update a `compile_commands.json` file{n}
+    /// when building a project with C sources.
+    #[arg(long)]
+    pub no_compile_commands: bool,
+
     /// Do not wrap Lua `bin` scripts.
     #[arg(long)]
     pub no_wrap_bin: bool,
@@ -209,8 +247,15 @@ pub struct Cli {
 pub enum Commands {
     /// Add a dependency to the current project.
     Add(Add),
+    /// Maintain a directory of rocks that can be served as a rocks server{n}
+    /// from any static file host.
+    #[command(subcommand, arg_required_else_help = true)]
+    Admin(Admin),
     /// Build/compile a project.
     Build(Build),
+    /// Show the last build log of a package,{n}
+    /// including the commands that were run, their environment, output and timings.
+    BuildLog(BuildLog),
     /// [EXPERIMENTAL]{n}
     /// Type check the current project based on EmmyLua/LuaCATS annotations.{n}
     /// Respects `.emmyrc.json` and `.luarc.json` files in the project directory.
@@ -269,9 +314,16 @@ pub enum Commands {
     /// If the command is not found, a package named after the command
     /// will be installed.
     Exec(Exec),
-    /// Query the luarocks servers.
+    /// Search the luarocks servers for packages,{n}
+    /// matching package names fuzzily and searching summaries, labels and licenses.
     #[command(arg_required_else_help = true)]
     Search(Search),
+    /// Serve a directory of rockspecs and packed rocks over HTTP,{n}
+    /// generating the manifests on the fly.{n}
+    /// The server supports range and conditional requests,{n}
+    /// and can optionally accept uploads via the luarocks API.
+    #[cfg(feature = "serve")]
+    Serve(Serve),
     /// Run the test suite in the current project directory.{n}
     /// Lux supports the following test backends, specified by the `[test]` table in the lux.toml:{n}
     /// {n}
@@ -339,6 +391,9 @@ pub enum Commands {
     Unpin(ChangePin),
     /// Updates all rocks in a project.
     Update(Update),
+    /// Re-download the manifests of the configured servers,{n}
+    /// ignoring the cached manifests and the `manifest_ttl` config option.
+    UpdateIndex,
     /// Generate a Lua rockspec for a Lux project and upload it to the public luarocks repository.{n}
     /// You can specify a source template for release and dev packages in the lux.toml.{n}
     /// {n}
@@ -375,6 +430,11 @@ pub enum Commands {
     /// Synchronize the project tree with the current lux.toml,{n}
     /// ensuring all packages are installed correctly.
     Sync(SyncProject),
+    /// Run commands across the members of a workspace.{n}
+    /// Members can be selected by package name globs or by the files{n}
+    /// that changed since a git ref.
+    #[command(subcommand, arg_required_else_help = true)]
+    Ws(Ws),
 }
 
 impl Commands {
@@ -432,11 +492,13 @@ impl Commands {
             | Self::Path(_)
             | Self::Shell(_)
             | Self::Sync(_)
-            | Self::Vendor(_) => {
+            | Self::Vendor(_)
+            | Self::Ws(_) => {
                 project_lua_version(&None)
             },
             | Self::New(_)
             // non-project commands
+            | Self::Admin(_)
             | Self::Config(_)
             | Self::Util(_)
             | Self::Debug(Debug::Unpack(_))
@@ -452,7 +514,11 @@ impl Commands {
             | Self::Purge
             | Self::Search(_)
             | Self::Uninstall(_)
+            | Self::UpdateIndex
+            | Self::BuildLog(_)
             | Self::Which(_) => None,
+            #[cfg(feature = "serve")]
+            Self::Serve(_) => None,
         }
     }
 
@@ -502,6 +568,7 @@ impl Commands {
             | Self::Vendor(_)
             | Self::New(_)
             | Self::Run(_)
+            | Self::Ws(_)
             | Self::Dist(Dist::Bin(_))
             | Self::Dist(Dist::FlatArchive(FlatArchive {
                 package_or_rockspec: Some(PackageOrRockspec::Package(_)),
@@ -531,6 +598,7 @@ impl Commands {
                 package_or_rockspec: Some(PackageOrRockspec::RockSpec(_)),
             })
             | Self::Util(_)
+            | Self::Admin(_)
             | Self::Doc(_)
             | Self::Download(_)
             | Self::Install(_)
@@ -540,7 +608,11 @@ impl Commands {
             | Self::Purge
             | Self::Search(_)
             | Self::Uninstall(_)
+            | Self::UpdateIndex
+            | Self::BuildLog(_)
             | Self::Which(_) => Ok(None),
+            #[cfg(feature = "serve")]
+            Self::Serve(_) => Ok(None),
         }
     }
 }
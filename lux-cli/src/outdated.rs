@@ -1,13 +1,16 @@
-use std::collections::HashMap;
-
 use clap::Args;
 use itertools::Itertools;
 use lux_lib::{
-    config::Config, lockfile::LocalPackage, lua_version::LuaVersion, package::PackageVersion,
-    remote_package_db::RemotePackageDB, workspace::Workspace,
+    config::Config,
+    lockfile::LockConstraint,
+    lua_version::LuaVersion,
+    package::{PackageName, PackageVersion, UpdateKind},
+    remote_package_db::RemotePackageDB,
+    workspace::Workspace,
 };
 
 use miette::{IntoDiagnostic, Result};
+use serde::Serialize;
 use text_trees::{FormatCharacters, StringTreeNode, TreeFormatting};
 
 use crate::{args::OutputFormat, workspace::sync_dependencies_if_locked};
@@ -18,7 +21,8 @@ pub struct Outdated {
     output_format: OutputFormat,
 }
 
-/// List rocks that are outdated
+/// List rocks that are outdated, classifying each update as a patch, minor or major update,
+/// and showing whether the latest version satisfies the constraint the rock was installed with.
 /// If in a project, this lists rocks in the project tree
 pub async fn outdated(outdated_data: Outdated, config: Config) -> Result<()> {
     let workspace = Workspace::current()?;
@@ -36,54 +40,77 @@ pub async fn outdated(outdated_data: Outdated, config: Config) -> Result<()> {
 
     let package_db = RemotePackageDB::from_config(&config).await?;
 
-    // NOTE: This will display all installed versions and each possible upgrade.
-    // However, this should also take into account dependency constraints made by other rocks.
-    // This will naturally occur with lockfiles and should be accounted for directly in the
-    // `has_update` function.
+    // NOTE: This does not take into account the dependency constraints of other rocks.
     let rock_list = tree.as_rock_list()?;
-    let rock_list = rock_list
+    let outdated_packages = rock_list
         .iter()
-        .map(|rock| {
-            rock.to_package()
-                .has_update(&package_db)
-                .map(|mb_version| mb_version.map(|version| (rock, version)))
+        .filter_map(|rock| {
+            let package = rock.to_package();
+            let latest = package.latest_update(UpdateKind::Major, &package_db)?;
+            let kind = rock.version().update_kind(&latest)?;
+            let compatible = package
+                .latest_update(UpdateKind::Minor, &package_db)
+                .filter(|compatible| compatible != &latest);
+            let constraint = match rock.constraint() {
+                LockConstraint::Constrained(req) => Some(req),
+                LockConstraint::Unconstrained => None,
+            };
+            let within_constraint = constraint.as_ref().is_none_or(|req| req.matches(&latest));
+            Some(OutdatedPackage {
+                name: rock.name().clone(),
+                version: rock.version().clone(),
+                latest,
+                kind,
+                compatible,
+                constraint: constraint.as_ref().map(ToString::to_string),
+                within_constraint,
+            })
         })
-        .filter_map_ok(|mb_tuple| mb_tuple)
-        .try_collect::<_, Vec<(&LocalPackage, PackageVersion)>, _>()?;
-
-    let rock_list = rock_list
-        .iter()
-        .sorted_by_key(|(rock, _)| rock.name().to_owned())
-        .into_group_map_by(|(rock, _)| rock.name().to_owned());
+        .sorted_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)))
+        .collect_vec();
 
     match outdated_data.output_format {
         OutputFormat::Json => {
-            let jsonified_rock_list = rock_list
-                .iter()
-                .map(|(key, values)| {
-                    (
-                        key,
-                        values
-                            .iter()
-                            .map(|(k, v)| (k.version().to_string(), v.to_string()))
-                            .collect::<HashMap<_, _>>(),
-                    )
-                })
-                .collect::<HashMap<_, _>>();
-
             println!(
                 "{}",
-                serde_json::to_string(&jsonified_rock_list).into_diagnostic()?
+                serde_json::to_string(&outdated_packages).into_diagnostic()?
             );
         }
         OutputFormat::Text => {
             let formatting = TreeFormatting::dir_tree(FormatCharacters::box_chars());
 
-            for (rock_name, updates) in rock_list {
+            for (rock_name, updates) in &outdated_packages
+                .into_iter()
+                .chunk_by(|package| package.name.clone())
+            {
                 let mut tree = StringTreeNode::new(rock_name.to_string());
 
-                for (rock, latest_version) in updates {
-                    tree.push(format!("{} => {}", rock.version(), latest_version));
+                for package in updates {
+                    let mut node = StringTreeNode::new(format!(
+                        "{} => {} ({})",
+                        package.version, package.latest, package.kind
+                    ));
+                    if let Some(constraint) = &package.constraint {
+                        let status = if package.within_constraint {
+                            "satisfied"
+                        } else {
+                            "not satisfied"
+                        };
+                        node.push(format!(
+                            "constraint: {constraint} ({status} by {})",
+                            package.latest
+                        ));
+                    }
+                    if let Some(compatible) = &package.compatible {
+                        node.push(format!(
+                            "compatible: {compatible} ({})",
+                            package
+                                .version
+                                .update_kind(compatible)
+                                .unwrap_or(UpdateKind::Patch)
+                        ));
+                    }
+                    tree.push_node(node);
                 }
 
                 println!(
@@ -96,3 +123,23 @@ pub async fn outdated(outdated_data: Outdated, config: Config) -> Result<()> {
 
     Ok(())
 }
+
+/// An installed package for which a newer version is available.
+#[derive(Serialize)]
+struct OutdatedPackage {
+    name: PackageName,
+    /// The installed version.
+    version: PackageVersion,
+    /// The latest available version.
+    latest: PackageVersion,
+    /// The kind of the update to the latest version.
+    kind: UpdateKind,
+    /// The latest SemVer compatible version, if it differs from the latest version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compatible: Option<PackageVersion>,
+    /// The constraint the package was installed with, e.g. from lux.toml.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    constraint: Option<String>,
+    /// Whether the latest version satisfies the constraint.
+    within_constraint: bool,
+}
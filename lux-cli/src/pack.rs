@@ -40,7 +40,7 @@ pub struct Pack {
     pub(crate) package_or_rockspec: Option<PackageOrRockspec>,
 }
 
-async fn pack_workspace(
+pub(crate) async fn pack_workspace(
     member: Option<&PackageName>,
     dest_dir: &Path,
     config: &Config,
@@ -83,6 +83,7 @@ async fn pack_workspace(
     for package in packages {
         let tree = workspace.tree(config)?;
         let rock_path = operations::Pack::new(dest_dir.to_path_buf(), tree, package)
+            .target(config.target())
             .pack()
             .await?;
         rock_paths.push(rock_path);
@@ -120,6 +121,7 @@ pub async fn pack(args: Pack, config: Config) -> Result<()> {
                         .first()
                         .ok_or_else(|| miette!("no packages installed"))?;
                     let rock_path = operations::Pack::new(dest_dir, tree, package.clone())
+                        .target(temp_config.target())
                         .pack()
                         .await?;
                     Ok(vec![rock_path])
@@ -130,6 +132,7 @@ pub async fn pack(args: Pack, config: Config) -> Result<()> {
                         miette!("package is installed, but was not found in the lockfile")
                     })?;
                     let rock_path = operations::Pack::new(dest_dir, user_tree, package.clone())
+                        .target(config.target())
                         .pack()
                         .await?;
                     Ok(vec![rock_path])
@@ -143,6 +146,7 @@ pub async fn pack(args: Pack, config: Config) -> Result<()> {
                         )
                     })?;
                     let rock_path = operations::Pack::new(dest_dir, user_tree, package.clone())
+                        .target(config.target())
                         .pack()
                         .await?;
                     Ok(vec![rock_path])
@@ -177,6 +181,7 @@ pub async fn pack(args: Pack, config: Config) -> Result<()> {
                 .build()
                 .await?;
             let rock_path = operations::Pack::new(dest_dir, tree, package)
+                .target(config.target())
                 .pack()
                 .await?;
             Ok(vec![rock_path])
@@ -8,8 +8,16 @@ use crate::build::{self, Build};
 
 #[derive(Args)]
 pub struct Run {
+    /// The name of a script in the `[scripts]` table of the `lux.toml`,{n}
+    /// followed by arguments to pass to it.{n}
+    /// If the first argument is not a script name, all arguments{n}
+    /// are passed to the `[run]` command.
     args: Vec<String>,
 
+    /// List the scripts declared in the `[scripts]` table of the `lux.toml`.
+    #[arg(long)]
+    list: bool,
+
     /// Do not add `require('lux').loader()` to `LUA_INIT`.{n}
     /// If a rock has conflicting transitive dependencies,{n}
     /// disabling the Lux loader may result in the wrong modules being loaded.
@@ -30,8 +38,41 @@ pub async fn run(run_args: Run, config: Config) -> Result<()> {
     let workspace = Workspace::current_or_err()?;
 
     let package = run_args.build.package.clone();
+    let project = workspace.single_member_or_select(&package)?;
+    let scripts = project.toml().into_local()?.scripts().clone();
+
+    if run_args.list {
+        if scripts.is_empty() {
+            println!("No scripts found in the `[scripts]` table of the `lux.toml`.");
+        }
+        for (name, script) in scripts {
+            match script.description().or(script.command()) {
+                Some(summary) => println!("{name}: {summary}"),
+                None => println!("{name}"),
+            }
+        }
+        return Ok(());
+    }
+
     build::build(run_args.build, config.clone()).await?;
 
+    if let Some((name, args)) = run_args
+        .args
+        .split_first()
+        .filter(|(name, _)| scripts.contains_key(*name))
+    {
+        return Ok(operations::RunScript::new()
+            .workspace(&workspace)
+            .maybe_package(package)
+            .name(name)
+            .maybe_dir(run_args.dir)
+            .args(args)
+            .config(&config)
+            .disable_loader(run_args.no_loader)
+            .run()
+            .await?);
+    }
+
     operations::Run::new()
         .workspace(&workspace)
         .maybe_package(package)
@@ -1,13 +1,13 @@
-use std::collections::HashMap;
-
 use clap::Args;
 use itertools::Itertools;
 use miette::{IntoDiagnostic, Result};
 use text_trees::{FormatCharacters, StringTreeNode, TreeFormatting};
 
 use lux_lib::{
-    config::Config,
-    package::{PackageName, PackageReq, PackageVersion},
+    config::{Config, ConfigBuilder},
+    lua_version::LuaVersion,
+    operations,
+    package::PackageReq,
     remote_package_db::RemotePackageDB,
 };
 
@@ -15,8 +15,33 @@ use crate::args::OutputFormat;
 
 #[derive(Args)]
 pub struct Search {
-    lua_package_req: PackageReq,
-    // TODO(vhyrro): Add options.
+    /// Search terms, which are matched against package names, summaries, labels and licenses.{n}
+    /// A single package requirement, e.g. `foo@1.0.0` or `"foo >= 1.0"`,{n}
+    /// restricts the versions that are considered.{n}
+    /// Summaries, labels and licenses are taken from the server's search index.{n}
+    /// If the server has none, they are downloaded from the rockspecs{n}
+    /// of at most 100 packages per search, and a warning is shown{n}
+    /// if the results may be incomplete.
+    query: Vec<String>,
+
+    /// Only show packages with this label.{n}
+    /// Can be specified multiple times.{n}
+    /// Subject to the same metadata download limit as the search terms.
+    #[arg(long)]
+    label: Vec<String>,
+
+    /// Only show packages that support this Lua version.
+    #[arg(long, value_name = "ver")]
+    lua: Option<LuaVersion>,
+
+    /// Only show packages with a binary rock for the current platform.
+    #[arg(long)]
+    has_binary: bool,
+
+    /// The maximum number of packages to show.
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+
     #[arg(long, default_value = "text", value_enum, ignore_case = true)]
     output_format: OutputFormat,
 }
@@ -24,28 +49,73 @@ pub struct Search {
 pub async fn search(data: Search, config: Config) -> Result<()> {
     let formatting = TreeFormatting::dir_tree(FormatCharacters::box_chars());
 
+    let config = match data.lua {
+        Some(lua_version) => ConfigBuilder::from(config)
+            .lua_version(Some(lua_version))
+            .build()?,
+        None => config,
+    };
     let package_db = RemotePackageDB::from_config(&config).await?;
 
-    let lua_package_req = data.lua_package_req;
+    let (query, version_req) = match data.query.as_slice() {
+        [package_req] if package_req.contains(['@', '=', '<', '>', '~']) => {
+            let package_req: PackageReq = package_req.parse()?;
+            (
+                package_req.name().to_string(),
+                Some(package_req.version_req().clone()),
+            )
+        }
+        query => (query.join(" "), None),
+    };
 
-    let result = package_db.search(&lua_package_req);
+    let results = operations::Search::new(query, &config)
+        .package_db(&package_db)
+        .maybe_version_req(version_req)
+        .labels(data.label)
+        .has_binary(data.has_binary)
+        .limit(data.limit)
+        .search()
+        .await?;
+
+    if results.truncated {
+        tracing::warn!(
+            "search results may be incomplete, as at most {} rockspecs are downloaded per search. Try more specific search terms.",
+            operations::MAX_METADATA_DOWNLOADS
+        );
+    }
 
     match data.output_format {
         OutputFormat::Json => {
-            let rock_to_version_map: HashMap<&PackageName, Vec<&PackageVersion>> =
-                HashMap::from_iter(result);
             println!(
                 "{}",
-                serde_json::to_string(&rock_to_version_map).into_diagnostic()?
+                serde_json::to_string(&results.packages).into_diagnostic()?
             );
         }
         OutputFormat::Text => {
-            for (key, versions) in result.into_iter().sorted() {
-                let mut tree = StringTreeNode::new(key.to_string().to_owned());
+            for result in results.packages {
+                let mut tree = StringTreeNode::new(format!("{} {}", result.name, result.version));
 
-                for version in versions {
-                    tree.push(version.to_string());
+                if let Some(metadata) = result.metadata {
+                    if let Some(summary) = metadata.summary.filter(|summary| !summary.is_empty()) {
+                        tree.push(summary);
+                    }
+                    if !metadata.labels.is_empty() {
+                        tree.push(format!("labels: {}", metadata.labels.join(", ")));
+                    }
+                    if let Some(license) = metadata.license {
+                        tree.push(format!("license: {license}"));
+                    }
+                    if let Some(homepage) = metadata.homepage {
+                        tree.push(format!("homepage: {homepage}"));
+                    }
+                }
+                if result.has_binary {
+                    tree.push("binary rock available".to_string());
                 }
+                tree.push(format!(
+                    "versions: {}",
+                    result.versions.iter().map(ToString::to_string).join(", ")
+                ));
 
                 println!(
                     "{}",
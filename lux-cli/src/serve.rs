@@ -0,0 +1,46 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+
+use clap::Args;
+use lux_lib::{operations, upload::ApiKey};
+use miette::{IntoDiagnostic, Result};
+
+#[derive(Args)]
+pub struct Serve {
+    /// The rocks directory to serve.{n}
+    /// Defaults to the current directory.
+    dir: Option<PathBuf>,
+
+    /// The port to listen on.
+    #[arg(long, short, default_value_t = 8080)]
+    port: u16,
+
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1")]
+    host: IpAddr,
+
+    /// Accept luarocks API compatible uploads (e.g. from `lx upload`) to the rocks directory.{n}
+    /// Uploads are authenticated with the API key in the $LUX_API_KEY environment variable.
+    #[arg(long)]
+    allow_upload: bool,
+}
+
+pub async fn serve(args: Serve) -> Result<()> {
+    let dir = match args.dir {
+        Some(dir) => dir,
+        None => std::env::current_dir().into_diagnostic()?,
+    };
+    let upload_api_key = if args.allow_upload {
+        Some(ApiKey::new()?)
+    } else {
+        None
+    };
+    let server = operations::Serve::new(dir.clone())
+        .addr(SocketAddr::new(args.host, args.port))
+        .maybe_upload_api_key(upload_api_key)
+        .bind()
+        .await?;
+    println!("Serving {} at {}", dir.display(), server.url());
+    server.run().await;
+    Ok(())
+}
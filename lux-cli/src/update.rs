@@ -1,6 +1,6 @@
 use clap::Args;
 use itertools::Itertools;
-use lux_lib::package::{PackageName, PackageReq};
+use lux_lib::package::{PackageName, PackageReq, UpdateKind};
 
 use lux_lib::remote_package_db::RemotePackageDB;
 use lux_lib::rockspec::lua_dependency::LuaDependencyType;
@@ -18,6 +18,18 @@ pub struct Update {
     #[arg(long)]
     toml: bool,
 
+    /// Only apply SemVer compatible (patch and minor) updates.{n}
+    /// With --toml, constraints are upgraded to the latest compatible version.
+    #[arg(long, conflicts_with = "major")]
+    compatible: bool,
+
+    /// Also apply major updates, which may contain breaking changes.{n}
+    /// Outside of a project, this updates packages beyond the constraints
+    /// they were installed with.{n}
+    /// With --toml, constraints are upgraded to the latest version (the default).
+    #[arg(long)]
+    major: bool,
+
     /// Packages to update.
     /// When used with the --toml flag in a project, these must be package names.
     packages: Option<Vec<PackageReq>>,
@@ -39,7 +51,15 @@ pub struct Update {
 }
 
 pub async fn update(args: Update, config: Config) -> Result<()> {
+    let update_kind = if args.compatible {
+        Some(UpdateKind::Minor)
+    } else if args.major {
+        Some(UpdateKind::Major)
+    } else {
+        None
+    };
     if args.toml {
+        let toml_update_kind = update_kind.unwrap_or(UpdateKind::Major);
         let mut workspace = Workspace::current_or_err()?;
 
         let db = RemotePackageDB::from_config(&config).await?;
@@ -52,6 +72,7 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
                 project
                     .upgrade(
                         LuaDependencyType::Regular(packages.iter().collect()),
+                        toml_update_kind,
                         &db,
                         &config,
                     )
@@ -61,6 +82,7 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
                     project
                         .upgrade(
                             LuaDependencyType::Regular(packages.iter().collect()),
+                            toml_update_kind,
                             &db,
                             &config,
                         )
@@ -76,6 +98,7 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
                 project
                     .upgrade(
                         LuaDependencyType::Build(packages.iter().collect()),
+                        toml_update_kind,
                         &db,
                         &config,
                     )
@@ -85,6 +108,7 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
                     project
                         .upgrade(
                             LuaDependencyType::Build(packages.iter().collect()),
+                            toml_update_kind,
                             &db,
                             &config,
                         )
@@ -100,6 +124,7 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
                 project
                     .upgrade(
                         LuaDependencyType::Test(packages.iter().collect()),
+                        toml_update_kind,
                         &db,
                         &config,
                     )
@@ -109,6 +134,7 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
                     project
                         .upgrade(
                             LuaDependencyType::Test(packages.iter().collect()),
+                            toml_update_kind,
                             &db,
                             &config,
                         )
@@ -119,10 +145,10 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
         if upgrade_all {
             if let Some(package) = &args.package {
                 let project = workspace.select_member_mut(package)?;
-                project.upgrade_all(&db, &config).await?;
+                project.upgrade_all(toml_update_kind, &db, &config).await?;
             } else {
                 for project in workspace.members_mut().iter_mut() {
-                    project.upgrade_all(&db, &config).await?;
+                    project.upgrade_all(toml_update_kind, &db, &config).await?;
                 }
             }
         }
@@ -133,6 +159,7 @@ pub async fn update(args: Update, config: Config) -> Result<()> {
         .build_dependencies(args.build)
         .test_dependencies(args.test)
         .validate_integrity(!args.no_integrity_check)
+        .maybe_update_kind(update_kind)
         .update()
         .await
         .wrap_err("update failed.")?;
@@ -0,0 +1,21 @@
+use lux_lib::{
+    config::{Config, ConfigBuilder},
+    remote_package_db::RemotePackageDB,
+};
+use miette::Result;
+
+pub async fn update_index(config: Config) -> Result<()> {
+    let config = ConfigBuilder::from(config)
+        .refresh_manifests(Some(true))
+        .build()?;
+    RemotePackageDB::from_config(&config).await?;
+    for server in config
+        .enabled_dev_servers()?
+        .iter()
+        .chain(config.extra_servers())
+        .chain(std::iter::once(config.server()))
+    {
+        println!("Updated the package index of {server}");
+    }
+    Ok(())
+}
@@ -1,10 +1,18 @@
 use clap::Args;
 use lux_lib::{
-    config::Config, package::PackageName, remote_package_db::RemotePackageDB,
-    upload::ProjectUpload, workspace::Workspace,
+    config::Config,
+    lockfile::LocalPackage,
+    package::PackageName,
+    project::Project,
+    remote_package_db::RemotePackageDB,
+    tree::Tree,
+    upload::{ApiKey, ProjectUpload},
+    workspace::Workspace,
 };
 
-use miette::{IntoDiagnostic, Result};
+use crate::build;
+
+use miette::{miette, IntoDiagnostic, Result};
 
 #[cfg(feature = "gpgme")]
 use lux_lib::upload::SignatureProtocol;
@@ -25,22 +33,41 @@ pub struct Upload {
     /// Lux can also generate a TOTP code if you expose your luarocks.org 2FA secret via the 'LUAROCKS_2FA_SECRET' environment variable.{n}
     #[arg(short, long, visible_short_alias = 'c')]
     tfa_code: Option<String>,
+
+    /// Name of a registry from the `[registries]` section of the config to upload to.{n}
+    /// If not set, packages are uploaded to the configured `server`.{n}
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Also pack the project's sources into a `.src.rock`{n}
+    /// and upload it alongside the rockspec.
+    #[arg(long)]
+    with_src_rock: bool,
+
+    /// Also build the project, pack it into a binary rock{n}
+    /// and upload it alongside the rockspec.
+    #[arg(long)]
+    with_binary_rocks: bool,
 }
 
 #[cfg(feature = "gpgme")]
 pub async fn upload(data: Upload, config: Config) -> Result<()> {
     let workspace = Workspace::current_or_err()?;
+    let config = registry_config(&data, config)?;
 
     let package_db = RemotePackageDB::from_config(&config).await?;
     let tfa_code = tfa_code_from_args_or_secret(&data, &config)?;
-    if let Some(package) = data.package {
-        let project = workspace.select_member(&package)?;
+    if let Some(package) = &data.package {
+        let project = workspace.select_member(package)?;
         ProjectUpload::new()
             .project(project)
             .config(&config)
             .sign_protocol(data.sign_protocol.clone())
             .maybe_tfa_code(tfa_code)
+            .maybe_api_key(registry_api_key(&data, &config)?)
             .package_db(&package_db)
+            .with_src_rock(data.with_src_rock)
+            .maybe_binary_rock(binary_rock(&data, &workspace, project, &config).await?)
             .upload_to_luarocks()
             .await?;
     } else {
@@ -50,7 +77,10 @@ pub async fn upload(data: Upload, config: Config) -> Result<()> {
                 .config(&config)
                 .sign_protocol(data.sign_protocol.clone())
                 .maybe_tfa_code(tfa_code.clone())
+                .maybe_api_key(registry_api_key(&data, &config)?)
                 .package_db(&package_db)
+                .with_src_rock(data.with_src_rock)
+                .maybe_binary_rock(binary_rock(&data, &workspace, project, &config).await?)
                 .upload_to_luarocks()
                 .await?;
         }
@@ -62,15 +92,19 @@ pub async fn upload(data: Upload, config: Config) -> Result<()> {
 #[cfg(not(feature = "gpgme"))]
 pub async fn upload(data: Upload, config: Config) -> Result<()> {
     let workspace = Workspace::current_or_err()?;
+    let config = registry_config(&data, config)?;
     let package_db = RemotePackageDB::from_config(&config).await?;
     let tfa_code = tfa_code_from_args_or_secret(&data, &config)?;
-    if let Some(package) = data.package {
-        let project = workspace.select_member(&package)?;
+    if let Some(package) = &data.package {
+        let project = workspace.select_member(package)?;
         ProjectUpload::new()
             .project(project)
             .config(&config)
             .maybe_tfa_code(tfa_code)
+            .maybe_api_key(registry_api_key(&data, &config)?)
             .package_db(&package_db)
+            .with_src_rock(data.with_src_rock)
+            .maybe_binary_rock(binary_rock(&data, &workspace, project, &config).await?)
             .upload_to_luarocks()
             .await?;
     } else {
@@ -79,7 +113,10 @@ pub async fn upload(data: Upload, config: Config) -> Result<()> {
                 .project(project)
                 .config(&config)
                 .maybe_tfa_code(tfa_code.clone())
+                .maybe_api_key(registry_api_key(&data, &config)?)
                 .package_db(&package_db)
+                .with_src_rock(data.with_src_rock)
+                .maybe_binary_rock(binary_rock(&data, &workspace, project, &config).await?)
                 .upload_to_luarocks()
                 .await?;
         }
@@ -88,9 +125,53 @@ pub async fn upload(data: Upload, config: Config) -> Result<()> {
     Ok(())
 }
 
+/// The config to upload with, targeting the `--registry`, if set.
+fn registry_config(data: &Upload, config: Config) -> Result<Config> {
+    match &data.registry {
+        Some(name) => Ok(config.for_registry(name)?),
+        None => Ok(config),
+    }
+}
+
+/// The API key for the `--registry`, if set.
+/// Otherwise, the API key is read from the `$LUX_API_KEY` environment variable.
+fn registry_api_key(data: &Upload, config: &Config) -> Result<Option<ApiKey>> {
+    match &data.registry {
+        Some(name) => Ok(Some(ApiKey::for_registry(name, config)?)),
+        None => Ok(None),
+    }
+}
+
+/// Build the project to pack it into a binary rock, if requested.
+async fn binary_rock(
+    data: &Upload,
+    workspace: &Workspace,
+    project: &Project,
+    config: &Config,
+) -> Result<Option<(Tree, LocalPackage)>> {
+    if !data.with_binary_rocks {
+        return Ok(None);
+    }
+    let package_name = project.toml().package().clone();
+    let mut build = build::Build::default();
+    build.package = Some(package_name.clone());
+    let package = build::build(build, config.clone())
+        .await?
+        .into_iter()
+        .find(|package| package.name() == &package_name)
+        .ok_or_else(|| miette!("build did not produce a package"))?;
+    Ok(Some((workspace.tree(config)?, package)))
+}
+
 fn tfa_code_from_args_or_secret(data: &Upload, config: &Config) -> Result<Option<String>> {
+    let registry_expects_tfa = data
+        .registry
+        .as_ref()
+        .and_then(|name| config.registries().get(name))
+        .is_none_or(|registry| registry.tfa());
     match &data.tfa_code {
         Some(code) => Ok(Some(code.to_owned())),
+        None if !registry_expects_tfa => Ok(None),
         None => match std::env::var("LUAROCKS_2FA_SECRET") {
             Ok(secret) => {
                 let secret = base32::decode(base32::Alphabet::Crockford, &secret)
@@ -2,8 +2,13 @@ use std::{collections::HashSet, path::PathBuf, str::FromStr};
 
 use itertools::Itertools;
 use lux_lib::{
-    config::Config, git::shorthand::RemoteGitUrlShorthand, lua_version::LuaVersion,
-    operations::Sync, package::PackageReq, tree::Tree, workspace::Workspace,
+    config::Config,
+    git::{shorthand::RemoteGitUrlShorthand, url::RemoteGitUrl},
+    lua_version::LuaVersion,
+    operations::Sync,
+    package::{PackageName, PackageReq},
+    tree::Tree,
+    workspace::Workspace,
 };
 use miette::{Context, Result};
 use walkdir::WalkDir;
@@ -63,6 +68,18 @@ impl FromStr for PackageReqOrGitShorthand {
     }
 }
 
+impl PackageReqOrGitShorthand {
+    /// The name of the dependency, as it appears in the lux.toml.
+    pub fn package_name(&self) -> PackageName {
+        match self {
+            Self::PackageReq(req) => req.name().clone(),
+            Self::GitShorthand(url) => {
+                PackageName::new(RemoteGitUrl::from(url.clone()).repo().to_string())
+            }
+        }
+    }
+}
+
 /// Get the current workspaces tree, or fall back to
 /// the user tree if not in a project
 pub fn current_workspace_or_user_tree(config: &Config) -> Result<Tree> {
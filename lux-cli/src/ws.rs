@@ -0,0 +1,116 @@
+use clap::{Args, Subcommand};
+use itertools::Itertools;
+use lux_lib::{
+    config::Config,
+    operations::{self, MemberCommand},
+    project::Project,
+    workspace::Workspace,
+};
+use miette::Result;
+
+#[derive(Subcommand)]
+pub enum Ws {
+    /// Execute a command in each workspace member's directory,{n}
+    /// with the workspace's PATH, LUA_PATH, LUA_CPATH and LUA_INIT set.{n}
+    /// {n}
+    /// Example: `lx ws exec --changed-since main -- busted`
+    Exec(WsExec),
+    /// Run each workspace member's `[run]` command, or a named script{n}
+    /// from its `[scripts]` table, in its directory, with the provided arguments.{n}
+    /// {n}
+    /// Example: `lx ws run test -- --verbose`
+    Run(WsRun),
+}
+
+#[derive(Args)]
+pub struct WsExec {
+    /// The command to run.
+    command: String,
+
+    /// Arguments to pass to the program.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+
+    #[clap(flatten)]
+    opts: WsOpts,
+}
+
+#[derive(Args)]
+pub struct WsRun {
+    /// The script to run from each member's `[scripts]` table.{n}
+    /// If not set, each member's `[run]` command is run.
+    script: Option<String>,
+
+    /// Arguments to pass to each member's script or `[run]` command.
+    #[arg(last = true)]
+    args: Vec<String>,
+
+    #[clap(flatten)]
+    opts: WsOpts,
+}
+
+#[derive(Args)]
+pub struct WsOpts {
+    /// Only include members whose package names match these glob patterns.{n}
+    /// Example: `--package 'lux-*'`.
+    #[arg(short, long, visible_short_alias = 'p')]
+    package: Vec<String>,
+
+    /// Only include members whose project files changed since this git ref,{n}
+    /// and the workspace members that depend on them.
+    #[arg(long, value_name = "git-ref")]
+    changed_since: Option<String>,
+
+    /// Run the command in up to N members concurrently,{n}
+    /// prefixing each line of output with the member's package name.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    parallel: usize,
+
+    /// Stop after the first member in which the command fails.
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Do not add `require('lux').loader()` to `LUA_INIT`.{n}
+    /// If a rock has conflicting transitive dependencies,{n}
+    /// disabling the Lux loader may result in the wrong modules being loaded.
+    #[arg(long)]
+    no_loader: bool,
+}
+
+pub async fn ws(ws: Ws, config: Config) -> Result<()> {
+    let workspace = Workspace::current_or_err()?;
+    let (command, opts) = match ws {
+        Ws::Exec(WsExec {
+            command,
+            args,
+            opts,
+        }) => (MemberCommand::Exec { command, args }, opts),
+        Ws::Run(WsRun { script, args, opts }) => (MemberCommand::Run { script, args }, opts),
+    };
+    let members = select_members(&workspace, &opts)?;
+    operations::WorkspaceExec::new(&workspace, &config, command)
+        .members(members)
+        .parallel(opts.parallel)
+        .fail_fast(opts.fail_fast)
+        .disable_loader(opts.no_loader)
+        .exec()
+        .await?;
+    Ok(())
+}
+
+fn select_members<'a>(workspace: &'a Workspace, opts: &WsOpts) -> Result<Vec<&'a Project>> {
+    let mut members = if opts.package.is_empty() {
+        workspace.members().iter().collect_vec()
+    } else {
+        workspace.select_members_matching(&opts.package)?
+    };
+    if let Some(reference) = &opts.changed_since {
+        let changed = workspace.select_members_changed_since(reference)?;
+        members.retain(|project| {
+            changed
+                .iter()
+                .any(|changed| changed.toml().package() == project.toml().package())
+        });
+    }
+    Ok(members)
+}
@@ -0,0 +1,372 @@
+use itertools::Itertools;
+use miette::Diagnostic;
+use path_slash::PathExt;
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    time::Instant,
+};
+use thiserror::Error;
+use tokio::process::Command;
+use tracing::{info_span, Instrument};
+
+use crate::{
+    build::{
+        backend::{BuildBackend, BuildInfo, RunBuildArgs},
+        external_dependency::ExternalDependencyInfo,
+        utils::{self, InstallBinaryError},
+    },
+    config::{build::Profile, Config},
+    fs,
+    lua_installation::LuaInstallation,
+    lua_rockspec::AutotoolsBuildSpec,
+    path::{Paths, PathsError},
+    tree::{InstallTree, RockLayout, TreeError},
+    variables::VariableSubstitutionError,
+};
+
+/// The directory in which out-of-tree builds are configured.
+const AUTOTOOLS_BUILD_DIR: &str = "build.lux";
+
+#[derive(Error, Debug, Diagnostic)]
+#[non_exhaustive]
+pub enum AutotoolsError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Tree(#[from] TreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Paths(#[from] PathsError),
+    #[error("{name} step failed.\n\n{status}\n\nstdout:\n{stdout}\n\nstderr:\n{stderr}")]
+    CommandFailure {
+        name: String,
+        status: ExitStatus,
+        stdout: String,
+        stderr: String,
+    },
+    #[error("failed to run `{name}` step")]
+    Io {
+        name: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to run `{0}` step: command not found!")]
+    #[diagnostic(help("run `lx debug toolchains` to check available build tools."))]
+    CommandNotFound(String),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    VariableSubstitution(#[from] VariableSubstitutionError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("no configure script found in {0}")]
+    #[diagnostic(help(
+        "the source must contain a `configure` script, an `autogen.sh` script or a `configure.ac` file."
+    ))]
+    ConfigureScriptNotFound(PathBuf),
+    #[error("failed to determine the arguments for linking Lua")]
+    #[diagnostic(help("run `lx debug toolchains` to check available build tools."))]
+    LuaLinkArgs(#[from] cc::Error),
+    #[error("failed to install binary {file_name}")]
+    InstallBinary {
+        file_name: String,
+        #[source]
+        source: InstallBinaryError,
+    },
+}
+
+impl BuildBackend for AutotoolsBuildSpec {
+    type Err = AutotoolsError;
+
+    #[tracing::instrument(name = "autotools::run", skip_all, level = "debug")]
+    async fn run<T>(self, args: RunBuildArgs<'_, T>) -> Result<BuildInfo, Self::Err>
+    where
+        T: InstallTree,
+    {
+        let output_paths = args.output_paths;
+        let no_install = args.no_install;
+        let lua = args.lua;
+        let external_dependencies = args.external_dependencies;
+        let config = args.config;
+        let build_dir = args.build_dir;
+
+        let build_tree = args.tree.build_tree(config)?;
+        let build_paths = Paths::new(&build_tree)?;
+        let lua_path = build_paths.package_path_prepended().joined();
+        let lua_cpath = build_paths.package_cpath_prepended().joined();
+        let bin_path = build_paths.path_prepended().joined();
+
+        let command = |program: String, args: Vec<String>, dir: &Path| {
+            let mut cmd = config.wrapped_command(program, args);
+            cmd.current_dir(dir)
+                .env("PATH", &bin_path)
+                .env("LUA_PATH", &lua_path)
+                .env("LUA_CPATH", &lua_cpath);
+            cmd
+        };
+
+        let configure_script = build_dir.join("configure");
+        if !configure_script.is_file() {
+            let (name, mut cmd) = if build_dir.join("autogen.sh").is_file() {
+                (
+                    "autogen.sh",
+                    command("sh".into(), vec!["autogen.sh".into()], build_dir),
+                )
+            } else if build_dir.join("configure.ac").is_file()
+                || build_dir.join("configure.in").is_file()
+            {
+                (
+                    "autoreconf",
+                    command(
+                        "autoreconf".into(),
+                        vec!["--install".into(), "--force".into()],
+                        build_dir,
+                    ),
+                )
+            } else {
+                return Err(AutotoolsError::ConfigureScriptNotFound(
+                    build_dir.to_path_buf(),
+                ));
+            };
+            spawn_cmd(&mut cmd, name, config)
+                .instrument(info_span!("Autotools autoreconf"))
+                .await?;
+        }
+
+        let configure_dir = if self.out_of_tree {
+            let configure_dir = build_dir.join(AUTOTOOLS_BUILD_DIR);
+            fs::tokio::create_dir_all(&configure_dir).await?;
+            configure_dir
+        } else {
+            build_dir.to_path_buf()
+        };
+
+        let configure_args = std::iter::once(configure_script.to_slash_lossy().to_string())
+            .chain(install_dir_args(output_paths))
+            .map(Ok)
+            .chain(self.configure_args.iter().map(|arg| {
+                utils::substitute_variables(arg, output_paths, lua, external_dependencies, config)
+            }))
+            .try_collect::<_, Vec<_>, _>()?;
+        let flags = CompilerFlags::new(
+            lua,
+            external_dependencies,
+            utils::lua_link_args(lua, config)?,
+            config,
+        );
+        let mut cmd = command("sh".into(), configure_args, &configure_dir);
+        cmd.env("CFLAGS", &flags.cflags)
+            .env("CPPFLAGS", &flags.cppflags)
+            .env("LDFLAGS", &flags.ldflags);
+        spawn_cmd(&mut cmd, "configure", config)
+            .instrument(info_span!("Autotools configure"))
+            .await?;
+
+        if self.build_pass {
+            spawn_cmd(
+                &mut command(config.make_cmd(), Vec::new(), &configure_dir),
+                &config.make_cmd(),
+                config,
+            )
+            .instrument(info_span!("Autotools build pass"))
+            .await?;
+        }
+
+        let mut binaries = Vec::new();
+        if self.install_pass && !no_install {
+            spawn_cmd(
+                &mut command(config.make_cmd(), vec!["install".into()], &configure_dir),
+                &format!("{} install", config.make_cmd()),
+                config,
+            )
+            .instrument(info_span!("Autotools install pass"))
+            .await?;
+
+            // Executables are installed into the prefix, so we move them to the tree's bin directory.
+            let autotools_bin_dir = autotools_bin_dir(output_paths);
+            if autotools_bin_dir.is_dir() {
+                for entry in fs::sync::read_dir(&autotools_bin_dir)?
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .sorted_by_key(|entry| entry.file_name())
+                {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    let installed_bin = utils::install_binary(
+                        &entry.path(),
+                        &file_name,
+                        args.tree,
+                        lua,
+                        args.deploy,
+                        config,
+                    )
+                    .await
+                    .map_err(|err| AutotoolsError::InstallBinary {
+                        file_name: file_name.clone(),
+                        source: err,
+                    })?;
+                    if let Some(installed_bin_file_name) = installed_bin.file_name() {
+                        binaries.push(installed_bin_file_name.into());
+                    }
+                }
+                fs::tokio::remove_dir_all(&autotools_bin_dir).await?;
+            }
+        }
+
+        Ok(BuildInfo {
+            binaries,
+            ..BuildInfo::default()
+        })
+    }
+}
+
+/// The directory inside the prefix that executables are installed into,
+/// so that they can be installed to the tree's bin directory with wrappers, if needed.
+fn autotools_bin_dir(output_paths: &RockLayout) -> PathBuf {
+    output_paths.rock_path.join("bin")
+}
+
+/// `configure` arguments for installing into the rock layout.
+/// Lua modules are installed into `LUADIR`, which a `configure.ac`
+/// can declare with `AC_ARG_VAR([LUADIR], ...)`.
+fn install_dir_args(output_paths: &RockLayout) -> Vec<String> {
+    vec![
+        format!("--prefix={}", output_paths.rock_path.to_slash_lossy()),
+        format!("--libdir={}", output_paths.lib.to_slash_lossy()),
+        format!(
+            "--bindir={}",
+            autotools_bin_dir(output_paths).to_slash_lossy()
+        ),
+        format!("--sysconfdir={}", output_paths.conf.to_slash_lossy()),
+        format!("--docdir={}", output_paths.doc.to_slash_lossy()),
+        format!("LUADIR={}", output_paths.src.to_slash_lossy()),
+    ]
+}
+
+/// The compiler flags passed to `configure` via the environment.
+struct CompilerFlags {
+    cflags: String,
+    cppflags: String,
+    ldflags: String,
+}
+
+impl CompilerFlags {
+    fn new(
+        lua: &LuaInstallation,
+        external_dependencies: &HashMap<String, ExternalDependencyInfo>,
+        lua_link_args: Vec<String>,
+        config: &Config,
+    ) -> Self {
+        let external_dependencies = external_dependencies
+            .iter()
+            .sorted_by_key(|(name, _)| *name)
+            .map(|(_, info)| info)
+            .collect_vec();
+        let include_dirs = lua.includes().into_iter().chain(
+            external_dependencies
+                .iter()
+                .filter_map(|info| info.include_dir.as_ref()),
+        );
+        let lib_dirs = external_dependencies
+            .iter()
+            .filter_map(|info| info.lib_dir.as_ref());
+        Self {
+            cflags: config
+                .variables()
+                .get("CFLAGS")
+                .map(String::as_str)
+                .into_iter()
+                .chain(profile_cflags(config.build_profile()))
+                .join(" "),
+            cppflags: include_dirs
+                .map(|dir| format!("-I{}", dir.to_slash_lossy()))
+                .chain(lua.define_flags())
+                .chain(
+                    external_dependencies
+                        .iter()
+                        .flat_map(|info| info.define_flags()),
+                )
+                .join(" "),
+            ldflags: config
+                .variables()
+                .get("LDFLAGS")
+                .cloned()
+                .into_iter()
+                .chain(lib_dirs.map(|dir| format!("-L{}", dir.to_slash_lossy())))
+                .chain(lua_link_args)
+                .filter(|flag| !flag.is_empty())
+                .join(" "),
+        }
+    }
+}
+
+/// The compiler flags for a build profile.
+/// These are appended to the configured `CFLAGS`, so they take precedence.
+fn profile_cflags(profile: Profile) -> Vec<&'static str> {
+    match profile {
+        Profile::Release => vec!["-O3"],
+        Profile::Dev => vec!["-O0", "-g"],
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(cmd, config))]
+async fn spawn_cmd(cmd: &mut Command, name: &str, config: &Config) -> Result<(), AutotoolsError> {
+    let started = Instant::now();
+    match cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => match child
+            .wait_with_output()
+            .await
+            .inspect(|output| config.log_command(cmd, output, started.elapsed()))
+        {
+            Ok(output) if output.status.success() => utils::trace_command_output(&output),
+            Ok(output) => {
+                return Err(AutotoolsError::CommandFailure {
+                    name: name.to_string(),
+                    status: output.status,
+                    stdout: String::from_utf8_lossy(&output.stdout).into(),
+                    stderr: String::from_utf8_lossy(&output.stderr).into(),
+                });
+            }
+            Err(source) => {
+                return Err(AutotoolsError::Io {
+                    name: name.to_string(),
+                    source,
+                })
+            }
+        },
+        Err(_) => return Err(AutotoolsError::CommandNotFound(name.to_string())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_dirs_from_rock_layout() {
+        let output_paths = RockLayout {
+            rock_path: "/tree/foo".into(),
+            etc: "/tree/foo/etc".into(),
+            lib: "/tree/foo/lib".into(),
+            src: "/tree/foo/src".into(),
+            bin: "/tree/bin".into(),
+            conf: "/tree/foo/etc/conf".into(),
+            doc: "/tree/foo/etc/doc".into(),
+        };
+        assert_eq!(
+            install_dir_args(&output_paths),
+            vec![
+                "--prefix=/tree/foo".to_string(),
+                "--libdir=/tree/foo/lib".into(),
+                "--bindir=/tree/foo/bin".into(),
+                "--sysconfdir=/tree/foo/etc/conf".into(),
+                "--docdir=/tree/foo/etc/doc".into(),
+                "LUADIR=/tree/foo/src".into(),
+            ]
+        );
+        assert_eq!(profile_cflags(Profile::Release), vec!["-O3"]);
+        assert_eq!(profile_cflags(Profile::Dev), vec!["-O0", "-g"]);
+    }
+}
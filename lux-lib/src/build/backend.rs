@@ -7,7 +7,7 @@ use std::{
 use bon::Builder;
 
 use crate::{
-    build::external_dependency::ExternalDependencyInfo,
+    build::{compile_commands::CompileCommand, external_dependency::ExternalDependencyInfo},
     config::Config,
     lua_installation::LuaInstallation,
     lua_rockspec::DeploySpec,
@@ -39,4 +39,6 @@ pub(crate) trait BuildBackend {
 #[derive(Default)]
 pub(crate) struct BuildInfo {
     pub binaries: Vec<PathBuf>,
+    /// Compile commands for the C sources compiled by the build, if known.
+    pub compile_commands: Vec<CompileCommand>,
 }
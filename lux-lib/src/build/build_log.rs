@@ -0,0 +1,270 @@
+use std::{
+    fmt::Write as _,
+    io,
+    path::{Path, PathBuf},
+    process::Output,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use itertools::Itertools;
+use tokio::process::Command;
+
+use crate::{
+    build::compile_commands::CompileCommand,
+    config::Config,
+    fs,
+    lockfile::LocalPackage,
+    package::{PackageName, PackageReq, PackageVersion},
+};
+
+/// The directory in the cache directory in which build logs are stored.
+const BUILD_LOGS_DIR: &str = "build-logs";
+const BUILD_LOG_EXTENSION: &str = "log";
+
+/// The directory containing the build logs of a package,
+/// one per [`LocalPackageId`](crate::lockfile::LocalPackageId).
+pub fn build_log_dir(package: &PackageName, config: &Config) -> PathBuf {
+    config
+        .cache_dir()
+        .join(BUILD_LOGS_DIR)
+        .join(package.to_string())
+}
+
+/// The most recently written build log of a package that matches the requirement.
+pub fn latest_build_log(package: &PackageReq, config: &Config) -> io::Result<Option<PathBuf>> {
+    let dir = build_log_dir(package.name(), config);
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let mut logs = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_none_or(|extension| extension != BUILD_LOG_EXTENSION)
+        {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let matches_version =
+            logged_version(&content).is_some_and(|version| package.version_req().matches(&version));
+        if matches_version {
+            logs.push((std::fs::metadata(&path)?.modified()?, path));
+        }
+    }
+    Ok(logs
+        .into_iter()
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path))
+}
+
+fn logged_version(content: &str) -> Option<PackageVersion> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("version: "))
+        .and_then(|version| PackageVersion::parse(version).ok())
+}
+
+/// A build command that was run, with its output and timing.
+#[derive(Debug)]
+struct LoggedCommand {
+    program: String,
+    args: Vec<String>,
+    current_dir: Option<PathBuf>,
+    env: Vec<(String, Option<String>)>,
+    status: String,
+    duration: Duration,
+    stdout: String,
+    stderr: String,
+}
+
+/// Records the commands run while building a package.
+/// Cheap to clone, so that it can be passed to the build backends via the [`Config`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BuildLog {
+    commands: Arc<Mutex<Vec<LoggedCommand>>>,
+}
+
+impl BuildLog {
+    /// Records a command that ran to completion.
+    pub(crate) fn record(&self, cmd: &Command, output: &Output, duration: Duration) {
+        let cmd = cmd.as_std();
+        let logged = LoggedCommand {
+            program: cmd.get_program().to_string_lossy().to_string(),
+            args: cmd
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect_vec(),
+            current_dir: cmd.get_current_dir().map(Path::to_path_buf),
+            env: cmd
+                .get_envs()
+                .map(|(key, value)| {
+                    (
+                        key.to_string_lossy().to_string(),
+                        value.map(|value| value.to_string_lossy().to_string()),
+                    )
+                })
+                .sorted()
+                .collect_vec(),
+            status: output.status.to_string(),
+            duration,
+            stdout: String::from_utf8_lossy(&output.stdout).into(),
+            stderr: String::from_utf8_lossy(&output.stderr).into(),
+        };
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.push(logged);
+        }
+    }
+
+    /// Writes the log to the package's build log directory and returns its path.
+    pub(crate) async fn persist(
+        &self,
+        package: &LocalPackage,
+        compile_commands: &[CompileCommand],
+        result: Result<(), String>,
+        duration: Duration,
+        config: &Config,
+    ) -> Result<PathBuf, fs::FsError> {
+        let dir = build_log_dir(package.name(), config);
+        fs::tokio::create_dir_all(&dir).await?;
+        let path = dir.join(format!("{}.{BUILD_LOG_EXTENSION}", package.id()));
+        let content = self.render(package, compile_commands, &result, duration);
+        fs::tokio::write(&path, content).await?;
+        Ok(path)
+    }
+
+    fn render(
+        &self,
+        package: &LocalPackage,
+        compile_commands: &[CompileCommand],
+        result: &Result<(), String>,
+        duration: Duration,
+    ) -> String {
+        let mut log = String::new();
+        let _ = writeln!(log, "package: {}", package.name());
+        let _ = writeln!(log, "version: {}", package.version());
+        let _ = writeln!(log, "id: {}", package.id());
+        let _ = writeln!(log, "date: {}", httpdate::fmt_http_date(SystemTime::now()));
+        let _ = writeln!(log, "duration: {:.2?}", duration);
+        match result {
+            Ok(()) => {
+                let _ = writeln!(log, "result: success");
+            }
+            Err(err) => {
+                let _ = writeln!(log, "result: failure\n\n{err}");
+            }
+        }
+        if let Ok(commands) = self.commands.lock() {
+            for command in commands.iter() {
+                let _ = writeln!(
+                    log,
+                    "\n$ {} {}",
+                    command.program,
+                    command.args.iter().join(" ")
+                );
+                if let Some(dir) = &command.current_dir {
+                    let _ = writeln!(log, "directory: {}", dir.display());
+                }
+                for (key, value) in &command.env {
+                    match value {
+                        Some(value) => {
+                            let _ = writeln!(log, "env: {key}={value}");
+                        }
+                        None => {
+                            let _ = writeln!(log, "env: unset {key}");
+                        }
+                    }
+                }
+                let _ = writeln!(log, "status: {}", command.status);
+                let _ = writeln!(log, "duration: {:.2?}", command.duration);
+                if !command.stdout.is_empty() {
+                    let _ = writeln!(log, "--- stdout ---\n{}", command.stdout.trim_end());
+                }
+                if !command.stderr.is_empty() {
+                    let _ = writeln!(log, "--- stderr ---\n{}", command.stderr.trim_end());
+                }
+            }
+        }
+        if !compile_commands.is_empty() {
+            let _ = writeln!(log, "\n# C sources compiled by lux");
+        }
+        for compile_command in compile_commands {
+            let _ = writeln!(
+                log,
+                "\n$ {}\ndirectory: {}",
+                compile_command
+                    .command
+                    .clone()
+                    .unwrap_or_else(|| compile_command.arguments.join(" ")),
+                compile_command.directory.display(),
+            );
+        }
+        log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        config::ConfigBuilder,
+        lockfile::{LocalPackageHashes, LockConstraint},
+        package::PackageSpec,
+        remote_package_source::RemotePackageSource,
+        rockspec::RockBinaries,
+    };
+
+    #[tokio::test]
+    async fn persist_and_find_build_log() {
+        let cache_dir = assert_fs::TempDir::new().unwrap();
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .cache_dir(Some(cache_dir.to_path_buf()))
+            .build()
+            .unwrap();
+        let package = LocalPackage::from(
+            &PackageSpec::parse("foo".into(), "1.0.0-1".into()).unwrap(),
+            LockConstraint::Unconstrained,
+            RockBinaries::default(),
+            RemotePackageSource::Test,
+            None,
+            LocalPackageHashes {
+                rockspec: "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+                    .parse()
+                    .unwrap(),
+                source: "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+                    .parse()
+                    .unwrap(),
+            },
+        );
+        let echo = which::which("echo").unwrap();
+        let mut cmd = Command::new(echo);
+        cmd.arg("hello").env("CFLAGS", "-O2");
+        let output = cmd.output().await.unwrap();
+        let build_log = BuildLog::default();
+        build_log.record(&cmd, &output, Duration::from_millis(5));
+        let path = build_log
+            .persist(
+                &package,
+                &[],
+                Err("make step failed".into()),
+                Duration::from_secs(1),
+                &config,
+            )
+            .await
+            .unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("result: failure"));
+        assert!(content.contains("make step failed"));
+        assert!(content.contains("echo hello"));
+        assert!(content.contains("env: CFLAGS=-O2"));
+        assert!(content.contains("--- stdout ---\nhello"));
+
+        let req = PackageReq::parse("foo@1.0.0").unwrap();
+        assert_eq!(latest_build_log(&req, &config).unwrap(), Some(path));
+        let req = PackageReq::parse("foo@2.0.0").unwrap();
+        assert_eq!(latest_build_log(&req, &config).unwrap(), None);
+    }
+}
@@ -11,6 +11,7 @@ use walkdir::{DirEntry, WalkDir};
 use crate::{
     build::{
         backend::{BuildBackend, BuildInfo, RunBuildArgs},
+        transpile::{transpile_modules, SourceLanguage, TranspileError, TranspiledModule},
         utils,
     },
     fs,
@@ -44,6 +45,9 @@ pub enum BuiltinBuildError {
     #[error("module auto-detection failed")]
     #[diagnostic(transparent)]
     AutoDetectModules(#[from] AutoDetectModulesError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Transpile(#[from] TranspileError),
 }
 
 impl BuildBackend for BuiltinBuildSpec {
@@ -62,25 +66,33 @@ impl BuildBackend for BuiltinBuildSpec {
         let build_dir = args.build_dir;
 
         // Detect all Lua modules
-        let modules = autodetect_modules(build_dir, source_paths(build_dir, &self.modules))?
-            .into_iter()
-            .chain(self.modules)
-            .collect::<HashMap<_, _>>();
+        let modules = with_autodetected_modules(build_dir, self.modules)?;
 
+        let mut compile_commands = Vec::new();
+        let mut transpiled_modules = Vec::new();
         for (destination_path, module_type) in modules.iter() {
             match module_type {
                 ModuleSpec::SourcePath(source) => {
-                    if source.extension().map(|ext| ext == "c").unwrap_or(false) {
+                    if let Some(language) = SourceLanguage::from_path(source) {
+                        transpiled_modules.push(TranspiledModule {
+                            module: destination_path,
+                            source,
+                            language,
+                        });
+                    } else if source.extension().map(|ext| ext == "c").unwrap_or(false) {
                         let absolute_source_paths = vec![build_dir.join(source)];
-                        utils::compile_c_files(
-                            &absolute_source_paths,
-                            destination_path,
-                            &output_paths.lib,
-                            lua,
-                            external_dependencies,
-                            config,
-                        )
-                        .await?;
+                        compile_commands.extend(
+                            utils::compile_c_files(
+                                &absolute_source_paths,
+                                build_dir,
+                                destination_path,
+                                &output_paths.lib,
+                                lua,
+                                external_dependencies,
+                                config,
+                            )
+                            .await?,
+                        );
                     } else {
                         let absolute_source_path = build_dir.join(source);
                         utils::copy_lua_to_module_path(
@@ -93,31 +105,38 @@ impl BuildBackend for BuiltinBuildSpec {
                 ModuleSpec::SourcePaths(files) => {
                     let absolute_source_paths =
                         files.iter().map(|file| build_dir.join(file)).collect();
-                    utils::compile_c_files(
-                        &absolute_source_paths,
-                        destination_path,
-                        &output_paths.lib,
-                        lua,
-                        external_dependencies,
-                        config,
-                    )
-                    .await?
+                    compile_commands.extend(
+                        utils::compile_c_files(
+                            &absolute_source_paths,
+                            build_dir,
+                            destination_path,
+                            &output_paths.lib,
+                            lua,
+                            external_dependencies,
+                            config,
+                        )
+                        .await?,
+                    );
                 }
                 ModuleSpec::ModulePaths(data) => {
-                    utils::compile_c_modules(
-                        data,
-                        build_dir,
-                        destination_path,
-                        &output_paths.lib,
-                        lua,
-                        external_dependencies,
-                        config,
-                    )
-                    .await?
+                    compile_commands.extend(
+                        utils::compile_c_modules(
+                            data,
+                            build_dir,
+                            destination_path,
+                            &output_paths.lib,
+                            lua,
+                            external_dependencies,
+                            config,
+                        )
+                        .await?,
+                    );
                 }
             }
         }
 
+        transpile_modules(&transpiled_modules, output_paths, tree, config, build_dir).await?;
+
         let mut binaries = Vec::new();
         for bin_script in autodetect_bin_scripts(build_dir) {
             if let Some(target) = bin_script.file_name() {
@@ -135,10 +154,43 @@ impl BuildBackend for BuiltinBuildSpec {
             }
         }
 
-        Ok(BuildInfo { binaries })
+        Ok(BuildInfo {
+            binaries,
+            compile_commands,
+        })
     }
 }
 
+/// The languages that transpile to Lua of the modules the builtin build backend
+/// builds from the sources in `build_dir`, including auto-detected modules.
+pub(crate) fn transpiled_languages(
+    build_dir: &Path,
+    modules: &HashMap<LuaModule, ModuleSpec>,
+) -> Result<Vec<SourceLanguage>, AutoDetectModulesError> {
+    Ok(with_autodetected_modules(build_dir, modules.clone())?
+        .into_values()
+        .filter_map(|spec| match spec {
+            ModuleSpec::SourcePath(source) => SourceLanguage::from_path(&source),
+            _ => None,
+        })
+        .unique()
+        .collect_vec())
+}
+
+/// Adds the modules that are auto-detected in `build_dir` to the declared modules.
+/// Declared modules take precedence.
+fn with_autodetected_modules(
+    build_dir: &Path,
+    modules: HashMap<LuaModule, ModuleSpec>,
+) -> Result<HashMap<LuaModule, ModuleSpec>, AutoDetectModulesError> {
+    Ok(
+        autodetect_modules(build_dir, source_paths(build_dir, &modules))?
+            .into_iter()
+            .chain(modules)
+            .collect(),
+    )
+}
+
 fn source_paths(build_dir: &Path, modules: &HashMap<LuaModule, ModuleSpec>) -> HashSet<PathBuf> {
     modules
         .values()
@@ -164,17 +216,18 @@ fn autodetect_modules(
     build_dir: &Path,
     exclude: HashSet<PathBuf>,
 ) -> Result<HashMap<LuaModule, ModuleSpec>, AutoDetectModulesError> {
-    WalkDir::new(build_dir.join("src"))
+    let detected: Vec<_> = WalkDir::new(build_dir.join("src"))
         .into_iter()
         .chain(WalkDir::new(build_dir.join("lua")))
         .chain(WalkDir::new(build_dir.join("lib")))
         .filter_map(|file| {
             file.ok().and_then(|file| {
-                let is_lua_file = PathBuf::from(file.file_name())
-                    .extension()
-                    .map(|ext| ext == "lua")
-                    .unwrap_or(false);
-                if is_lua_file && !exclude.contains(&file.clone().into_path()) {
+                let path = PathBuf::from(file.file_name());
+                let is_lua_file = path.extension().map(|ext| ext == "lua").unwrap_or(false);
+                let is_transpiled_file = SourceLanguage::from_path(&path).is_some();
+                if (is_lua_file || is_transpiled_file)
+                    && !exclude.contains(&file.clone().into_path())
+                {
                     Some(file)
                 } else {
                     None
@@ -194,7 +247,7 @@ fn autodetect_modules(
             // data in this form allows us to respect any overrides made by the user (which follow
             // the `module.name` format, not our internal one).
             let mut pathbuf = diff.components().skip(1).collect::<PathBuf>();
-            let lua_module = if pathbuf
+            let lua_module: Result<_, AutoDetectModulesError> = if pathbuf
                 .parent()
                 .is_none_or(|parent| parent.as_os_str().is_empty())
             {
@@ -204,7 +257,7 @@ fn autodetect_modules(
                 let mut lua_module = LuaModule::from_pathbuf(pathbuf)?;
                 // NOTE(mrcjkb): `LuaModule` does not parse as "<module>.init" from files named "init.lua"
                 // To make sure we don't change the file structure when installing, we append it here.
-                if file.file_name().to_string_lossy().as_bytes() == b"init.lua" {
+                if file.path().file_stem().is_some_and(|stem| stem == "init") {
                     unsafe {
                         lua_module =
                             lua_module.join(&LuaModule::from_str("init").unwrap_unchecked())
@@ -214,7 +267,16 @@ fn autodetect_modules(
             };
             lua_module.map(|lua_module| (lua_module, ModuleSpec::SourcePath(diff)))
         })
-        .try_collect()
+        .collect::<Result<_, _>>()?;
+    // Projects may check in the Lua code generated from their sources,
+    // in which case we install the Lua code as-is.
+    Ok(detected
+        .into_iter()
+        .sorted_by_key(|(_, spec)| match spec {
+            ModuleSpec::SourcePath(source) => SourceLanguage::from_path(source).is_none(),
+            _ => true,
+        })
+        .collect())
 }
 
 #[tracing::instrument(level = "trace")]
@@ -227,3 +289,56 @@ fn autodetect_bin_scripts(build_dir: &Path) -> Vec<PathBuf> {
         .map(DirEntry::into_path)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn autodetect_transpiled_modules() {
+        let build_dir = assert_fs::TempDir::new().unwrap();
+        build_dir.child("src/foo.tl").touch().unwrap();
+        build_dir.child("src/foo.d.tl").touch().unwrap();
+        build_dir.child("src/bar/init.fnl").touch().unwrap();
+        build_dir.child("src/bar/baz.tl").touch().unwrap();
+        build_dir.child("src/bar/baz.lua").touch().unwrap();
+        let modules = autodetect_modules(build_dir.path(), HashSet::default()).unwrap();
+        assert_eq!(
+            modules,
+            HashMap::from([
+                (
+                    LuaModule::from_str("foo").unwrap(),
+                    ModuleSpec::SourcePath("src/foo.tl".into())
+                ),
+                (
+                    LuaModule::from_str("bar.init").unwrap(),
+                    ModuleSpec::SourcePath("src/bar/init.fnl".into())
+                ),
+                (
+                    LuaModule::from_str("bar.baz").unwrap(),
+                    ModuleSpec::SourcePath("src/bar/baz.lua".into())
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn transpiled_languages_include_autodetected_modules() {
+        let build_dir = assert_fs::TempDir::new().unwrap();
+        build_dir.child("src/foo.tl").touch().unwrap();
+        build_dir.child("src/bar.fnl").touch().unwrap();
+        build_dir.child("src/bar.lua").touch().unwrap();
+        build_dir.child("other/baz.moon").touch().unwrap();
+        let modules = HashMap::from([(
+            LuaModule::from_str("baz").unwrap(),
+            ModuleSpec::SourcePath("other/baz.moon".into()),
+        )]);
+        let languages = transpiled_languages(build_dir.path(), &modules).unwrap();
+        assert_eq!(
+            languages.into_iter().collect::<HashSet<_>>(),
+            HashSet::from([SourceLanguage::Teal, SourceLanguage::MoonScript])
+        );
+    }
+}
@@ -3,6 +3,7 @@ use miette::Diagnostic;
 use std::{
     env, io,
     process::{ExitStatus, Stdio},
+    time::Instant,
 };
 use thiserror::Error;
 use tokio::process::Command;
@@ -11,7 +12,7 @@ use tracing::{info_span, Instrument};
 use crate::{
     build::{
         backend::{BuildBackend, BuildInfo, RunBuildArgs},
-        utils,
+        compile_commands, utils,
     },
     config::Config,
     fs,
@@ -96,6 +97,8 @@ impl BuildBackend for CMakeBuildSpec {
             // With msvc and x64, CMake does not select it by default so we need to be explicit.
             args.push("-DCMAKE_GENERATOR_PLATFORM=x64".into());
         }
+        // Exported for development tooling. Only supported by the Makefile and Ninja generators.
+        args.push("-DCMAKE_EXPORT_COMPILE_COMMANDS=ON".into());
         self.variables
             .into_iter()
             .map(|(key, value)| {
@@ -173,14 +176,25 @@ impl BuildBackend for CMakeBuildSpec {
             .await?;
         }
 
-        Ok(BuildInfo::default())
+        Ok(BuildInfo {
+            compile_commands: compile_commands::read_compile_commands(
+                &build_dir.join(CMAKE_BUILD_FILE),
+            )
+            .await,
+            ..BuildInfo::default()
+        })
     }
 }
 
 #[tracing::instrument(level = "trace", skip(config))]
 async fn spawn_cmake_cmd(cmd: &mut Command, config: &Config) -> Result<(), CMakeError> {
+    let started = Instant::now();
     match cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
-        Ok(child) => match child.wait_with_output().await {
+        Ok(child) => match child
+            .wait_with_output()
+            .await
+            .inspect(|output| config.log_command(cmd, output, started.elapsed()))
+        {
             Ok(output) if output.status.success() => utils::trace_command_output(&output),
             Ok(output) => {
                 return Err(CMakeError::CommandFailure {
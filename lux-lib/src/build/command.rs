@@ -4,6 +4,7 @@ use std::{
     io,
     path::Path,
     process::{ExitStatus, Stdio},
+    time::Instant,
 };
 use thiserror::Error;
 
@@ -135,16 +136,15 @@ async fn run_command(
     #[cfg(not(target_env = "msvc"))]
     let (shell, shell_arg) = (which("sh")?, "-c");
 
-    match config
-        .wrapped_command(shell, [shell_arg, &substituted_cmd])
-        .current_dir(build_dir)
+    let mut cmd = config.wrapped_command(shell, [shell_arg, &substituted_cmd]);
+    cmd.current_dir(build_dir)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .env("PATH", &bin_path)
         .env("LUA_PATH", &lua_path)
-        .env("LUA_CPATH", &lua_cpath)
-        .spawn()
-    {
+        .env("LUA_CPATH", &lua_cpath);
+    let started = Instant::now();
+    match cmd.spawn() {
         Err(err) => {
             return Err(CommandError::Io {
                 err,
@@ -158,6 +158,7 @@ async fn run_command(
                 command = substituted_cmd
             ))
             .await
+            .inspect(|output| config.log_command(&cmd, output, started.elapsed()))
         {
             Ok(output) if output.status.success() => utils::trace_command_output(&output),
             Ok(output) => {
@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs;
+
+/// The file name of a JSON compilation database.
+pub(crate) const COMPILE_COMMANDS_FILE_NAME: &str = "compile_commands.json";
+
+/// An entry of a JSON compilation database (`compile_commands.json`),
+/// which tells tools like `clangd` how a C source file is compiled.
+///
+/// See <https://clang.llvm.org/docs/JSONCompilationDatabase.html>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CompileCommand {
+    /// The working directory of the compilation.
+    pub directory: PathBuf,
+    /// The source file compiled by this command.
+    pub file: PathBuf,
+    /// The compile command as a list of arguments, starting with the compiler.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<String>,
+    /// The compile command as a single shell-escaped string.
+    /// Generated by some build systems, such as CMake, instead of `arguments`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// The output of the compilation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+}
+
+impl CompileCommand {
+    /// The compile commands for each source file of a `cc::Build`,
+    /// which must be fully configured (include directories, defines, flags).
+    pub(crate) fn from_cc_build(
+        build: &cc::Build,
+        files: &[PathBuf],
+        directory: &Path,
+    ) -> Result<Vec<Self>, cc::Error> {
+        let compiler = build.try_get_compiler()?;
+        let compile_flag = if compiler.is_like_msvc() { "/c" } else { "-c" };
+        let base_args = std::iter::once(compiler.path().to_string_lossy().to_string())
+            .chain(
+                compiler
+                    .args()
+                    .iter()
+                    .map(|arg| arg.to_string_lossy().to_string()),
+            )
+            .collect::<Vec<_>>();
+        Ok(files
+            .iter()
+            .map(|file| Self {
+                directory: directory.to_path_buf(),
+                file: file.clone(),
+                arguments: base_args
+                    .iter()
+                    .cloned()
+                    .chain([compile_flag.to_string(), file.to_string_lossy().to_string()])
+                    .collect(),
+                command: None,
+                output: None,
+            })
+            .collect())
+    }
+
+    /// Rewrites all paths under the `from` directory to point to the `to` directory.
+    /// Used to point compile commands from a temporary build directory
+    /// to the sources they were copied from.
+    pub(crate) fn relocate(self, from: &Path, to: &Path) -> Self {
+        let relocate_path = |path: PathBuf| match path.strip_prefix(from) {
+            Ok(relative) => to.join(relative),
+            Err(_) => path,
+        };
+        let from_str = from.to_string_lossy().to_string();
+        let to_str = to.to_string_lossy().to_string();
+        let relocate_str = |value: String| value.replace(&from_str, &to_str);
+        Self {
+            directory: relocate_path(self.directory),
+            file: relocate_path(self.file),
+            arguments: self.arguments.into_iter().map(relocate_str).collect(),
+            command: self.command.map(relocate_str),
+            output: self.output.map(relocate_path),
+        }
+    }
+}
+
+/// Reads the compile commands a build system generated in the `build_dir`.
+/// Returns an empty list if there are none or they cannot be read,
+/// as the compile commands are only an aid for development tooling.
+pub(crate) async fn read_compile_commands(build_dir: &Path) -> Vec<CompileCommand> {
+    let path = build_dir.join(COMPILE_COMMANDS_FILE_NAME);
+    if !path.is_file() {
+        return Vec::new();
+    }
+    match fs::tokio::read_to_string(&path).await {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(compile_commands) => compile_commands,
+            Err(err) => {
+                tracing::warn!("ignoring invalid {}: {err}", path.display());
+                Vec::new()
+            }
+        },
+        Err(err) => {
+            tracing::warn!("failed to read {}: {err}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocate_compile_command() {
+        let compile_command = CompileCommand {
+            directory: "/tmp/build".into(),
+            file: "/tmp/build/src/foo.c".into(),
+            arguments: vec![
+                "cc".into(),
+                "-I/tmp/build/include".into(),
+                "-I/usr/include/lua5.1".into(),
+                "-c".into(),
+                "/tmp/build/src/foo.c".into(),
+            ],
+            command: None,
+            output: Some("/tmp/build/foo.o".into()),
+        };
+        let relocated = compile_command.relocate(Path::new("/tmp/build"), Path::new("/project"));
+        assert_eq!(
+            relocated,
+            CompileCommand {
+                directory: "/project".into(),
+                file: "/project/src/foo.c".into(),
+                arguments: vec![
+                    "cc".into(),
+                    "-I/project/include".into(),
+                    "-I/usr/include/lua5.1".into(),
+                    "-c".into(),
+                    "/project/src/foo.c".into(),
+                ],
+                command: None,
+                output: Some("/project/foo.o".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_cmake_compile_commands() {
+        let content = r#"[
+  {
+    "directory": "/tmp/build/build.lux",
+    "command": "/usr/bin/cc -I/usr/include/lua5.1 -o foo.o -c /tmp/build/foo.c",
+    "file": "/tmp/build/foo.c",
+    "output": "foo.o"
+  }
+]"#;
+        let compile_commands: Vec<CompileCommand> = serde_json::from_str(content).unwrap();
+        assert_eq!(compile_commands.len(), 1);
+        let compile_command = compile_commands.first().unwrap();
+        assert!(compile_command.arguments.is_empty());
+        assert_eq!(compile_command.file, PathBuf::from("/tmp/build/foo.c"));
+        let serialized = serde_json::to_string(&compile_commands).unwrap();
+        assert!(!serialized.contains("arguments"));
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io,
+    path::Path,
+    process::{ExitStatus, Stdio},
+    time::Instant,
+};
+
+use bon::Builder;
+use miette::Diagnostic;
+use thiserror::Error;
+use tracing::{info_span, Instrument};
+use which::which;
+
+use crate::{
+    build::{external_dependency::ExternalDependencyInfo, utils},
+    config::Config,
+    lua_installation::LuaInstallation,
+    lua_rockspec::BuildHook,
+    path::{Paths, PathsError},
+    tree::{InstallTree, RockLayout, TreeError},
+    variables::VariableSubstitutionError,
+};
+
+/// The stage of the build at which a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    PreBuild,
+    PostBuild,
+}
+
+impl Display for HookStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PreBuild => write!(f, "pre_build"),
+            Self::PostBuild => write!(f, "post_build"),
+        }
+    }
+}
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum BuildHookError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Tree(#[from] TreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Paths(#[from] PathsError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    VariableSubstitution(#[from] VariableSubstitutionError),
+    #[error("cannot find a shell to execute the {stage} hook")]
+    ShellNotFound {
+        stage: HookStage,
+        #[source]
+        err: which::Error,
+    },
+    #[error("no Lua interpreter found to run the {stage} hook script {hook}")]
+    #[diagnostic(help("set the `LUA` variable to the path of a Lua interpreter"))]
+    LuaBinaryNotFound { stage: HookStage, hook: String },
+    #[error("error executing the {stage} hook:\n{hook}\n\nerror: {err}")]
+    Io {
+        stage: HookStage,
+        hook: String,
+        err: io::Error,
+    },
+    #[error(
+        "the {stage} hook failed:\n{hook}\n\nstatus: {status}\nstdout: {stdout}\nstderr: {stderr}"
+    )]
+    #[diagnostic(help("check the `build.hooks.{stage}` entries in the lux.toml"))]
+    Failure {
+        stage: HookStage,
+        hook: String,
+        status: ExitStatus,
+        stdout: String,
+        stderr: String,
+    },
+}
+
+/// Arguments for running build hooks.
+#[derive(Builder)]
+#[builder(start_fn(name = "new"))]
+pub(crate) struct RunHooksArgs<'a, T: InstallTree> {
+    pub(crate) output_paths: &'a RockLayout,
+    pub(crate) lua: &'a LuaInstallation,
+    pub(crate) external_dependencies: &'a HashMap<String, ExternalDependencyInfo>,
+    pub(crate) config: &'a Config,
+    pub(crate) tree: &'a T,
+    pub(crate) build_dir: &'a Path,
+}
+
+/// Runs the hooks of a build stage in order, stopping at the first failure.
+pub(crate) async fn run_hooks<T: InstallTree>(
+    hooks: &[BuildHook],
+    stage: HookStage,
+    args: &RunHooksArgs<'_, T>,
+) -> Result<(), BuildHookError> {
+    if hooks.is_empty() {
+        return Ok(());
+    }
+    // The project's dependencies and build dependencies are available to the hooks.
+    let mut paths = Paths::new(args.tree)?;
+    paths.prepend(&Paths::new(&args.tree.build_tree(args.config)?)?);
+    for hook in hooks {
+        run_hook(hook, stage, &paths, args).await?;
+    }
+    Ok(())
+}
+
+async fn run_hook<T: InstallTree>(
+    hook: &BuildHook,
+    stage: HookStage,
+    paths: &Paths,
+    args: &RunHooksArgs<'_, T>,
+) -> Result<(), BuildHookError> {
+    let config = args.config;
+    let substitute = |input: &str| {
+        utils::substitute_variables(
+            input,
+            args.output_paths,
+            args.lua,
+            args.external_dependencies,
+            config,
+        )
+    };
+    let (mut cmd, description) = match hook {
+        BuildHook::Script {
+            script,
+            args: script_args,
+        } => {
+            let description = script.display().to_string();
+            let lua = args
+                .lua
+                .lua_binary_or_config_override(config)
+                .ok_or_else(|| BuildHookError::LuaBinaryNotFound {
+                    stage,
+                    hook: description.clone(),
+                })?;
+            let script_args = script_args
+                .iter()
+                .map(|arg| substitute(arg))
+                .collect::<Result<Vec<_>, _>>()?;
+            let cmd = config.wrapped_command(
+                lua,
+                std::iter::once(script.to_string_lossy().to_string()).chain(script_args),
+            );
+            (cmd, description)
+        }
+        BuildHook::Command { command } => {
+            let command = substitute(command)?;
+            #[cfg(target_env = "msvc")]
+            let (shell, shell_arg) = (which("cmd.exe"), "/C");
+            #[cfg(not(target_env = "msvc"))]
+            let (shell, shell_arg) = (which("sh"), "-c");
+            let shell = shell.map_err(|err| BuildHookError::ShellNotFound { stage, err })?;
+            let cmd = config.wrapped_command(shell, [shell_arg, &command]);
+            (cmd, command)
+        }
+    };
+    let layout = args.output_paths;
+    cmd.current_dir(args.build_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .env("PATH", paths.path_prepended().joined())
+        .env("LUA_PATH", paths.package_path_prepended().joined())
+        .env("LUA_CPATH", paths.package_cpath_prepended().joined())
+        .env("PREFIX", &layout.rock_path)
+        .env("LUADIR", &layout.src)
+        .env("LIBDIR", &layout.lib)
+        .env("BINDIR", &layout.bin)
+        .env("CONFDIR", &layout.conf)
+        .env("DOCDIR", &layout.doc);
+    let started = Instant::now();
+    let output = cmd
+        .output()
+        .instrument(info_span!(
+            "Running build hook",
+            stage = stage.to_string(),
+            hook = description
+        ))
+        .await
+        .inspect(|output| config.log_command(&cmd, output, started.elapsed()))
+        .map_err(|err| BuildHookError::Io {
+            stage,
+            hook: description.clone(),
+            err,
+        })?;
+    if output.status.success() {
+        utils::trace_command_output(&output);
+        Ok(())
+    } else {
+        Err(BuildHookError::Failure {
+            stage,
+            hook: description,
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).into(),
+            stderr: String::from_utf8_lossy(&output.stderr).into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{config::ConfigBuilder, lua_version::LuaVersion};
+
+    #[tokio::test]
+    async fn run_command_hooks() {
+        if std::env::var("LUX_SKIP_IMPURE_TESTS").unwrap_or("0".into()) == "1" {
+            println!("Skipping impure test");
+            return;
+        }
+        let tree_dir = assert_fs::TempDir::new().unwrap();
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .lua_version(Some(LuaVersion::Lua51))
+            .user_tree(Some(tree_dir.to_path_buf()))
+            .build()
+            .unwrap();
+        let tree = config.user_tree(LuaVersion::Lua51).unwrap();
+        let lua = LuaInstallation::new(&LuaVersion::Lua51, &config)
+            .await
+            .unwrap();
+        let build_dir = assert_fs::TempDir::new().unwrap();
+        let dest_dir = assert_fs::TempDir::new().unwrap();
+        let output_paths = RockLayout {
+            rock_path: dest_dir.to_path_buf(),
+            etc: dest_dir.join("etc"),
+            lib: dest_dir.join("lib"),
+            src: dest_dir.join("src"),
+            bin: tree.bin(),
+            conf: dest_dir.join("conf"),
+            doc: dest_dir.join("doc"),
+        };
+        let external_dependencies = HashMap::default();
+        let args = RunHooksArgs::new()
+            .output_paths(&output_paths)
+            .lua(&lua)
+            .external_dependencies(&external_dependencies)
+            .config(&config)
+            .tree(&tree)
+            .build_dir(build_dir.path())
+            .build();
+        let hooks = vec![BuildHook::Command {
+            command: "echo $(LUADIR) > luadir.txt && echo $LUADIR >> luadir.txt".into(),
+        }];
+        run_hooks(&hooks, HookStage::PreBuild, &args).await.unwrap();
+        let luadir = output_paths.src.display().to_string();
+        assert_eq!(
+            std::fs::read_to_string(build_dir.join("luadir.txt")).unwrap(),
+            format!("{luadir}\n{luadir}\n")
+        );
+
+        let hooks = vec![BuildHook::Command {
+            command: "exit 1".into(),
+        }];
+        let err = run_hooks(&hooks, HookStage::PostBuild, &args)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BuildHookError::Failure {
+                stage: HookStage::PostBuild,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,399 @@
+//! Incremental rebuilds of workspace members.
+//!
+//! A member is only rebuilt if the fingerprint of its build inputs changed since
+//! its last successful build. When it is rebuilt, the builtin backend reuses the
+//! C modules whose sources and flags did not change.
+
+use std::{
+    collections::HashSet,
+    env, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use ssri::{Algorithm, Integrity, IntegrityOpts};
+use walkdir::WalkDir;
+
+use crate::{
+    build::{
+        compile_commands::{CompileCommand, COMPILE_COMMANDS_FILE_NAME},
+        reproducible,
+    },
+    config::Config,
+    fs,
+    lockfile::LocalPackageId,
+    lua_version::LuaVersion,
+    package::PackageName,
+    project::Project,
+    tree::{InstallTree, Tree},
+};
+
+/// The directory in a tree's root in which incremental build state is stored.
+const INCREMENTAL_DIR: &str = "incremental";
+const BUILD_STAMP_FILE: &str = "build-stamp.json";
+const C_MODULES_DIR: &str = "c-modules";
+
+/// Environment variables that affect the outcome of a build.
+const FINGERPRINT_ENV_VARS: &[&str] = &[
+    "CC",
+    "CXX",
+    "AR",
+    "CFLAGS",
+    "CXXFLAGS",
+    "CPPFLAGS",
+    "LDFLAGS",
+    "LIBFLAG",
+    "RUSTFLAGS",
+    "PKG_CONFIG_PATH",
+    reproducible::SOURCE_DATE_EPOCH,
+];
+
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx"];
+
+/// The directory in which the incremental build state of a workspace member is stored.
+pub(crate) fn incremental_dir(tree: &Tree, package: &PackageName) -> PathBuf {
+    tree.root().join(INCREMENTAL_DIR).join(package.to_string())
+}
+
+/// Fingerprints the inputs of a workspace member's build:
+/// its project files (including the `lux.toml`), the IDs of the resolved dependencies,
+/// the enabled features, the Lua version, the build profile and target,
+/// the config variables and the environment variables that affect the build.
+pub(crate) fn project_fingerprint<'a>(
+    project: &Project,
+    dependencies: impl IntoIterator<Item = &'a LocalPackageId>,
+    features: &[String],
+    lua_version: &LuaVersion,
+    config: &Config,
+) -> io::Result<Integrity> {
+    let mut hasher = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+    hash_str(&mut hasher, env!("CARGO_PKG_VERSION"));
+    let root = project.root().to_path_buf();
+    // The compilation database is generated after building, so it is not a build input.
+    for file in project
+        .project_files()
+        .into_iter()
+        .filter(|file| {
+            file.file_name()
+                .is_none_or(|name| name != COMPILE_COMMANDS_FILE_NAME)
+        })
+        .sorted()
+    {
+        hash_str(
+            &mut hasher,
+            &file.strip_prefix(&root).unwrap_or(&file).to_string_lossy(),
+        );
+        hasher.input(std::fs::read(&file)?);
+    }
+    for dependency in dependencies.into_iter().sorted() {
+        hash_str(&mut hasher, &dependency.to_string());
+    }
+    for feature in features.iter().sorted() {
+        hash_str(&mut hasher, feature);
+    }
+    hash_str(&mut hasher, &lua_version.to_string());
+    hash_str(&mut hasher, &config.build_profile().to_string());
+    hash_str(&mut hasher, &config.target().to_string());
+    for (name, value) in config.variables().iter().sorted() {
+        hash_str(&mut hasher, name);
+        hash_str(&mut hasher, value);
+    }
+    hash_env(&mut hasher);
+    Ok(hasher.result())
+}
+
+/// The record of a workspace member's last successful build.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BuildStamp {
+    pub fingerprint: String,
+    pub package: LocalPackageId,
+    pub compile_commands: Vec<CompileCommand>,
+}
+
+impl BuildStamp {
+    /// Loads the build stamp from the incremental build directory.
+    /// Returns `None` if there is none, or if it can't be parsed.
+    pub(crate) async fn load(dir: &Path) -> Option<Self> {
+        let content = tokio::fs::read_to_string(dir.join(BUILD_STAMP_FILE))
+            .await
+            .ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub(crate) async fn save(&self, dir: &Path) -> io::Result<()> {
+        tokio::fs::create_dir_all(dir).await?;
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(dir.join(BUILD_STAMP_FILE), content).await
+    }
+}
+
+/// A cache of compiled C modules, keyed by the hash of their sources and flags.
+/// Cheap to clone, so that it can be passed to the builtin backend via the [`Config`].
+#[derive(Debug, Clone)]
+pub(crate) struct CModuleCache {
+    dir: PathBuf,
+    used: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl CModuleCache {
+    pub(crate) fn new(incremental_dir: &Path) -> Self {
+        Self {
+            dir: incremental_dir.join(C_MODULES_DIR),
+            used: Arc::default(),
+        }
+    }
+
+    /// Computes the cache key of a C module, from the contents of its sources,
+    /// the headers in the source directory, its compile commands and its link arguments.
+    pub(crate) fn key(
+        compile_commands: &[CompileCommand],
+        source_dir: &Path,
+        link_args: &[String],
+    ) -> io::Result<String> {
+        let source_dir_str = source_dir.to_string_lossy();
+        let mut hasher = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        for compile_command in compile_commands {
+            // Sorted, as the include directories of external dependencies are in no particular order.
+            for arg in compile_command.arguments.iter().sorted() {
+                hash_str(&mut hasher, &arg.replace(source_dir_str.as_ref(), "."));
+            }
+            hasher.input(std::fs::read(&compile_command.file)?);
+        }
+        let headers = WalkDir::new(source_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_ok(|entry| {
+                entry.file_type().is_file()
+                    && entry
+                        .path()
+                        .extension()
+                        .is_some_and(|ext| HEADER_EXTENSIONS.iter().any(|header| ext == *header))
+            })
+            .map_ok(|entry| entry.into_path())
+            .try_collect::<_, Vec<_>, _>()
+            .map_err(io::Error::from)?;
+        for header in headers {
+            hash_str(
+                &mut hasher,
+                &header
+                    .strip_prefix(source_dir)
+                    .unwrap_or(&header)
+                    .to_string_lossy(),
+            );
+            hasher.input(std::fs::read(&header)?);
+        }
+        for arg in link_args.iter().sorted() {
+            hash_str(&mut hasher, &arg.replace(source_dir_str.as_ref(), "."));
+        }
+        hash_env(&mut hasher);
+        Ok(hasher.result().to_hex().1)
+    }
+
+    /// Copies the cached module to `target`, if present.
+    /// Returns whether the module was restored from the cache.
+    pub(crate) async fn restore(&self, key: &str, target: &Path) -> Result<bool, fs::FsError> {
+        let cached = self.cached_path(key, target);
+        if !cached.is_file() {
+            return Ok(false);
+        }
+        fs::tokio::copy(&cached, target).await?;
+        self.mark_used(cached);
+        Ok(true)
+    }
+
+    /// Stores a freshly compiled module in the cache.
+    pub(crate) async fn store(&self, key: &str, target: &Path) -> Result<(), fs::FsError> {
+        let cached = self.cached_path(key, target);
+        fs::tokio::create_dir_all(&self.dir).await?;
+        fs::tokio::copy(target, &cached).await?;
+        self.mark_used(cached);
+        Ok(())
+    }
+
+    /// Removes the cached modules that were not used by the last build.
+    pub(crate) async fn prune(&self) -> Result<(), fs::FsError> {
+        if !self.dir.is_dir() {
+            return Ok(());
+        }
+        let used = self
+            .used
+            .lock()
+            .map(|used| used.clone())
+            .unwrap_or_default();
+        for entry in fs::sync::read_dir(&self.dir)?.flatten() {
+            let path = entry.path();
+            if !used.contains(&path) {
+                fs::tokio::remove_file(&path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn cached_path(&self, key: &str, target: &Path) -> PathBuf {
+        match target.extension() {
+            Some(extension) => self
+                .dir
+                .join(format!("{key}.{}", extension.to_string_lossy())),
+            None => self.dir.join(key),
+        }
+    }
+
+    fn mark_used(&self, cached: PathBuf) {
+        if let Ok(mut used) = self.used.lock() {
+            used.insert(cached);
+        }
+    }
+}
+
+/// Hashes a string, followed by a separator, so that
+/// consecutive inputs can't be shifted into one another.
+fn hash_str(hasher: &mut IntegrityOpts, value: &str) {
+    hasher.input(value);
+    hasher.input([0]);
+}
+
+fn hash_env(hasher: &mut IntegrityOpts) {
+    for name in FINGERPRINT_ENV_VARS {
+        hash_str(hasher, name);
+        hash_str(hasher, &env::var(name).unwrap_or_default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+
+    use crate::config::ConfigBuilder;
+
+    #[test]
+    fn fingerprint_changes_with_inputs() {
+        let project_root = assert_fs::TempDir::new().unwrap();
+        project_root
+            .child("lux.toml")
+            .write_str(
+                r#"
+package = "foo"
+version = "1.0.0"
+lua = "5.1"
+
+[build]
+type = "builtin"
+"#,
+            )
+            .unwrap();
+        project_root
+            .child("src/foo.lua")
+            .write_str("return 1")
+            .unwrap();
+        let project = Project::from_exact(project_root.path()).unwrap().unwrap();
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .lua_version(Some(LuaVersion::Lua51))
+            .build()
+            .unwrap();
+        let dependency = LocalPackageId::new(
+            &"bar".into(),
+            &"1.0.0".parse().unwrap(),
+            crate::lockfile::PinnedState::Unpinned,
+            crate::lockfile::OptState::Required,
+            crate::lockfile::LockConstraint::Unconstrained,
+            &[],
+        );
+        let fingerprint = |dependencies: &[LocalPackageId], features: &[String]| {
+            project_fingerprint(
+                &project,
+                dependencies,
+                features,
+                &LuaVersion::Lua51,
+                &config,
+            )
+            .unwrap()
+        };
+        let original = fingerprint(&[], &[]);
+        assert_eq!(original, fingerprint(&[], &[]));
+        assert_ne!(original, fingerprint(&[dependency], &[]));
+        assert_ne!(original, fingerprint(&[], &["extra".into()]));
+        project_root
+            .child("compile_commands.json")
+            .write_str("[]")
+            .unwrap();
+        assert_eq!(original, fingerprint(&[], &[]));
+        project_root
+            .child("src/foo.lua")
+            .write_str("return 2")
+            .unwrap();
+        assert_ne!(original, fingerprint(&[], &[]));
+    }
+
+    #[tokio::test]
+    async fn c_module_cache() {
+        let source_dir = assert_fs::TempDir::new().unwrap();
+        let incremental_dir = assert_fs::TempDir::new().unwrap();
+        let target_dir = assert_fs::TempDir::new().unwrap();
+        source_dir
+            .child("foo.c")
+            .write_str("int foo() { return 1; }")
+            .unwrap();
+        source_dir
+            .child("include/foo.h")
+            .write_str("int foo();")
+            .unwrap();
+        let compile_command = |flag: &str| CompileCommand {
+            directory: source_dir.to_path_buf(),
+            file: source_dir.join("foo.c"),
+            arguments: vec![
+                "cc".into(),
+                flag.into(),
+                format!("-I{}", source_dir.join("include").display()),
+                source_dir.join("foo.c").to_string_lossy().to_string(),
+            ],
+            command: None,
+            output: None,
+        };
+        let key = CModuleCache::key(&[compile_command("-O2")], source_dir.path(), &[]).unwrap();
+        assert_eq!(
+            key,
+            CModuleCache::key(&[compile_command("-O2")], source_dir.path(), &[]).unwrap()
+        );
+        assert_ne!(
+            key,
+            CModuleCache::key(&[compile_command("-O0")], source_dir.path(), &[]).unwrap()
+        );
+        assert_ne!(
+            key,
+            CModuleCache::key(
+                &[compile_command("-O2")],
+                source_dir.path(),
+                &["-lfoo".into()]
+            )
+            .unwrap()
+        );
+        source_dir
+            .child("include/foo.h")
+            .write_str("int foo(void);")
+            .unwrap();
+        let header_key =
+            CModuleCache::key(&[compile_command("-O2")], source_dir.path(), &[]).unwrap();
+        assert_ne!(key, header_key);
+
+        let cache = CModuleCache::new(incremental_dir.path());
+        let target = target_dir.join("foo.so");
+        assert!(!cache.restore(&key, &target).await.unwrap());
+        std::fs::write(&target, "compiled").unwrap();
+        cache.store(&key, &target).await.unwrap();
+        std::fs::remove_file(&target).unwrap();
+        assert!(cache.restore(&key, &target).await.unwrap());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "compiled");
+
+        let cache = CModuleCache::new(incremental_dir.path());
+        std::fs::write(&target, "recompiled").unwrap();
+        cache.store(&header_key, &target).await.unwrap();
+        cache.prune().await.unwrap();
+        assert!(!cache.restore(&key, &target).await.unwrap());
+        assert!(cache.restore(&header_key, &target).await.unwrap());
+    }
+}
@@ -5,6 +5,7 @@ use std::{
     io,
     path::PathBuf,
     process::{ExitStatus, Stdio},
+    time::Instant,
 };
 use thiserror::Error;
 
@@ -105,11 +106,13 @@ impl BuildBackend for MakeBuildSpec {
                 .env("PATH", &bin_path)
                 .env("LUA_PATH", &lua_path)
                 .env("LUA_CPATH", &lua_cpath);
+            let started = Instant::now();
             match cmd.spawn() {
                 Ok(child) => match child
                     .wait_with_output()
                     .instrument(info_span!("Make build pass"))
                     .await
+                    .inspect(|output| config.log_command(&cmd, output, started.elapsed()))
                 {
                     Ok(output) if output.status.success() => utils::trace_command_output(&output),
                     Ok(output) => {
@@ -155,15 +158,17 @@ impl BuildBackend for MakeBuildSpec {
             install_cmd_args.push("-f".into());
             install_cmd_args.push(self.makefile.to_slash_lossy().into());
             install_cmd_args.extend(install_args);
-            match config
-                .wrapped_command(config.make_cmd(), install_cmd_args)
-                .current_dir(build_dir)
+            let mut cmd = config.wrapped_command(config.make_cmd(), install_cmd_args);
+            cmd.current_dir(build_dir)
                 .env("PATH", &bin_path)
                 .env("LUA_PATH", &lua_path)
-                .env("LUA_CPATH", &lua_cpath)
+                .env("LUA_CPATH", &lua_cpath);
+            let started = Instant::now();
+            match cmd
                 .output()
                 .instrument(info_span!("Make install pass"))
                 .await
+                .inspect(|output| config.log_command(&cmd, output, started.elapsed()))
             {
                 Ok(output) if output.status.success() => utils::trace_command_output(&output),
                 Ok(output) => {
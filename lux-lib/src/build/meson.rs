@@ -0,0 +1,359 @@
+use itertools::Itertools;
+use miette::Diagnostic;
+use path_slash::PathExt;
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    time::Instant,
+};
+use thiserror::Error;
+use tokio::process::Command;
+use tracing::{info_span, Instrument};
+
+use crate::{
+    build::{
+        backend::{BuildBackend, BuildInfo, RunBuildArgs},
+        compile_commands,
+        external_dependency::ExternalDependencyInfo,
+        utils::{self, InstallBinaryError},
+    },
+    config::{build::Profile, Config},
+    fs,
+    lua_installation::LuaInstallation,
+    lua_rockspec::MesonBuildSpec,
+    path::{Paths, PathsError},
+    tree::{InstallTree, RockLayout, TreeError},
+    variables::VariableSubstitutionError,
+};
+
+const MESON_BUILD_DIR: &str = "build.lux";
+
+/// The native file with the properties that Lux provides to `meson.build`.
+const MESON_MACHINE_FILE: &str = "lux-machine.ini";
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum MesonError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Tree(#[from] TreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Paths(#[from] PathsError),
+    #[error("{name} step failed.\n\n{status}\n\nstdout:\n{stdout}\n\nstderr:\n{stderr}")]
+    CommandFailure {
+        name: String,
+        status: ExitStatus,
+        stdout: String,
+        stderr: String,
+    },
+    #[error("failed to run `meson` step")]
+    Io { source: io::Error },
+    #[error("failed to run `meson` step: '{0}' command not found!")]
+    #[diagnostic(help("run `lx debug toolchains` to check available build tools."))]
+    CommandNotFound(String),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    VariableSubstitution(#[from] VariableSubstitutionError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("failed to determine the arguments for linking Lua")]
+    #[diagnostic(help("run `lx debug toolchains` to check available build tools."))]
+    LuaLinkArgs(#[from] cc::Error),
+    #[error("failed to install binary {file_name}")]
+    InstallBinary {
+        file_name: String,
+        #[source]
+        source: InstallBinaryError,
+    },
+}
+
+impl BuildBackend for MesonBuildSpec {
+    type Err = MesonError;
+
+    #[tracing::instrument(name = "meson::run", skip_all, level = "debug")]
+    async fn run<T>(self, args: RunBuildArgs<'_, T>) -> Result<BuildInfo, Self::Err>
+    where
+        T: InstallTree,
+    {
+        let output_paths = args.output_paths;
+        let no_install = args.no_install;
+        let lua = args.lua;
+        let external_dependencies = args.external_dependencies;
+        let config = args.config;
+        let build_dir = args.build_dir;
+
+        let build_tree = args.tree.build_tree(config)?;
+        let build_paths = Paths::new(&build_tree)?;
+        let lua_path = build_paths.package_path_prepended().joined();
+        let lua_cpath = build_paths.package_cpath_prepended().joined();
+        let bin_path = build_paths.path_prepended().joined();
+
+        let mut setup_args = vec!["setup".into(), MESON_BUILD_DIR.into()];
+        let (c_args, mut c_link_args) = compiler_args(lua, external_dependencies);
+        c_link_args.extend(utils::lua_link_args(lua, config)?);
+        let machine_file = build_dir.join(MESON_MACHINE_FILE);
+        fs::tokio::write(&machine_file, machine_file_content(output_paths)).await?;
+        setup_args.extend(builtin_options(
+            output_paths,
+            &machine_file,
+            config.build_profile(),
+            &c_args,
+            &c_link_args,
+        ));
+        self.variables
+            .into_iter()
+            .sorted()
+            .map(|(key, value)| {
+                let substituted_value = utils::substitute_variables(
+                    &value,
+                    output_paths,
+                    lua,
+                    external_dependencies,
+                    config,
+                )?;
+                Ok::<_, Self::Err>(format!("-D{key}={substituted_value}"))
+            })
+            .fold_ok((), |(), option| setup_args.push(option))?;
+
+        let meson_cmd = |args: Vec<String>| {
+            let mut cmd = config.wrapped_command(config.meson_cmd(), args);
+            cmd.current_dir(build_dir)
+                .env("PATH", &bin_path)
+                .env("LUA_PATH", &lua_path)
+                .env("LUA_CPATH", &lua_cpath);
+            cmd
+        };
+
+        spawn_meson_cmd(&mut meson_cmd(setup_args), config)
+            .instrument(info_span!("Meson setup"))
+            .await?;
+
+        if self.build_pass {
+            spawn_meson_cmd(
+                &mut meson_cmd(vec!["compile".into(), "-C".into(), MESON_BUILD_DIR.into()]),
+                config,
+            )
+            .instrument(info_span!("Meson build pass"))
+            .await?;
+        }
+
+        let mut binaries = Vec::new();
+        if self.install_pass && !no_install {
+            spawn_meson_cmd(
+                &mut meson_cmd(vec!["install".into(), "-C".into(), MESON_BUILD_DIR.into()]),
+                config,
+            )
+            .instrument(info_span!("Meson install pass"))
+            .await?;
+
+            // Meson installs executables into the prefix, so we move them to the tree's bin directory.
+            let meson_bin_dir = meson_bin_dir(output_paths);
+            if meson_bin_dir.is_dir() {
+                for entry in fs::sync::read_dir(&meson_bin_dir)?
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .sorted_by_key(|entry| entry.file_name())
+                {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    let installed_bin = utils::install_binary(
+                        &entry.path(),
+                        &file_name,
+                        args.tree,
+                        lua,
+                        args.deploy,
+                        config,
+                    )
+                    .await
+                    .map_err(|err| MesonError::InstallBinary {
+                        file_name: file_name.clone(),
+                        source: err,
+                    })?;
+                    if let Some(installed_bin_file_name) = installed_bin.file_name() {
+                        binaries.push(installed_bin_file_name.into());
+                    }
+                }
+                fs::tokio::remove_dir_all(&meson_bin_dir).await?;
+            }
+        }
+
+        // Meson always generates compile commands in its build directory.
+        Ok(BuildInfo {
+            binaries,
+            compile_commands: compile_commands::read_compile_commands(
+                &build_dir.join(MESON_BUILD_DIR),
+            )
+            .await,
+        })
+    }
+}
+
+/// The directory inside the prefix that Meson installs executables into.
+/// Meson requires the installation directories to be inside the prefix,
+/// so we can't install them into the tree's bin directory directly.
+fn meson_bin_dir(output_paths: &RockLayout) -> PathBuf {
+    output_paths.rock_path.join("bin")
+}
+
+/// A native file that provides the rock layout's Lua module directory to `meson.build`
+/// as `meson.get_external_property('luadir')`.
+/// We can't pass it as a `-D` option, because Meson rejects options a project doesn't declare.
+fn machine_file_content(output_paths: &RockLayout) -> String {
+    format!(
+        "[properties]\nluadir = {}\n",
+        meson_string(&output_paths.src.to_slash_lossy())
+    )
+}
+
+/// Meson's built-in options, which install the package into the rock layout
+/// and pass the given machine file and compiler and linker arguments.
+fn builtin_options(
+    output_paths: &RockLayout,
+    machine_file: &Path,
+    profile: Profile,
+    c_args: &[String],
+    c_link_args: &[String],
+) -> Vec<String> {
+    let buildtype = match profile {
+        Profile::Release => "release",
+        Profile::Dev => "debug",
+    };
+    vec![
+        format!("--prefix={}", output_paths.rock_path.to_slash_lossy()),
+        format!("--libdir={}", output_paths.lib.to_slash_lossy()),
+        format!("--bindir={}", meson_bin_dir(output_paths).to_slash_lossy()),
+        // Data files go with the rock's other files in `etc`,
+        // which is packed into the root of binary rocks, like luarocks does.
+        // Meson's default `share` directory would not be packed.
+        format!("--datadir={}", output_paths.etc.to_slash_lossy()),
+        format!("--sysconfdir={}", output_paths.conf.to_slash_lossy()),
+        format!("--native-file={}", machine_file.to_slash_lossy()),
+        format!("--buildtype={buildtype}"),
+        format!("-Dc_args={}", meson_array(c_args)),
+        format!("-Dc_link_args={}", meson_array(c_link_args)),
+    ]
+}
+
+/// The compiler and linker arguments that make the Lua headers
+/// and external dependencies available to the build.
+fn compiler_args(
+    lua: &LuaInstallation,
+    external_dependencies: &HashMap<String, ExternalDependencyInfo>,
+) -> (Vec<String>, Vec<String>) {
+    let external_dependencies = external_dependencies
+        .iter()
+        .sorted_by_key(|(name, _)| *name)
+        .map(|(_, info)| info)
+        .collect_vec();
+    let c_args = lua
+        .includes()
+        .into_iter()
+        .chain(
+            external_dependencies
+                .iter()
+                .filter_map(|info| info.include_dir.as_ref()),
+        )
+        .map(|dir| format!("-I{}", dir.to_slash_lossy()))
+        .chain(lua.define_flags())
+        .chain(
+            external_dependencies
+                .iter()
+                .flat_map(|info| info.define_flags()),
+        )
+        .collect_vec();
+    let c_link_args = external_dependencies
+        .iter()
+        .filter_map(|info| info.lib_dir.as_ref())
+        .map(|dir| format!("-L{}", dir.to_slash_lossy()))
+        .collect_vec();
+    (c_args, c_link_args)
+}
+
+/// Formats a list of strings as a Meson array option value.
+fn meson_array(values: &[String]) -> String {
+    let values = values.iter().map(|value| meson_string(value)).join(", ");
+    format!("[{values}]")
+}
+
+fn meson_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[tracing::instrument(level = "trace", skip(config))]
+async fn spawn_meson_cmd(cmd: &mut Command, config: &Config) -> Result<(), MesonError> {
+    let started = Instant::now();
+    match cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => match child
+            .wait_with_output()
+            .await
+            .inspect(|output| config.log_command(cmd, output, started.elapsed()))
+        {
+            Ok(output) if output.status.success() => utils::trace_command_output(&output),
+            Ok(output) => {
+                return Err(MesonError::CommandFailure {
+                    name: config.meson_cmd(),
+                    status: output.status,
+                    stdout: String::from_utf8_lossy(&output.stdout).into(),
+                    stderr: String::from_utf8_lossy(&output.stderr).into(),
+                });
+            }
+            Err(source) => return Err(MesonError::Io { source }),
+        },
+        Err(_) => return Err(MesonError::CommandNotFound(config.meson_cmd())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_meson_array() {
+        assert_eq!(meson_array(&[]), "[]");
+        assert_eq!(
+            meson_array(&["-I/usr/include/lua5.1".into(), "-DFOO='bar'".into()]),
+            r"['-I/usr/include/lua5.1', '-DFOO=\'bar\'']"
+        );
+    }
+
+    #[test]
+    fn builtin_options_use_rock_layout_and_profile() {
+        let output_paths = RockLayout {
+            rock_path: "/tree/foo".into(),
+            etc: "/tree/foo/etc".into(),
+            lib: "/tree/foo/lib".into(),
+            src: "/tree/foo/src".into(),
+            bin: "/tree/bin".into(),
+            conf: "/tree/foo/etc/conf".into(),
+            doc: "/tree/foo/etc/doc".into(),
+        };
+        let options = builtin_options(
+            &output_paths,
+            Path::new("/build/lux-machine.ini"),
+            Profile::Dev,
+            &["-I/usr/include/lua5.1".into()],
+            &["-L/opt/foo/lib".into()],
+        );
+        assert_eq!(
+            options,
+            vec![
+                "--prefix=/tree/foo".to_string(),
+                "--libdir=/tree/foo/lib".into(),
+                "--bindir=/tree/foo/bin".into(),
+                "--datadir=/tree/foo/etc".into(),
+                "--sysconfdir=/tree/foo/etc/conf".into(),
+                "--native-file=/build/lux-machine.ini".into(),
+                "--buildtype=debug".into(),
+                "-Dc_args=['-I/usr/include/lua5.1']".into(),
+                "-Dc_link_args=['-L/opt/foo/lib']".into(),
+            ]
+        );
+        assert_eq!(
+            machine_file_content(&output_paths),
+            "[properties]\nluadir = '/tree/foo/src'\n"
+        );
+    }
+}
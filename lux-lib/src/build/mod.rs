@@ -11,6 +11,7 @@ use std::collections::HashMap;
 use std::fs::DirEntry;
 use std::io::Cursor;
 use std::path::PathBuf;
+use std::time::Instant;
 use std::{io, path::Path};
 use tracing::Instrument;
 
@@ -21,37 +22,52 @@ use crate::{
     lua_installation::LuaInstallation,
     lua_rockspec::BuildBackendSpec,
     operations::{self, FetchSrcError},
-    package::PackageSpec,
+    package::{PackageName, PackageSpec},
     remote_package_source::RemotePackageSource,
     tree::RockLayout,
 };
+use autotools::AutotoolsError;
 use bon::Builder;
 use builtin::BuiltinBuildError;
 use cmake::CMakeError;
 use command::CommandError;
+use compile_commands::CompileCommand;
 use external_dependency::{ExternalDependencyError, ExternalDependencyInfo};
+use hooks::{run_hooks, BuildHookError, HookStage, RunHooksArgs};
+use meson::MesonError;
 
 use itertools::Itertools;
 use luarocks::LuarocksBuildError;
 use make::MakeError;
 
+use build_log::BuildLog;
 use miette::Diagnostic;
 use patch::{Patch, PatchError};
 use rust_mlua::RustError;
+use sandbox::{Sandbox, SandboxDenial, SandboxError};
 use source::SourceBuildError;
 use ssri::Integrity;
 use thiserror::Error;
 use treesitter_parser::TreesitterBuildError;
 use utils::{recursive_copy_dir, CompileCFilesError, InstallBinaryError};
 
+mod autotools;
+pub mod build_log;
 mod builtin;
 mod cmake;
 mod command;
+pub(crate) mod compile_commands;
+pub mod hooks;
+pub(crate) mod incremental;
 mod luarocks;
 mod make;
+mod meson;
 mod patch;
+pub mod reproducible;
 mod rust_mlua;
+pub(crate) mod sandbox;
 mod source;
+pub mod transpile;
 mod treesitter_parser;
 
 pub(crate) mod backend;
@@ -78,6 +94,10 @@ pub struct Build<'a, R: Rockspec + HasIntegrity, T: InstallTree> {
     constraint: LockConstraint,
     #[builder(default)]
     behaviour: BuildBehaviour,
+    /// Optional features to enable, which are recorded in the lockfile
+    /// and can be substituted in the build as `$(FEATURES)` and `$(FEATURE_<NAME>)`.
+    #[builder(default)]
+    features: Vec<String>,
 
     #[builder(setters(vis = "pub(crate)"))]
     source_spec: Option<RemotePackageSourceSpec>,
@@ -106,6 +126,16 @@ where
     State: build_builder::State + build_builder::IsComplete,
 {
     pub async fn build(self) -> Result<LocalPackage, BuildError> {
+        let (package, _) = self.build_with_compile_commands().await?;
+        Ok(package)
+    }
+
+    /// Build the package, also returning the compile commands for its C sources.
+    /// For packages built from a local directory, the compile commands
+    /// point to the sources in that directory.
+    pub(crate) async fn build_with_compile_commands(
+        self,
+    ) -> Result<(LocalPackage, Vec<CompileCommand>), BuildError> {
         let build = self._build();
         let span = tracing::info_span!(
             "Building",
@@ -128,6 +158,12 @@ pub enum BuildError {
     #[error("make build failed")]
     #[diagnostic(forward(0))]
     Make(#[from] MakeError),
+    #[error("meson build failed")]
+    #[diagnostic(forward(0))]
+    Meson(#[from] MesonError),
+    #[error("autotools build failed")]
+    #[diagnostic(forward(0))]
+    Autotools(#[from] AutotoolsError),
     #[error("command build failed")]
     #[diagnostic(forward(0))]
     Command(#[from] CommandError),
@@ -197,6 +233,30 @@ check the source, then rerun the command with `--no-lock` to update the hash."#
     #[error(transparent)]
     #[diagnostic(transparent)]
     LuaInstallation(#[from] LuaInstallationError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Sandbox(#[from] SandboxError),
+    #[error("build hook failed")]
+    #[diagnostic(forward(0))]
+    Hook(#[from] BuildHookError),
+    #[error("sandboxed build of '{package}' failed: {denial}")]
+    SandboxDenied {
+        package: PackageName,
+        denial: SandboxDenial,
+        #[help]
+        help: String,
+        #[source]
+        source: Box<BuildError>,
+    },
+    #[error("failed to build '{package}', the build log was written to {}", log.display())]
+    Logged {
+        package: PackageName,
+        log: PathBuf,
+        #[help]
+        help: String,
+        #[source]
+        source: Box<BuildError>,
+    },
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
@@ -214,10 +274,17 @@ async fn run_build<R: Rockspec + HasIntegrity, T: InstallTree + Sync>(
     args: RunBuildArgs<'_, T>,
 ) -> Result<BuildInfo, BuildError> {
     Ok(
-        match rockspec.build().current_platform().build_backend.to_owned() {
+        match rockspec
+            .build()
+            .for_target(&args.config.target())
+            .build_backend
+            .to_owned()
+        {
             Some(BuildBackendSpec::Builtin(build_spec)) => build_spec.run(args).await?,
             Some(BuildBackendSpec::Make(make_spec)) => make_spec.run(args).await?,
             Some(BuildBackendSpec::CMake(cmake_spec)) => cmake_spec.run(args).await?,
+            Some(BuildBackendSpec::Meson(meson_spec)) => meson_spec.run(args).await?,
+            Some(BuildBackendSpec::Autotools(autotools_spec)) => autotools_spec.run(args).await?,
             Some(BuildBackendSpec::Command(command_spec)) => command_spec.run(args).await?,
             Some(BuildBackendSpec::RustMlua(rust_mlua_spec)) => rust_mlua_spec.run(args).await?,
             Some(BuildBackendSpec::TreesitterParser(treesitter_parser_spec)) => {
@@ -243,7 +310,7 @@ async fn install<R: Rockspec + HasIntegrity, T: InstallTree>(
     entry_type: &EntryType,
     config: &Config,
 ) -> Result<(), BuildError> {
-    let install_spec = &rockspec.build().current_platform().install;
+    let install_spec = &rockspec.build().for_target(&config.target()).install;
     {
         let span = tracing::info_span!("Copying Lua modules");
         let _enter = span.enter();
@@ -267,7 +334,7 @@ async fn install<R: Rockspec + HasIntegrity, T: InstallTree>(
     if entry_type.is_entrypoint() {
         let span = tracing::info_span!("Installing binaries");
         let _enter = span.enter();
-        let deploy_spec = rockspec.deploy().current_platform();
+        let deploy_spec = rockspec.deploy().for_target(&config.target());
         for (target, source) in &install_spec.bin {
             utils::install_binary(
                 &build_dir.join(source),
@@ -305,7 +372,9 @@ async fn install<R: Rockspec + HasIntegrity, T: InstallTree>(
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
-async fn do_build<R, T>(build: Build<'_, R, T>) -> Result<LocalPackage, BuildError>
+async fn do_build<R, T>(
+    build: Build<'_, R, T>,
+) -> Result<(LocalPackage, Vec<CompileCommand>), BuildError>
 where
     R: Rockspec + HasIntegrity,
     T: InstallTree + Sync,
@@ -323,9 +392,16 @@ where
         Some(RemotePackageSourceSpec::SrcRock(SrcRockSource { bytes, source_url })) => {
             let hash = bytes.hash().await?;
             let cursor = Cursor::new(bytes);
-            operations::unpack_src_rock(cursor, temp_dir.path().to_path_buf())
-                .await
-                .map_err(BuildError::UnpackSrcRock)?;
+            // Source rocks may contain the source archive, which we also need to unpack.
+            operations::unpack(
+                Some("application/zip"),
+                cursor,
+                true,
+                "src.rock".into(),
+                temp_dir.path(),
+            )
+            .await
+            .map_err(BuildError::UnpackSrcRock)?;
             RemotePackageSourceMetadata { hash, source_url }
         }
         Some(RemotePackageSourceSpec::RockSpec(source_url)) => {
@@ -364,16 +440,19 @@ where
     );
     package.spec.pinned = build.pin;
     package.spec.opt = build.opt;
+    package.spec.features = build.features.iter().sorted().dedup().cloned().collect();
 
     match tree.lockfile()?.get(&package.id()) {
-        Some(package) if build.behaviour == BuildBehaviour::NoForce => Ok(package.clone()),
+        Some(package) if build.behaviour == BuildBehaviour::NoForce => {
+            Ok((package.clone(), Vec::new()))
+        }
         _ => {
             let output_paths = match build.entry_type {
                 tree::EntryType::Entrypoint => tree.entrypoint(&package)?,
                 tree::EntryType::DependencyOnly => tree.dependency(&package)?,
             };
 
-            let rock_source = rockspec.source().current_platform();
+            let rock_source = rockspec.source().for_target(&build.config.target());
             let build_dir = match &rock_source.unpack_dir {
                 Some(unpack_dir) => temp_dir.path().join(unpack_dir),
                 None => {
@@ -425,11 +504,15 @@ where
                 }
             };
 
-            Patch::new(&build_dir, &rockspec.build().current_platform().patches).apply()?;
+            Patch::new(
+                &build_dir,
+                &rockspec.build().for_target(&build.config.target()).patches,
+            )
+            .apply()?;
 
             let external_dependencies = rockspec
                 .external_dependencies()
-                .current_platform()
+                .for_target(&build.config.target())
                 .iter()
                 .map(|(name, dep)| {
                     ExternalDependencyInfo::probe(name, dep, build.config.external_deps())
@@ -437,23 +520,127 @@ where
                 })
                 .try_collect::<_, HashMap<_, _>, _>()?;
 
-            let output = run_build(
-                rockspec,
-                RunBuildArgs::new()
-                    .output_paths(&output_paths)
-                    .no_install(false)
-                    .lua(lua)
-                    .external_dependencies(&external_dependencies)
-                    .deploy(rockspec.deploy().current_platform())
-                    .config(build.config)
-                    .tree(tree)
-                    .build_dir(&build_dir)
-                    .build(),
-            )
-            .await?;
+            let config = if package.spec.features.is_empty() {
+                build.config.clone()
+            } else {
+                build
+                    .config
+                    .clone()
+                    .with_variables(feature_variables(package.spec.features()))
+            };
+
+            let package_name = rockspec.package();
+            let is_sandboxed = config.sandbox_config().is_enabled_for(package_name);
+            let config = if is_sandboxed {
+                let sandbox = Sandbox::new(
+                    package_name,
+                    temp_dir.path(),
+                    &output_paths,
+                    vec![tree.root(), tree.build_tree(&config)?.root()],
+                    lua,
+                    &external_dependencies,
+                    &config,
+                )?;
+                // Install directories must exist to be mounted into the sandbox.
+                fs::tokio::create_dir_all(&output_paths.rock_path).await?;
+                fs::tokio::create_dir_all(&output_paths.bin).await?;
+                config.with_sandbox(sandbox)
+            } else {
+                config
+            };
+
+            let build_log = BuildLog::default();
+            let config = config.with_build_log(build_log.clone());
+            let started = Instant::now();
+            let hooks = &rockspec.build().for_target(&config.target()).hooks;
+            let hook_args = RunHooksArgs::new()
+                .output_paths(&output_paths)
+                .lua(lua)
+                .external_dependencies(&external_dependencies)
+                .config(&config)
+                .tree(tree)
+                .build_dir(&build_dir)
+                .build();
+            let output = async {
+                run_hooks(&hooks.pre_build, HookStage::PreBuild, &hook_args).await?;
+                let output = run_build(
+                    rockspec,
+                    RunBuildArgs::new()
+                        .output_paths(&output_paths)
+                        .no_install(false)
+                        .lua(lua)
+                        .external_dependencies(&external_dependencies)
+                        .deploy(rockspec.deploy().for_target(&config.target()))
+                        .config(&config)
+                        .tree(tree)
+                        .build_dir(&build_dir)
+                        .build(),
+                )
+                .await?;
+                run_hooks(&hooks.post_build, HookStage::PostBuild, &hook_args).await?;
+                Ok(output)
+            }
+            .await
+            .map_err(|err| match SandboxDenial::diagnose(&err) {
+                Some(denial) if is_sandboxed => BuildError::SandboxDenied {
+                    package: package_name.clone(),
+                    denial,
+                    help: denial.help(package_name),
+                    source: Box::new(err),
+                },
+                _ => err,
+            });
+            let log = build_log
+                .persist(
+                    &package,
+                    output
+                        .as_ref()
+                        .map(|output| output.compile_commands.as_slice())
+                        .unwrap_or_default(),
+                    output.as_ref().map(|_| ()).map_err(error_chain),
+                    started.elapsed(),
+                    &config,
+                )
+                .await
+                .inspect_err(|err| tracing::warn!("failed to write the build log: {err}"))
+                .ok();
+            let output = output.map_err(|err| match log {
+                Some(log) => {
+                    let help = match &err {
+                        BuildError::SandboxDenied { help, .. } => format!(
+                            "{help}\nrun `lx build-log {}@{}` to view the full build log",
+                            package.name(),
+                            package.version()
+                        ),
+                        _ => format!(
+                            "run `lx build-log {}@{}` to view the full build log",
+                            package.name(),
+                            package.version()
+                        ),
+                    };
+                    BuildError::Logged {
+                        package: package_name.clone(),
+                        log,
+                        help,
+                        source: Box::new(err),
+                    }
+                }
+                None => err,
+            })?;
 
             package.spec.binaries.extend(output.binaries);
 
+            // Point the compile commands of local sources back to the source directory,
+            // as the temporary build directory is removed after the build.
+            let compile_commands = match &source_metadata.source_url {
+                RemotePackageSourceUrl::File { path } if path.is_dir() => output
+                    .compile_commands
+                    .into_iter()
+                    .map(|compile_command| compile_command.relocate(temp_dir.path(), path))
+                    .collect_vec(),
+                _ => output.compile_commands,
+            };
+
             install(
                 rockspec,
                 tree,
@@ -461,13 +648,13 @@ where
                 lua,
                 &build_dir,
                 &build.entry_type,
-                build.config,
+                &config,
             )
             .await?;
 
             for directory in rockspec
                 .build()
-                .current_platform()
+                .for_target(&config.target())
                 .copy_directories
                 .iter()
                 .filter(|dir| {
@@ -488,11 +675,32 @@ where
                 fs::sync::write(output_paths.rockspec_path(), rockspec_str)?;
             }
 
-            Ok(package)
+            Ok((package, compile_commands))
         }
     }
 }
 
+/// Renders an error and its sources, one per line.
+fn error_chain(err: &BuildError) -> String {
+    std::iter::successors(Some(err as &dyn std::error::Error), |err| (*err).source())
+        .map(ToString::to_string)
+        .join("\n")
+}
+
+/// The variables that expose the enabled features to the build:
+/// `FEATURES`, a space separated list of all enabled features,
+/// and `FEATURE_<NAME>`, set to `1` for each enabled feature.
+fn feature_variables(features: &[String]) -> impl Iterator<Item = (String, String)> + '_ {
+    std::iter::once(("FEATURES".to_string(), features.join(" "))).chain(features.iter().map(
+        |feature| {
+            (
+                format!("FEATURE_{}", feature.to_uppercase().replace('-', "_")),
+                "1".to_string(),
+            )
+        },
+    ))
+}
+
 fn is_source_or_etc_dir<R>(dir: &DirEntry, rockspec: &R) -> bool
 where
     R: Rockspec + HasIntegrity,
@@ -0,0 +1,92 @@
+use std::{env, fs::Metadata};
+
+use zip::{write::SimpleFileOptions, DateTime};
+
+/// The environment variable that specifies the timestamp to use for
+/// reproducible artefacts, in seconds since the Unix epoch.
+///
+/// See <https://reproducible-builds.org/specs/source-date-epoch/>
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// The timestamp specified by the `SOURCE_DATE_EPOCH` environment variable, if set and valid.
+pub fn source_date_epoch() -> Option<i64> {
+    env::var(SOURCE_DATE_EPOCH)
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+}
+
+/// Options for adding an entry to a zip archive, with a normalised modification time
+/// and permissions, so that archives of the same files are bit-for-bit identical.
+///
+/// The modification time is taken from `SOURCE_DATE_EPOCH` if set,
+/// otherwise it defaults to the earliest time a zip archive can represent (1980-01-01).
+pub fn zip_file_options(executable: bool) -> SimpleFileOptions {
+    let last_modified_time = source_date_epoch().map(zip_date_time).unwrap_or_default();
+    SimpleFileOptions::default()
+        .last_modified_time(last_modified_time)
+        .unix_permissions(if executable { 0o755 } else { 0o644 })
+}
+
+/// Whether a file is executable by its owner.
+/// Always `false` on platforms without Unix permissions.
+pub fn is_executable(metadata: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o100 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Converts a Unix timestamp to a (UTC) zip timestamp,
+/// clamping it to the range a zip archive can represent.
+fn zip_date_time(epoch: i64) -> DateTime {
+    let days = epoch.div_euclid(86400);
+    let seconds = epoch.rem_euclid(86400);
+    // Civil date from days since the epoch,
+    // see <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    if year < 1980 {
+        return DateTime::default();
+    }
+    DateTime::from_date_and_time(
+        year.min(2107) as u16,
+        month as u8,
+        day as u8,
+        (seconds / 3600) as u8,
+        (seconds % 3600 / 60) as u8,
+        (seconds % 60) as u8,
+    )
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zip_date_time_from_epoch() {
+        assert_eq!(
+            zip_date_time(1_700_000_000),
+            DateTime::from_date_and_time(2023, 11, 14, 22, 13, 20).unwrap()
+        );
+        assert_eq!(
+            zip_date_time(951_782_400),
+            DateTime::from_date_and_time(2000, 2, 29, 0, 0, 0).unwrap()
+        );
+        assert_eq!(zip_date_time(0), DateTime::default());
+    }
+}
@@ -12,6 +12,7 @@ use std::collections::HashMap;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::process::ExitStatus;
+use std::time::Instant;
 use thiserror::Error;
 
 use tracing::Instrument;
@@ -62,11 +63,18 @@ impl BuildBackend for RustMluaBuildSpec {
             .chain(std::iter::once(lua_feature.into()))
             .join(",");
         let target_dir_arg = format!("--target-dir={}", self.target_path.display());
+        let target_triple = config
+            .is_cross_compiling()
+            .then(|| config.target().to_string());
         let mut build_args = vec!["build"];
         if config.build_profile() == build::Profile::Release {
             build_args.push("--release");
         }
         build_args.push(&target_dir_arg);
+        if let Some(target_triple) = &target_triple {
+            build_args.push("--target");
+            build_args.push(target_triple);
+        }
         if !self.default_features {
             build_args.push("--no-default-features");
         }
@@ -74,15 +82,17 @@ impl BuildBackend for RustMluaBuildSpec {
         build_args.push(&features);
         build_args.extend(self.cargo_extra_args.iter().map(|arg| arg.as_str()));
         {
-            match config
-                .wrapped_command("cargo", build_args)
-                .current_dir(build_dir)
+            let mut cmd = config.wrapped_command("cargo", build_args);
+            cmd.current_dir(build_dir);
+            let started = Instant::now();
+            match cmd
                 .output()
                 .instrument(tracing::info_span!(
                     "Compiling rust-mlua module",
                     profile = config.build_profile().to_string()
                 ))
                 .await
+                .inspect(|output| config.log_command(&cmd, output, started.elapsed()))
             {
                 Ok(output) if output.status.success() => utils::trace_command_output(&output),
                 Ok(output) => {
@@ -100,12 +110,17 @@ impl BuildBackend for RustMluaBuildSpec {
             build::Profile::Release => "release",
             build::Profile::Dev => "debug",
         };
+        // When cross-compiling, cargo places the artifacts in a target-specific subdirectory.
+        let profile_dir = match &target_triple {
+            Some(target_triple) => PathBuf::from(target_triple).join(profile_dir),
+            None => PathBuf::from(profile_dir),
+        };
         if let Err(err) = install_rust_libs(
             self.modules,
             &self.target_path,
             build_dir,
             output_paths,
-            profile_dir,
+            &profile_dir,
         )
         .await
         {
@@ -127,7 +142,7 @@ async fn install_rust_libs(
     target_path: &Path,
     build_dir: &Path,
     output_paths: &RockLayout,
-    profile_dir: &str,
+    profile_dir: &Path,
 ) -> Result<(), fs::FsError> {
     for (module, rust_lib) in modules {
         let src = build_dir.join(target_path).join(profile_dir).join(rust_lib);
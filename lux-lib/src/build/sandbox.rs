@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    ffi::{OsStr, OsString},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use miette::Diagnostic;
+use thiserror::Error;
+use tokio::process::Command;
+
+use crate::{
+    build::external_dependency::ExternalDependencyInfo, config::Config,
+    lua_installation::LuaInstallation, package::PackageName, tree::RockLayout,
+};
+
+/// System directories that sandboxed build commands can read from, if they exist.
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/nix", "/gnu",
+];
+
+/// Environment variables that are passed to sandboxed build commands.
+/// All other variables are removed, unless configured otherwise.
+const PASSED_ENV: &[&str] = &[
+    "PATH",
+    "LANG",
+    "LC_ALL",
+    "TERM",
+    "CC",
+    "CXX",
+    "AR",
+    "LD",
+    "CFLAGS",
+    "CXXFLAGS",
+    "CPPFLAGS",
+    "LDFLAGS",
+    "MAKEFLAGS",
+    "PKG_CONFIG_PATH",
+    "SOURCE_DATE_EPOCH",
+];
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum SandboxError {
+    #[error("build sandboxing is only supported on Linux")]
+    #[diagnostic(help("disable `build.sandbox` in your Lux config on this platform."))]
+    Unsupported,
+    #[error("cannot sandbox the build: `{0}` not found")]
+    #[diagnostic(help(
+        "install bubblewrap, or set `build.sandbox.bwrap` in your Lux config to the `bwrap` executable."
+    ))]
+    BwrapNotFound(String),
+}
+
+/// How a sandboxed build command was prevented from doing something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxDenial {
+    /// The command tried to access the network.
+    Network,
+    /// The command tried to write outside the writable directories.
+    Write,
+    /// The command tried to access a file it isn't permitted to.
+    Access,
+    /// The sandbox could not be set up, because unprivileged user namespaces are not available.
+    Namespaces,
+}
+
+impl Display for SandboxDenial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Network => "network access was denied".fmt(f),
+            Self::Write => "writing outside the build directory was denied".fmt(f),
+            Self::Access => "file access was denied".fmt(f),
+            Self::Namespaces => "user namespaces are not available".fmt(f),
+        }
+    }
+}
+
+impl SandboxDenial {
+    /// Tries to determine if a build error was caused by a sandbox denial,
+    /// based on the error messages and command output in its source chain.
+    pub(crate) fn diagnose(err: &(dyn Error + 'static)) -> Option<Self> {
+        let messages = std::iter::successors(Some(err), |err| (*err).source())
+            .map(|err| err.to_string())
+            .join("\n");
+        Self::from_output(&messages)
+    }
+
+    fn from_output(output: &str) -> Option<Self> {
+        const NETWORK_ERRORS: &[&str] = &[
+            "Could not resolve host",
+            "Couldn't resolve host",
+            "Temporary failure in name resolution",
+            "Name or service not known",
+            "Network is unreachable",
+        ];
+        if output.contains("bwrap:") && (output.contains("namespace") || output.contains("uid map"))
+        {
+            Some(Self::Namespaces)
+        } else if NETWORK_ERRORS.iter().any(|error| output.contains(error)) {
+            Some(Self::Network)
+        } else if output.contains("Read-only file system") {
+            Some(Self::Write)
+        } else if output.contains("Permission denied") {
+            Some(Self::Access)
+        } else {
+            None
+        }
+    }
+
+    /// Guidance on how to allow the denied operation for a package.
+    pub(crate) fn help(&self, package: &PackageName) -> String {
+        let escape_hatch = match self {
+            Self::Network => format!("[build.sandbox.packages.{package}]\nnetwork = true"),
+            Self::Write => format!("[build.sandbox.packages.{package}]\nwritable = [\"/path/to/dir\"]"),
+            Self::Access => format!("[build.sandbox.packages.{package}]\nreadable = [\"/path/to/dir\"]"),
+            Self::Namespaces => {
+                return "enable unprivileged user namespaces (e.g. `sysctl kernel.unprivileged_userns_clone=1`), \
+                        or disable `build.sandbox` in your Lux config."
+                    .into()
+            }
+        };
+        format!(
+            "if '{package}' needs this to build, allow it in your Lux config:\n\n{escape_hatch}\n\n\
+             or disable the sandbox for it with `enabled = false`."
+        )
+    }
+}
+
+/// A [bubblewrap](https://github.com/containers/bubblewrap) sandbox for build commands.
+#[derive(Debug, Clone)]
+pub(crate) struct Sandbox {
+    bwrap: PathBuf,
+    readable: Vec<PathBuf>,
+    writable: Vec<PathBuf>,
+    network: bool,
+    env: Vec<String>,
+}
+
+impl Sandbox {
+    /// Creates the sandbox for building a package.
+    pub(crate) fn new(
+        package: &PackageName,
+        build_dir: &Path,
+        output_paths: &RockLayout,
+        tree_dirs: Vec<PathBuf>,
+        lua: &LuaInstallation,
+        external_dependencies: &HashMap<String, ExternalDependencyInfo>,
+        config: &Config,
+    ) -> Result<Self, SandboxError> {
+        let sandbox_config = config.sandbox_config();
+        if !cfg!(target_os = "linux") {
+            return Err(SandboxError::Unsupported);
+        }
+        let bwrap = sandbox_config
+            .bwrap
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("bwrap"));
+        let bwrap = which::which(&bwrap)
+            .map_err(|_| SandboxError::BwrapNotFound(bwrap.to_string_lossy().to_string()))?;
+        let package_override = sandbox_config.packages.get(package);
+        let readable = SYSTEM_DIRS
+            .iter()
+            .map(PathBuf::from)
+            .chain(tree_dirs)
+            .chain(lua.install_dirs())
+            .chain(external_dependencies.values().flat_map(|info| {
+                [&info.include_dir, &info.lib_dir, &info.bin_dir]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+            }))
+            .chain(sandbox_config.readable.iter().cloned())
+            .chain(
+                package_override
+                    .iter()
+                    .flat_map(|o| o.readable.iter().cloned()),
+            )
+            .collect_vec();
+        let writable = [
+            build_dir.to_path_buf(),
+            output_paths.rock_path.clone(),
+            output_paths.bin.clone(),
+        ]
+        .into_iter()
+        .chain(sandbox_config.writable.iter().cloned())
+        .chain(
+            package_override
+                .iter()
+                .flat_map(|o| o.writable.iter().cloned()),
+        )
+        .collect_vec();
+        let env = PASSED_ENV
+            .iter()
+            .map(|var| var.to_string())
+            .chain(sandbox_config.env.iter().cloned())
+            .chain(package_override.iter().flat_map(|o| o.env.iter().cloned()))
+            .unique()
+            .collect_vec();
+        Ok(Self {
+            bwrap,
+            readable,
+            writable,
+            network: sandbox_config.network_for(package),
+            env,
+        })
+    }
+
+    /// The `bwrap` arguments that set up the sandbox.
+    /// Directories that don't exist are skipped, as `bwrap` can't bind them.
+    fn bwrap_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "--die-with-parent".into(),
+            "--new-session".into(),
+            "--unshare-user-try".into(),
+            "--unshare-ipc".into(),
+            "--unshare-pid".into(),
+            "--unshare-uts".into(),
+            "--unshare-cgroup-try".into(),
+        ];
+        if !self.network {
+            args.push("--unshare-net".into());
+        }
+        for dir in self.readable.iter().unique().filter(|dir| dir.exists()) {
+            args.extend(["--ro-bind".into(), dir.into(), dir.into()]);
+        }
+        args.extend([
+            "--dev".into(),
+            "/dev".into(),
+            "--proc".into(),
+            "/proc".into(),
+            "--tmpfs".into(),
+            "/tmp".into(),
+        ]);
+        // Bound after the read-only directories, so that they take precedence
+        // if they are nested in them (e.g. the output directories in the install tree).
+        for dir in self.writable.iter().unique().filter(|dir| dir.exists()) {
+            args.extend(["--bind".into(), dir.into(), dir.into()]);
+        }
+        args
+    }
+
+    /// Constructs a [`Command`] that runs the given program inside the sandbox,
+    /// with a scrubbed environment.
+    pub(crate) fn command<P, A>(&self, program: P, args: A) -> Command
+    where
+        P: AsRef<OsStr>,
+        A: IntoIterator,
+        A::Item: AsRef<OsStr>,
+    {
+        let mut cmd = Command::new(&self.bwrap);
+        cmd.args(self.bwrap_args())
+            .arg("--")
+            .arg(program)
+            .args(args)
+            .env_clear();
+        for var in &self.env {
+            if let Some(value) = env::var_os(var) {
+                cmd.env(var, value);
+            }
+        }
+        cmd.env("HOME", "/tmp").env("TMPDIR", "/tmp");
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnose_sandbox_denials() {
+        assert_eq!(
+            SandboxDenial::from_output("curl: (6) Could not resolve host: github.com"),
+            Some(SandboxDenial::Network)
+        );
+        assert_eq!(
+            SandboxDenial::from_output(
+                "install: cannot create '/usr/local/lib/foo.so': Read-only file system"
+            ),
+            Some(SandboxDenial::Write)
+        );
+        assert_eq!(
+            SandboxDenial::from_output("cat: /home/user/.ssh/id_rsa: Permission denied"),
+            Some(SandboxDenial::Access)
+        );
+        assert_eq!(
+            SandboxDenial::from_output("bwrap: setting up uid map: Permission denied"),
+            Some(SandboxDenial::Namespaces)
+        );
+        assert_eq!(
+            SandboxDenial::from_output("foo.c:1:10: fatal error: bar.h: No such file or directory"),
+            None
+        );
+    }
+
+    #[test]
+    fn bwrap_args() {
+        let build_dir = assert_fs::TempDir::new().unwrap();
+        let sandbox = Sandbox {
+            bwrap: "bwrap".into(),
+            readable: vec!["/usr".into(), "/does/not/exist".into()],
+            writable: vec![build_dir.to_path_buf()],
+            network: false,
+            env: Vec::new(),
+        };
+        let args = sandbox.bwrap_args();
+        let args = args.iter().map(|arg| arg.to_string_lossy()).collect_vec();
+        assert!(args.contains(&"--unshare-net".into()));
+        assert!(!args.contains(&"/does/not/exist".into()));
+        let ro_bind = args.iter().position(|arg| arg == "--ro-bind").unwrap();
+        assert_eq!(args[ro_bind + 1], "/usr");
+        let bind = args.iter().position(|arg| arg == "--bind").unwrap();
+        assert!(bind > ro_bind);
+        assert_eq!(args[bind + 1], build_dir.to_string_lossy());
+
+        let sandbox = Sandbox {
+            network: true,
+            ..sandbox
+        };
+        assert!(!sandbox
+            .bwrap_args()
+            .contains(&OsString::from("--unshare-net")));
+    }
+}
@@ -15,8 +15,9 @@ use miette::Diagnostic;
 use thiserror::Error;
 
 use super::{
-    builtin::BuiltinBuildError, cmake::CMakeError, command::CommandError, make::MakeError,
-    rust_mlua::RustError, treesitter_parser::TreesitterBuildError, utils::recursive_copy_dir,
+    autotools::AutotoolsError, builtin::BuiltinBuildError, cmake::CMakeError,
+    command::CommandError, make::MakeError, meson::MesonError, rust_mlua::RustError,
+    treesitter_parser::TreesitterBuildError, utils::recursive_copy_dir,
 };
 
 #[derive(Error, Debug, Diagnostic)]
@@ -42,6 +43,12 @@ pub enum SourceBuildError {
     #[error("cmake build failed")]
     #[diagnostic(forward(0))]
     CMake(#[from] CMakeError),
+    #[error("meson build failed")]
+    #[diagnostic(forward(0))]
+    Meson(#[from] MesonError),
+    #[error("autotools build failed")]
+    #[diagnostic(forward(0))]
+    Autotools(#[from] AutotoolsError),
     #[error("make build failed")]
     #[diagnostic(forward(0))]
     Make(#[from] MakeError),
@@ -81,13 +88,16 @@ where
             let project_toml =
                 PartialProjectToml::new(PROJECT_TOML, &toml_content, ProjectRoot::new())?
                     .into_local()?;
-            build_spec = project_toml.build().current_platform().clone();
+            build_spec = project_toml
+                .build()
+                .for_target(&args.config.target())
+                .clone();
             copy_directories = Some(build_spec.copy_directories);
             break;
         } else if path.extension().is_some_and(|ext| ext == "rockspec") {
             let rockspec_content = String::from_utf8(fs::tokio::read(path).await?)?;
             let rockspec = LocalLuaRockspec::new(&rockspec_content, ProjectRoot::new())?;
-            build_spec = rockspec.build().current_platform().clone();
+            build_spec = rockspec.build().for_target(&args.config.target()).clone();
             copy_directories = Some(build_spec.copy_directories);
             break;
         }
@@ -108,6 +118,16 @@ where
                 .run(args)
                 .await?
         }
+        Some(BuildBackendSpec::Meson(meson_spec)) => {
+            meson_spec
+                .run(args)
+                .await?
+        }
+        Some(BuildBackendSpec::Autotools(autotools_spec)) => {
+            autotools_spec
+                .run(args)
+                .await?
+        }
         Some(BuildBackendSpec::Command(command_spec)) => {
             command_spec
                 .run(args)
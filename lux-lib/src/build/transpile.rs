@@ -0,0 +1,424 @@
+//! Compilation of languages that transpile to Lua in the builtin build backend.
+
+use std::{
+    fmt::Display,
+    io,
+    path::Path,
+    process::{Output, Stdio},
+    time::Instant,
+};
+
+use itertools::Itertools;
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use path_slash::PathExt;
+use thiserror::Error;
+use tracing::{info_span, Instrument};
+
+use crate::{
+    build::{builtin, utils},
+    config::Config,
+    fs,
+    lua_rockspec::{BuildBackendSpec, LuaModule, ModuleSpec},
+    package::PackageName,
+    path::{Paths, PathsError},
+    rockspec::Rockspec,
+    tree::{InstallTree, RockLayout, TreeError},
+};
+
+/// A language that compiles to Lua.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceLanguage {
+    Teal,
+    Fennel,
+    MoonScript,
+    YueScript,
+}
+
+impl SourceLanguage {
+    /// Detect the language of a source file by its extension.
+    /// Teal type declaration files (`.d.tl`) are not compiled.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if path.to_string_lossy().ends_with(".d.tl") {
+            return None;
+        }
+        match path.extension()?.to_str()? {
+            "tl" => Some(Self::Teal),
+            "fnl" => Some(Self::Fennel),
+            "moon" => Some(Self::MoonScript),
+            "yue" => Some(Self::YueScript),
+            _ => None,
+        }
+    }
+
+    /// The rock that provides the compiler.
+    pub fn compiler_package(&self) -> PackageName {
+        PackageName::new(
+            match self {
+                Self::Teal => "tl",
+                Self::Fennel => "fennel",
+                Self::MoonScript => "moonscript",
+                Self::YueScript => "yuescript",
+            }
+            .into(),
+        )
+    }
+
+    fn compiler_binary(&self) -> &'static str {
+        match self {
+            Self::Teal => "tl",
+            Self::Fennel => "fennel",
+            Self::MoonScript => "moonc",
+            Self::YueScript => "yue",
+        }
+    }
+
+    /// Arguments for compiling `source` to `destination`, and whether the compiler
+    /// writes to `destination` itself (rather than printing the Lua code to stdout).
+    fn compiler_args<'a>(&self, source: &'a str, destination: &'a str) -> (Vec<&'a str>, bool) {
+        match self {
+            Self::Teal => (vec!["gen", source, "-o", destination], true),
+            Self::Fennel => (vec!["--compile", source], false),
+            Self::MoonScript | Self::YueScript => (vec!["-o", destination, source], true),
+        }
+    }
+}
+
+impl Display for SourceLanguage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Teal => write!(f, "Teal"),
+            Self::Fennel => write!(f, "Fennel"),
+            Self::MoonScript => write!(f, "MoonScript"),
+            Self::YueScript => write!(f, "YueScript"),
+        }
+    }
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[non_exhaustive]
+pub enum TranspileError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Tree(#[from] TreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Paths(#[from] PathsError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("cannot find the {language} compiler `{binary}` in the build tree")]
+    #[diagnostic(help("make sure '{package}' is a build dependency and provides `{binary}`"))]
+    CompilerNotFound {
+        language: SourceLanguage,
+        binary: &'static str,
+        package: PackageName,
+        #[source]
+        err: which::Error,
+    },
+    #[error("error executing the {language} compiler for {file}: {err}")]
+    Io {
+        language: SourceLanguage,
+        file: String,
+        err: io::Error,
+    },
+    #[error("{language} compilation failed in {location}:\n{message}")]
+    Compile {
+        language: SourceLanguage,
+        location: String,
+        message: String,
+        #[source_code]
+        source_code: NamedSource<String>,
+        #[label("{message}")]
+        span: Option<SourceSpan>,
+    },
+}
+
+/// A module to compile to Lua.
+pub(crate) struct TranspiledModule<'a> {
+    pub(crate) module: &'a LuaModule,
+    /// The source file, relative to the build directory.
+    pub(crate) source: &'a Path,
+    pub(crate) language: SourceLanguage,
+}
+
+/// The rocks that provide the compilers for the builtin build backend's modules
+/// with sources in languages that transpile to Lua.
+/// If the package's `source_dir` is known (e.g. for a local project),
+/// this includes the modules that the builtin build backend auto-detects.
+pub(crate) fn compiler_packages<R: Rockspec>(
+    rockspec: &R,
+    source_dir: Option<&Path>,
+) -> Vec<PackageName> {
+    let build_spec = match &rockspec.build().current_platform().build_backend {
+        Some(BuildBackendSpec::Builtin(build_spec)) => build_spec,
+        _ => return Vec::new(),
+    };
+    let declared_languages = || {
+        build_spec
+            .modules
+            .values()
+            .filter_map(|module| match module {
+                ModuleSpec::SourcePath(source) => SourceLanguage::from_path(source),
+                _ => None,
+            })
+            .collect_vec()
+    };
+    let languages = match source_dir {
+        // Module auto-detection errors are reported when building.
+        Some(source_dir) => builtin::transpiled_languages(source_dir, &build_spec.modules)
+            .unwrap_or_else(|_| declared_languages()),
+        None => declared_languages(),
+    };
+    languages
+        .into_iter()
+        .map(|language| language.compiler_package())
+        .sorted()
+        .dedup()
+        .collect_vec()
+}
+
+/// Compiles the modules to Lua, installing the generated files to the rock's `src` directory.
+/// The compilers are expected to be installed in the build tree, as build dependencies.
+pub(crate) async fn transpile_modules<T: InstallTree>(
+    modules: &[TranspiledModule<'_>],
+    output_paths: &RockLayout,
+    tree: &T,
+    config: &Config,
+    build_dir: &Path,
+) -> Result<(), TranspileError> {
+    if modules.is_empty() {
+        return Ok(());
+    }
+    let build_tree = tree.build_tree(config)?;
+    let build_paths = Paths::new(&build_tree)?;
+    for module in modules {
+        transpile(module, output_paths, &build_paths, config, build_dir).await?;
+    }
+    Ok(())
+}
+
+async fn transpile(
+    module: &TranspiledModule<'_>,
+    output_paths: &RockLayout,
+    build_paths: &Paths,
+    config: &Config,
+    build_dir: &Path,
+) -> Result<(), TranspileError> {
+    let language = module.language;
+    let file = module.source.to_slash_lossy().to_string();
+    let bin_path = build_paths.path_prepended().joined();
+    let compiler = which::which_in(language.compiler_binary(), Some(&bin_path), build_dir)
+        .map_err(|err| TranspileError::CompilerNotFound {
+            language,
+            binary: language.compiler_binary(),
+            package: language.compiler_package(),
+            err,
+        })?;
+    let destination = output_paths.src.join(module.module.to_lua_path());
+    if let Some(parent) = destination.parent() {
+        fs::sync::create_dir_all(parent)?;
+    }
+    let destination_str = destination.to_string_lossy();
+    let (args, writes_destination) = language.compiler_args(&file, &destination_str);
+    let mut cmd = config.wrapped_command(compiler, args);
+    cmd.current_dir(build_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .env("PATH", &bin_path)
+        .env("LUA_PATH", build_paths.package_path_prepended().joined())
+        .env("LUA_CPATH", build_paths.package_cpath_prepended().joined());
+    let started = Instant::now();
+    let output = cmd
+        .output()
+        .instrument(info_span!(
+            "Compiling",
+            language = language.to_string(),
+            file
+        ))
+        .await
+        .inspect(|output| config.log_command(&cmd, output, started.elapsed()))
+        .map_err(|err| TranspileError::Io {
+            language,
+            file: file.clone(),
+            err,
+        })?;
+    if !output.status.success() {
+        return Err(compile_error(
+            language,
+            &file,
+            &build_dir.join(module.source),
+            &output,
+        ));
+    }
+    if !writes_destination {
+        fs::sync::write(&destination, &output.stdout)?;
+    }
+    utils::trace_command_output(&output);
+    Ok(())
+}
+
+/// Maps the output of a failed compiler invocation to a diagnostic pointing at the source.
+fn compile_error(
+    language: SourceLanguage,
+    file: &str,
+    source_path: &Path,
+    output: &Output,
+) -> TranspileError {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let compiler_output = format!("{stderr}\n{stdout}");
+    let file_name = source_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.to_string());
+    let source = std::fs::read_to_string(source_path).unwrap_or_default();
+    let (position, message) = match find_error_position(&compiler_output, &file_name) {
+        Some((position, message)) => (Some(position), message),
+        None => (None, compiler_output.trim().to_string()),
+    };
+    let location = match position {
+        Some((line, _)) => format!("{file}:{line}"),
+        None => file.to_string(),
+    };
+    let span = position.and_then(|(line, column)| line_span(&source, line, column));
+    TranspileError::Compile {
+        language,
+        location,
+        message,
+        source_code: NamedSource::new(file, source),
+        span,
+    }
+}
+
+/// Searches compiler output for an error position and its message.
+/// Recognises `<file>:<line>[:<column>]` (Teal, Fennel, YueScript)
+/// and ` [<line>] >>` (MoonScript).
+fn find_error_position(output: &str, file_name: &str) -> Option<((usize, Option<usize>), String)> {
+    let pattern = format!("{file_name}:");
+    let with_file_position = output.lines().find_map(|line| {
+        line.match_indices(&pattern).find_map(|(index, _)| {
+            let rest = &line[index + pattern.len()..];
+            let (line_number, rest) = split_number(rest)?;
+            let (column, rest) = rest
+                .strip_prefix(':')
+                .and_then(split_number)
+                .map_or((None, rest), |(column, rest)| (Some(column), rest));
+            let message = rest.trim_start_matches(':').trim();
+            let message = if message.is_empty() {
+                line.trim()
+            } else {
+                message
+            };
+            Some(((line_number, column), message.to_string()))
+        })
+    });
+    with_file_position.or_else(|| {
+        output.lines().enumerate().find_map(|(index, line)| {
+            let rest = line.trim_start().strip_prefix('[')?;
+            let (line_number, rest) = split_number(rest)?;
+            rest.strip_prefix("] >>")?;
+            let message = output
+                .lines()
+                .take(index)
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .last()
+                .unwrap_or(line.trim());
+            Some(((line_number, None), message.to_string()))
+        })
+    })
+}
+
+fn split_number(input: &str) -> Option<(usize, &str)> {
+    let end = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let number = input[..end].parse().ok()?;
+    Some((number, &input[end..]))
+}
+
+/// The span of a (1-based) line in `source`, starting at the (1-based) column, if known.
+fn line_span(source: &str, line: usize, column: Option<usize>) -> Option<SourceSpan> {
+    let mut offset = 0;
+    for (index, content) in source.split_inclusive('\n').enumerate() {
+        if index + 1 == line {
+            let content = content.trim_end_matches(['\r', '\n']);
+            let start = column
+                .map(|column| column.saturating_sub(1).min(content.len()))
+                .unwrap_or(0);
+            let len = (content.len() - start).max(1);
+            return Some((offset + start, len).into());
+        }
+        offset += content.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_language_from_path() {
+        assert_eq!(
+            SourceLanguage::from_path(Path::new("src/foo.tl")),
+            Some(SourceLanguage::Teal)
+        );
+        assert_eq!(SourceLanguage::from_path(Path::new("src/foo.d.tl")), None);
+        assert_eq!(
+            SourceLanguage::from_path(Path::new("src/foo.fnl")),
+            Some(SourceLanguage::Fennel)
+        );
+        assert_eq!(
+            SourceLanguage::from_path(Path::new("src/foo.moon")),
+            Some(SourceLanguage::MoonScript)
+        );
+        assert_eq!(
+            SourceLanguage::from_path(Path::new("src/foo.yue")),
+            Some(SourceLanguage::YueScript)
+        );
+        assert_eq!(SourceLanguage::from_path(Path::new("src/foo.lua")), None);
+    }
+
+    #[test]
+    fn find_teal_error_position() {
+        let output = "========================================
+1 syntax error:
+src/foo/bar.tl:3:7: syntax error, expected 'end'
+";
+        assert_eq!(
+            find_error_position(output, "bar.tl"),
+            Some(((3, Some(7)), "syntax error, expected 'end'".into()))
+        );
+    }
+
+    #[test]
+    fn find_fennel_error_position() {
+        let output = "src/foo.fnl:2:1: Compile error: unknown identifier: prnt\n";
+        assert_eq!(
+            find_error_position(output, "foo.fnl"),
+            Some((
+                (2, Some(1)),
+                "Compile error: unknown identifier: prnt".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn find_moonscript_error_position() {
+        let output = "Failed to parse:\n [4] >>    x = = 1\n";
+        assert_eq!(
+            find_error_position(output, "foo.moon"),
+            Some(((4, None), "Failed to parse:".into()))
+        );
+        assert_eq!(find_error_position("error", "foo.moon"), None);
+    }
+
+    #[test]
+    fn line_span_of_error() {
+        let source = "local x = 1\nlocal y = = 2\n";
+        assert_eq!(line_span(source, 2, Some(11)), Some((22, 3).into()));
+        assert_eq!(line_span(source, 1, None), Some((0, 11).into()));
+        assert_eq!(line_span(source, 5, None), None);
+    }
+}
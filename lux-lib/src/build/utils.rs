@@ -14,12 +14,14 @@ use path_slash::PathExt;
 use shlex::try_quote;
 use std::{
     collections::HashMap,
+    ffi::{OsStr, OsString},
     io,
     path::{Path, PathBuf},
     process::{ExitStatus, Output, Stdio},
     string::FromUtf8Error,
+    time::Instant,
 };
-use target_lexicon::Triple;
+use target_lexicon::{Environment as TargetEnvironment, Triple};
 use thiserror::Error;
 use tokio::process::Command;
 use which::which;
@@ -27,7 +29,9 @@ use which::which;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+use super::compile_commands::CompileCommand;
 use super::external_dependency::ExternalDependencyInfo;
+use super::incremental::CModuleCache;
 
 pub(crate) trait CCExt {
     fn try_compile_objects(&self, config: &Config) -> Result<Vec<PathBuf>, cc::Error>;
@@ -158,12 +162,13 @@ if no C compiler was found, run `lx debug toolchains` to verify your build tools
 )]
 pub(crate) async fn compile_c_files(
     files: &Vec<PathBuf>,
+    source_dir: &Path,
     target_module: &LuaModule,
     target_dir: &Path,
     lua: &LuaInstallation,
     external_dependencies: &HashMap<String, ExternalDependencyInfo>,
     config: &Config,
-) -> Result<(), CompileCFilesError> {
+) -> Result<Vec<CompileCommand>, CompileCFilesError> {
     let target = target_dir.join(target_module.to_lib_path());
     let target_parent_dir = target.parent().unwrap_or_else(|| {
         unreachable!(
@@ -180,11 +185,13 @@ pub(crate) async fn compile_c_files(
     fs::sync::create_dir_all(target_parent_dir)?;
 
     let host = Triple::host();
+    let target_triple = config.target();
 
     // See https://github.com/rust-lang/cc-rs/issues/594#issuecomment-2110551057
 
     let mut build = cc::Build::new();
-    let intermediate_dir = fs::tempfile::tempdir()?;
+    // Kept in the build directory, so that sandboxed link commands can access the objects.
+    let intermediate_dir = fs::tempfile::tempdir_in(source_dir)?;
     let build = build
         .cargo_output(config.verbose())
         .cargo_debug(config.verbose())
@@ -193,7 +200,7 @@ pub(crate) async fn compile_c_files(
         .warnings(config.verbose())
         .files(files)
         .host(&host.to_string())
-        .target(&host.to_string())
+        .target(&target_triple.to_string())
         .includes(lua.includes())
         .includes(
             external_dependencies
@@ -201,7 +208,7 @@ pub(crate) async fn compile_c_files(
                 .filter_map(|dep| dep.include_dir.as_ref()),
         )
         .opt_level(config.build_profile().opt_level())
-        .out_dir(intermediate_dir);
+        .out_dir(intermediate_dir.path());
 
     let compiler = build.try_get_compiler()?;
     // Suppress all warnings
@@ -209,11 +216,21 @@ pub(crate) async fn compile_c_files(
         build.flag("-W0");
     } else {
         build.flag("-w");
+        build.flag(file_prefix_map_flag(source_dir));
     }
     for arg in lua.define_flags() {
         build.flag(&arg);
     }
 
+    let compile_commands = CompileCommand::from_cc_build(build, files, source_dir)?;
+    let link_inputs = link_inputs(lua, external_dependencies, &compiler, &[], &[], config);
+    let cache_entry = c_module_cache_entry(&compile_commands, source_dir, &link_inputs, config);
+    if let Some((cache, key)) = &cache_entry {
+        if cache.restore(key, &target).await? {
+            tracing::debug!("reusing unchanged C module {}", target_module);
+            return Ok(compile_commands);
+        }
+    }
     let objects = build
         .try_compile_objects(config)
         .map_err(|source| CompileCFilesError::CompileIntermediates { source })?;
@@ -227,11 +244,15 @@ pub(crate) async fn compile_c_files(
         Vec::new(),
         target_module,
         target_parent_dir,
+        intermediate_dir.path(),
         objects,
         config,
     )
     .await?;
-    Ok(())
+    if let Some((cache, key)) = cache_entry {
+        cache.store(&key, &target).await?;
+    }
+    Ok(compile_commands)
 }
 
 /// On MSVC, we need to create Lua definitions manually
@@ -261,7 +282,12 @@ luaopen_{exported_name}
 
 /// the extension for C shared libraries.
 pub(crate) fn c_dylib_extension() -> &'static str {
-    if cfg!(target_env = "msvc") {
+    target_c_dylib_extension(&Triple::host())
+}
+
+/// the extension for C shared libraries built for a target.
+pub(crate) fn target_c_dylib_extension(target: &Triple) -> &'static str {
+    if target.environment == TargetEnvironment::Msvc {
         "dll"
     } else {
         "so"
@@ -277,9 +303,9 @@ pub(crate) fn c_lib_extension() -> &'static str {
     }
 }
 
-/// the extension for C objects.
-pub(crate) fn c_obj_extension() -> &'static str {
-    if cfg!(target_env = "msvc") {
+/// the extension for C objects built for a target.
+pub(crate) fn target_c_obj_extension(target: &Triple) -> &'static str {
+    if target.environment == TargetEnvironment::Msvc {
         "obj"
     } else {
         "o"
@@ -350,6 +376,12 @@ pub enum LinkCModulesError {
     LibOutputNotCreated(String),
 }
 
+/// Maps the build directory to `.` in debug info and `__FILE__` macros,
+/// so that compiled objects don't depend on the directory they were built in.
+fn file_prefix_map_flag(build_dir: &Path) -> String {
+    format!("-ffile-prefix-map={}=.", build_dir.display())
+}
+
 /// Compiles a set of C files (with extra metadata) to a given destination.
 /// # Panics
 /// Panics if no filename for the target path can be determined.
@@ -367,7 +399,7 @@ pub(crate) async fn compile_c_modules(
     lua: &LuaInstallation,
     external_dependencies: &HashMap<String, ExternalDependencyInfo>,
     config: &Config,
-) -> Result<(), CompileCModulesError> {
+) -> Result<Vec<CompileCommand>, CompileCModulesError> {
     let target = target_dir.join(target_module.to_lib_path());
 
     let target_parent_dir = target.parent().unwrap_or_else(|| {
@@ -380,6 +412,7 @@ pub(crate) async fn compile_c_modules(
     fs::tokio::create_dir_all(target_parent_dir).await?;
 
     let host = Triple::host();
+    let target_triple = config.target();
 
     let mut build = cc::Build::new();
     let source_files = data
@@ -414,16 +447,17 @@ pub(crate) async fn compile_c_modules(
         .unique() // Some rocks specify the include dirs via variable substitution.
         .collect_vec();
 
-    let intermediate_dir = fs::tempfile::tempdir()?;
+    // Kept in the build directory, so that sandboxed link commands can access the objects.
+    let intermediate_dir = fs::tempfile::tempdir_in(source_dir)?;
     let build = build
         .cargo_output(config.verbose())
         .cargo_debug(config.verbose())
         .cargo_metadata(config.verbose())
         .cargo_warnings(config.verbose())
         .warnings(config.verbose())
-        .files(source_files)
+        .files(&source_files)
         .host(&host.to_string())
-        .target(&host.to_string())
+        .target(&target_triple.to_string())
         .includes(&include_dirs)
         .includes(lua.includes())
         .includes(
@@ -432,7 +466,7 @@ pub(crate) async fn compile_c_modules(
                 .filter_map(|dep| dep.include_dir.as_ref()),
         )
         .opt_level(config.build_profile().opt_level())
-        .out_dir(intermediate_dir);
+        .out_dir(intermediate_dir.path());
 
     let compiler = build.try_get_compiler()?;
     let is_msvc = compiler.is_like_msvc();
@@ -441,6 +475,7 @@ pub(crate) async fn compile_c_modules(
         build.flag("-W0");
     } else {
         build.flag("-w");
+        build.flag(file_prefix_map_flag(source_dir));
     }
     for arg in lua.define_flags() {
         build.flag(&arg);
@@ -457,9 +492,7 @@ pub(crate) async fn compile_c_modules(
         .unwrap_or_default()
         .to_string_lossy()
         .to_string();
-    // See https://github.com/rust-lang/cc-rs/issues/594#issuecomment-2110551057
-    let objects = build
-        .try_compile_objects(config)
+    let compile_commands = CompileCommand::from_cc_build(build, &source_files, source_dir)
         .map_err(CompileCModulesError::CompileIntermediates)?;
 
     let libdir_args = data
@@ -502,6 +535,27 @@ pub(crate) async fn compile_c_modules(
         })
         .try_collect::<_, Vec<_>, _>()?;
 
+    let link_inputs = link_inputs(
+        lua,
+        external_dependencies,
+        &compiler,
+        &libdir_args,
+        &library_args,
+        config,
+    );
+    let cache_entry = c_module_cache_entry(&compile_commands, source_dir, &link_inputs, config);
+    if let Some((cache, key)) = &cache_entry {
+        if cache.restore(key, &target).await? {
+            tracing::debug!("reusing unchanged C module {}", target_module);
+            return Ok(compile_commands);
+        }
+    }
+
+    // See https://github.com/rust-lang/cc-rs/issues/594#issuecomment-2110551057
+    let objects = build
+        .try_compile_objects(config)
+        .map_err(CompileCModulesError::CompileIntermediates)?;
+
     link_c_artifacts(
         build,
         lua,
@@ -511,13 +565,61 @@ pub(crate) async fn compile_c_modules(
         library_args,
         target_module,
         target_parent_dir,
+        intermediate_dir.path(),
         objects,
         config,
     )
     .await?;
-    Ok(())
+    if let Some((cache, key)) = cache_entry {
+        cache.store(&key, &target).await?;
+    }
+    Ok(compile_commands)
+}
+
+/// The arguments, other than the objects, that determine how a C module is linked.
+fn link_inputs(
+    lua: &LuaInstallation,
+    external_dependencies: &HashMap<String, ExternalDependencyInfo>,
+    compiler: &cc::Tool,
+    libdir_args: &[String],
+    library_args: &[String],
+    config: &Config,
+) -> Vec<String> {
+    ["LIBFLAG", "LDFLAGS"]
+        .into_iter()
+        .filter_map(|name| config.variables().get(name).cloned())
+        .chain(lua_lib_link_args(lua, compiler))
+        .chain(
+            external_dependencies
+                .values()
+                .flat_map(|dep| dep.lib_link_args(compiler)),
+        )
+        .chain(libdir_args.iter().cloned())
+        .chain(library_args.iter().cloned())
+        .collect_vec()
 }
 
+/// The cache to restore a C module from, and the module's cache key,
+/// if building with a [`CModuleCache`].
+fn c_module_cache_entry<'a>(
+    compile_commands: &[CompileCommand],
+    source_dir: &Path,
+    link_inputs: &[String],
+    config: &'a Config,
+) -> Option<(&'a CModuleCache, String)> {
+    let cache = config.c_module_cache()?;
+    match CModuleCache::key(compile_commands, source_dir, link_inputs) {
+        Ok(key) => Some((cache, key)),
+        Err(err) => {
+            tracing::debug!("not caching C module: {err}");
+            None
+        }
+    }
+}
+
+/// Links the compiled objects into a C module.
+/// The link command runs in the build's sandbox, if any.
+/// Compiling the objects with `cc` is not sandboxed, as it only runs the C compiler.
 #[allow(clippy::too_many_arguments)]
 async fn link_c_artifacts(
     build: &mut cc::Build,
@@ -528,53 +630,72 @@ async fn link_c_artifacts(
     library_args: Vec<String>,
     target_module: &LuaModule,
     target_parent_dir: &Path,
+    work_dir: &Path,
     objects: Vec<PathBuf>,
     config: &Config,
 ) -> Result<(), LinkCModulesError> {
     let output_path = target_parent_dir.join(target_file_name);
     let compiler = build.try_get_compiler()?;
+    let is_msvc = compiler.is_like_msvc();
+    let compiler_cmd = compiler.to_command();
+    let mut args: Vec<OsString> = compiler_cmd.get_args().map(OsStr::to_os_string).collect();
+    args.extend(variable_args(config, "LIBFLAG"));
+    if is_msvc {
+        let def_file = mk_def_file(work_dir, target_file_name, target_module)?;
+        args.extend(["/NOIMPLIB", "/NOEXP"].map(OsString::from));
+        args.extend(objects.into_iter().map(OsString::from));
+        args.push("/LD".into());
+        args.extend(variable_args(config, "LDFLAGS"));
+        args.push("/link".into());
+        args.push(format!("/DEF:{}", def_file.display()).into());
+        args.push(format!("/OUT:{}", output_path.display()).into());
+        args.extend(
+            lua_lib_link_args(lua, &compiler)
+                .into_iter()
+                .map(OsString::from),
+        );
+        args.extend(
+            external_dependencies
+                .values()
+                .flat_map(|dep| dep.lib_link_args(&compiler))
+                .map(OsString::from),
+        );
+        args.extend(libdir_args.into_iter().map(OsString::from));
+        args.extend(library_args.into_iter().map(OsString::from));
+    } else {
+        args.extend(variable_args(config, "LDFLAGS"));
+        args.push("-o".into());
+        args.push(output_path.clone().into_os_string());
+        args.extend(
+            lua_lib_link_args(lua, &compiler)
+                .into_iter()
+                .map(OsString::from),
+        );
+        args.extend(
+            external_dependencies
+                .values()
+                .flat_map(|dep| dep.lib_link_args(&compiler))
+                .map(OsString::from),
+        );
+        args.extend(objects.into_iter().map(OsString::from));
+        args.extend(libdir_args.into_iter().map(OsString::from));
+        args.extend(library_args.into_iter().map(OsString::from));
+    }
+    let mut cmd = config.wrapped_command(compiler_cmd.get_program(), args);
+    for (key, value) in compiler_cmd.get_envs() {
+        match value {
+            Some(value) => cmd.env(key, value),
+            None => cmd.env_remove(key),
+        };
+    }
     // Linking from within a temp directory makes sure the linker doesn't create any build artifacts in
     // the current working directory.
     // See https://github.com/lumen-oss/lux/issues/1106
-    let temp_work_dir = fs::tempfile::tempdir()?;
-    let is_msvc = compiler.is_like_msvc();
-    let cmd = build.try_get_compiler()?.to_command();
-    let mut cmd: tokio::process::Command = cmd.into();
-    cmd.current_dir(temp_work_dir.path());
-    add_variable_if_set(config, "LIBFLAG", &mut cmd);
-    let output = if is_msvc {
-        let def_file = mk_def_file(temp_work_dir.path(), target_file_name, target_module)?;
-        cmd.arg("/NOIMPLIB").arg("/NOEXP").args(&objects).arg("/LD");
-        add_variable_if_set(config, "LDFLAGS", &mut cmd);
-        cmd.arg("/link")
-            .arg(format!("/DEF:{}", def_file.display()))
-            .arg(format!("/OUT:{}", output_path.display()))
-            .args(lua_lib_link_args(lua, &compiler))
-            .args(
-                external_dependencies
-                    .values()
-                    .flat_map(|dep| dep.lib_link_args(&compiler)),
-            )
-            .args(libdir_args)
-            .args(library_args)
-            .output()
-            .await?
-    } else {
-        add_variable_if_set(config, "LDFLAGS", &mut cmd);
-        cmd.args(vec!["-o".into(), output_path.to_string_lossy().to_string()])
-            .args(lua_lib_link_args(lua, &build.try_get_compiler()?))
-            .args(
-                external_dependencies
-                    .values()
-                    .flat_map(|dep| dep.lib_link_args(&compiler)),
-            )
-            .args(&objects)
-            .args(libdir_args)
-            .args(library_args)
-            .output()
-            .await?
-    };
+    cmd.current_dir(work_dir);
+    let started = Instant::now();
+    let output = cmd.output().await?;
     trace_command_output(&output);
+    config.log_command(&cmd, &output, started.elapsed());
     validate_output(&output)?;
 
     if output_path.exists() {
@@ -586,6 +707,23 @@ async fn link_c_artifacts(
     }
 }
 
+/// The Lua linker arguments for build backends that invoke the C compiler themselves,
+/// formatted for the C compiler of the configured target.
+pub(crate) fn lua_link_args(
+    lua: &LuaInstallation,
+    config: &Config,
+) -> Result<Vec<String>, cc::Error> {
+    let mut build = cc::Build::new();
+    let compiler = build
+        .cargo_metadata(false)
+        .cargo_warnings(false)
+        .host(&Triple::host().to_string())
+        .target(&config.target().to_string())
+        .opt_level(config.build_profile().opt_level())
+        .try_get_compiler()?;
+    Ok(lua_lib_link_args(lua, &compiler))
+}
+
 /// NOTE: In luarocks, these are behind a link_lua_explicity config option
 fn lua_lib_link_args(lua: &LuaInstallation, compiler: &cc::Tool) -> Vec<String> {
     if cfg!(target_os = "macos") {
@@ -598,14 +736,13 @@ fn lua_lib_link_args(lua: &LuaInstallation, compiler: &cc::Tool) -> Vec<String>
 
 /// Lookup a variable in the `Config` and if set and non-empty,
 /// split it into multiple arguments and add them to the `Command`.
-fn add_variable_if_set(config: &Config, name: &str, cmd: &mut Command) {
-    let variables = config.variables();
-    if let Some(var_str) = variables.get(name) {
-        if !var_str.is_empty() {
-            let vars = var_str.split_whitespace().collect_vec();
-            cmd.args(vars);
-        }
-    }
+/// The whitespace-separated arguments in a variable, if it is set.
+fn variable_args(config: &Config, name: &str) -> Vec<OsString> {
+    config
+        .variables()
+        .get(name)
+        .map(|var_str| var_str.split_whitespace().map(OsString::from).collect())
+        .unwrap_or_default()
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -1,6 +1,10 @@
+use std::{collections::HashMap, path::PathBuf};
+
 use serde::{Deserialize, Serialize};
 use strum_macros::Display;
 
+use crate::package::PackageName;
+
 /// Configuration for the build process.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(super) struct BuildConfig {
@@ -15,6 +19,104 @@ pub(super) struct BuildConfig {
     /// If unset, no wrapping is performed.
     #[serde(default)]
     pub(super) runner: Vec<String>,
+    /// The target triple to compile packages for, e.g. `aarch64-unknown-linux-gnu`.
+    ///
+    /// If unset, packages are compiled for the host.
+    pub(super) target: Option<String>,
+    /// Sandboxing of build commands (Linux only).
+    #[serde(default)]
+    pub(super) sandbox: SandboxConfig,
+}
+
+/// Configuration for running build commands in a sandbox,
+/// using [`bubblewrap`](https://github.com/containers/bubblewrap) (Linux only).
+///
+/// Sandboxed build commands can only write to the build directory and
+/// the package's install directories, can only read system directories,
+/// the install tree and the Lua installation, have no network access
+/// and run with a scrubbed environment.
+///
+/// The `builtin` build backend links C modules in the sandbox,
+/// but compiles their sources with the C compiler outside of it.
+///
+/// # Examples
+///
+/// ```toml
+/// [build.sandbox]
+/// enabled = true
+/// # Additional directories build commands can read from
+/// readable = ["/home/user/.cargo", "/home/user/.rustup"]
+/// # Additional environment variables to keep
+/// env = ["CUDA_HOME"]
+///
+/// # Per-package escape hatches
+/// [build.sandbox.packages.luasocket]
+/// network = true
+///
+/// [build.sandbox.packages.some-rock]
+/// enabled = false
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    /// Whether to sandbox build commands.
+    /// Default: `false`
+    #[serde(default)]
+    pub(crate) enabled: bool,
+    /// Whether to allow network access.
+    /// Default: `false`
+    #[serde(default)]
+    pub(crate) network: bool,
+    /// Additional directories that build commands can read from.
+    #[serde(default)]
+    pub(crate) readable: Vec<PathBuf>,
+    /// Additional directories that build commands can write to.
+    #[serde(default)]
+    pub(crate) writable: Vec<PathBuf>,
+    /// Additional environment variables to pass to build commands.
+    #[serde(default)]
+    pub(crate) env: Vec<String>,
+    /// The `bwrap` executable.
+    /// Default: `bwrap` on the `PATH`.
+    pub(crate) bwrap: Option<PathBuf>,
+    /// Per-package overrides.
+    #[serde(default)]
+    pub(crate) packages: HashMap<PackageName, SandboxOverride>,
+}
+
+/// Overrides of the [`SandboxConfig`] for a single package.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SandboxOverride {
+    /// Whether to sandbox build commands of this package.
+    pub(crate) enabled: Option<bool>,
+    /// Whether to allow network access for this package.
+    pub(crate) network: Option<bool>,
+    /// Additional directories that build commands of this package can read from.
+    #[serde(default)]
+    pub(crate) readable: Vec<PathBuf>,
+    /// Additional directories that build commands of this package can write to.
+    #[serde(default)]
+    pub(crate) writable: Vec<PathBuf>,
+    /// Additional environment variables to pass to build commands of this package.
+    #[serde(default)]
+    pub(crate) env: Vec<String>,
+}
+
+impl SandboxConfig {
+    /// Whether build commands of the given package are sandboxed.
+    pub(crate) fn is_enabled_for(&self, package: &PackageName) -> bool {
+        self.packages
+            .get(package)
+            .and_then(|package_override| package_override.enabled)
+            .unwrap_or(self.enabled)
+    }
+
+    /// Whether build commands of the given package have network access.
+    pub(crate) fn network_for(&self, package: &PackageName) -> bool {
+        self.packages
+            .get(package)
+            .and_then(|package_override| package_override.network)
+            .unwrap_or(self.network)
+    }
 }
 
 /// The build profile to use when compiling packages.
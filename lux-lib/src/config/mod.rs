@@ -5,15 +5,22 @@ use itertools::Itertools;
 
 use miette::Diagnostic;
 use serde::{Deserialize, Serialize, Serializer};
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::path::Path;
+use std::process::Output;
+use std::str::FromStr;
 use std::{collections::HashMap, env, path::PathBuf, time::Duration};
+use target_lexicon::Triple;
 use thiserror::Error;
 use tokio::process::Command;
 use tree::RockLayoutConfig;
 use url::Url;
 
+use crate::build::build_log::BuildLog;
+use crate::build::incremental::CModuleCache;
+use crate::build::sandbox::Sandbox;
 use crate::config::access_tokens::AccessToken;
+use crate::config::registries::Registry;
 use crate::fs;
 use crate::lua_version::LuaVersion;
 use crate::package::RemotePackageTypeFilterSpec;
@@ -25,6 +32,7 @@ use crate::{build::utils, variables::HasVariables};
 pub mod access_tokens;
 pub mod build;
 pub mod external_deps;
+pub mod registries;
 pub mod tree;
 
 const DEV_PATH: &str = "dev/";
@@ -56,13 +64,20 @@ pub struct Config {
     no_progress: bool,
     /// Skip prompts (choosing the default choice)
     no_prompt: bool,
+    /// Allow plain HTTP connections to rocks servers that are not on the loopback interface
+    allow_insecure_servers: bool,
     timeout: Duration,
+    manifest_ttl: Duration,
+    refresh_manifests: bool,
     max_jobs: usize,
     variables: HashMap<String, String>,
     access_tokens: HashMap<String, AccessToken>,
+    registries: HashMap<String, Registry>,
     external_deps: ExternalDependencySearchConfig,
 
     build: BuildConfig,
+    /// The parsed `build.target`, or the host triple.
+    target: Triple,
     entrypoint_layout: RockLayoutConfig,
 
     cache_dir: PathBuf,
@@ -72,10 +87,21 @@ pub struct Config {
     user_agent: String,
 
     generate_luarc: bool,
+    generate_compile_commands: bool,
     luarc_file_name: String,
     wrap_bin_scripts: bool,
     package_types: RemotePackageTypeFilterSpec,
     no_tfa: bool,
+
+    /// The sandbox in which build commands are run, if any.
+    /// Set for the duration of a package build.
+    sandbox: Option<Sandbox>,
+    /// The log in which build commands are recorded, if any.
+    /// Set for the duration of a package build.
+    build_log: Option<BuildLog>,
+    /// The cache from which unchanged C modules are restored, if any.
+    /// Set for the duration of a workspace member's build.
+    c_module_cache: Option<CModuleCache>,
 }
 
 impl Config {
@@ -104,6 +130,30 @@ impl Config {
         }
     }
 
+    /// Create a copy of this config that runs build commands in the given sandbox
+    pub(crate) fn with_sandbox(self, sandbox: Sandbox) -> Self {
+        Self {
+            sandbox: Some(sandbox),
+            ..self
+        }
+    }
+
+    /// Create a copy of this config that records build commands in the given log
+    pub(crate) fn with_build_log(self, build_log: BuildLog) -> Self {
+        Self {
+            build_log: Some(build_log),
+            ..self
+        }
+    }
+
+    /// Create a copy of this config that reuses unchanged C modules from the given cache
+    pub(crate) fn with_c_module_cache(self, c_module_cache: CModuleCache) -> Self {
+        Self {
+            c_module_cache: Some(c_module_cache),
+            ..self
+        }
+    }
+
     /// Create a copy of this config with the specified install tree
     pub fn with_tree(self, tree: PathBuf) -> Self {
         Self {
@@ -120,6 +170,34 @@ impl Config {
         }
     }
 
+    /// Create a copy of this config with additional variables,
+    /// which take precedence over the configured ones.
+    pub(crate) fn with_variables(
+        self,
+        variables: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let mut config = self;
+        config.variables.extend(variables);
+        config
+    }
+
+    /// Create a copy of this config that targets the named upload registry,
+    /// using its URL as the only server and its namespace and 2FA settings.
+    pub fn for_registry(self, name: &str) -> Result<Self, ConfigError> {
+        let registry = self
+            .registries
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ConfigError::UnknownRegistry(name.to_string()))?;
+        Ok(Self {
+            server: registry.url().clone(),
+            extra_servers: Vec::new(),
+            namespace: registry.namespace().cloned(),
+            no_tfa: self.no_tfa || !registry.tfa(),
+            ..self
+        })
+    }
+
     /// The luarocks repository server
     pub fn server(&self) -> &Url {
         &self.server
@@ -196,12 +274,29 @@ impl Config {
         self.no_prompt
     }
 
+    /// Whether to allow plain HTTP connections to rocks servers
+    /// that are not on the loopback interface
+    pub fn allow_insecure_servers(&self) -> bool {
+        self.allow_insecure_servers
+    }
+
     /// Timeout on network operations, in seconds.
     /// 0 means no timeout (wait forever).
     pub fn timeout(&self) -> &Duration {
         &self.timeout
     }
 
+    /// How long a cached manifest is used without revalidating it with the server.
+    /// 0 means cached manifests are always revalidated.
+    pub fn manifest_ttl(&self) -> &Duration {
+        &self.manifest_ttl
+    }
+
+    /// Whether to re-download manifests, ignoring cached manifests.
+    pub fn refresh_manifests(&self) -> bool {
+        self.refresh_manifests
+    }
+
     /// Maximum buffer size for parallel jobs, such as downloading rockspecs and installing rocks.
     /// 0 means no limit.
     pub fn max_jobs(&self) -> usize {
@@ -224,6 +319,14 @@ impl Config {
         }
     }
 
+    /// Command to use for running `meson` builds
+    pub fn meson_cmd(&self) -> String {
+        match self.variables.get("MESON") {
+            Some(meson) => meson.clone(),
+            None => "meson".into(),
+        }
+    }
+
     /// Construct a [`Command`] for the given program and arguments,
     /// wrapped in the configured [`BuildConfig::runner`], if any.
     ///
@@ -235,18 +338,50 @@ impl Config {
         A: IntoIterator,
         A::Item: AsRef<OsStr>,
     {
-        if self.build.runner.is_empty() {
-            let mut cmd = Command::new(program);
-            cmd.args(args);
-            cmd
-        } else {
-            let runner = &self.build.runner;
-            let mut cmd = Command::new(&runner[0]);
-            cmd.args(&runner[1..]).arg(program).args(args);
-            cmd
+        let (program, args) = match self.build.runner.split_first() {
+            Some((runner, runner_args)) => (
+                OsString::from(runner),
+                runner_args
+                    .iter()
+                    .map(OsString::from)
+                    .chain(std::iter::once(program.as_ref().to_os_string()))
+                    .chain(args.into_iter().map(|arg| arg.as_ref().to_os_string()))
+                    .collect_vec(),
+            ),
+            None => (
+                program.as_ref().to_os_string(),
+                args.into_iter()
+                    .map(|arg| arg.as_ref().to_os_string())
+                    .collect_vec(),
+            ),
+        };
+        match &self.sandbox {
+            Some(sandbox) => sandbox.command(program, args),
+            None => {
+                let mut cmd = Command::new(program);
+                cmd.args(args);
+                cmd
+            }
+        }
+    }
+
+    /// Records a build command that ran to completion in the build log, if one is set.
+    pub(crate) fn log_command(&self, cmd: &Command, output: &Output, duration: Duration) {
+        if let Some(build_log) = &self.build_log {
+            build_log.record(cmd, output, duration);
         }
     }
 
+    /// The cache from which unchanged C modules are restored, if any.
+    pub(crate) fn c_module_cache(&self) -> Option<&CModuleCache> {
+        self.c_module_cache.as_ref()
+    }
+
+    /// The configuration for sandboxing build commands.
+    pub(crate) fn sandbox_config(&self) -> &build::SandboxConfig {
+        &self.build.sandbox
+    }
+
     /// The build profile to use when compiling packages.
     pub(crate) fn build_profile(&self) -> build::Profile {
         self.build
@@ -256,6 +391,17 @@ impl Config {
             .unwrap_or(build::Profile::Release)
     }
 
+    /// The target triple to compile packages for.
+    /// Default: The host triple.
+    pub fn target(&self) -> Triple {
+        self.target.clone()
+    }
+
+    /// Whether packages are compiled for a target other than the host.
+    pub fn is_cross_compiling(&self) -> bool {
+        self.target() != Triple::host()
+    }
+
     /// Variable names, mapped to their values.
     /// Lux populates variables in the `lux.toml` and in RockSpecs
     /// with these before building.
@@ -280,6 +426,11 @@ impl Config {
             })
     }
 
+    /// Named upload registries, configured in the `[registries]` section.
+    pub fn registries(&self) -> &HashMap<String, Registry> {
+        &self.registries
+    }
+
     pub fn external_deps(&self) -> &ExternalDependencySearchConfig {
         &self.external_deps
     }
@@ -317,6 +468,11 @@ impl Config {
         self.generate_luarc
     }
 
+    /// Whether to generate a `compile_commands.json` when building a project with C sources.
+    pub fn generate_compile_commands(&self) -> bool {
+        self.generate_compile_commands
+    }
+
     // Lua runtime configuration file name
     pub fn luarc_file_name(&self) -> &str {
         &self.luarc_file_name
@@ -366,6 +522,12 @@ pub enum ConfigError {
         #[help]
         help: Option<String>,
     },
+    #[error("no registry named '{0}' is configured")]
+    #[diagnostic(help("add a `[registries.{0}]` section with a `url` to your Lux config"))]
+    UnknownRegistry(String),
+    #[error("invalid build target '{target}': {reason}")]
+    #[diagnostic(help("expected a target triple, e.g. `aarch64-unknown-linux-gnu`"))]
+    InvalidTarget { target: String, reason: String },
 }
 
 /// Incrementally builds a [`Config`] by layering configuration sources.
@@ -400,13 +562,19 @@ pub struct ConfigBuilder {
     verbose: Option<bool>,
     no_progress: Option<bool>,
     no_prompt: Option<bool>,
+    allow_insecure_servers: Option<bool>,
     timeout: Option<Duration>,
+    manifest_ttl: Option<Duration>,
+    #[serde(default, skip_serializing)]
+    refresh_manifests: Option<bool>,
     max_jobs: Option<usize>,
     variables: Option<HashMap<String, String>>,
     /// Access tokens for fetching sources from private hosts, mapped by host.
     /// These can also be set via the `LUX_ACCESS_TOKENS` environment variable.
     #[serde(default, skip_serializing)]
     access_tokens: Option<HashMap<String, AccessToken>>,
+    /// Named luarocks-site compatible servers that packages can be uploaded to.
+    registries: Option<HashMap<String, Registry>>,
     #[serde(default)]
     external_deps: ExternalDependencySearchConfig,
     #[serde(default)]
@@ -416,6 +584,7 @@ pub struct ConfigBuilder {
     entrypoint_layout: RockLayoutConfig,
     user_agent: Option<String>,
     generate_luarc: Option<bool>,
+    generate_compile_commands: Option<bool>,
     luarc_file_name: Option<String>,
     wrap_bin_scripts: Option<bool>,
     package_types: Option<RemotePackageTypeFilterSpec>,
@@ -558,6 +727,16 @@ impl ConfigBuilder {
         }
     }
 
+    /// Whether to allow plain HTTP connections to rocks servers
+    /// that are not on the loopback interface
+    /// Default: `false`
+    pub fn allow_insecure_servers(self, allow_insecure_servers: Option<bool>) -> Self {
+        Self {
+            allow_insecure_servers: allow_insecure_servers.or(self.allow_insecure_servers),
+            ..self
+        }
+    }
+
     /// Timeout on network operations, in seconds.
     /// 0 means no timeout (wait forever).
     /// Default: 30 s
@@ -568,6 +747,25 @@ impl ConfigBuilder {
         }
     }
 
+    /// How long a cached manifest is used without revalidating it with the server.
+    /// 0 means cached manifests are always revalidated.
+    /// Default: 0 s
+    pub fn manifest_ttl(self, manifest_ttl: Option<Duration>) -> Self {
+        Self {
+            manifest_ttl: manifest_ttl.or(self.manifest_ttl),
+            ..self
+        }
+    }
+
+    /// Whether to re-download manifests, ignoring cached manifests.
+    /// Default: `false`
+    pub fn refresh_manifests(self, refresh_manifests: Option<bool>) -> Self {
+        Self {
+            refresh_manifests: refresh_manifests.or(self.refresh_manifests),
+            ..self
+        }
+    }
+
     /// Maximum buffer size for parallel jobs, such as downloading rockspecs and installing rocks.
     /// 0 means no limit.
     /// Default: 0
@@ -631,6 +829,16 @@ impl ConfigBuilder {
         }
     }
 
+    /// Whether to generate a `compile_commands.json` in the workspace root
+    /// when building a project with C sources.
+    /// Default: `true`
+    pub fn generate_compile_commands(self, generate: Option<bool>) -> Self {
+        Self {
+            generate_compile_commands: generate.or(self.generate_compile_commands),
+            ..self
+        }
+    }
+
     /// Lua runtime configuration file name
     /// Default: `.luarc.json`
     pub fn luarc_file_name(self, file: Option<String>) -> Self {
@@ -731,6 +939,41 @@ impl ConfigBuilder {
         }
     }
 
+    /// The target triple to compile packages for, e.g. `aarch64-unknown-linux-gnu`.
+    ///
+    /// Lux selects a cross C compiler for the target (which can be overridden
+    /// with the `CC_<target>` or `TARGET_CC` environment variables),
+    /// passes `--target` to `cargo` and builds Lua for the target.
+    /// Default: The host triple.
+    pub fn build_target(self, target: Option<String>) -> Self {
+        Self {
+            build: BuildConfig {
+                target: target.or(self.build.target),
+                ..self.build
+            },
+            ..self
+        }
+    }
+
+    /// Whether to run build commands in a sandbox (Linux only).
+    ///
+    /// Sandboxed build commands can only write to the build directory and the
+    /// package's install directories, have no network access and run with a
+    /// scrubbed environment. Requires [`bubblewrap`](https://github.com/containers/bubblewrap).
+    /// Default: `false`
+    pub fn build_sandbox(self, sandbox: Option<bool>) -> Self {
+        Self {
+            build: BuildConfig {
+                sandbox: build::SandboxConfig {
+                    enabled: sandbox.unwrap_or(self.build.sandbox.enabled),
+                    ..self.build.sandbox
+                },
+                ..self.build
+            },
+            ..self
+        }
+    }
+
     /// set the default build profile to use when compiling packages.
     pub fn default_build_profile(self, profile: build::Profile) -> Self {
         Self {
@@ -761,18 +1004,26 @@ impl ConfigBuilder {
             verbose: other.verbose.or(self.verbose),
             no_progress: other.no_progress.or(self.no_progress),
             no_prompt: other.no_prompt.or(self.no_prompt),
+            allow_insecure_servers: other.allow_insecure_servers.or(self.allow_insecure_servers),
             timeout: other.timeout.or(self.timeout),
+            manifest_ttl: other.manifest_ttl.or(self.manifest_ttl),
+            refresh_manifests: other.refresh_manifests.or(self.refresh_manifests),
             max_jobs: other.max_jobs.or(self.max_jobs),
             variables: other.variables.or(self.variables),
             access_tokens: other.access_tokens.or(self.access_tokens),
+            registries: other.registries.or(self.registries),
             external_deps: other.external_deps,
             build: BuildConfig {
                 profile: other.build.profile.or(self.build.profile),
+                target: other.build.target.or(self.build.target),
                 ..other.build
             },
             entrypoint_layout: other.entrypoint_layout,
             user_agent: other.user_agent.or(self.user_agent),
             generate_luarc: other.generate_luarc.or(self.generate_luarc),
+            generate_compile_commands: other
+                .generate_compile_commands
+                .or(self.generate_compile_commands),
             luarc_file_name: other.luarc_file_name.or(self.luarc_file_name),
             wrap_bin_scripts: other.wrap_bin_scripts.or(self.wrap_bin_scripts),
             package_types: other.package_types.or(self.package_types),
@@ -790,6 +1041,14 @@ impl ConfigBuilder {
             .lua_version
             .or(crate::lua_installation::detect_installed_lua_version());
 
+        let target = match &self.build.target {
+            Some(target) => Triple::from_str(target).map_err(|err| ConfigError::InvalidTarget {
+                target: target.clone(),
+                reason: err.to_string(),
+            })?,
+            None => Triple::host(),
+        };
+
         Ok(Config {
             enable_development_packages: self.enable_development_packages.unwrap_or(false),
             server: self.server.unwrap_or_else(|| unsafe {
@@ -804,29 +1063,38 @@ impl ConfigBuilder {
             verbose: self.verbose.unwrap_or(false),
             no_progress: self.no_progress.unwrap_or(false),
             no_prompt: self.no_prompt.unwrap_or(false),
+            allow_insecure_servers: self.allow_insecure_servers.unwrap_or(false),
             timeout: self.timeout.unwrap_or_else(|| Duration::from_secs(30)),
+            manifest_ttl: self.manifest_ttl.unwrap_or_default(),
+            refresh_manifests: self.refresh_manifests.unwrap_or(false),
             max_jobs: match self.max_jobs.unwrap_or(usize::MAX) {
                 0 => usize::MAX,
                 max_jobs => max_jobs,
             },
-            variables: default_variables()
+            variables: default_variables(&target)
                 .chain(self.variables.unwrap_or_default())
                 .collect(),
             access_tokens: self.access_tokens.unwrap_or_default(),
+            registries: self.registries.unwrap_or_default(),
             external_deps: self.external_deps,
             build: self.build,
+            target,
             entrypoint_layout: self.entrypoint_layout,
             cache_dir,
             data_dir,
             vendor_dir: self.vendor_dir,
             user_agent: self.user_agent.unwrap_or(DEFAULT_USER_AGENT.into()),
             generate_luarc: self.generate_luarc.unwrap_or(true),
+            generate_compile_commands: self.generate_compile_commands.unwrap_or(true),
             luarc_file_name: self
                 .luarc_file_name
                 .unwrap_or_else(|| ".luarc.json".to_string()),
             wrap_bin_scripts: self.wrap_bin_scripts.unwrap_or(true),
             package_types: self.package_types.unwrap_or_default(),
             no_tfa: self.no_tfa.unwrap_or(false),
+            sandbox: None,
+            build_log: None,
+            c_module_cache: None,
         })
     }
 }
@@ -846,7 +1114,10 @@ impl From<Config> for ConfigBuilder {
             verbose: Some(value.verbose),
             no_progress: Some(value.no_progress),
             no_prompt: Some(value.no_prompt),
+            allow_insecure_servers: Some(value.allow_insecure_servers),
             timeout: Some(value.timeout),
+            manifest_ttl: Some(value.manifest_ttl),
+            refresh_manifests: Some(value.refresh_manifests),
             max_jobs: if value.max_jobs == usize::MAX {
                 None
             } else {
@@ -854,6 +1125,7 @@ impl From<Config> for ConfigBuilder {
             },
             variables: Some(value.variables),
             access_tokens: Some(value.access_tokens),
+            registries: Some(value.registries),
             cache_dir: Some(value.cache_dir),
             data_dir: Some(value.data_dir),
             vendor_dir: value.vendor_dir,
@@ -862,6 +1134,7 @@ impl From<Config> for ConfigBuilder {
             entrypoint_layout: value.entrypoint_layout,
             user_agent: Some(value.user_agent),
             generate_luarc: Some(value.generate_luarc),
+            generate_compile_commands: Some(value.generate_compile_commands),
             luarc_file_name: Some(value.luarc_file_name),
             wrap_bin_scripts: Some(value.wrap_bin_scripts),
             package_types: Some(value.package_types),
@@ -870,14 +1143,21 @@ impl From<Config> for ConfigBuilder {
     }
 }
 
-fn default_variables() -> impl Iterator<Item = (String, String)> {
+fn default_variables(target: &Triple) -> impl Iterator<Item = (String, String)> {
     let cflags = env::var("CFLAGS").unwrap_or(utils::default_cflags().into());
     let ldflags = env::var("LDFLAGS").unwrap_or("".into());
     vec![
         ("MAKE".into(), "make".into()),
         ("CMAKE".into(), "cmake".into()),
-        ("LIB_EXTENSION".into(), utils::c_dylib_extension().into()),
-        ("OBJ_EXTENSION".into(), utils::c_obj_extension().into()),
+        ("MESON".into(), "meson".into()),
+        (
+            "LIB_EXTENSION".into(),
+            utils::target_c_dylib_extension(target).into(),
+        ),
+        (
+            "OBJ_EXTENSION".into(),
+            utils::target_c_obj_extension(target).into(),
+        ),
         ("CFLAGS".into(), cflags),
         ("LDFLAGS".into(), ldflags),
         ("LIBFLAG".into(), utils::default_libflag().into()),
@@ -885,12 +1165,37 @@ fn default_variables() -> impl Iterator<Item = (String, String)> {
     .into_iter()
 }
 
+#[derive(Error, Debug, Diagnostic)]
+#[error("invalid server URL or path: '{0}'")]
+#[diagnostic(help("specify an HTTP(S) URL, a `file://` URL or a path to a local rocks directory"))]
+pub struct InvalidServerUrl(String);
+
+/// Parse a package server, which may be a URL or a path to a local rocks directory.
+/// Local directories are converted to `file://` URLs with a trailing slash,
+/// so that manifest and rock file names can be joined onto them.
+pub fn parse_server_url(server: &str) -> Result<Url, InvalidServerUrl> {
+    match Url::parse(server) {
+        Ok(mut url) if url.scheme() == "file" => {
+            if !url.path().ends_with('/') {
+                url.set_path(&format!("{}/", url.path()));
+            }
+            Ok(url)
+        }
+        // Single-letter schemes are Windows drive letters
+        Ok(url) if url.scheme().len() > 1 => Ok(url),
+        _ => std::path::absolute(server)
+            .ok()
+            .and_then(|path| Url::from_directory_path(path).ok())
+            .ok_or_else(|| InvalidServerUrl(server.to_string())),
+    }
+}
+
 fn deserialize_url<'de, D>(deserializer: D) -> Result<Option<Url>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
     let s = Option::<String>::deserialize(deserializer)?;
-    s.map(|s| Url::parse(&s).map_err(serde::de::Error::custom))
+    s.map(|s| parse_server_url(&s).map_err(serde::de::Error::custom))
         .transpose()
 }
 
@@ -911,7 +1216,7 @@ where
     let s = Option::<Vec<String>>::deserialize(deserializer)?;
     s.map(|v| {
         v.into_iter()
-            .map(|s| Url::parse(&s).map_err(serde::de::Error::custom))
+            .map(|s| parse_server_url(&s).map_err(serde::de::Error::custom))
             .try_collect()
     })
     .transpose()
@@ -969,6 +1274,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn build_target() {
+        let config = ConfigBuilder::default().build().unwrap();
+        assert_eq!(config.target(), Triple::host());
+        assert!(!config.is_cross_compiling());
+
+        let config: Config =
+            toml::from_str::<ConfigBuilder>("[build]\ntarget = \"aarch64-unknown-linux-gnu\"\n")
+                .unwrap()
+                .build()
+                .unwrap();
+        assert_eq!(config.target().to_string(), "aarch64-unknown-linux-gnu");
+
+        let config: Config =
+            toml::from_str::<ConfigBuilder>("[build]\ntarget = \"x86_64-pc-windows-msvc\"\n")
+                .unwrap()
+                .build()
+                .unwrap();
+        assert_eq!(config.variables().get("LIB_EXTENSION").unwrap(), "dll");
+        assert_eq!(config.variables().get("OBJ_EXTENSION").unwrap(), "obj");
+
+        assert!(matches!(
+            ConfigBuilder::default()
+                .build_target(Some("not-a-valid-triple-at-all".into()))
+                .build(),
+            Err(ConfigError::InvalidTarget { .. })
+        ));
+    }
+
+    #[test]
+    fn build_sandbox() {
+        let config: Config = toml::from_str::<ConfigBuilder>(
+            r#"
+[build.sandbox]
+enabled = true
+readable = ["/opt/cuda"]
+
+[build.sandbox.packages.luasocket]
+network = true
+
+[build.sandbox.packages.foo]
+enabled = false
+"#,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        let sandbox_config = config.sandbox_config();
+        assert!(sandbox_config.is_enabled_for(&"bar".into()));
+        assert!(!sandbox_config.network_for(&"bar".into()));
+        assert!(sandbox_config.is_enabled_for(&"luasocket".into()));
+        assert!(sandbox_config.network_for(&"luasocket".into()));
+        assert!(!sandbox_config.is_enabled_for(&"foo".into()));
+        assert_eq!(sandbox_config.readable, vec![PathBuf::from("/opt/cuda")]);
+
+        let config = ConfigBuilder::default()
+            .build_sandbox(Some(true))
+            .build()
+            .unwrap();
+        assert!(config.sandbox_config().is_enabled_for(&"bar".into()));
+    }
+
     #[test]
     fn debug_redacts_access_tokens() {
         let config: Config =
@@ -980,4 +1347,63 @@ mod tests {
         assert!(!debug.contains("supersecret"));
         assert!(debug.contains("access_tokens"));
     }
+
+    #[test]
+    fn for_registry() {
+        let config: Config = toml::from_str::<ConfigBuilder>(
+            r#"
+extra_servers = ["https://extra.example.com/"]
+
+[registries.internal]
+url = "https://rocks.internal.example.com/"
+api_key_env = "INTERNAL_ROCKS_API_KEY"
+namespace = "myorg"
+tfa = false
+"#,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        let registry = config.registries().get("internal").unwrap();
+        assert_eq!(registry.api_key_env(), Some("INTERNAL_ROCKS_API_KEY"));
+        assert!(!registry.tfa());
+
+        let registry_config = config.clone().for_registry("internal").unwrap();
+        assert_eq!(
+            registry_config.server().as_str(),
+            "https://rocks.internal.example.com/"
+        );
+        assert!(registry_config.extra_servers().is_empty());
+        assert_eq!(registry_config.namespace(), Some(&"myorg".to_string()));
+        assert!(registry_config.no_tfa());
+
+        assert!(matches!(
+            config.for_registry("public"),
+            Err(ConfigError::UnknownRegistry(_))
+        ));
+    }
+
+    #[test]
+    fn parse_local_server_urls() {
+        let url = parse_server_url("https://luarocks.org/").unwrap();
+        assert_eq!(url.as_str(), "https://luarocks.org/");
+        let url = parse_server_url("file:///srv/rocks").unwrap();
+        assert_eq!(url.as_str(), "file:///srv/rocks/");
+        let cwd = env::current_dir().unwrap();
+        let url = parse_server_url("rocks").unwrap();
+        assert_eq!(url.to_file_path().unwrap(), cwd.join("rocks"));
+        assert!(url.path().ends_with('/'));
+        let config: Config = toml::from_str::<ConfigBuilder>(
+            r#"
+server = "/srv/rocks"
+extra_servers = ["file:///mnt/nfs/rocks", "https://luarocks.org/"]
+"#,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        #[cfg(unix)]
+        assert_eq!(config.server().as_str(), "file:///srv/rocks/");
+        assert_eq!(config.extra_servers()[0].as_str(), "file:///mnt/nfs/rocks/");
+    }
 }
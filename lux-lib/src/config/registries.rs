@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize, Serializer};
+use url::Url;
+
+/// A named luarocks-site compatible server that packages can be uploaded to,
+/// configured in the `[registries.<name>]` section of the config file.
+///
+/// API keys are looked up (in order of precedence) from the `api_key_env`
+/// environment variable, the `[access_tokens]` entry for the registry name,
+/// the `[access_tokens]` entry for the registry's host, and finally `$LUX_API_KEY`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Registry {
+    #[serde(deserialize_with = "deserialize_url", serialize_with = "serialize_url")]
+    url: Url,
+    /// The environment variable to read the API key from.
+    api_key_env: Option<String>,
+    /// The server namespace (user) to use for manifest lookups.
+    namespace: Option<String>,
+    /// Whether the server expects two-factor authentication.
+    /// Default: `true`
+    tfa: Option<bool>,
+}
+
+impl Registry {
+    /// The registry's server URL
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The environment variable to read the API key from, if configured
+    pub fn api_key_env(&self) -> Option<&str> {
+        self.api_key_env.as_deref()
+    }
+
+    /// The server namespace to use, if any
+    pub fn namespace(&self) -> Option<&String> {
+        self.namespace.as_ref()
+    }
+
+    /// Whether the server expects two-factor authentication
+    pub fn tfa(&self) -> bool {
+        self.tfa.unwrap_or(true)
+    }
+}
+
+fn deserialize_url<'de, D>(deserializer: D) -> Result<Url, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    Url::parse(&s).map_err(serde::de::Error::custom)
+}
+
+fn serialize_url<S>(url: &Url, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(url.as_str())
+}
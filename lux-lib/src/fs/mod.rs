@@ -60,6 +60,15 @@ pub enum FsError {
         help("ensure the '{}' exists and is writable", std::env::temp_dir().display())
     )]
     CreateTempDir { source: std::io::Error },
+    #[error("failed to create a temporary directory in '{}'", path.display())]
+    #[diagnostic(
+        code(lux_lib::fs::create_tempdir_in),
+        help("ensure '{}' exists and is writable", path.display())
+    )]
+    CreateTempDirIn {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
     #[error("failed to create directory tree '{}'", path.display())]
     #[diagnostic(
         code(lux_lib::fs::create_dir_all),
@@ -67,6 +67,16 @@ pub(crate) fn read_dir(path: impl AsRef<Path>) -> Result<fs::ReadDir, FsError> {
     })
 }
 
+/// Wrapped [`fs::metadata`].
+#[cfg(feature = "serve")]
+pub(crate) fn metadata(path: impl AsRef<Path>) -> Result<fs::Metadata, FsError> {
+    let path = path.as_ref();
+    fs::metadata(path).map_err(|source| FsError::Metadata {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
 /// Wrapped [`fs::File::open`].
 pub(crate) fn open(path: impl AsRef<Path>) -> Result<fs::File, FsError> {
     let path = path.as_ref();
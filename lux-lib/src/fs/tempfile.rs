@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use tempfile::TempDir;
 
 use crate::fs::FsError;
@@ -6,3 +8,11 @@ use crate::fs::FsError;
 pub(crate) fn tempdir() -> Result<TempDir, FsError> {
     tempfile::tempdir().map_err(|source| FsError::CreateTempDir { source })
 }
+
+/// Wrapped [`tempfile::tempdir_in`].
+pub(crate) fn tempdir_in(dir: &Path) -> Result<TempDir, FsError> {
+    tempfile::tempdir_in(dir).map_err(|source| FsError::CreateTempDirIn {
+        path: dir.to_path_buf(),
+        source,
+    })
+}
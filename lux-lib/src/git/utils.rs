@@ -1,5 +1,7 @@
+use std::path::{Path, PathBuf};
+
 use crate::{config::Config, fs, git::url::RemoteGitUrl};
-use git2::{AutotagOption, Cred, FetchOptions, RemoteCallbacks, Repository};
+use git2::{AutotagOption, Cred, DiffOptions, FetchOptions, RemoteCallbacks, Repository};
 use itertools::Itertools;
 use miette::Diagnostic;
 use thiserror::Error;
@@ -19,6 +21,13 @@ pub enum GitError {
     RemoteList(String, #[source] git2::Error),
     #[error("could not determine latest tag or commit sha for '{0}'")]
     NoTagOrCommitSha(String),
+    #[error("no git repository found at '{0}'")]
+    #[diagnostic(help("make sure the workspace is inside a git repository"))]
+    RepoNotFound(PathBuf, #[source] git2::Error),
+    #[error("git reference '{0}' not found")]
+    RefNotFound(String, #[source] git2::Error),
+    #[error("error computing the changes since '{0}'")]
+    Diff(String, #[source] git2::Error),
 }
 
 pub(crate) enum SemVerTagOrSha {
@@ -79,6 +88,41 @@ fn latest_semver_tag(url: &RemoteGitUrl, config: &Config) -> Result<Option<Strin
         .cloned())
 }
 
+/// Get the absolute paths of the files that were added, modified or deleted
+/// in the working directory of the repository containing `path`, relative to the git `reference`.
+/// Untracked files are included.
+pub(crate) fn changed_files_since(path: &Path, reference: &str) -> Result<Vec<PathBuf>, GitError> {
+    let repo = Repository::discover(path)
+        .map_err(|err| GitError::RepoNotFound(path.to_path_buf(), err))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| {
+            GitError::RepoNotFound(
+                path.to_path_buf(),
+                git2::Error::from_str("repository has no working directory"),
+            )
+        })?
+        .to_path_buf();
+    let tree = repo
+        .revparse_single(reference)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|err| GitError::RefNotFound(reference.to_string(), err))?;
+    let mut diff_opts = DiffOptions::new();
+    diff_opts
+        .include_untracked(true)
+        .recurse_untracked_dirs(true);
+    let diff = repo
+        .diff_tree_to_workdir_with_index(Some(&tree), Some(&mut diff_opts))
+        .map_err(|err| GitError::Diff(reference.to_string(), err))?;
+    Ok(diff
+        .deltas()
+        .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .map(|file| workdir.join(file))
+        .unique()
+        .collect_vec())
+}
+
 fn mk_credentials_callback(
     url: &RemoteGitUrl,
     config: &Config,
@@ -133,6 +177,35 @@ mod tests {
         ConfigBuilder::new().unwrap().build().unwrap()
     }
 
+    #[tokio::test]
+    async fn test_changed_files_since() {
+        let temp_dir = assert_fs::TempDir::new().unwrap();
+        let repo = Repository::init(&temp_dir).unwrap();
+        let unchanged = temp_dir.join("unchanged.lua");
+        let changed = temp_dir.join("changed.lua");
+        std::fs::write(&unchanged, "return 1").unwrap();
+        std::fs::write(&changed, "return 1").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("lux", "lux@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+
+        std::fs::write(&changed, "return 2").unwrap();
+        let untracked = temp_dir.join("untracked.lua");
+        std::fs::write(&untracked, "return 3").unwrap();
+
+        let workdir = repo.workdir().unwrap().to_path_buf();
+        let changed_files = changed_files_since(&temp_dir, "HEAD").unwrap();
+        assert!(changed_files.contains(&workdir.join("changed.lua")));
+        assert!(changed_files.contains(&workdir.join("untracked.lua")));
+        assert!(!changed_files.contains(&workdir.join("unchanged.lua")));
+    }
+
     #[tokio::test]
     async fn test_latest_semver_tag_http() {
         if std::env::var("LUX_SKIP_IMPURE_TESTS").unwrap_or("0".into()) == "1" {
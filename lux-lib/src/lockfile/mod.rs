@@ -18,6 +18,8 @@ use url::Url;
 
 use crate::config::tree::RockLayoutConfig;
 use crate::fs;
+use crate::git::GitSource;
+use crate::lua_rockspec::RockSourceSpec;
 use crate::package::{
     PackageName, PackageReq, PackageSpec, PackageVersion, PackageVersionReq,
     PackageVersionReqError, RemotePackageTypeFilterSpec,
@@ -155,6 +157,11 @@ pub(crate) struct LocalPackageSpec {
     // TODO: Deserialize this directly into a `LuaPackageReq`
     pub constraint: Option<String>,
     pub binaries: RockBinaries,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Whether this package was forced by an entry in the `[overrides]` table.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub overridden: bool,
 }
 
 /// ID of a local package, a hash that is comprised of:
@@ -163,6 +170,7 @@ pub(crate) struct LocalPackageSpec {
 /// - pinned state
 /// - opt state
 /// - lock constraint
+/// - enabled features (if any)
 #[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Clone)]
 pub struct LocalPackageId(String);
 
@@ -173,11 +181,12 @@ impl LocalPackageId {
         pinned: PinnedState,
         opt: OptState,
         constraint: LockConstraint,
+        features: &[String],
     ) -> Self {
         let mut hasher = Sha256::new();
 
         hasher.update(format!(
-            "{}{}{}{}{}",
+            "{}{}{}{}{}{}",
             name,
             version,
             pinned.as_bool(),
@@ -186,6 +195,12 @@ impl LocalPackageId {
                 LockConstraint::Unconstrained => String::default(),
                 LockConstraint::Constrained(version_req) => version_req.to_string(),
             },
+            // Packages without features keep the IDs they had before features were introduced
+            if features.is_empty() {
+                String::default()
+            } else {
+                format!("+{}", features.join(","))
+            },
         ));
 
         Self(hex::encode(hasher.finalize()))
@@ -214,6 +229,7 @@ impl Display for LocalPackageId {
 }
 
 impl LocalPackageSpec {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: &PackageName,
         version: &PackageVersion,
@@ -222,6 +238,7 @@ impl LocalPackageSpec {
         pinned: &PinnedState,
         opt: &OptState,
         binaries: RockBinaries,
+        features: Vec<String>,
     ) -> Self {
         Self {
             name: name.clone(),
@@ -234,6 +251,8 @@ impl LocalPackageSpec {
                 LockConstraint::Constrained(version_req) => Some(version_req.to_string()),
             },
             binaries,
+            features: features.into_iter().sorted().dedup().collect(),
+            overridden: false,
         }
     }
 
@@ -247,6 +266,7 @@ impl LocalPackageSpec {
                 None => LockConstraint::Unconstrained,
                 Some(_) => self.constraint(),
             },
+            &self.features,
         )
     }
 
@@ -279,6 +299,20 @@ impl LocalPackageSpec {
         self.binaries.iter().collect()
     }
 
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// Whether this package was forced by an entry in the `[overrides]` table.
+    pub fn overridden(&self) -> bool {
+        self.overridden
+    }
+
+    /// Whether exactly the given features are enabled for this package.
+    pub(crate) fn has_features(&self, features: &[String]) -> bool {
+        self.features.iter().eq(features.iter().sorted().dedup())
+    }
+
     pub fn to_package(&self) -> PackageSpec {
         PackageSpec::new(self.name.clone(), self.version.clone())
     }
@@ -351,6 +385,10 @@ struct LocalPackageIntermediate {
     dependencies: Vec<LocalPackageId>,
     constraint: Option<String>,
     binaries: RockBinaries,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    features: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    overridden: bool,
     source: RemotePackageSource,
     source_url: Option<RemotePackageSourceUrl>,
     hashes: LocalPackageHashes,
@@ -361,16 +399,19 @@ impl TryFrom<LocalPackageIntermediate> for LocalPackage {
 
     fn try_from(value: LocalPackageIntermediate) -> Result<Self, Self::Error> {
         let constraint = LockConstraint::try_from(&value.constraint)?;
+        let mut spec = LocalPackageSpec::new(
+            &value.name,
+            &value.version,
+            constraint,
+            value.dependencies,
+            &value.pinned,
+            &value.opt,
+            value.binaries,
+            value.features,
+        );
+        spec.overridden = value.overridden;
         Ok(Self {
-            spec: LocalPackageSpec::new(
-                &value.name,
-                &value.version,
-                constraint,
-                value.dependencies,
-                &value.pinned,
-                &value.opt,
-                value.binaries,
-            ),
+            spec,
             source: value.source,
             source_url: value.source_url,
             hashes: value.hashes,
@@ -388,6 +429,8 @@ impl From<&LocalPackage> for LocalPackageIntermediate {
             dependencies: value.spec.dependencies.clone(),
             constraint: value.spec.constraint.clone(),
             binaries: value.spec.binaries.clone(),
+            features: value.spec.features.clone(),
+            overridden: value.spec.overridden,
             source: value.source.clone(),
             source_url: value.source_url.clone(),
             hashes: value.hashes.clone(),
@@ -432,6 +475,7 @@ impl LocalPackage {
                 &PinnedState::Unpinned,
                 &OptState::Required,
                 binaries,
+                Vec::new(),
             ),
             source,
             source_url,
@@ -459,6 +503,49 @@ impl LocalPackage {
         self.spec.opt()
     }
 
+    pub fn features(&self) -> &[String] {
+        self.spec.features()
+    }
+
+    /// Whether this package was forced by an entry in the `[overrides]` table.
+    pub fn overridden(&self) -> bool {
+        self.spec.overridden()
+    }
+
+    /// Whether this package satisfies the override for its name, if any.
+    pub(crate) fn satisfies_override(
+        &self,
+        overrides: &HashMap<PackageName, LuaDependencySpec>,
+    ) -> bool {
+        let Some(dep) = overrides.get(self.name()) else {
+            return true;
+        };
+        dep.version_req().matches(self.version())
+            && match (dep.source(), &self.source_url) {
+                (None, _) => true,
+                (
+                    Some(RockSourceSpec::Git(GitSource { url, checkout_ref })),
+                    Some(RemotePackageSourceUrl::Git {
+                        url: locked_url,
+                        checkout_ref: locked_ref,
+                        ..
+                    }),
+                ) => {
+                    &url.to_string() == locked_url
+                        && checkout_ref.as_ref().is_none_or(|rev| rev == locked_ref)
+                }
+                (
+                    Some(RockSourceSpec::File(path)),
+                    Some(RemotePackageSourceUrl::File { path: locked_path }),
+                ) => path == locked_path,
+                (
+                    Some(RockSourceSpec::Url(url)),
+                    Some(RemotePackageSourceUrl::Url { url: locked_url }),
+                ) => url == locked_url,
+                _ => false,
+            }
+    }
+
     pub(crate) fn source(&self) -> &RemotePackageSource {
         &self.source
     }
@@ -666,10 +753,10 @@ impl LocalPackageLock {
         self.list()
             .get(req.name())
             .map(|packages| {
-                packages
-                    .iter()
-                    .rev()
-                    .find(|package| package.constraint().matches_version_req(req.version_req()))
+                packages.iter().rev().find(|package| {
+                    package.constraint().matches_version_req(req.version_req())
+                        && package.spec.has_features(req.features())
+                })
             })?
             .cloned()
     }
@@ -699,6 +786,7 @@ impl LocalPackageLock {
                     local_pkg
                         .constraint()
                         .matches_version_req(req.version_req())
+                        && local_pkg.spec.has_features(req.features())
                 })
             })
             .cloned()
@@ -729,6 +817,27 @@ impl LocalPackageLock {
         PackageSyncSpec { to_add, to_remove }
     }
 
+    /// Entrypoints that depend (transitively) on a package that does not
+    /// satisfy the override for its name, and which must therefore be re-resolved.
+    pub(crate) fn entrypoints_violating_overrides(
+        &self,
+        overrides: &HashMap<PackageName, LuaDependencySpec>,
+    ) -> Vec<LocalPackage> {
+        if overrides.is_empty() {
+            return Vec::new();
+        }
+        self.entrypoints
+            .iter()
+            .filter(|id| {
+                self.get_all_dependencies(id)
+                    .iter()
+                    .any(|pkg| !pkg.satisfies_override(overrides))
+            })
+            .filter_map(|id| self.get(id))
+            .cloned()
+            .collect_vec()
+    }
+
     /// Return all dependencies of a package, including itself
     fn get_all_dependencies(&self, id: &LocalPackageId) -> HashSet<&LocalPackage> {
         let mut packages = HashSet::new();
@@ -1692,6 +1801,111 @@ mod tests {
             .any(|pkg| pkg.name().to_string() == "nvim-nio"));
     }
 
+    #[test]
+    fn test_sync_spec_different_features() {
+        let lockfile = get_test_lockfile();
+        let mut neorg: LuaDependencySpec = PackageReq::parse("neorg@8.8.1-1").unwrap().into();
+        neorg.features = vec!["treesitter".into()];
+        let sync_spec = lockfile
+            .lock
+            .package_sync_spec(&[neorg], &SyncStrategy::LockfileOnly);
+
+        // The installed neorg has no features enabled, so it should be replaced
+        assert!(sync_spec
+            .to_add
+            .iter()
+            .any(|req| req.name().to_string() == "neorg" && req.features() == ["treesitter"]));
+        assert!(sync_spec
+            .to_remove
+            .iter()
+            .any(|pkg| pkg.name().to_string() == "neorg"));
+    }
+
+    #[test]
+    fn test_entrypoints_violating_overrides() {
+        let lockfile = get_test_lockfile();
+        let overrides = |deps: Vec<LuaDependencySpec>| {
+            deps.into_iter()
+                .map(|dep| (dep.name().clone(), dep))
+                .collect::<HashMap<_, _>>()
+        };
+        let violating = |deps: Vec<LuaDependencySpec>| {
+            lockfile
+                .lock
+                .entrypoints_violating_overrides(&overrides(deps))
+                .into_iter()
+                .map(|pkg| pkg.name().to_string())
+                .sorted()
+                .collect_vec()
+        };
+
+        assert!(violating(Vec::new()).is_empty());
+        assert!(violating(vec![PackageReq::parse("nvim-nio>=1.7.0").unwrap().into()]).is_empty());
+        // neorg depends on nvim-nio 1.7.0
+        assert_eq!(
+            violating(vec![PackageReq::parse("nvim-nio@1.10.1-1").unwrap().into()]),
+            vec!["neorg"]
+        );
+
+        let say_from_git = |rev: &str| LuaDependencySpec {
+            source: Some(RockSourceSpec::Git(GitSource {
+                url: "https://github.com/lunarmodules/say.git".parse().unwrap(),
+                checkout_ref: Some(rev.into()),
+            })),
+            ..PackageReq::parse("say@1.4.1-3").unwrap().into()
+        };
+        assert!(violating(vec![say_from_git("v1.4.1")]).is_empty());
+        assert_eq!(violating(vec![say_from_git("fix-say")]), vec!["say"]);
+    }
+
+    #[test]
+    fn local_package_features() {
+        let package = PackageSpec::parse("foo".to_string(), "1.0.0".to_string()).unwrap();
+        let spec = |features: Vec<String>| {
+            LocalPackageSpec::new(
+                package.name(),
+                package.version(),
+                LockConstraint::Unconstrained,
+                Vec::new(),
+                &PinnedState::Unpinned,
+                &OptState::Required,
+                RockBinaries::default(),
+                features,
+            )
+        };
+        let without_features = spec(Vec::new());
+        let with_features = spec(vec!["tls".into(), "curl".into(), "tls".into()]);
+
+        // Features are normalised, so their order doesn't affect the package ID
+        assert_eq!(with_features.features(), ["curl", "tls"]);
+        assert!(with_features.has_features(&["tls".into(), "curl".into()]));
+        assert!(!without_features.has_features(&["tls".into()]));
+        assert_eq!(
+            with_features.id(),
+            spec(vec!["curl".into(), "tls".into()]).id()
+        );
+        assert_ne!(with_features.id(), without_features.id());
+
+        // Packages without features keep their IDs
+        assert_eq!(
+            without_features.id(),
+            LocalPackageId::new(
+                package.name(),
+                package.version(),
+                PinnedState::Unpinned,
+                OptState::Required,
+                LockConstraint::Unconstrained,
+                &[],
+            )
+        );
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}{}falsefalse", package.name(), package.version()));
+        assert_eq!(
+            without_features.id().into_string(),
+            hex::encode(hasher.finalize())
+        );
+    }
+
     #[test]
     fn test_sync_spec_ensure_installed() {
         let temp = assert_fs::TempDir::new().unwrap();
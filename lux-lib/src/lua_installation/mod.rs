@@ -108,8 +108,11 @@ impl LuaInstallation {
     #[tracing::instrument(level = "trace", skip(config))]
     pub async fn new(version: &LuaVersion, config: &Config) -> Result<Self, LuaInstallationError> {
         let _lock = NEW_MUTEX.lock().await;
-        if let Some(lua_intallation) = Self::probe(version, config.external_deps()) {
-            return Ok(lua_intallation);
+        // A system Lua installation is built for the host, so we can't use it when cross-compiling.
+        if !config.is_cross_compiling() {
+            if let Some(lua_intallation) = Self::probe(version, config.external_deps()) {
+                return Ok(lua_intallation);
+            }
         }
         let output = Self::root_dir(version, config);
         let include_dir = output.join("include");
@@ -234,13 +237,37 @@ impl LuaInstallation {
         &self.bin
     }
 
+    /// The directories containing the Lua headers, library and binary.
+    pub(crate) fn install_dirs(&self) -> Vec<PathBuf> {
+        let info = &self.dependency_info;
+        [&info.include_dir, &info.lib_dir, &info.bin_dir]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .chain(
+                self.bin
+                    .iter()
+                    .filter_map(|bin| bin.parent())
+                    .map(PathBuf::from),
+            )
+            .collect_vec()
+    }
+
     fn root_dir(version: &LuaVersion, config: &Config) -> PathBuf {
         if let Some(lua_dir) = config.lua_dir() {
             return lua_dir.clone();
-        } else if let Ok(tree) = config.user_tree(version.clone()) {
-            return tree.root().join(".lua");
         }
-        config.data_dir().join(".lua").join(version.to_string())
+        let root_dir = if let Ok(tree) = config.user_tree(version.clone()) {
+            tree.root().join(".lua")
+        } else {
+            config.data_dir().join(".lua").join(version.to_string())
+        };
+        // Lua builds for other targets are kept apart from the host build.
+        if config.is_cross_compiling() {
+            root_dir.join(config.target().to_string())
+        } else {
+            root_dir
+        }
     }
 
     #[cfg(not(target_env = "msvc"))]
@@ -0,0 +1,31 @@
+/// Specification for building a rock with the `autotools` build backend
+#[derive(Debug, PartialEq, Clone)]
+pub struct AutotoolsBuildSpec {
+    /// Extra arguments to pass to the `configure` script.
+    pub configure_args: Vec<String>,
+    /// Whether to configure and build in a separate build directory,
+    /// instead of the source directory.
+    /// Default is false.
+    pub out_of_tree: bool,
+    /// Whether to perform a build pass.
+    /// Default is true.
+    pub build_pass: bool,
+    /// Whether to perform an install pass.
+    /// Default is true.
+    pub install_pass: bool,
+}
+
+impl Default for AutotoolsBuildSpec {
+    fn default() -> Self {
+        Self {
+            configure_args: Vec::default(),
+            out_of_tree: false,
+            build_pass: default_pass(),
+            install_pass: default_pass(),
+        }
+    }
+}
+
+fn default_pass() -> bool {
+    true
+}
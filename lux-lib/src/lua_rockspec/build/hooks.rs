@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::lua_rockspec::{DisplayAsLuaKV, DisplayAsLuaValue, DisplayLuaKV, DisplayLuaValue};
+
+/// Hooks that are run before and after the build backend, specified by `build.hooks`.
+///
+/// The hooks run in the build directory, with the install directories of the rock
+/// available as the `PREFIX`, `LUADIR`, `LIBDIR`, `BINDIR`, `CONFDIR` and `DOCDIR`
+/// environment variables.
+#[derive(Debug, PartialEq, Default, Deserialize, Clone, lux_macros::DisplayAsLuaKV)]
+#[display_lua(key = "hooks")]
+pub struct BuildHooks {
+    /// Hooks to run before the build backend, e.g. to generate sources.
+    #[serde(default)]
+    pub pre_build: Vec<BuildHook>,
+    /// Hooks to run after the build backend.
+    #[serde(default)]
+    pub post_build: Vec<BuildHook>,
+}
+
+impl BuildHooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_build.is_empty() && self.post_build.is_empty()
+    }
+}
+
+/// A build hook.
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum BuildHook {
+    /// A Lua script, relative to the build directory,
+    /// run with the project's Lua interpreter and its dependencies on the `LUA_PATH`.
+    Script {
+        script: PathBuf,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// A shell command, in which variables like `$(PREFIX)` are substituted.
+    Command { command: String },
+}
+
+impl DisplayAsLuaValue for BuildHook {
+    fn display_lua_value(&self) -> DisplayLuaValue {
+        match self {
+            Self::Script { script, args } => {
+                let mut fields = vec![DisplayLuaKV {
+                    key: "script".into(),
+                    value: script.display_lua_value(),
+                }];
+                if !args.is_empty() {
+                    fields.push(DisplayLuaKV {
+                        key: "args".into(),
+                        value: args.display_lua_value(),
+                    });
+                }
+                DisplayLuaValue::Table(fields)
+            }
+            Self::Command { command } => DisplayLuaValue::Table(vec![DisplayLuaKV {
+                key: "command".into(),
+                value: command.display_lua_value(),
+            }]),
+        }
+    }
+}
+
+impl DisplayAsLuaValue for Vec<BuildHook> {
+    fn display_lua_value(&self) -> DisplayLuaValue {
+        DisplayLuaValue::List(self.iter().map(|hook| hook.display_lua_value()).collect())
+    }
+}
+
+impl DisplayAsLuaValue for BuildHooks {
+    fn display_lua_value(&self) -> DisplayLuaValue {
+        self.display_lua().value
+    }
+}
@@ -0,0 +1,28 @@
+use std::collections::HashMap;
+
+/// Specification for building a rock with the `meson` build backend
+#[derive(Debug, PartialEq, Clone)]
+pub struct MesonBuildSpec {
+    /// Whether to perform a build pass.
+    /// Default is true.
+    pub build_pass: bool,
+    /// Whether to perform an install pass.
+    /// Default is true.
+    pub install_pass: bool,
+    /// Build options, passed to `meson setup` as `-D<key>=<value>`.
+    pub variables: HashMap<String, String>,
+}
+
+impl Default for MesonBuildSpec {
+    fn default() -> Self {
+        Self {
+            build_pass: default_pass(),
+            install_pass: default_pass(),
+            variables: Default::default(),
+        }
+    }
+}
+
+fn default_pass() -> bool {
+    true
+}
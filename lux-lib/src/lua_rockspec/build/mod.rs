@@ -1,12 +1,18 @@
+mod autotools;
 mod builtin;
 mod cmake;
+mod hooks;
 mod make;
+mod meson;
 mod rust_mlua;
 mod tree_sitter;
 
+pub use autotools::*;
 pub use builtin::{BuiltinBuildSpec, LuaModule, ModulePaths, ModuleSpec, ParseLuaModuleError};
 pub use cmake::*;
+pub use hooks::*;
 pub use make::*;
+pub use meson::*;
 use path_slash::PathBufExt;
 pub use rust_mlua::*;
 pub use tree_sitter::*;
@@ -49,6 +55,8 @@ pub struct BuildSpec {
     // NOTE: This cannot be a diffy::Patch<'a, str>
     // because Lua::from_value requires a DeserializeOwned
     pub patches: HashMap<PathBuf, String>,
+    /// Hooks to run before and after the build backend.
+    pub hooks: BuildHooks,
 }
 
 impl Default for BuildSpec {
@@ -58,6 +66,7 @@ impl Default for BuildSpec {
             install: InstallSpec::default(),
             copy_directories: Vec::default(),
             patches: HashMap::default(),
+            hooks: BuildHooks::default(),
         }
     }
 }
@@ -129,6 +138,23 @@ impl BuildSpec {
                     variables: internal.variables.unwrap_or_default(),
                 }))
             }
+            BuildType::Meson => {
+                let default = MesonBuildSpec::default();
+                Some(BuildBackendSpec::Meson(MesonBuildSpec {
+                    build_pass: internal.build_pass.unwrap_or(default.build_pass),
+                    install_pass: internal.install_pass.unwrap_or(default.install_pass),
+                    variables: internal.variables.unwrap_or_default(),
+                }))
+            }
+            BuildType::Autotools => {
+                let default = AutotoolsBuildSpec::default();
+                Some(BuildBackendSpec::Autotools(AutotoolsBuildSpec {
+                    configure_args: internal.configure_args.unwrap_or_default(),
+                    out_of_tree: internal.out_of_tree.unwrap_or(default.out_of_tree),
+                    build_pass: internal.build_pass.unwrap_or(default.build_pass),
+                    install_pass: internal.install_pass.unwrap_or(default.install_pass),
+                }))
+            }
             BuildType::Command => Some(BuildBackendSpec::Command(CommandBuildSpec {
                 build_command: internal.build_command,
                 install_command: internal.install_command,
@@ -191,6 +217,7 @@ impl BuildSpec {
             install: internal.install.unwrap_or_default(),
             copy_directories: internal.copy_directories.unwrap_or_default(),
             patches: internal.patches.unwrap_or_default(),
+            hooks: internal.hooks.unwrap_or_default(),
         })
     }
 }
@@ -243,6 +270,8 @@ pub enum BuildBackendSpec {
     Builtin(BuiltinBuildSpec),
     Make(MakeBuildSpec),
     CMake(CMakeBuildSpec),
+    Meson(MesonBuildSpec),
+    Autotools(AutotoolsBuildSpec),
     Command(CommandBuildSpec),
     LuaRock(String),
     RustMlua(RustMluaBuildSpec),
@@ -255,10 +284,33 @@ pub enum BuildBackendSpec {
     Source,
 }
 
+/// Displays the `build.type` of the build backend.
+impl Display for BuildBackendSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Builtin(_) => write!(f, "builtin"),
+            Self::Make(_) => write!(f, "make"),
+            Self::CMake(_) => write!(f, "cmake"),
+            Self::Meson(_) => write!(f, "meson"),
+            Self::Autotools(_) => write!(f, "autotools"),
+            Self::Command(_) => write!(f, "command"),
+            Self::LuaRock(s) => write!(f, "{s}"),
+            Self::RustMlua(_) => write!(f, "rust-mlua"),
+            Self::TreesitterParser(_) => write!(f, "treesitter-parser"),
+            Self::Source => write!(f, "source"),
+        }
+    }
+}
+
 impl BuildBackendSpec {
     pub(crate) fn can_use_build_dependencies(&self) -> bool {
         match self {
-            Self::Make(_) | Self::CMake(_) | Self::Command(_) | Self::LuaRock(_) => true,
+            Self::Make(_)
+            | Self::CMake(_)
+            | Self::Meson(_)
+            | Self::Autotools(_)
+            | Self::Command(_)
+            | Self::LuaRock(_) => true,
             Self::Builtin(_) | Self::RustMlua(_) | Self::TreesitterParser(_) | Self::Source => {
                 false
             }
@@ -529,6 +581,10 @@ pub(crate) struct BuildSpecInternal {
     #[display_lua(rename = "cmake")]
     pub(crate) cmake_lists_content: Option<String>,
     #[serde(default)]
+    pub(crate) configure_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub(crate) out_of_tree: Option<bool>,
+    #[serde(default)]
     pub(crate) build_command: Option<String>,
     #[serde(default)]
     pub(crate) install_command: Option<String>,
@@ -560,6 +616,8 @@ pub(crate) struct BuildSpecInternal {
     #[serde(default)]
     #[display_lua(convert_with = "display_path_string_map")]
     pub(crate) queries: Option<HashMap<PathBuf, String>>,
+    #[serde(default)]
+    pub(crate) hooks: Option<BuildHooks>,
 }
 
 impl PartialOverride for BuildSpecInternal {
@@ -637,6 +695,8 @@ fn override_build_spec_internal(
             &override_spec.cmake_lists_content,
             &base.cmake_lists_content,
         ),
+        configure_args: override_opt(&override_spec.configure_args, &base.configure_args),
+        out_of_tree: override_opt(&override_spec.out_of_tree, &base.out_of_tree),
         build_command: override_opt(&override_spec.build_command, &base.build_command),
         install_command: override_opt(&override_spec.install_command, &base.install_command),
         install: override_opt(&override_spec.install, &base.install),
@@ -664,6 +724,7 @@ fn override_build_spec_internal(
         generate: override_opt(&override_spec.generate, &base.generate),
         location: override_opt(&override_spec.location, &base.location),
         queries: merge_map_opts(&override_spec.queries, &base.queries),
+        hooks: override_opt(&override_spec.hooks, &base.hooks),
     })
 }
 
@@ -706,6 +767,10 @@ pub(crate) enum BuildType {
     Make,
     /// "cmake"
     CMake,
+    /// "meson"
+    Meson,
+    /// "autotools"
+    Autotools,
     /// "command"
     Command,
     /// "none"
@@ -725,6 +790,8 @@ impl BuildType {
             &BuildType::Builtin
             | &BuildType::Make
             | &BuildType::CMake
+            | &BuildType::Meson
+            | &BuildType::Autotools
             | &BuildType::Command
             | &BuildType::None
             | &BuildType::Source => None,
@@ -769,6 +836,8 @@ impl Display for BuildType {
             BuildType::Builtin => write!(f, "builtin"),
             BuildType::Make => write!(f, "make"),
             BuildType::CMake => write!(f, "cmake"),
+            BuildType::Meson => write!(f, "meson"),
+            BuildType::Autotools => write!(f, "autotools"),
             BuildType::Command => write!(f, "command"),
             BuildType::None => write!(f, "none"),
             BuildType::LuaRock(s) => write!(f, "{s}"),
@@ -895,4 +964,63 @@ mod tests {
         let restored: BuildSpecInternal = eval_lua_global(&lua, "build");
         assert_eq!(spec, restored);
     }
+
+    #[test]
+    pub fn build_spec_internal_hooks_roundtrip() {
+        let hooks = BuildHooks {
+            pre_build: vec![
+                BuildHook::Script {
+                    script: "scripts/gen-proto.lua".into(),
+                    args: vec!["proto".into()],
+                },
+                BuildHook::Command {
+                    command: "echo $(PREFIX)".into(),
+                },
+            ],
+            post_build: vec![BuildHook::Script {
+                script: "scripts/post.lua".into(),
+                args: Vec::new(),
+            }],
+        };
+        let spec = BuildSpecInternal {
+            build_type: Some(BuildType::Builtin),
+            hooks: Some(hooks.clone()),
+            ..Default::default()
+        };
+        let lua = spec.display_lua().to_string();
+        let restored: BuildSpecInternal = eval_lua_global(&lua, "build");
+        assert_eq!(spec, restored);
+        assert_eq!(
+            BuildSpec::from_internal_spec(restored).unwrap().hooks,
+            hooks
+        );
+    }
+
+    #[test]
+    pub fn build_spec_internal_autotools_roundtrip() {
+        let spec = BuildSpecInternal {
+            build_type: Some(BuildType::Autotools),
+            configure_args: Some(vec![
+                "--disable-docs".into(),
+                "--with-foo=$(FOO_DIR)".into(),
+            ]),
+            out_of_tree: Some(true),
+            install_pass: Some(false),
+            ..Default::default()
+        };
+        let lua = spec.display_lua().to_string();
+        let restored: BuildSpecInternal = eval_lua_global(&lua, "build");
+        assert_eq!(spec, restored);
+        assert_eq!(
+            BuildSpec::from_internal_spec(restored)
+                .unwrap()
+                .build_backend,
+            Some(BuildBackendSpec::Autotools(AutotoolsBuildSpec {
+                configure_args: vec!["--disable-docs".into(), "--with-foo=$(FOO_DIR)".into()],
+                out_of_tree: true,
+                build_pass: true,
+                install_pass: false,
+            }))
+        );
+    }
 }
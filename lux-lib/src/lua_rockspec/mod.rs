@@ -480,6 +480,7 @@ build = {{
                 install: InstallSpec::default(),
                 copy_directories: Vec::new(),
                 patches: HashMap::new(),
+                hooks: BuildHooks::default(),
             }),
             source: PerPlatform::new(source.clone()),
             test: PerPlatform::default(),
@@ -1169,6 +1170,31 @@ mod tests {
         source = {\n
             url = 'git+https://hub.com/example-project/foo.zip',\n
         }\n
+        build = {\n
+            type = 'meson',\n
+            install_pass = false,\n
+            variables = {\n
+                with_docs = 'false',\n
+            },\n
+        }\n
+        "
+        .to_string();
+        let rockspec = RemoteLuaRockspec::new(&rockspec_content).unwrap();
+        assert_eq!(
+            rockspec.local.build.default.build_backend,
+            Some(BuildBackendSpec::Meson(MesonBuildSpec {
+                build_pass: true,
+                install_pass: false,
+                variables: HashMap::from([("with_docs".into(), "false".into())]),
+            }))
+        );
+        let rockspec_content = "
+        rockspec_format = '1.0'\n
+        package = 'foo'\n
+        version = '1.0.0-1'\n
+        source = {\n
+            url = 'git+https://hub.com/example-project/foo.zip',\n
+        }\n
         build = {\n
             type = 'command',\n
             build_command = 'foo',\n
@@ -9,6 +9,7 @@ use serde_enum_str::{Deserialize_enum_str, Serialize_enum_str};
 use std::{cmp::Ordering, collections::HashMap};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
+use target_lexicon::{Environment, OperatingSystem, Triple};
 use thiserror::Error;
 
 use super::{normalize_lua_value, DisplayAsLuaKV, DisplayLuaKV, DisplayLuaValue, LuaValueSeed};
@@ -57,12 +58,9 @@ impl PartialOrd for PlatformIdentifier {
     }
 }
 
-/// Retrieves the platform identifier for the target platform
+/// Retrieves the platform identifier for the platform lux was built with.
 ///
-/// NOTE: This is the platform lux was built with.
-/// As we don't support cross-compiling luarocks packages, we currently expect
-/// users to use a version of lux that was built with the same platform
-/// as the one they are targeting
+/// NOTE: Use [`PlatformIdentifier::for_target`] where the configured build target is known.
 fn target_identifier() -> PlatformIdentifier {
     if cfg!(target_env = "msvc") {
         PlatformIdentifier::Windows
@@ -80,6 +78,21 @@ fn target_identifier() -> PlatformIdentifier {
 }
 
 impl PlatformIdentifier {
+    /// The platform identifier of a compilation target.
+    pub fn for_target(target: &Triple) -> Self {
+        if *target == Triple::host() {
+            return target_identifier();
+        }
+        match target.operating_system {
+            _ if target.environment == Environment::Msvc => PlatformIdentifier::Windows,
+            OperatingSystem::Windows => PlatformIdentifier::Windows,
+            OperatingSystem::Linux => PlatformIdentifier::Linux,
+            OperatingSystem::Darwin(_) | OperatingSystem::MacOSX(_) => PlatformIdentifier::MacOSX,
+            OperatingSystem::Freebsd => PlatformIdentifier::FreeBSD,
+            _ => PlatformIdentifier::Unix,
+        }
+    }
+
     /// Get identifiers that are a subset of this identifier.
     /// For example, Unix is a subset of Linux
     pub fn get_subsets(&self) -> Vec<Self> {
@@ -287,12 +300,28 @@ impl<T> PerPlatform<T> {
         }
     }
 
-    /// Merge per-platform overrides for the configured build target platform,
+    /// The base data, applicable if no platform-specific override is present.
+    pub fn base(&self) -> &T {
+        &self.default
+    }
+
+    /// The per-platform overrides.
+    pub fn per_platform(&self) -> &HashMap<PlatformIdentifier, T> {
+        &self.per_platform
+    }
+
+    /// Merge per-platform overrides for the platform lux was built with,
     /// with more specific platform overrides having higher priority.
     pub fn current_platform(&self) -> &T {
         self.for_platform_identifier(&target_identifier())
     }
 
+    /// Merge per-platform overrides for a compilation target's platform,
+    /// with more specific platform overrides having higher priority.
+    pub fn for_target(&self, target: &Triple) -> &T {
+        self.for_platform_identifier(&PlatformIdentifier::for_target(target))
+    }
+
     fn for_platform_identifier(&self, identifier: &PlatformIdentifier) -> &T {
         self.get(identifier)
     }
@@ -621,6 +650,31 @@ mod tests {
 
     fn run_test_target_identifier(expected: PlatformIdentifier) {
         assert_eq!(expected, target_identifier());
+        assert_eq!(expected, PlatformIdentifier::for_target(&Triple::host()));
+    }
+
+    #[tokio::test]
+    async fn per_platform_for_target() {
+        let foo = PerPlatform {
+            default: "default",
+            per_platform: vec![
+                (PlatformIdentifier::Unix, "unix"),
+                (PlatformIdentifier::Windows, "windows"),
+                (PlatformIdentifier::Linux, "linux"),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let target = |triple: &str| triple.parse::<Triple>().unwrap();
+        assert_eq!(
+            *foo.for_target(&target("x86_64-pc-windows-msvc")),
+            "windows"
+        );
+        assert_eq!(*foo.for_target(&target("aarch64-apple-darwin")), "unix");
+        assert_eq!(
+            *foo.for_target(&target("riscv64gc-unknown-linux-gnu")),
+            "linux"
+        );
     }
 
     proptest! {
@@ -24,6 +24,7 @@ use crate::{
 };
 use crate::{fs, lockfile::RemotePackageSourceUrl, rockspec::LuaVersionCompatibility};
 use bytes::Bytes;
+use itertools::Itertools;
 use miette::Diagnostic;
 use thiserror::Error;
 
@@ -74,6 +75,7 @@ where
     entry_type: tree::EntryType,
     constraint: LockConstraint,
     behaviour: BuildBehaviour,
+    features: Vec<String>,
     config: &'a Config,
     tree: &'a T,
 }
@@ -100,6 +102,7 @@ where
             behaviour: BuildBehaviour::default(),
             pin: PinnedState::default(),
             opt: OptState::default(),
+            features: Vec::new(),
             entry_type,
         }
     }
@@ -120,6 +123,15 @@ where
         Self { behaviour, ..self }
     }
 
+    /// The enabled features to record in the lockfile.
+    /// Binary rocks are pre-built, so they don't affect the build.
+    pub(crate) fn features(self, features: Vec<String>) -> Self {
+        Self {
+            features: features.into_iter().sorted().dedup().collect(),
+            ..self
+        }
+    }
+
     #[tracing::instrument(name = "Installing binary rock", skip_all)]
     pub(crate) async fn install(self) -> Result<LocalPackage, InstallBinaryRockError> {
         let rockspec = self.rockspec;
@@ -149,6 +161,7 @@ where
         );
         package.spec.pinned = self.pin;
         package.spec.opt = self.opt;
+        package.spec.features = self.features;
         match self.tree.lockfile()?.get(&package.id()) {
             Some(package) if self.behaviour == BuildBehaviour::NoForce => Ok(package.clone()),
             _ => {
@@ -294,9 +307,11 @@ mod tests {
             &config,
             &tree,
         )
+        .features(vec!["b".into(), "a".into(), "b".into()])
         .install()
         .await
         .unwrap();
+        assert_eq!(local_package.spec.features(), ["a", "b"]);
         let rock_layout = tree.entrypoint_layout(&local_package);
         let foo_bar_module = rock_layout.src.join("foo").join("bar.lua");
         assert!(foo_bar_module.is_file());
@@ -5,6 +5,7 @@ use std::{
     io,
     path::{Path, PathBuf},
     process::ExitStatus,
+    time::Instant,
 };
 use thiserror::Error;
 
@@ -267,10 +268,8 @@ variables = {{
         if !luarocks_bin.is_file() {
             return Err(ExecLuaRocksError::LuarocksBinNotFound(luarocks_bin));
         }
-        let output = self
-            .config
-            .wrapped_command(luarocks_bin, args)
-            .current_dir(cwd)
+        let mut cmd = self.config.wrapped_command(luarocks_bin, args);
+        cmd.current_dir(cwd)
             .env("PATH", luarocks_paths.path_prepended().joined())
             .env("LUA_PATH", luarocks_paths.package_path().joined())
             .env("LUA_CPATH", luarocks_paths.package_cpath().joined())
@@ -278,9 +277,10 @@ variables = {{
             .env(
                 "LUAROCKS_CONFIG",
                 luarocks_config.to_slash_lossy().to_string(),
-            )
-            .output()
-            .await?;
+            );
+        let started = Instant::now();
+        let output = cmd.output().await?;
+        self.config.log_command(&cmd, &output, started.elapsed());
         if output.status.success() {
             build::utils::trace_command_output(&output);
             Ok(())
@@ -2,6 +2,10 @@ pub mod install_binary_rock;
 pub mod luarocks_installation;
 pub mod rock_manifest;
 
+use target_lexicon::{OperatingSystem, Triple};
+
+use crate::config::Config;
+
 /// Retrieves the target compilation platform and returns it as a luarocks identifier.
 pub(crate) fn current_platform_luarocks_identifier() -> String {
     let platform = match std::env::consts::OS {
@@ -10,3 +14,84 @@ pub(crate) fn current_platform_luarocks_identifier() -> String {
     };
     format!("{}-{}", platform, std::env::consts::ARCH)
 }
+
+/// Returns the luarocks architecture of the binary rocks that can be installed
+/// for the configured target, e.g. `linux-x86_64`.
+///
+/// Returns `None` when cross-compiling, as binary rocks are built against the host's Lua
+/// and we can't verify that a rock for the target's architecture links against the
+/// target's Lua. Only architecture-independent (`all`) binary rocks are considered then.
+pub(crate) fn binary_rock_arch(config: &Config) -> Option<String> {
+    if config.is_cross_compiling() {
+        None
+    } else {
+        Some(target_platform_luarocks_identifier(&config.target()))
+    }
+}
+
+/// Returns the luarocks identifier of the platform a target triple compiles for,
+/// e.g. `linux-aarch64` for `aarch64-unknown-linux-gnu`.
+pub(crate) fn target_platform_luarocks_identifier(target: &Triple) -> String {
+    let platform = match target.operating_system {
+        OperatingSystem::Darwin(_) | OperatingSystem::MacOSX(_) => "macosx".to_string(),
+        OperatingSystem::Windows => "windows".to_string(),
+        os => os.to_string(),
+    };
+    format!("{}-{}", platform, target.architecture)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::config::ConfigBuilder;
+
+    use super::*;
+
+    #[test]
+    fn host_target_platform_identifier() {
+        assert_eq!(
+            target_platform_luarocks_identifier(&Triple::host()),
+            current_platform_luarocks_identifier()
+        );
+    }
+
+    #[test]
+    fn binary_rock_arch_for_target() {
+        let config = ConfigBuilder::new().unwrap().build().unwrap();
+        assert_eq!(
+            binary_rock_arch(&config),
+            Some(current_platform_luarocks_identifier())
+        );
+        let target = if Triple::host().to_string() == "aarch64-unknown-linux-gnu" {
+            "x86_64-unknown-linux-gnu"
+        } else {
+            "aarch64-unknown-linux-gnu"
+        };
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .build_target(Some(target.into()))
+            .build()
+            .unwrap();
+        assert_eq!(binary_rock_arch(&config), None);
+    }
+
+    #[test]
+    fn cross_target_platform_identifier() {
+        let target = Triple::from_str("aarch64-unknown-linux-gnu").unwrap();
+        assert_eq!(
+            target_platform_luarocks_identifier(&target),
+            "linux-aarch64"
+        );
+        let target = Triple::from_str("aarch64-apple-darwin").unwrap();
+        assert_eq!(
+            target_platform_luarocks_identifier(&target),
+            "macosx-aarch64"
+        );
+        let target = Triple::from_str("x86_64-pc-windows-msvc").unwrap();
+        assert_eq!(
+            target_platform_luarocks_identifier(&target),
+            "windows-x86_64"
+        );
+    }
+}
@@ -119,10 +119,7 @@ impl DisplayAsLuaKV for RockManifest {
                     self.bin.display_lua(),
                 ]
                 .into_iter()
-                .chain(self.root.entries.iter().map(|(key, entry)| DisplayLuaKV {
-                    key: key.to_slash_lossy().to_string(),
-                    value: entry.display_lua_value(),
-                }))
+                .chain(to_lua_kv_vec(&self.root.entries))
                 .collect_vec(),
             ),
         }
@@ -140,7 +137,12 @@ impl DisplayAsLuaKV for (&PathBuf, &String) {
 
 impl DisplayAsLuaValue for HashMap<PathBuf, String> {
     fn display_lua_value(&self) -> DisplayLuaValue {
-        DisplayLuaValue::Table(self.iter().map(|it| it.display_lua()).collect_vec())
+        DisplayLuaValue::Table(
+            self.iter()
+                .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|it| it.display_lua())
+                .collect_vec(),
+        )
     }
 }
 
@@ -169,6 +171,7 @@ impl DisplayAsLuaValue for HashMap<PathBuf, DirOrFileEntry> {
 fn to_lua_kv_vec(dir_map: &HashMap<PathBuf, DirOrFileEntry>) -> Vec<DisplayLuaKV> {
     dir_map
         .iter()
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
         .map(|(k, v)| DisplayLuaKV {
             key: k.to_slash_lossy().to_string(),
             value: v.display_lua_value(),
@@ -0,0 +1,615 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use miette::Diagnostic;
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::fs;
+use crate::lua_rockspec::{DisplayLuaValue, RemoteLuaRockspec};
+use crate::lua_version::LuaVersion;
+use crate::manifest::{
+    ManifestLuaError, ManifestMetadata, SearchIndex, SearchMetadata, SEARCH_INDEX_FILE_NAME,
+};
+use crate::package::{PackageName, PackageSpec, PackageVersion};
+use crate::rockspec::{LuaVersionCompatibility, Rockspec};
+
+/// The Lua versions for which version-specific manifests are written.
+const MANIFEST_LUA_VERSIONS: [LuaVersion; 5] = [
+    LuaVersion::Lua51,
+    LuaVersion::Lua52,
+    LuaVersion::Lua53,
+    LuaVersion::Lua54,
+    LuaVersion::Lua55,
+];
+
+#[derive(Error, Debug, Diagnostic)]
+#[non_exhaustive]
+pub enum ManifestIndexError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("'{0}' is not a rockspec or packed rock")]
+    #[diagnostic(help(
+        "expected a file named '<package>-<version>.rockspec' or '<package>-<version>.<arch>.rock'"
+    ))]
+    InvalidFileName(PathBuf),
+    #[error("failed to read packed rock '{0}'")]
+    ZipRead(PathBuf, #[source] zip::result::ZipError),
+    #[error("failed to write '{0}'")]
+    ZipWrite(PathBuf, #[source] zip::result::ZipError),
+    #[error("{0} not found in packed rock '{1}'")]
+    RockspecNotFound(String, PathBuf),
+    #[error("failed to parse the rockspec of '{0}':\n{1}")]
+    Rockspec(PathBuf, String),
+    #[error("failed to parse manifest '{0}'")]
+    #[diagnostic(forward(1))]
+    Manifest(PathBuf, #[source] ManifestLuaError),
+    #[error("'{0}' is not in the manifest")]
+    NotFound(String),
+}
+
+/// A rockspec or packed rock in a rocks directory, identified by its file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RockFile {
+    package: PackageSpec,
+    /// e.g. "rockspec", "src", "all", "linux-x86_64", ...
+    arch: String,
+}
+
+impl RockFile {
+    /// Parse a file name of the form `<package>-<version>.rockspec`
+    /// or `<package>-<version>.<arch>.rock`.
+    pub(crate) fn from_file_name(file_name: &str) -> Option<Self> {
+        let (stem, arch) = match file_name.strip_suffix(".rockspec") {
+            Some(stem) => (stem, "rockspec"),
+            None => file_name.strip_suffix(".rock")?.rsplit_once('.')?,
+        };
+        let (specrev, version, name) = stem.rsplitn(3, '-').collect_tuple()?;
+        let package = PackageSpec::parse(name.to_string(), format!("{version}-{specrev}")).ok()?;
+        Some(Self {
+            package,
+            arch: arch.to_string(),
+        })
+    }
+
+    #[cfg(feature = "serve")]
+    pub(crate) fn package(&self) -> &PackageSpec {
+        &self.package
+    }
+
+    #[cfg(feature = "serve")]
+    pub(crate) fn is_rockspec(&self) -> bool {
+        self.arch == "rockspec"
+    }
+
+    pub(crate) fn file_name(&self) -> String {
+        if self.arch == "rockspec" {
+            format!(
+                "{}-{}.rockspec",
+                self.package.name(),
+                self.package.version()
+            )
+        } else {
+            format!(
+                "{}-{}.{}.rock",
+                self.package.name(),
+                self.package.version(),
+                self.arch
+            )
+        }
+    }
+}
+
+/// The rockspecs and packed rocks that are available for a package version.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct IndexedVersion {
+    arches: BTreeSet<String>,
+    /// The Lua versions (e.g. "5.1") the package version supports
+    lua_versions: BTreeSet<String>,
+    /// The searchable metadata from the package version's rockspec
+    metadata: Option<SearchMetadata>,
+}
+
+/// An index of a directory of rocks, which can be written as luarocks manifests
+/// that are readable by [`ManifestMetadata`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct ManifestIndex {
+    repository: BTreeMap<PackageName, BTreeMap<PackageVersion, IndexedVersion>>,
+}
+
+impl ManifestIndex {
+    /// Build an index by scanning a directory for rockspecs and packed rocks.
+    pub(crate) fn scan(dir: &Path) -> Result<Self, ManifestIndexError> {
+        let mut index = Self::default();
+        let files = fs::sync::read_dir(dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .sorted();
+        for file in files {
+            let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+            if file_name.ends_with(".rockspec") || file_name.ends_with(".rock") {
+                index.insert(&file)?;
+            }
+        }
+        Ok(index)
+    }
+
+    /// Load the index from the manifests that have been written to a directory,
+    /// falling back to scanning the directory if there is no `manifest`.
+    pub(crate) fn load(dir: &Path) -> Result<Self, ManifestIndexError> {
+        let manifest_path = dir.join("manifest");
+        if !manifest_path.is_file() {
+            return Self::scan(dir);
+        }
+        let mut index = Self::default();
+        for (name, versions) in read_manifest_arches(&manifest_path)? {
+            for (version, arches) in versions {
+                let Ok(version) = PackageVersion::parse(&version) else {
+                    continue;
+                };
+                index
+                    .repository
+                    .entry(name.clone())
+                    .or_default()
+                    .entry(version)
+                    .or_default()
+                    .arches
+                    .extend(arches);
+            }
+        }
+        for lua_version in MANIFEST_LUA_VERSIONS {
+            let lua_version = lua_version.version_compatibility_str();
+            let manifest_path = dir.join(format!("manifest-{lua_version}"));
+            if !manifest_path.is_file() {
+                continue;
+            }
+            for (name, versions) in read_manifest_arches(&manifest_path)? {
+                for version in versions.into_keys() {
+                    let entry = PackageVersion::parse(&version).ok().and_then(|version| {
+                        index
+                            .repository
+                            .get_mut(&name)
+                            .and_then(|versions| versions.get_mut(&version))
+                    });
+                    if let Some(entry) = entry {
+                        entry.lua_versions.insert(lua_version.clone());
+                    }
+                }
+            }
+        }
+        let search_index_path = dir.join(SEARCH_INDEX_FILE_NAME);
+        if search_index_path.is_file() {
+            let search_index: SearchIndex =
+                serde_json::from_str(&fs::sync::read_to_string(&search_index_path)?)
+                    .unwrap_or_default();
+            for (name, versions) in index.repository.iter_mut() {
+                for (version, entry) in versions.iter_mut() {
+                    entry.metadata = search_index.get(name, version).cloned();
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    /// Add a rockspec or packed rock to the index.
+    /// The Lua versions it supports are determined from its rockspec.
+    pub(crate) fn insert(&mut self, file: &Path) -> Result<RockFile, ManifestIndexError> {
+        let rock = file
+            .file_name()
+            .and_then(|file_name| RockFile::from_file_name(&file_name.to_string_lossy()))
+            .ok_or_else(|| ManifestIndexError::InvalidFileName(file.to_path_buf()))?;
+        let rockspec = read_rockspec(file, &rock)?;
+        let entry = self
+            .repository
+            .entry(rock.package.name().clone())
+            .or_default()
+            .entry(rock.package.version().clone())
+            .or_default();
+        entry.arches.insert(rock.arch.clone());
+        entry.metadata = Some(rockspec.description().into());
+        entry.lua_versions.extend(
+            MANIFEST_LUA_VERSIONS
+                .iter()
+                .filter(|lua_version| rockspec.supports_lua_version(lua_version))
+                .map(LuaVersion::version_compatibility_str),
+        );
+        Ok(rock)
+    }
+
+    /// Remove a single rockspec or packed rock from the index.
+    pub(crate) fn remove(&mut self, rock: &RockFile) -> Result<(), ManifestIndexError> {
+        let versions = self
+            .repository
+            .get_mut(rock.package.name())
+            .ok_or_else(|| ManifestIndexError::NotFound(rock.file_name()))?;
+        let entry = versions
+            .get_mut(rock.package.version())
+            .filter(|entry| entry.arches.contains(&rock.arch))
+            .ok_or_else(|| ManifestIndexError::NotFound(rock.file_name()))?;
+        entry.arches.remove(&rock.arch);
+        if entry.arches.is_empty() {
+            versions.remove(rock.package.version());
+        }
+        if versions.is_empty() {
+            self.repository.remove(rock.package.name());
+        }
+        Ok(())
+    }
+
+    /// Remove all rockspecs and packed rocks of a package version from the index,
+    /// returning the removed files.
+    pub(crate) fn remove_package(
+        &mut self,
+        package: &PackageSpec,
+    ) -> Result<Vec<RockFile>, ManifestIndexError> {
+        let versions = self
+            .repository
+            .get_mut(package.name())
+            .ok_or_else(|| ManifestIndexError::NotFound(package.to_string()))?;
+        let entry = versions
+            .remove(package.version())
+            .ok_or_else(|| ManifestIndexError::NotFound(package.to_string()))?;
+        if versions.is_empty() {
+            self.repository.remove(package.name());
+        }
+        Ok(entry
+            .arches
+            .into_iter()
+            .map(|arch| RockFile {
+                package: package.clone(),
+                arch,
+            })
+            .collect())
+    }
+
+    /// Render the manifest in the luarocks format.
+    /// If a Lua version is specified, only rocks that support it are included.
+    pub(crate) fn to_lua_string(&self, lua_version: Option<&LuaVersion>) -> String {
+        let lua_version = lua_version.map(LuaVersion::version_compatibility_str);
+        let mut manifest = String::from("commands = {}\nmodules = {}\nrepository = {\n");
+        for (name, versions) in &self.repository {
+            let versions = versions
+                .iter()
+                .filter(|(_, entry)| {
+                    lua_version
+                        .as_ref()
+                        .is_none_or(|lua_version| entry.lua_versions.contains(lua_version))
+                })
+                .collect_vec();
+            if versions.is_empty() {
+                continue;
+            }
+            let _ = writeln!(manifest, "   {} = {{", lua_table_key(&name.to_string()));
+            for (version, entry) in versions {
+                let _ = writeln!(
+                    manifest,
+                    "      {} = {{",
+                    lua_table_key(&version.to_string())
+                );
+                for arch in &entry.arches {
+                    let _ = writeln!(
+                        manifest,
+                        "         {{\n            arch = {}\n         }},",
+                        DisplayLuaValue::String(arch.clone())
+                    );
+                }
+                manifest.push_str("      },\n");
+            }
+            manifest.push_str("   },\n");
+        }
+        manifest.push_str("}\n");
+        manifest
+    }
+
+    /// Render an `index.html` listing the rocks in the directory.
+    pub(crate) fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Lua rocks</title>\n</head>\n<body>\n<h1>Lua rocks</h1>\n<table>\n",
+        );
+        for (name, versions) in &self.repository {
+            let name_str = html_escape::encode_text(&name.to_string()).to_string();
+            let _ = writeln!(
+                html,
+                "<tr><td colspan=\"2\"><h2 id=\"{name_str}\">{name_str}</h2></td></tr>"
+            );
+            for (version, entry) in versions.iter().rev() {
+                let links = entry
+                    .arches
+                    .iter()
+                    .map(|arch| {
+                        let rock = RockFile {
+                            package: PackageSpec::new(name.clone(), version.clone()),
+                            arch: arch.clone(),
+                        };
+                        format!(
+                            "<a href=\"{}\">{}</a>",
+                            html_escape::encode_double_quoted_attribute(&rock.file_name()),
+                            html_escape::encode_text(arch),
+                        )
+                    })
+                    .join(", ");
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{links}</td></tr>",
+                    html_escape::encode_text(&version.to_string())
+                );
+            }
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+
+    /// The searchable metadata of the indexed package versions,
+    /// which is served as `search-index.json`.
+    pub(crate) fn to_search_index(&self) -> SearchIndex {
+        let mut search_index = SearchIndex::default();
+        for (name, versions) in &self.repository {
+            for (version, entry) in versions {
+                if let Some(metadata) = &entry.metadata {
+                    search_index.insert(name.clone(), version.clone(), metadata.clone());
+                }
+            }
+        }
+        search_index
+    }
+
+    /// Render the manifest with the given file name,
+    /// i.e. `manifest` or `manifest-<lua-version>`.
+    #[cfg(feature = "serve")]
+    pub(crate) fn render_manifest(&self, name: &str) -> Option<String> {
+        if name == "manifest" {
+            return Some(self.to_lua_string(None));
+        }
+        let version = name.strip_prefix("manifest-")?;
+        MANIFEST_LUA_VERSIONS
+            .iter()
+            .find(|lua_version| lua_version.version_compatibility_str() == version)
+            .map(|lua_version| self.to_lua_string(Some(lua_version)))
+    }
+
+    /// Write `manifest`, `manifest-<lua-version>`, their zipped variants,
+    /// a `search-index.json` and an `index.html` to a directory.
+    pub(crate) fn write(&self, dir: &Path) -> Result<(), ManifestIndexError> {
+        write_manifest(dir, "manifest", &self.to_lua_string(None))?;
+        for lua_version in MANIFEST_LUA_VERSIONS {
+            let name = format!("manifest-{}", lua_version.version_compatibility_str());
+            write_manifest(dir, &name, &self.to_lua_string(Some(&lua_version)))?;
+        }
+        fs::sync::write(
+            dir.join(SEARCH_INDEX_FILE_NAME),
+            self.to_search_index().to_json(),
+        )?;
+        fs::sync::write(dir.join("index.html"), self.to_html())?;
+        Ok(())
+    }
+}
+
+/// Whether a file name is that of a manifest written by [`ManifestIndex::write`],
+/// i.e. `manifest` or `manifest-<lua-version>`.
+#[cfg(feature = "serve")]
+pub(crate) fn is_manifest_name(name: &str) -> bool {
+    name == "manifest"
+        || name.strip_prefix("manifest-").is_some_and(|version| {
+            MANIFEST_LUA_VERSIONS
+                .iter()
+                .any(|lua_version| lua_version.version_compatibility_str() == version)
+        })
+}
+
+/// Zip a manifest, containing a single file with the manifest's name.
+pub(crate) fn zip_manifest(name: &str, content: &str) -> Result<Vec<u8>, zip::result::ZipError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(name, SimpleFileOptions::default())?;
+    zip.write_all(content.as_bytes())?;
+    Ok(zip.finish()?.into_inner())
+}
+
+fn read_manifest_arches(
+    manifest_path: &Path,
+) -> Result<
+    std::collections::HashMap<PackageName, std::collections::HashMap<String, Vec<String>>>,
+    ManifestIndexError,
+> {
+    let content = fs::sync::read_to_string(manifest_path)?;
+    ManifestMetadata::arches(&content)
+        .map_err(|err| ManifestIndexError::Manifest(manifest_path.to_path_buf(), err))
+}
+
+/// Read the rockspec of a rockspec file or packed rock.
+fn read_rockspec(file: &Path, rock: &RockFile) -> Result<RemoteLuaRockspec, ManifestIndexError> {
+    let content = if rock.arch == "rockspec" {
+        fs::sync::read_to_string(file)?
+    } else {
+        let bytes = std::fs::read(file).map_err(|source| fs::FsError::Read {
+            path: file.to_path_buf(),
+            source,
+        })?;
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+            .map_err(|err| ManifestIndexError::ZipRead(file.to_path_buf(), err))?;
+        let rockspec_file_name = format!(
+            "{}-{}.rockspec",
+            rock.package.name(),
+            rock.package.version()
+        );
+        let mut rockspec_file = archive.by_name(&rockspec_file_name).map_err(|_| {
+            ManifestIndexError::RockspecNotFound(rockspec_file_name.clone(), file.to_path_buf())
+        })?;
+        let mut content = String::new();
+        rockspec_file
+            .read_to_string(&mut content)
+            .map_err(|source| fs::FsError::ReadToString {
+                path: file.to_path_buf(),
+                source,
+            })?;
+        content
+    };
+    RemoteLuaRockspec::new(&content)
+        .map_err(|err| ManifestIndexError::Rockspec(file.to_path_buf(), err.to_string()))
+}
+
+/// Render a string as a Lua table key, e.g. `["foo"]`.
+fn lua_table_key(key: &str) -> String {
+    let key = DisplayLuaValue::String(key.to_string()).to_string();
+    if key.starts_with('[') {
+        // Separate long strings from the brackets, so that `[[[` is not lexed as a long string.
+        format!("[ {key} ]")
+    } else {
+        format!("[{key}]")
+    }
+}
+
+/// Write a manifest and its zipped variant.
+fn write_manifest(dir: &Path, name: &str, content: &str) -> Result<(), ManifestIndexError> {
+    fs::sync::write(dir.join(name), content)?;
+    let zip_path = dir.join(format!("{name}.zip"));
+    let zipped = zip_manifest(name, content)
+        .map_err(|err| ManifestIndexError::ZipWrite(zip_path.clone(), err))?;
+    fs::sync::write(&zip_path, zipped)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use crate::package::{PackageReq, RemotePackageType, RemotePackageTypeFilterSpec};
+
+    use super::*;
+
+    fn rocks_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/test");
+        for file in [
+            "lua-cjson-2.1.0-1.rockspec",
+            "luatest-0.2-1.src.rock",
+            "sample-project-0.1.0-1.all.rock",
+        ] {
+            std::fs::copy(resources.join(file), dir.join(file)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn rock_file_from_file_name() {
+        let rock = RockFile::from_file_name("lua-cjson-2.1.0-1.rockspec").unwrap();
+        assert_eq!(rock.package.name().to_string(), "lua-cjson");
+        assert_eq!(rock.package.version().to_string(), "2.1.0-1");
+        assert_eq!(rock.arch, "rockspec");
+        assert_eq!(rock.file_name(), "lua-cjson-2.1.0-1.rockspec");
+        let rock = RockFile::from_file_name("toml-edit-0.6.0-1.linux-x86_64.rock").unwrap();
+        assert_eq!(rock.package.name().to_string(), "toml-edit");
+        assert_eq!(rock.arch, "linux-x86_64");
+        assert_eq!(rock.file_name(), "toml-edit-0.6.0-1.linux-x86_64.rock");
+        assert!(RockFile::from_file_name("manifest-5.1").is_none());
+        assert!(RockFile::from_file_name("foo.rock").is_none());
+    }
+
+    #[test]
+    fn make_manifest_round_trip() {
+        let dir = rocks_dir();
+        let index = ManifestIndex::scan(&dir).unwrap();
+        index.write(&dir).unwrap();
+        for file in [
+            "manifest",
+            "manifest.zip",
+            "manifest-5.1",
+            "manifest-5.1.zip",
+            "manifest-5.4",
+            "search-index.json",
+            "index.html",
+        ] {
+            assert!(dir.join(file).is_file(), "{file} not written");
+        }
+        let manifest = fs::sync::read_to_string(dir.join("manifest-5.1")).unwrap();
+        let metadata = ManifestMetadata::new(&manifest).unwrap();
+        let filter = RemotePackageTypeFilterSpec::default();
+        let (package, package_type) = metadata
+            .latest_match(&PackageReq::parse("luatest").unwrap(), &filter)
+            .unwrap();
+        assert_eq!(package.version().to_string(), "0.2-1");
+        assert_eq!(package_type, RemotePackageType::Src);
+        let (_, package_type) = metadata
+            .latest_match(&PackageReq::parse("sample-project").unwrap(), &filter)
+            .unwrap();
+        assert_eq!(package_type, RemotePackageType::Binary);
+        assert!(metadata
+            .latest_match(&PackageReq::parse("lua-cjson").unwrap(), &filter)
+            .is_some());
+
+        assert_eq!(ManifestIndex::load(&dir).unwrap(), index);
+
+        let search_index: SearchIndex =
+            serde_json::from_str(&fs::sync::read_to_string(dir.join("search-index.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            search_index
+                .latest(&"lua-cjson".into())
+                .and_then(|metadata| metadata.summary.as_deref()),
+            Some("A fast JSON encoding/parsing module")
+        );
+
+        let html = fs::sync::read_to_string(dir.join("index.html")).unwrap();
+        assert!(html.contains("href=\"luatest-0.2-1.src.rock\""));
+    }
+
+    #[test]
+    fn remove_entries() {
+        let dir = rocks_dir();
+        let mut index = ManifestIndex::scan(&dir).unwrap();
+        let rock = RockFile::from_file_name("luatest-0.2-1.src.rock").unwrap();
+        index.remove(&rock).unwrap();
+        assert!(matches!(
+            index.remove(&rock),
+            Err(ManifestIndexError::NotFound(_))
+        ));
+        let removed = index
+            .remove_package(&"lua-cjson@2.1.0-1".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            removed.iter().map(RockFile::file_name).collect_vec(),
+            vec!["lua-cjson-2.1.0-1.rockspec"]
+        );
+        let manifest = index.to_lua_string(None);
+        assert!(!manifest.contains("luatest"));
+        assert!(!manifest.contains("lua-cjson"));
+        assert!(manifest.contains("sample-project"));
+    }
+
+    #[test]
+    fn manifest_escapes_lua_strings() {
+        let dir = rocks_dir();
+        let mut index = ManifestIndex::scan(&dir).unwrap();
+        let arches = [
+            "quote\"d".to_string(),
+            "back\\slash".to_string(),
+            "ctrl\u{1b}é".to_string(),
+            "new\nline".to_string(),
+        ];
+        for versions in index.repository.values_mut() {
+            for entry in versions.values_mut() {
+                entry.arches.extend(arches.iter().cloned());
+            }
+        }
+        let manifest = index.to_lua_string(None);
+        let parsed = ManifestMetadata::arches(&manifest).unwrap();
+        let luatest_arches = parsed
+            .get(&"luatest".into())
+            .and_then(|versions| versions.get("0.2-1"))
+            .unwrap();
+        for arch in &arches {
+            assert!(luatest_arches.contains(arch), "{arch:?} not round-tripped");
+        }
+    }
+
+    #[test]
+    fn lua_table_key_long_string() {
+        assert_eq!(lua_table_key("foo"), r#"["foo"]"#);
+        assert_eq!(lua_table_key("foo\nbar"), "[ [[\nfoo\nbar]] ]");
+    }
+}
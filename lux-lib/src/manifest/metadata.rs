@@ -19,7 +19,10 @@ impl<'de> serde::Deserialize<'de> for ManifestMetadata {
         D: serde::Deserializer<'de>,
     {
         let intermediate = IntermediateManifest::deserialize(deserializer)?;
-        Ok(Self::from_intermediate(intermediate))
+        Ok(Self::from_intermediate(
+            intermediate,
+            Some(&crate::luarocks::current_platform_luarocks_identifier()),
+        ))
     }
 }
 
@@ -39,30 +42,55 @@ pub enum ManifestLuaError {
 }
 
 impl ManifestMetadata {
-    pub fn new(manifest: &String) -> Result<Self, ManifestLuaError> {
-        let mut lua = Lua::core();
-
-        let success = lua.try_enter(|ctx| {
-            let closure = Closure::load(ctx, None, manifest.as_bytes())?;
-
-            let executor = Executor::start(ctx, closure.into(), ());
-
-            Ok(executor.step(ctx, &mut Fuel::with(ROCKSPEC_FUEL_LIMIT))?)
-        })?;
-
-        if !success {
-            return Err(ManifestLuaError::FuelLimitExceeded);
-        }
+    /// Parse a manifest, considering binary rocks for the host platform.
+    #[cfg(test)]
+    pub fn new(manifest: &str) -> Result<Self, ManifestLuaError> {
+        Self::with_binary_arch(
+            manifest,
+            Some(&crate::luarocks::current_platform_luarocks_identifier()),
+        )
+    }
 
-        let intermediate = IntermediateManifest {
-            repository: lua.enter(|ctx| from_value(ctx.globals().get_value(ctx, "repository")))?,
-        };
+    /// Parse a manifest, considering binary rocks built for `binary_arch`
+    /// (e.g. "linux-x86_64") in addition to architecture-independent ones.
+    /// If `binary_arch` is `None`, only architecture-independent binary rocks are considered.
+    pub(crate) fn with_binary_arch(
+        manifest: &str,
+        binary_arch: Option<&str>,
+    ) -> Result<Self, ManifestLuaError> {
+        let intermediate = IntermediateManifest::new(manifest)?;
 
-        let manifest = Self::from_intermediate(intermediate);
+        let manifest = Self::from_intermediate(intermediate, binary_arch);
 
         Ok(manifest)
     }
 
+    /// Parse the architectures (e.g. "rockspec", "src", "linux-x86_64", ...) of each
+    /// package version in a manifest, including those for other platforms.
+    pub(crate) fn arches(
+        manifest: &str,
+    ) -> Result<HashMap<PackageName, HashMap<String, Vec<String>>>, ManifestLuaError> {
+        let intermediate = IntermediateManifest::new(manifest)?;
+        Ok(intermediate
+            .repository
+            .into_iter()
+            .map(|(name, package_map)| {
+                (
+                    name,
+                    package_map
+                        .into_iter()
+                        .map(|(version, entries)| {
+                            (
+                                version,
+                                entries.into_iter().map(|entry| entry.arch).collect_vec(),
+                            )
+                        })
+                        .collect(),
+                )
+            })
+            .collect())
+    }
+
     pub fn has_rock(&self, rock_name: &PackageName) -> bool {
         self.repository.contains_key(rock_name)
     }
@@ -108,7 +136,7 @@ impl ManifestMetadata {
 
     /// Construct a `ManifestMetadata` from an intermediate representation,
     /// silently skipping entries for versions we don't know how to parse.
-    fn from_intermediate(intermediate: IntermediateManifest) -> Self {
+    fn from_intermediate(intermediate: IntermediateManifest, binary_arch: Option<&str>) -> Self {
         let repository = intermediate
             .repository
             .into_iter()
@@ -121,7 +149,7 @@ impl ManifestMetadata {
                             let version = PackageVersion::parse(version_str.as_str()).ok()?;
                             let entries = entries
                                 .into_iter()
-                                .filter_map(|entry| RemotePackageType::try_from(entry).ok())
+                                .filter_map(|entry| entry.package_type(binary_arch))
                                 .collect_vec();
                             Some((version, entries))
                         })
@@ -133,27 +161,22 @@ impl ManifestMetadata {
     }
 }
 
-struct UnsupportedArchitectureError;
-
 #[derive(Clone, serde::Deserialize)]
 struct ManifestRockEntry {
     /// e.g. "linux-x86_64", "rockspec", "src", ...
     pub arch: String,
 }
 
-impl TryFrom<ManifestRockEntry> for RemotePackageType {
-    type Error = UnsupportedArchitectureError;
-    fn try_from(
-        ManifestRockEntry { arch }: ManifestRockEntry,
-    ) -> Result<Self, UnsupportedArchitectureError> {
-        match arch.as_str() {
-            "rockspec" => Ok(RemotePackageType::Rockspec),
-            "src" => Ok(RemotePackageType::Src),
-            "all" => Ok(RemotePackageType::Binary),
-            arch if arch == crate::luarocks::current_platform_luarocks_identifier() => {
-                Ok(RemotePackageType::Binary)
-            }
-            _ => Err(UnsupportedArchitectureError),
+impl ManifestRockEntry {
+    /// The package type of this entry, or `None` if it is a binary rock
+    /// for an architecture other than `binary_arch`.
+    fn package_type(&self, binary_arch: Option<&str>) -> Option<RemotePackageType> {
+        match self.arch.as_str() {
+            "rockspec" => Some(RemotePackageType::Rockspec),
+            "src" => Some(RemotePackageType::Src),
+            "all" => Some(RemotePackageType::Binary),
+            arch if Some(arch) == binary_arch => Some(RemotePackageType::Binary),
+            _ => None,
         }
     }
 }
@@ -165,6 +188,28 @@ struct IntermediateManifest {
     repository: HashMap<PackageName, HashMap<String, Vec<ManifestRockEntry>>>,
 }
 
+impl IntermediateManifest {
+    fn new(manifest: &str) -> Result<Self, ManifestLuaError> {
+        let mut lua = Lua::core();
+
+        let success = lua.try_enter(|ctx| {
+            let closure = Closure::load(ctx, None, manifest.as_bytes())?;
+
+            let executor = Executor::start(ctx, closure.into(), ());
+
+            Ok(executor.step(ctx, &mut Fuel::with(ROCKSPEC_FUEL_LIMIT))?)
+        })?;
+
+        if !success {
+            return Err(ManifestLuaError::FuelLimitExceeded);
+        }
+
+        Ok(Self {
+            repository: lua.enter(|ctx| from_value(ctx.globals().get_value(ctx, "repository")))?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -223,4 +268,31 @@ mod tests {
         };
         assert!(metadata.latest_match(&package_req, &filter).is_none());
     }
+
+    #[tokio::test]
+    pub async fn binary_rocks_match_binary_arch() {
+        let manifest = r#"
+            repository = {
+                foo = {
+                    ["1.0.0-1"] = {
+                        { arch = "linux-aarch64" },
+                    },
+                    ["2.0.0-1"] = {
+                        { arch = "linux-x86_64" },
+                    },
+                },
+            }
+            "#;
+        let package_req: PackageReq = "foo".parse().unwrap();
+        let metadata = ManifestMetadata::with_binary_arch(manifest, Some("linux-aarch64")).unwrap();
+        let (package, package_type) = metadata
+            .latest_match(&package_req, &Default::default())
+            .unwrap();
+        assert_eq!(package.version().to_string(), "1.0.0-1");
+        assert_eq!(package_type, RemotePackageType::Binary);
+        let metadata = ManifestMetadata::with_binary_arch(manifest, None).unwrap();
+        assert!(metadata
+            .latest_match(&package_req, &Default::default())
+            .is_none());
+    }
 }
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::fs;
+use crate::lua_version::LuaVersion;
+use crate::manifest::{ManifestLuaError, ManifestMetadata};
+use crate::package::{PackageName, PackageVersion, RemotePackageType, RemotePackageTypeFilterSpec};
+
+/// Identifies a binary metadata cache file.
+/// Bump the trailing version when the format or the parsed representation changes.
+const MAGIC: &[u8] = b"LUXMETA1";
+
+type Repository = HashMap<PackageName, HashMap<PackageVersion, Vec<RemotePackageType>>>;
+
+#[derive(Error, Debug)]
+enum MetadataCacheError {
+    #[error(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("failed to serialize manifest metadata: {0}")]
+    Serialize(#[from] postcard::Error),
+}
+
+/// Load the parsed metadata of a manifest from a binary cache,
+/// parsing the manifest and writing the cache if it is missing or stale.
+///
+/// The cache is keyed by a hash of the manifest's content, the binary rock architecture
+/// (which determines the binary rocks that are considered), the Lua version
+/// and the package type filter, so that changing any of them invalidates the cache.
+/// Failures to read or write the cache fall back to parsing the manifest.
+pub(crate) async fn metadata_from_cache_or_parse(
+    manifest: &str,
+    cache: &Path,
+    binary_arch: Option<&str>,
+    lua_version: &LuaVersion,
+    filter: &RemotePackageTypeFilterSpec,
+) -> Result<ManifestMetadata, ManifestLuaError> {
+    let key = cache_key(manifest, binary_arch, lua_version, filter);
+    if let Some(metadata) = read_cache(cache, &key).await {
+        return Ok(metadata);
+    }
+    let metadata = ManifestMetadata::with_binary_arch(manifest, binary_arch)?;
+    if let Err(err) = write_cache(cache, &key, &metadata).await {
+        tracing::debug!("failed to write manifest metadata cache: {err}");
+    }
+    Ok(metadata)
+}
+
+fn cache_key(
+    manifest: &str,
+    binary_arch: Option<&str>,
+    lua_version: &LuaVersion,
+    filter: &RemotePackageTypeFilterSpec,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    // "all" can't collide with a platform identifier
+    hasher.update(binary_arch.unwrap_or("all").as_bytes());
+    hasher.update([0]);
+    hasher.update(lua_version.to_string().as_bytes());
+    hasher.update([0]);
+    hasher.update([filter.rockspec, filter.src, filter.binary].map(u8::from));
+    hasher.update(manifest.as_bytes());
+    hasher.finalize().into()
+}
+
+async fn read_cache(cache: &Path, key: &[u8]) -> Option<ManifestMetadata> {
+    let bytes = tokio::fs::read(cache).await.ok()?;
+    let payload = bytes.strip_prefix(MAGIC)?.strip_prefix(key)?;
+    match postcard::from_bytes::<Repository>(payload) {
+        Ok(repository) => Some(ManifestMetadata { repository }),
+        Err(err) => {
+            tracing::debug!("ignoring invalid manifest metadata cache: {err}");
+            None
+        }
+    }
+}
+
+async fn write_cache(
+    cache: &Path,
+    key: &[u8],
+    metadata: &ManifestMetadata,
+) -> Result<(), MetadataCacheError> {
+    let mut bytes = [MAGIC, key].concat();
+    bytes.extend(postcard::to_stdvec(&metadata.repository)?);
+    // Write to a temporary file first, so that concurrent readers never see a partial cache
+    let mut tmp = cache.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    fs::tokio::write(&tmp, bytes).await?;
+    fs::tokio::rename(&tmp, cache).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    const ARCH: &str = "linux-x86_64";
+
+    fn key(manifest: &str, binary_arch: Option<&str>) -> [u8; 32] {
+        cache_key(
+            manifest,
+            binary_arch,
+            &LuaVersion::Lua51,
+            &RemotePackageTypeFilterSpec::default(),
+        )
+    }
+
+    async fn from_cache_or_parse(
+        manifest: &str,
+        cache: &Path,
+        binary_arch: Option<&str>,
+    ) -> Result<ManifestMetadata, ManifestLuaError> {
+        metadata_from_cache_or_parse(
+            manifest,
+            cache,
+            binary_arch,
+            &LuaVersion::Lua51,
+            &RemotePackageTypeFilterSpec::default(),
+        )
+        .await
+    }
+
+    fn test_manifest() -> String {
+        std::fs::read_to_string(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/test/manifest-5.1"),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn metadata_cache_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let cache = temp.path().join("manifest-5.1.metadata");
+        let manifest = test_manifest();
+
+        let parsed = from_cache_or_parse(&manifest, &cache, Some(ARCH))
+            .await
+            .unwrap();
+        assert!(cache.is_file());
+        let cached = read_cache(&cache, &key(&manifest, Some(ARCH)))
+            .await
+            .unwrap();
+        assert_eq!(cached, parsed);
+        assert_eq!(
+            parsed,
+            ManifestMetadata::with_binary_arch(&manifest, Some(ARCH)).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn metadata_cache_is_keyed_by_manifest() {
+        let temp = TempDir::new().unwrap();
+        let cache = temp.path().join("manifest-5.1.metadata");
+        let manifest = test_manifest();
+        let empty_manifest = "repository = {}";
+
+        let empty = from_cache_or_parse(empty_manifest, &cache, Some(ARCH))
+            .await
+            .unwrap();
+        assert!(empty.repository.is_empty());
+        // A fresh cache is loaded without parsing the manifest
+        write_cache(&cache, &key(&manifest, Some(ARCH)), &empty)
+            .await
+            .unwrap();
+        let loaded = from_cache_or_parse(&manifest, &cache, Some(ARCH))
+            .await
+            .unwrap();
+        assert!(loaded.repository.is_empty());
+
+        // A stale cache is replaced
+        let updated_manifest = format!("{manifest}\n");
+        let parsed = from_cache_or_parse(&updated_manifest, &cache, Some(ARCH))
+            .await
+            .unwrap();
+        assert!(parsed.has_rock(&"30log".into()));
+        assert!(read_cache(&cache, &key(&updated_manifest, Some(ARCH)))
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn invalid_metadata_cache_is_ignored() {
+        let temp = TempDir::new().unwrap();
+        let cache = temp.path().join("manifest-5.1.metadata");
+        let manifest = test_manifest();
+        let mut corrupted = [MAGIC, &key(&manifest, Some(ARCH))].concat();
+        corrupted.extend(b"garbage");
+        std::fs::write(&cache, corrupted).unwrap();
+
+        let metadata = from_cache_or_parse(&manifest, &cache, Some(ARCH))
+            .await
+            .unwrap();
+        assert!(metadata.has_rock(&"30log".into()));
+    }
+
+    #[tokio::test]
+    async fn metadata_cache_is_keyed_by_lua_version_and_filter() {
+        let temp = TempDir::new().unwrap();
+        let cache = temp.path().join("manifest-5.1.metadata");
+        let manifest = test_manifest();
+        let rockspecs_only = RemotePackageTypeFilterSpec {
+            rockspec: true,
+            src: false,
+            binary: false,
+        };
+
+        from_cache_or_parse(&manifest, &cache, Some(ARCH))
+            .await
+            .unwrap();
+        let other_lua_key = cache_key(
+            &manifest,
+            Some(ARCH),
+            &LuaVersion::Lua54,
+            &RemotePackageTypeFilterSpec::default(),
+        );
+        assert!(read_cache(&cache, &other_lua_key).await.is_none());
+        let other_filter_key =
+            cache_key(&manifest, Some(ARCH), &LuaVersion::Lua51, &rockspecs_only);
+        assert!(read_cache(&cache, &other_filter_key).await.is_none());
+        metadata_from_cache_or_parse(
+            &manifest,
+            &cache,
+            Some(ARCH),
+            &LuaVersion::Lua51,
+            &rockspecs_only,
+        )
+        .await
+        .unwrap();
+        assert!(read_cache(&cache, &other_filter_key).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn metadata_cache_is_keyed_by_binary_arch() {
+        let temp = TempDir::new().unwrap();
+        let cache = temp.path().join("manifest-5.1.metadata");
+        let manifest = test_manifest();
+
+        from_cache_or_parse(&manifest, &cache, Some(ARCH))
+            .await
+            .unwrap();
+        assert!(read_cache(&cache, &key(&manifest, None)).await.is_none());
+        from_cache_or_parse(&manifest, &cache, None).await.unwrap();
+        assert!(read_cache(&cache, &key(&manifest, None)).await.is_some());
+    }
+}
@@ -1,9 +1,11 @@
 use miette::Diagnostic;
-use reqwest::header::ToStrError;
-use reqwest::Client;
+use reqwest::header::{ToStrError, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::string::FromUtf8Error;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 use tokio::fs::OpenOptions;
 use tokio::io;
@@ -49,6 +51,13 @@ if you are using a custom server, make sure it returns correctly formatted manif
     #[error(transparent)]
     #[diagnostic(transparent)]
     LuaVersion(#[from] LuaVersionUnset),
+    #[error("'{0}' is not a valid local directory URL")]
+    FilePath(Url),
+    #[error("no manifest found in local rocks directory '{0}'")]
+    #[diagnostic(help(
+        "the directory must contain a `manifest-<lua-version>`, `manifest-<lua-version>.zip` or `manifest` file"
+    ))]
+    LocalManifestNotFound(PathBuf),
 }
 
 impl From<reqwest::Error> for ManifestFromServerError {
@@ -57,34 +66,111 @@ impl From<reqwest::Error> for ManifestFromServerError {
     }
 }
 
-#[tracing::instrument(level = "trace", skip(client, config))]
-pub(super) async fn get_manifest(
+/// The HTTP cache validators of a cached manifest, which are stored next to it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CachedManifestInfo {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the manifest was last downloaded or revalidated, in seconds since the UNIX epoch.
+    checked_at: u64,
+}
+
+impl CachedManifestInfo {
+    fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            checked_at: unix_timestamp(SystemTime::now()),
+        }
+    }
+
+    fn path(cache: &Path) -> PathBuf {
+        let mut path = cache.as_os_str().to_owned();
+        path.push(".info.json");
+        path.into()
+    }
+
+    async fn load(cache: &Path) -> Option<Self> {
+        let content = tokio::fs::read_to_string(Self::path(cache)).await.ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    async fn save(&self, cache: &Path) -> Result<(), fs::FsError> {
+        let content = serde_json::to_string(self).unwrap_or_default();
+        fs::tokio::write(Self::path(cache), content).await
+    }
+
+    /// Whether the cached manifest can be used without revalidating it with the server.
+    fn is_fresh(&self, ttl: &Duration) -> bool {
+        unix_timestamp(SystemTime::now()).saturating_sub(self.checked_at) < ttl.as_secs()
+    }
+
+    /// Add conditional request headers, so that the server can respond with `304 Not Modified`.
+    fn apply_validators(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = match &self.etag {
+            Some(etag) => request.header(IF_NONE_MATCH, etag),
+            None => request,
+        };
+        match &self.last_modified {
+            Some(last_modified) => request.header(IF_MODIFIED_SINCE, last_modified),
+            None => request,
+        }
+    }
+}
+
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Download the manifest and write it to the `target` cache.
+/// If a cached manifest's `info` is provided, the request is conditional,
+/// and `None` is returned if the server responds that the manifest has not been modified.
+#[tracing::instrument(level = "trace", skip(client, config, info))]
+async fn get_manifest(
     url: Url,
     manifest_version: &str,
     target: &Path,
     client: &Client,
     config: &Config,
-) -> Result<String, ManifestFromServerError> {
-    let response = client
-        .get(url.clone())
-        .apply_access_token(config, &url)
-        .send()
-        .await?;
-    if response.status().is_client_error() {
+    info: Option<&CachedManifestInfo>,
+) -> Result<Option<String>, ManifestFromServerError> {
+    let get = |url: &Url| {
+        let request = client.get(url.clone()).apply_access_token(config, url);
+        match info {
+            Some(info) => info.apply_validators(request),
+            None => request,
+        }
+    };
+    let response = get(&url).send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let manifest = if response.status().is_client_error() {
         let fallback_url = fallback_unzipped_url(&url)?;
-        let manifest_bytes = client
-            .get(fallback_url.clone())
-            .apply_access_token(config, &fallback_url)
-            .send()
-            .await?
-            .error_for_status()?
-            .bytes()
-            .await?;
+        let response = get(&fallback_url).send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+        let info = CachedManifestInfo::from_response(&response);
+        let manifest_bytes = response.bytes().await?;
         let manifest = String::from_utf8(manifest_bytes.to_vec())?;
         fs::tokio::write(&target, &manifest).await?;
-        Ok(manifest)
+        info.save(target).await?;
+        manifest
     } else {
-        let manifest_bytes = response.error_for_status()?.bytes().await?;
+        let response = response.error_for_status()?;
+        let info = CachedManifestInfo::from_response(&response);
+        let manifest_bytes = response.bytes().await?;
         let mut archive = ZipArchive::new(std::io::Cursor::new(manifest_bytes))
             .map_err(|err| ManifestFromServerError::ZipRead(url.clone(), err))?;
 
@@ -121,7 +207,24 @@ pub(super) async fn get_manifest(
         target_file.seek(io::SeekFrom::Start(0)).await?;
         target_file.read_to_string(&mut manifest).await?;
 
-        Ok(manifest)
+        info.save(target).await?;
+        manifest
+    };
+    Ok(Some(manifest))
+}
+
+/// Download the manifest, falling back to the cached manifest
+/// if the server unexpectedly responds with `304 Not Modified`.
+async fn get_manifest_unconditionally(
+    url: Url,
+    manifest_version: &str,
+    target: &Path,
+    client: &Client,
+    config: &Config,
+) -> Result<String, ManifestFromServerError> {
+    match get_manifest(url, manifest_version, target, client, config, None).await? {
+        Some(manifest) => Ok(manifest),
+        None => Ok(fs::tokio::read_to_string(target).await?),
     }
 }
 
@@ -132,7 +235,17 @@ pub(crate) async fn manifest_from_cache_or_server(
 ) -> Result<String, ManifestFromServerError> {
     let manifest_version = LuaVersion::from(config)?.version_compatibility_str();
     let url = mk_manifest_url(server_url, &manifest_version, config)?;
-    manifest_from_cache_or_server_impl(&manifest_version, url, config).await
+    if url.scheme() == "file" {
+        return manifest_from_local_dir(&url, &manifest_version).await;
+    }
+    // Boxed, because the revalidation future is deeply nested
+    // and it is awaited by the futures of most operations
+    Box::pin(manifest_from_cache_or_server_impl(
+        &manifest_version,
+        url,
+        config,
+    ))
+    .await
 }
 
 #[tracing::instrument(
@@ -148,45 +261,37 @@ async fn manifest_from_cache_or_server_impl(
 ) -> Result<String, ManifestFromServerError> {
     let cache = mk_manifest_cache(&url, config).await?;
 
-    #[cfg(not(test))]
-    let client = crate::reqwest::https_client(config)?;
+    let client = crate::reqwest::server_client(&url, config)?;
 
-    #[cfg(test)]
-    let client = crate::reqwest::http_client(config)?;
+    if config.refresh_manifests() {
+        return get_manifest_unconditionally(url, manifest_version, &cache, client, config).await;
+    }
 
     if let Ok(metadata) = fs::tokio::metadata(&cache).await {
-        let last_modified_local: SystemTime = metadata.modified()?;
-
-        let response = match client
-            .head(url.clone())
-            .apply_access_token(config, &url)
-            .send()
-            .await?
-        {
-            response if response.status().is_client_error() => {
-                let url = fallback_unzipped_url(&url)?;
-                client
-                    .head(url.clone())
-                    .apply_access_token(config, &url)
-                    .send()
-                    .await?
-                    .error_for_status()?
-            }
-            response => response.error_for_status()?,
+        let mut info = match CachedManifestInfo::load(&cache).await {
+            Some(info) => info,
+            // Fall back to the modification time of caches without validators
+            None => CachedManifestInfo {
+                last_modified: Some(httpdate::fmt_http_date(metadata.modified()?)),
+                ..CachedManifestInfo::default()
+            },
         };
-
-        if let Some(last_modified_header) = response.headers().get("Last-Modified") {
-            let server_last_modified = httpdate::parse_http_date(last_modified_header.to_str()?)?;
-
-            if server_last_modified > last_modified_local {
-                return get_manifest(url, manifest_version, &cache, client, config).await;
-            }
-
+        if info.is_fresh(config.manifest_ttl()) {
             return Ok(fs::tokio::read_to_string(&cache).await?);
         }
+        if let Some(manifest) =
+            get_manifest(url, manifest_version, &cache, client, config, Some(&info)).await?
+        {
+            return Ok(manifest);
+        }
+        info.checked_at = unix_timestamp(SystemTime::now());
+        if let Err(err) = info.save(&cache).await {
+            tracing::debug!("failed to update cached manifest info: {err}");
+        }
+        return Ok(fs::tokio::read_to_string(&cache).await?);
     }
 
-    get_manifest(url, manifest_version, &cache, client, config).await
+    get_manifest_unconditionally(url, manifest_version, &cache, client, config).await
 }
 
 #[tracing::instrument(level = "trace", skip(config))]
@@ -196,9 +301,65 @@ pub(crate) async fn manifest_from_server_only(
 ) -> Result<String, ManifestFromServerError> {
     let manifest_version = LuaVersion::from(config)?.version_compatibility_str();
     let url = mk_manifest_url(server_url, &manifest_version, config)?;
+    if url.scheme() == "file" {
+        return manifest_from_local_dir(&url, &manifest_version).await;
+    }
     manifest_from_server_only_impl(url, &manifest_version, config).await
 }
 
+/// The path of the binary cache of the server's parsed manifest metadata,
+/// which is stored next to the cached manifest.
+pub(crate) async fn metadata_cache_path(
+    server_url: &Url,
+    config: &Config,
+) -> Result<PathBuf, ManifestFromServerError> {
+    let manifest_version = LuaVersion::from(config)?.version_compatibility_str();
+    let url = mk_manifest_url(server_url, &manifest_version, config)?;
+    let mut path = mk_manifest_cache(&url, config).await?.into_os_string();
+    path.push(".metadata");
+    Ok(path.into())
+}
+
+/// Read the manifest from a local rocks directory, given the URL of its zipped manifest.
+/// Prefers the unzipped `manifest-<version>`, then the zipped variant,
+/// then the version-agnostic `manifest`.
+/// Local manifests are not cached.
+#[tracing::instrument(level = "trace")]
+async fn manifest_from_local_dir(
+    url: &Url,
+    manifest_version: &str,
+) -> Result<String, ManifestFromServerError> {
+    let zipped_manifest = url
+        .to_file_path()
+        .map_err(|_| ManifestFromServerError::FilePath(url.clone()))?;
+    let dir = zipped_manifest
+        .parent()
+        .ok_or_else(|| ManifestFromServerError::FilePath(url.clone()))?;
+    let manifest_file_name = format!("manifest-{manifest_version}");
+    let unzipped_manifest = dir.join(&manifest_file_name);
+    if unzipped_manifest.is_file() {
+        return Ok(fs::tokio::read_to_string(&unzipped_manifest).await?);
+    }
+    if zipped_manifest.is_file() {
+        let manifest_bytes = fs::tokio::read(&zipped_manifest).await?;
+        let mut archive = ZipArchive::new(std::io::Cursor::new(manifest_bytes))
+            .map_err(|err| ManifestFromServerError::ZipRead(url.clone(), err))?;
+        let mut manifest_file = archive
+            .by_name(&manifest_file_name)
+            .map_err(|err| ManifestFromServerError::ZipExtract(url.clone(), err))?;
+        let mut manifest = String::new();
+        manifest_file.read_to_string(&mut manifest)?;
+        return Ok(manifest);
+    }
+    let generic_manifest = dir.join("manifest");
+    if generic_manifest.is_file() {
+        return Ok(fs::tokio::read_to_string(&generic_manifest).await?);
+    }
+    Err(ManifestFromServerError::LocalManifestNotFound(
+        dir.to_path_buf(),
+    ))
+}
+
 #[tracing::instrument(
     name = "Downloading manifest",
     level = "info",
@@ -211,8 +372,8 @@ async fn manifest_from_server_only_impl(
     config: &Config,
 ) -> Result<String, ManifestFromServerError> {
     let cache = mk_manifest_cache(&url, config).await?;
-    let client = crate::reqwest::https_client(config)?;
-    get_manifest(url, manifest_version, &cache, client, config).await
+    let client = crate::reqwest::server_client(&url, config)?;
+    get_manifest_unconditionally(url, manifest_version, &cache, client, config).await
 }
 
 fn mk_manifest_url(
@@ -251,7 +412,12 @@ fn fallback_unzipped_url(url: &Url) -> Result<Url, url::ParseError> {
 
 #[cfg(test)]
 mod tests {
-    use httptest::{matchers::request, responders::status_code, Expectation, Server};
+    use httptest::{
+        all_of,
+        matchers::{contains, key, not, request},
+        responders::status_code,
+        Expectation, Server,
+    };
     use serial_test::serial;
 
     use crate::{config::ConfigBuilder, fs};
@@ -348,4 +514,197 @@ mod tests {
             .unwrap();
         assert_eq!(result, manifest_content);
     }
+
+    #[tokio::test]
+    pub async fn get_manifest_from_local_dir() {
+        let rocks_dir = assert_fs::TempDir::new().unwrap();
+        let server_url = Url::from_directory_path(rocks_dir.path()).unwrap();
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .lua_version(Some(LuaVersion::Lua51))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            manifest_from_cache_or_server(&server_url, &config).await,
+            Err(ManifestFromServerError::LocalManifestNotFound(_))
+        ));
+
+        let resources = format!("{}/resources/test", env!("CARGO_MANIFEST_DIR"));
+        let manifest_content =
+            fs::sync::read_to_string(format!("{resources}/manifest-5.1")).unwrap();
+        fs::tokio::write(rocks_dir.join("manifest"), "repository = {}")
+            .await
+            .unwrap();
+        let result = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(result, "repository = {}");
+
+        fs::tokio::copy(
+            format!("{resources}/manifest-5.1.zip"),
+            rocks_dir.join("manifest-5.1.zip"),
+        )
+        .await
+        .unwrap();
+        let result = manifest_from_server_only(&server_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(result, manifest_content);
+
+        fs::tokio::write(rocks_dir.join("manifest-5.1"), "repository = { foo = {} }")
+            .await
+            .unwrap();
+        let result = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(result, "repository = { foo = {} }");
+    }
+
+    fn test_manifest_zip() -> Vec<u8> {
+        std::fs::read(format!(
+            "{}/resources/test/manifest-5.1.zip",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap()
+    }
+
+    fn test_config(cache_dir: &Path) -> ConfigBuilder {
+        ConfigBuilder::new()
+            .unwrap()
+            .cache_dir(Some(cache_dir.to_path_buf()))
+            .lua_version(Some(LuaVersion::Lua51))
+            .no_progress(Some(true))
+    }
+
+    #[tokio::test]
+    #[serial]
+    pub async fn revalidate_cached_manifest() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::path("/manifest-5.1.zip"),
+                request::headers(not(contains(key("if-none-match")))),
+            ])
+            .times(1)
+            .respond_with(
+                status_code(200)
+                    .append_header("ETag", "\"abc\"")
+                    .body(test_manifest_zip()),
+            ),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::path("/manifest-5.1.zip"),
+                request::headers(contains(("if-none-match", "\"abc\""))),
+            ])
+            .times(1)
+            .respond_with(status_code(304)),
+        );
+        let cache_dir = assert_fs::TempDir::new().unwrap();
+        let config = test_config(cache_dir.path()).build().unwrap();
+        let server_url = Url::parse(&server.url_str("/")).unwrap();
+        let manifest = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        let revalidated = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(revalidated, manifest);
+    }
+
+    #[tokio::test]
+    #[serial]
+    pub async fn fresh_cached_manifest_is_not_revalidated() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::path("/manifest-5.1.zip"))
+                .times(1)
+                .respond_with(status_code(200).body(test_manifest_zip())),
+        );
+        let cache_dir = assert_fs::TempDir::new().unwrap();
+        let config = test_config(cache_dir.path())
+            .manifest_ttl(Some(Duration::from_secs(3600)))
+            .build()
+            .unwrap();
+        let server_url = Url::parse(&server.url_str("/")).unwrap();
+        let manifest = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        let cached = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(cached, manifest);
+    }
+
+    #[tokio::test]
+    #[serial]
+    pub async fn refresh_cached_manifest() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::path("/manifest-5.1.zip"),
+                request::headers(not(contains(key("if-none-match")))),
+                request::headers(not(contains(key("if-modified-since")))),
+            ])
+            .times(2)
+            .respond_with(
+                status_code(200)
+                    .append_header("ETag", "\"abc\"")
+                    .body(test_manifest_zip()),
+            ),
+        );
+        let cache_dir = assert_fs::TempDir::new().unwrap();
+        let config = test_config(cache_dir.path())
+            .manifest_ttl(Some(Duration::from_secs(3600)))
+            .refresh_manifests(Some(true))
+            .build()
+            .unwrap();
+        let server_url = Url::parse(&server.url_str("/")).unwrap();
+        manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+    }
+
+    #[cfg(feature = "serve")]
+    #[tokio::test]
+    #[serial]
+    pub async fn get_manifest_from_rocks_server() {
+        let rocks_dir = assert_fs::TempDir::new().unwrap();
+        let cache_dir = assert_fs::TempDir::new().unwrap();
+        fs::tokio::copy(
+            format!(
+                "{}/resources/test/luatest-0.2-1.src.rock",
+                env!("CARGO_MANIFEST_DIR")
+            ),
+            rocks_dir.join("luatest-0.2-1.src.rock"),
+        )
+        .await
+        .unwrap();
+        let server = crate::operations::Serve::new(rocks_dir.to_path_buf())
+            .addr(([127, 0, 0, 1], 0).into())
+            .bind()
+            .await
+            .unwrap();
+        let server_url = server.url();
+        tokio::spawn(server.run());
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .cache_dir(Some(cache_dir.to_path_buf()))
+            .lua_version(Some(LuaVersion::Lua51))
+            .no_progress(Some(true))
+            .build()
+            .unwrap();
+        let manifest = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        assert!(manifest.contains("luatest"));
+        // served from the cache, as the rocks directory hasn't changed
+        let cached = manifest_from_cache_or_server(&server_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(cached, manifest);
+    }
 }
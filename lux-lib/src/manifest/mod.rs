@@ -3,19 +3,33 @@ use path_slash::PathExt;
 use thiserror::Error;
 use url::Url;
 
+pub use crate::manifest::index::ManifestIndexError;
+#[cfg(feature = "serve")]
+pub(crate) use crate::manifest::index::{is_manifest_name, zip_manifest};
+pub(crate) use crate::manifest::index::{ManifestIndex, RockFile};
 pub use crate::manifest::metadata::*;
+use crate::manifest::metadata_cache::metadata_from_cache_or_parse;
 use crate::manifest::metadata_from_server::*;
 use crate::manifest::metadata_from_vendor_dir::manifest_from_vendor_dir;
+pub use crate::manifest::search_index::SearchMetadata;
+pub(crate) use crate::manifest::search_index::{
+    search_index_cache_path, search_index_from_server, SearchIndex, SEARCH_INDEX_FILE_NAME,
+};
 use crate::package::{RemotePackageType, RemotePackageTypeFilterSpec};
 use crate::{
     config::Config,
-    package::{PackageReq, RemotePackage},
+    lua_version::LuaVersion,
+    luarocks,
+    package::{PackageReq, PackageSpec, PackageVersion, RemotePackage},
     remote_package_source::RemotePackageSource,
 };
 
+mod index;
 pub mod metadata;
+mod metadata_cache;
 mod metadata_from_server;
 mod metadata_from_vendor_dir;
+mod search_index;
 
 #[derive(Error, Debug, Diagnostic)]
 #[non_exhaustive]
@@ -70,7 +84,27 @@ impl Manifest {
                 url: server_url.to_string(),
                 source: Box::new(source),
             })?;
-        match ManifestMetadata::new(&content) {
+        let binary_arch = luarocks::binary_rock_arch(config);
+        let cache = metadata_cache_path(&server_url, config)
+            .await
+            .and_then(|cache| Ok((cache, LuaVersion::from(config)?)));
+        let metadata = match cache {
+            Ok((cache, lua_version)) => {
+                metadata_from_cache_or_parse(
+                    &content,
+                    &cache,
+                    binary_arch.as_deref(),
+                    lua_version,
+                    config.package_types(),
+                )
+                .await
+            }
+            Err(err) => {
+                tracing::debug!("not caching manifest metadata: {err}");
+                ManifestMetadata::with_binary_arch(&content, binary_arch.as_deref())
+            }
+        };
+        match metadata {
             Ok(metadata) => Ok(Self::new(
                 server_url,
                 metadata,
@@ -85,7 +119,7 @@ impl Manifest {
                     })?;
                 Ok(Self::new(
                     server_url,
-                    ManifestMetadata::new(&manifest)?,
+                    ManifestMetadata::with_binary_arch(&manifest, binary_arch.as_deref())?,
                     config.package_types().clone(),
                 ))
             }
@@ -100,6 +134,22 @@ impl Manifest {
         &self.metadata
     }
 
+    /// The architectures (e.g. "src" or "linux-x86_64") of the rocks the server lists
+    /// for the exact package version, including binary rocks for other platforms.
+    pub(crate) async fn package_arches(
+        server_url: &Url,
+        package: &PackageSpec,
+        config: &Config,
+    ) -> Result<Vec<String>, ManifestError> {
+        let content = manifest_from_cache_or_server(server_url, config)
+            .await
+            .map_err(|source| ManifestError::Server {
+                url: server_url.to_string(),
+                source: Box::new(source),
+            })?;
+        Ok(package_arches(&content, package)?)
+    }
+
     /// Find a package that matches the requirement, returning the latest match
     pub fn find(
         &self,
@@ -126,3 +176,48 @@ impl Manifest {
         }
     }
 }
+
+/// The architectures of the rocks a manifest lists for the exact package version.
+fn package_arches(manifest: &str, package: &PackageSpec) -> Result<Vec<String>, ManifestLuaError> {
+    Ok(ManifestMetadata::arches(manifest)?
+        .remove(package.name())
+        .into_iter()
+        .flatten()
+        .filter(|(version, _)| {
+            PackageVersion::parse(version).is_ok_and(|version| &version == package.version())
+        })
+        .flat_map(|(_, arches)| arches)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn package_arches_include_other_platforms() {
+        let manifest = r#"
+repository = {
+    foo = {
+        ["1.0.0-1"] = {
+            { arch = "rockspec" },
+            { arch = "src" },
+            { arch = "macosx-aarch64" },
+        },
+        ["1.0.0-2"] = {
+            { arch = "linux-x86_64" },
+        },
+    },
+}
+"#;
+        let package = PackageSpec::parse("foo".into(), "1.0.0-1".into()).unwrap();
+        let arches = package_arches(manifest, &package).unwrap();
+        assert_eq!(
+            arches.into_iter().sorted().collect_vec(),
+            vec!["macosx-aarch64", "rockspec", "src"]
+        );
+        let package = PackageSpec::parse("bar".into(), "1.0.0-1".into()).unwrap();
+        assert!(package_arches(manifest, &package).unwrap().is_empty());
+    }
+}
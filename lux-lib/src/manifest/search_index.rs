@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::config::Config;
+use crate::fs;
+use crate::lua_rockspec::RockDescription;
+use crate::package::{PackageName, PackageVersion};
+use crate::reqwest::RequestBuilderExt;
+
+/// The file name of a search index provided by a rocks server.
+pub(crate) const SEARCH_INDEX_FILE_NAME: &str = "search-index.json";
+
+/// The searchable metadata of a package version, taken from its rockspec's `description`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchMetadata {
+    /// A one-line description of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// A longer description of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detailed: Option<String>,
+    /// The license used by the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// The package's website.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    /// Labels for categorization of the package.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+impl From<&RockDescription> for SearchMetadata {
+    fn from(description: &RockDescription) -> Self {
+        Self {
+            summary: description.summary.clone(),
+            detailed: description.detailed.clone(),
+            license: description.license.clone(),
+            homepage: description.homepage.as_ref().map(Url::to_string),
+            labels: description
+                .labels
+                .iter()
+                .filter(|label| !label.is_empty())
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// The searchable metadata of package versions.
+/// Rocks servers may provide one as `search-index.json`,
+/// and Lux caches the metadata of rockspecs it has downloaded for searching in one.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SearchIndex {
+    packages: BTreeMap<PackageName, BTreeMap<PackageVersion, SearchMetadata>>,
+}
+
+impl SearchIndex {
+    pub(crate) fn get(
+        &self,
+        name: &PackageName,
+        version: &PackageVersion,
+    ) -> Option<&SearchMetadata> {
+        self.packages.get(name)?.get(version)
+    }
+
+    /// The metadata of the latest indexed version of a package.
+    pub(crate) fn latest(&self, name: &PackageName) -> Option<&SearchMetadata> {
+        self.packages.get(name)?.values().next_back()
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        name: PackageName,
+        version: PackageVersion,
+        metadata: SearchMetadata,
+    ) {
+        self.packages
+            .entry(name)
+            .or_default()
+            .insert(version, metadata);
+    }
+
+    pub(crate) fn extend(&mut self, other: SearchIndex) {
+        for (name, versions) in other.packages {
+            self.packages.entry(name).or_default().extend(versions);
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Load a search index from a file, returning an empty index if it does not exist or is invalid.
+    pub(crate) async fn load(path: &Path) -> Self {
+        let Ok(content) = tokio::fs::read_to_string(path).await else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|err| {
+            tracing::debug!("ignoring invalid search index {}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    pub(crate) async fn save(&self, path: &Path) -> Result<(), fs::FsError> {
+        if let Some(parent) = path.parent() {
+            fs::tokio::create_dir_all(parent).await?;
+        }
+        // Write to a temporary file first, so that concurrent readers never see a partial index
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        fs::tokio::write(&tmp, self.to_json()).await?;
+        fs::tokio::rename(&tmp, path).await
+    }
+}
+
+/// The search index in which the metadata of downloaded rockspecs is cached.
+pub(crate) fn search_index_cache_path(config: &Config) -> PathBuf {
+    config.cache_dir().join(SEARCH_INDEX_FILE_NAME)
+}
+
+/// Fetch the search index provided by a rocks server, if it has one.
+pub(crate) async fn search_index_from_server(
+    server_url: &Url,
+    config: &Config,
+) -> Option<SearchIndex> {
+    let url = server_url.join(SEARCH_INDEX_FILE_NAME).ok()?;
+    let content = if url.scheme() == "file" {
+        tokio::fs::read_to_string(url.to_file_path().ok()?)
+            .await
+            .ok()?
+    } else {
+        let client = crate::reqwest::server_client(&url, config).ok()?;
+        let response = client
+            .get(url.clone())
+            .apply_access_token(config, &url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .inspect_err(|err| tracing::debug!("no search index at {url}: {err}"))
+            .ok()?;
+        response.text().await.ok()?
+    };
+    serde_json::from_str(&content)
+        .inspect_err(|err| tracing::debug!("ignoring invalid search index at {url}: {err}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn search_index_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("cache").join(SEARCH_INDEX_FILE_NAME);
+        let mut index = SearchIndex::default();
+        index.insert(
+            "foo".into(),
+            "1.0.0-1".parse().unwrap(),
+            SearchMetadata {
+                summary: Some("old".into()),
+                ..SearchMetadata::default()
+            },
+        );
+        index.insert(
+            "foo".into(),
+            "2.0.0-1".parse().unwrap(),
+            SearchMetadata {
+                summary: Some("A foo".into()),
+                labels: vec!["neovim".into()],
+                ..SearchMetadata::default()
+            },
+        );
+        index.save(&path).await.unwrap();
+        let loaded = SearchIndex::load(&path).await;
+        assert_eq!(loaded, index);
+        assert_eq!(
+            loaded.latest(&"foo".into()).unwrap().summary.as_deref(),
+            Some("A foo")
+        );
+        assert_eq!(
+            SearchIndex::load(&temp.path().join("missing.json")).await,
+            SearchIndex::default()
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Operations for maintaining a directory of rocks that can be served
+//! as a luarocks server from any static file host.
+
+use std::path::PathBuf;
+
+use bon::Builder;
+
+use crate::{
+    fs,
+    manifest::{ManifestIndex, ManifestIndexError, RockFile},
+    package::PackageSpec,
+};
+
+/// Generate the manifests for a directory of rockspecs and packed rocks.
+/// Writes `manifest`, `manifest-<lua-version>`, their zipped variants and an `index.html`.
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct MakeManifest {
+    #[builder(start_fn)]
+    dir: PathBuf,
+}
+
+impl<State> MakeManifestBuilder<State>
+where
+    State: make_manifest_builder::State + make_manifest_builder::IsComplete,
+{
+    pub fn make(self) -> Result<(), ManifestIndexError> {
+        let args = self._build();
+        ManifestIndex::scan(&args.dir)?.write(&args.dir)
+    }
+}
+
+/// Add rockspecs or packed rocks to a rocks directory, updating its manifests.
+/// Existing entries are overwritten.
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct AdminAdd {
+    #[builder(start_fn)]
+    dir: PathBuf,
+    rocks: Vec<PathBuf>,
+}
+
+impl<State> AdminAddBuilder<State>
+where
+    State: admin_add_builder::State + admin_add_builder::IsComplete,
+{
+    /// Returns the names of the files that were added.
+    pub fn add(self) -> Result<Vec<String>, ManifestIndexError> {
+        let args = self._build();
+        let mut index = ManifestIndex::load(&args.dir)?;
+        let mut added = Vec::new();
+        for rock in args.rocks {
+            let file_name = rock
+                .file_name()
+                .ok_or_else(|| ManifestIndexError::InvalidFileName(rock.clone()))?;
+            let dest = args.dir.join(file_name);
+            if rock != dest {
+                fs::sync::copy(&rock, &dest)?;
+            }
+            added.push(index.insert(&dest)?.file_name());
+        }
+        index.write(&args.dir)?;
+        Ok(added)
+    }
+}
+
+/// Remove rockspecs or packed rocks from a rocks directory, updating its manifests.
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct AdminRemove {
+    #[builder(start_fn)]
+    dir: PathBuf,
+    /// File names of single rockspecs or packed rocks to remove.
+    #[builder(default)]
+    files: Vec<String>,
+    /// Package versions to remove all rockspecs and packed rocks of.
+    #[builder(default)]
+    packages: Vec<PackageSpec>,
+}
+
+impl<State> AdminRemoveBuilder<State>
+where
+    State: admin_remove_builder::State + admin_remove_builder::IsComplete,
+{
+    /// Returns the names of the files that were removed.
+    pub fn remove(self) -> Result<Vec<String>, ManifestIndexError> {
+        let args = self._build();
+        let mut index = ManifestIndex::load(&args.dir)?;
+        let mut removed = Vec::new();
+        for file in args.files {
+            let rock = RockFile::from_file_name(&file)
+                .ok_or_else(|| ManifestIndexError::InvalidFileName(file.into()))?;
+            index.remove(&rock)?;
+            removed.push(rock);
+        }
+        for package in args.packages {
+            removed.extend(index.remove_package(&package)?);
+        }
+        index.write(&args.dir)?;
+        let mut removed_files = Vec::new();
+        for rock in removed {
+            let file_name = rock.file_name();
+            let path = args.dir.join(&file_name);
+            if path.is_file() {
+                fs::sync::remove_file(&path)?;
+            }
+            removed_files.push(file_name);
+        }
+        Ok(removed_files)
+    }
+}
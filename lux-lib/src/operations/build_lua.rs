@@ -18,7 +18,7 @@ use git2::{build::RepoBuilder, FetchOptions};
 use miette::Diagnostic;
 use path_slash::PathExt;
 use ssri::Integrity;
-use target_lexicon::Triple;
+use target_lexicon::{OperatingSystem, PointerWidth, Triple};
 use thiserror::Error;
 use tokio::process::Command;
 use tracing::{span, Instrument};
@@ -148,6 +148,7 @@ async fn do_build_luajit_unix(args: BuildLua<'_>, build_dir: &Path) -> Result<()
     let install_dir = args.install_dir;
 
     let host = Triple::host();
+    let target_triple = config.target();
 
     let mut cc = cc::Build::new();
     cc.cargo_output(false)
@@ -156,7 +157,7 @@ async fn do_build_luajit_unix(args: BuildLua<'_>, build_dir: &Path) -> Result<()
         .warnings(config.verbose())
         .opt_level(3)
         .host(&host.to_string())
-        .target(&host.to_string());
+        .target(&target_triple.to_string());
     let compiler = cc.try_get_compiler()?;
     let compiler_path = compiler.path().to_slash_lossy().to_string();
     let mut make_cmd = Command::new(config.make_cmd());
@@ -164,7 +165,7 @@ async fn do_build_luajit_unix(args: BuildLua<'_>, build_dir: &Path) -> Result<()
     make_cmd.arg("-e");
     make_cmd.stdout(Stdio::piped());
     make_cmd.stderr(Stdio::piped());
-    let target = host.to_string();
+    let target = target_triple.to_string();
     match target.as_str() {
         "x86_64-apple-darwin" if env::var_os("MACOSX_DEPLOYMENT_TARGET").is_none() => {
             make_cmd.env("MACOSX_DEPLOYMENT_TARGET", "10.11");
@@ -177,6 +178,37 @@ async fn do_build_luajit_unix(args: BuildLua<'_>, build_dir: &Path) -> Result<()
         }
         _ => {}
     }
+    if config.is_cross_compiling() {
+        // LuaJIT builds and runs its `buildvm` tool on the host,
+        // so it needs a host compiler that produces code with the target's pointer width.
+        let host_compiler = cc::Build::new()
+            .cargo_output(false)
+            .cargo_metadata(false)
+            .cargo_warnings(false)
+            .host(&host.to_string())
+            .target(&host.to_string())
+            .try_get_compiler()?;
+        let mut host_cc = host_compiler.path().to_slash_lossy().to_string();
+        if target_triple.pointer_width() == Ok(PointerWidth::U32)
+            && host.pointer_width() == Ok(PointerWidth::U64)
+        {
+            host_cc.push_str(" -m32");
+        }
+        if env::var_os("HOST_CC").is_none() {
+            make_cmd.env("HOST_CC", host_cc);
+        }
+        if env::var_os("TARGET_AR").is_none() {
+            let archiver = cc.try_get_archiver()?;
+            make_cmd.env(
+                "TARGET_AR",
+                format!("{} rcus", archiver.get_program().to_string_lossy()),
+            );
+        }
+        if env::var_os("TARGET_STRIP").is_none() {
+            // The host's `strip` can't handle binaries for other architectures.
+            make_cmd.env("TARGET_STRIP", "@:");
+        }
+    }
     let compiler_path = which::which(&compiler_path)
         .map_err(|err| io::Error::other(format!("cannot find {}:\n{}", compiler_path, err)))?;
     let compiler_path = compiler_path.to_slash_lossy().to_string();
@@ -297,8 +329,7 @@ async fn do_build_luajit_msvc(args: BuildLua<'_>, build_dir: &Path) -> Result<()
         msvcbuild.arg("lua52compat");
     }
     msvcbuild.arg("static");
-    let host = Triple::host();
-    let target = host.to_string();
+    let target = args.config.target().to_string();
     let cl = cc::windows_registry::find_tool(&target, "cl.exe").ok_or(BuildLuaError::ClNotFound)?;
     for (k, v) in cl.env() {
         msvcbuild.env(k, v);
@@ -405,25 +436,18 @@ async fn do_build_lua_unix(
     let config = args.config;
     let install_dir = args.install_dir;
 
-    let build_target = if cfg!(target_os = "linux") {
-        // only lua 5.4 has a specific `linux-readline` target
-        if matches!(&lua_version, LuaVersion::Lua54) {
-            "linux-readline"
-        } else {
-            "linux"
-        }
-    } else if cfg!(target_os = "macos") {
-        "macosx"
-    } else if cfg!(target_os = "freebsd") {
-        "freebsd"
+    let build_target = lua_make_target(&config.target(), lua_version, config.is_cross_compiling());
+    let toolchain_args = if config.is_cross_compiling() {
+        cross_toolchain_make_args(config)?
     } else {
-        "generic"
+        Vec::new()
     };
     match Command::new(config.make_cmd())
         .current_dir(build_dir)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .arg(build_target)
+        .args(&toolchain_args)
         .output()
         .await
     {
@@ -436,6 +460,7 @@ async fn do_build_lua_unix(
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
                 .arg("generic")
+                .args(&toolchain_args)
                 .output()
                 .await;
             guard_success(fallback_output, config, "build (generic)")?;
@@ -473,6 +498,52 @@ async fn do_build_lua_unix(
     Ok(())
 }
 
+/// The platform target of Lua's Makefile to build for the given target triple.
+fn lua_make_target(
+    target: &Triple,
+    lua_version: &LuaVersion,
+    is_cross_compiling: bool,
+) -> &'static str {
+    match target.operating_system {
+        // only lua 5.4 has a specific `linux-readline` target.
+        // We can't expect the readline headers and libraries to be available
+        // for the target when cross-compiling.
+        OperatingSystem::Linux
+            if matches!(&lua_version, LuaVersion::Lua54) && !is_cross_compiling =>
+        {
+            "linux-readline"
+        }
+        OperatingSystem::Linux => "linux",
+        OperatingSystem::Darwin(_) | OperatingSystem::MacOSX(_) => "macosx",
+        OperatingSystem::Freebsd => "freebsd",
+        _ => "generic",
+    }
+}
+
+/// `make` variables that override the toolchain in Lua's Makefile
+/// with the C compiler, archiver and ranlib for the configured target.
+fn cross_toolchain_make_args(config: &Config) -> Result<Vec<String>, BuildLuaError> {
+    let mut cc = cc::Build::new();
+    cc.cargo_output(false)
+        .cargo_metadata(false)
+        .cargo_warnings(false)
+        .warnings(config.verbose())
+        .host(&Triple::host().to_string())
+        .target(&config.target().to_string());
+    let compiler = cc.try_get_compiler()?;
+    let archiver = cc.try_get_archiver()?;
+    let ranlib = cc.try_get_ranlib()?;
+    Ok(vec![
+        format!(
+            "CC={} -std=gnu99 {}",
+            compiler.path().to_slash_lossy(),
+            compiler.cflags_env().to_string_lossy()
+        ),
+        format!("AR={} rcu", archiver.get_program().to_string_lossy()),
+        format!("RANLIB={}", ranlib.get_program().to_string_lossy()),
+    ])
+}
+
 #[tracing::instrument(name = "Compiling Lua", skip_all)]
 async fn do_build_lua_msvc(
     args: BuildLua<'_>,
@@ -521,6 +592,7 @@ async fn do_build_lua_msvc(
     };
 
     let host = Triple::host();
+    let target_triple = config.target();
     let mut cc = cc::Build::new();
     cc.cargo_output(false)
         .cargo_metadata(false)
@@ -528,7 +600,7 @@ async fn do_build_lua_msvc(
         .warnings(config.verbose())
         .opt_level(3)
         .host(&host.to_string())
-        .target(&host.to_string());
+        .target(&target_triple.to_string());
 
     cc.define("LUA_USE_WINDOWS", None);
     cc.define("LUA_BUILD_AS_DLL", None);
@@ -578,7 +650,7 @@ async fn do_build_lua_msvc(
         })
     });
 
-    let target = host.to_string();
+    let target = target_triple.to_string();
     let link =
         cc::windows_registry::find_tool(&target, "link.exe").ok_or(BuildLuaError::LinkNotFound)?;
 
@@ -672,3 +744,37 @@ async fn copy_includes(src_dir: &Path, include_dir: &Path) -> Result<(), io::Err
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn lua_make_target_for_triple() {
+        let linux = Triple::from_str("x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(
+            lua_make_target(&linux, &LuaVersion::Lua54, false),
+            "linux-readline"
+        );
+        assert_eq!(lua_make_target(&linux, &LuaVersion::Lua51, false), "linux");
+        let aarch64_linux = Triple::from_str("aarch64-unknown-linux-gnu").unwrap();
+        assert_eq!(
+            lua_make_target(&aarch64_linux, &LuaVersion::Lua54, true),
+            "linux"
+        );
+        let macos = Triple::from_str("aarch64-apple-darwin").unwrap();
+        assert_eq!(lua_make_target(&macos, &LuaVersion::Lua53, true), "macosx");
+        let freebsd = Triple::from_str("x86_64-unknown-freebsd").unwrap();
+        assert_eq!(
+            lua_make_target(&freebsd, &LuaVersion::Lua52, false),
+            "freebsd"
+        );
+        let bare_metal = Triple::from_str("riscv64gc-unknown-none-elf").unwrap();
+        assert_eq!(
+            lua_make_target(&bare_metal, &LuaVersion::Lua54, true),
+            "generic"
+        );
+    }
+}
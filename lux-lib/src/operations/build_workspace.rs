@@ -1,5 +1,9 @@
 use crate::{
-    build::{Build, BuildBehaviour, BuildError},
+    build::{
+        compile_commands::CompileCommand,
+        incremental::{self, BuildStamp, CModuleCache},
+        Build, BuildBehaviour, BuildError,
+    },
     config::Config,
     lockfile::LocalPackage,
     lua_installation::{LuaInstallation, LuaInstallationError},
@@ -13,10 +17,12 @@ use crate::{
 use bon::Builder;
 use itertools::Itertools;
 use miette::Diagnostic;
+use ssri::Integrity;
+use std::path::Path;
 use thiserror::Error;
 use tracing::{info_span, Instrument};
 
-use super::{InstallError, Sync, SyncError};
+use super::{GenCompileCommands, GenCompileCommandsError, InstallError, Sync, SyncError};
 
 #[derive(Debug, Error, Diagnostic)]
 pub enum BuildWorkspaceError {
@@ -56,6 +62,9 @@ pub enum BuildWorkspaceError {
     #[error("error building the workspace")]
     #[diagnostic(forward(0))]
     Build(#[from] BuildError),
+    #[error("failed to generate `compile_commands.json`")]
+    #[diagnostic(forward(0))]
+    GenCompileCommands(#[from] GenCompileCommandsError),
 }
 
 #[derive(Builder)]
@@ -70,11 +79,22 @@ pub struct BuildWorkspace<'a> {
     /// Package to build
     package: Option<PackageName>,
 
+    /// The members to build, overriding `package`.
+    /// Defaults to all workspace members.
+    members: Option<Vec<&'a Project>>,
+
     /// Ignore the project's lockfile and don't create one
     no_lock: bool,
 
     /// Build only the dependencies
     only_deps: bool,
+
+    /// Rebuild the members, even if their previous build is up to date
+    #[builder(default)]
+    force: bool,
+
+    /// Optional features to enable in the workspace members that declare them
+    features: Option<Vec<String>>,
 }
 
 impl<State: build_workspace_builder::State + build_workspace_builder::IsComplete>
@@ -86,7 +106,10 @@ impl<State: build_workspace_builder::State + build_workspace_builder::IsComplete
             Some(package) => info_span!("Building workspace", package = package.to_string()),
             None => info_span!("Building workspace"),
         };
-        do_build(build).instrument(span).await
+        // The build future is large, because it awaits the builds of all dependencies.
+        // Without boxing it, computing the layouts of the futures that await it
+        // (e.g. `Exec::exec` and `WorkspaceExec::exec`) exceeds the compiler's query depth limit.
+        Box::pin(do_build(build)).instrument(span).await
     }
 }
 
@@ -96,8 +119,22 @@ async fn do_build(args: BuildWorkspace<'_>) -> Result<Vec<LocalPackage>, BuildWo
     let workspace_tree = workspace.tree(config)?;
     let build_tree = workspace.build_tree(config)?;
     let lua = LuaInstallation::new_from_config(config).await?;
+    let features = args.features.unwrap_or_default();
+    let members = match (args.members, &args.package) {
+        (Some(members), _) => members,
+        (None, Some(package)) => vec![workspace.select_member(package)?],
+        (None, None) => workspace.members().iter().collect_vec(),
+    };
+    if let Some(unknown) = features.iter().find(|feature| {
+        !members
+            .iter()
+            .any(|project| project.toml().declares_feature(feature))
+    }) {
+        return Err(LocalProjectTomlValidationError::UnknownFeature(unknown.clone()).into());
+    }
     if !args.no_lock {
         Sync::new(workspace, config)
+            .features(features.clone())
             .sync_dependencies()
             .await
             .map_err(BuildWorkspaceError::SyncDependencies)?;
@@ -110,25 +147,18 @@ async fn do_build(args: BuildWorkspace<'_>) -> Result<Vec<LocalPackage>, BuildWo
         let luarocks = LuaRocksInstallation::new(config, build_tree.clone())?;
         let mut dependencies_to_install = Vec::new();
         let mut build_dependencies_to_install = Vec::new();
-        if let Some(package) = &args.package {
-            let project = workspace.select_member(package)?;
-            let project_toml = project.toml().into_local()?;
+        for project in &members {
+            let project_toml = project
+                .toml()
+                .into_local()?
+                .with_declared_features(&features);
             prepare_dependencies_for_build(
                 &project_toml,
+                project.root(),
                 &workspace_tree,
                 &mut dependencies_to_install,
                 &mut build_dependencies_to_install,
             );
-        } else {
-            for project in workspace.members() {
-                let project_toml = project.toml().into_local()?;
-                prepare_dependencies_for_build(
-                    &project_toml,
-                    &workspace_tree,
-                    &mut dependencies_to_install,
-                    &mut build_dependencies_to_install,
-                );
-            }
         }
 
         let tree = workspace.tree(config)?;
@@ -141,6 +171,7 @@ async fn do_build(args: BuildWorkspace<'_>) -> Result<Vec<LocalPackage>, BuildWo
                     .unique()
                     .collect_vec(),
             )
+            .overrides(workspace.overrides()?)
             .tree(&tree)
             .lua(&lua)
             .luarocks(&luarocks)
@@ -152,16 +183,25 @@ async fn do_build(args: BuildWorkspace<'_>) -> Result<Vec<LocalPackage>, BuildWo
 
     let mut packages = Vec::new();
     if !args.only_deps {
-        if let Some(package) = &args.package {
-            let project = workspace.select_member(package)?;
-            let pkg = build_project(project, workspace, &lua, config).await?;
+        let mut compile_commands = Vec::new();
+        for project in &members {
+            let (pkg, project_compile_commands) =
+                build_project(project, workspace, &lua, &features, args.force, config).await?;
             packages.push(pkg);
-        } else {
-            for project in workspace.members() {
-                let pkg = build_project(project, workspace, &lua, config).await?;
-                packages.push(pkg);
-            }
+            compile_commands.extend(project_compile_commands);
         }
+        GenCompileCommands::new()
+            .config(config)
+            .workspace(workspace)
+            .members(
+                members
+                    .iter()
+                    .map(|project| project.root().to_path_buf())
+                    .collect_vec(),
+            )
+            .compile_commands(compile_commands)
+            .generate_compile_commands()
+            .await?;
     }
     Ok(packages)
 }
@@ -170,21 +210,63 @@ async fn build_project(
     project: &Project,
     workspace: &Workspace,
     lua: &LuaInstallation,
+    features: &[String],
+    force: bool,
     config: &Config,
-) -> Result<LocalPackage, BuildWorkspaceError> {
+) -> Result<(LocalPackage, Vec<CompileCommand>), BuildWorkspaceError> {
     let workspace_tree = workspace.tree(config)?;
-    let project_toml = project.toml().into_local()?;
+    let project_toml = project
+        .toml()
+        .into_local()?
+        .with_declared_features(features);
+    let config = config
+        .clone()
+        .with_variables(project_toml.feature_variables());
+
+    let incremental_dir = incremental::incremental_dir(&workspace_tree, project.toml().package());
+    let fingerprint = build_fingerprint(
+        project,
+        workspace,
+        &workspace_tree,
+        lua,
+        project_toml.enabled_features(),
+        &config,
+    )?;
+    if let Some(fingerprint) = fingerprint.as_ref().filter(|_| !force) {
+        if let Some(up_to_date) =
+            up_to_date_build(&incremental_dir, fingerprint, &workspace_tree).await?
+        {
+            tracing::info!("{} is up to date", project.toml().package());
+            return Ok(up_to_date);
+        }
+    }
 
-    let package = Build::new()
+    let c_module_cache = CModuleCache::new(&incremental_dir);
+    let (package, compile_commands) = Build::new()
         .rockspec(&project_toml)
         .lua(lua)
         .tree(&workspace_tree)
         .entry_type(tree::EntryType::Entrypoint)
-        .config(config)
+        .config(&config.clone().with_c_module_cache(c_module_cache.clone()))
         .behaviour(BuildBehaviour::Force)
-        .build()
+        .features(project_toml.enabled_features().to_vec())
+        .build_with_compile_commands()
         .await?;
 
+    if let Err(err) = c_module_cache.prune().await {
+        tracing::warn!("failed to prune the C module cache: {err}");
+    }
+    if let Some(fingerprint) = fingerprint {
+        let stamp = BuildStamp {
+            fingerprint: fingerprint.to_string(),
+            package: package.id(),
+            compile_commands: compile_commands.clone(),
+        };
+        if let Err(err) = stamp.save(&incremental_dir).await {
+            tracing::warn!("failed to record the build fingerprint: {err}");
+        }
+    }
+
     let lockfile = workspace_tree.lockfile()?;
     let dependencies = lockfile
         .rocks()
@@ -204,7 +286,66 @@ async fn build_project(
         lockfile.add_dependency(&package, &dep);
         lockfile.remove_entrypoint(&dep);
     }
-    Ok(package)
+    Ok((package, compile_commands))
+}
+
+/// Fingerprints a workspace member's build inputs, including the IDs of the
+/// resolved dependencies and build dependencies.
+/// Returns `None` if the project files can't be read, in which case the member is always rebuilt.
+fn build_fingerprint(
+    project: &Project,
+    workspace: &Workspace,
+    workspace_tree: &tree::Tree,
+    lua: &LuaInstallation,
+    features: &[String],
+    config: &Config,
+) -> Result<Option<Integrity>, TreeError> {
+    let members = workspace
+        .members()
+        .iter()
+        .map(|member| member.toml().package())
+        .collect_vec();
+    let lockfile = workspace_tree.lockfile()?;
+    let build_lockfile = workspace_tree.build_tree(config)?.lockfile()?;
+    let dependencies = lockfile
+        .rocks()
+        .iter()
+        .filter(|(_, package)| !members.contains(&package.name()))
+        .chain(build_lockfile.rocks())
+        .map(|(id, _)| id);
+    match incremental::project_fingerprint(project, dependencies, features, &lua.version, config) {
+        Ok(fingerprint) => Ok(Some(fingerprint)),
+        Err(err) => {
+            tracing::warn!("failed to fingerprint the project files: {err}");
+            Ok(None)
+        }
+    }
+}
+
+/// The installed package and compile commands of a workspace member's last successful build,
+/// if its fingerprint matches and the package is still installed.
+async fn up_to_date_build(
+    incremental_dir: &Path,
+    fingerprint: &Integrity,
+    workspace_tree: &tree::Tree,
+) -> Result<Option<(LocalPackage, Vec<CompileCommand>)>, TreeError> {
+    let stamp = match BuildStamp::load(incremental_dir).await {
+        Some(stamp) if stamp.fingerprint == fingerprint.to_string() => stamp,
+        _ => return Ok(None),
+    };
+    let lockfile = workspace_tree.lockfile()?;
+    match lockfile.get(&stamp.package) {
+        Some(package)
+            if lockfile.is_entrypoint(&stamp.package)
+                && workspace_tree
+                    .installed_rock_layout(package)?
+                    .rock_path
+                    .is_dir() =>
+        {
+            Ok(Some((package.clone(), stamp.compile_commands)))
+        }
+        _ => Ok(None),
+    }
 }
 
 #[cfg(test)]
@@ -296,4 +437,43 @@ mod tests {
         let src_dir = layout.src;
         assert!(src_dir.join("init.lua").is_file());
     }
+
+    #[tokio::test]
+    async fn build_generates_compile_commands() {
+        if std::env::var("LUX_SKIP_IMPURE_TESTS").unwrap_or("0".into()) == "1" {
+            println!("Skipping impure test");
+            return;
+        }
+        let data_dir: PathBuf = assert_fs::TempDir::new().unwrap().path().into();
+        let project_root =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/test/sample-projects/c-src/");
+        let temp_dir = assert_fs::TempDir::new().unwrap();
+        temp_dir.copy_from(&project_root, &["**"]).unwrap();
+        let project_root = temp_dir.path();
+        let lua_version = detect_installed_lua_version().or(Some(LuaVersion::Lua51));
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .data_dir(Some(data_dir))
+            .lua_version(lua_version)
+            .build()
+            .unwrap();
+        let workspace = Workspace::from_exact(project_root).unwrap().unwrap();
+        BuildWorkspace::new(&workspace, &config)
+            .no_lock(false)
+            .only_deps(false)
+            .build()
+            .await
+            .unwrap();
+        let content = fs::tokio::read_to_string(project_root.join("compile_commands.json"))
+            .await
+            .unwrap();
+        let compile_commands: Vec<CompileCommand> = serde_json::from_str(&content).unwrap();
+        let compile_command = compile_commands.first().unwrap();
+        assert_eq!(compile_command.file, project_root.join("src").join("foo.c"));
+        let lua = LuaInstallation::new_from_config(&config).await.unwrap();
+        for include_dir in lua.includes() {
+            let include_dir = include_dir.to_string_lossy().to_string();
+            assert!(compile_command.arguments.contains(&include_dir));
+        }
+    }
 }
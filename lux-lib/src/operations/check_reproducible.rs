@@ -0,0 +1,283 @@
+use std::{collections::BTreeSet, fmt::Display, io, path::Path, path::PathBuf};
+
+use bon::Builder;
+use itertools::Itertools;
+use miette::Diagnostic;
+use ssri::Integrity;
+use thiserror::Error;
+use walkdir::WalkDir;
+
+use crate::{
+    build::{reproducible, Build, BuildBehaviour, BuildError},
+    config::Config,
+    fs,
+    hash::HasIntegrity,
+    lockfile::LocalPackage,
+    lua_installation::{LuaInstallation, LuaInstallationError},
+    package::PackageName,
+    project::{project_toml::LocalProjectTomlValidationError, Project},
+    tree::{self, InstallTree, TreeError},
+    workspace::{Workspace, WorkspaceError, WorkspaceTreeError},
+};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum CheckReproducibleError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    LocalProjectTomlValidation(#[from] LocalProjectTomlValidationError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceTree(#[from] WorkspaceTreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    LuaInstallation(#[from] LuaInstallationError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Tree(#[from] TreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("failed to compare the build outputs")]
+    Io(#[from] io::Error),
+    #[error("error building the package")]
+    #[diagnostic(forward(0))]
+    Build(#[from] BuildError),
+}
+
+/// Checks that workspace members build reproducibly,
+/// by building each of them twice in separate temporary trees
+/// and comparing the NAR hashes of the installed packages.
+///
+/// The dependencies must already be installed,
+/// e.g. with [`BuildWorkspace`](crate::operations::BuildWorkspace).
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct CheckReproducible<'a> {
+    #[builder(start_fn)]
+    workspace: &'a Workspace,
+
+    #[builder(start_fn)]
+    config: &'a Config,
+
+    /// Package to check. Checks all members if not set.
+    package: Option<PackageName>,
+
+    /// Optional features to enable in the workspace members that declare them
+    features: Option<Vec<String>>,
+}
+
+impl<State: check_reproducible_builder::State + check_reproducible_builder::IsComplete>
+    CheckReproducibleBuilder<'_, State>
+{
+    pub async fn check(self) -> Result<Vec<ReproducibilityReport>, CheckReproducibleError> {
+        do_check_reproducible(self._build()).await
+    }
+}
+
+/// The result of building a package twice.
+#[derive(Debug)]
+pub struct ReproducibilityReport {
+    package: LocalPackage,
+    hashes: (Integrity, Integrity),
+    differences: Vec<FileDifference>,
+}
+
+impl ReproducibilityReport {
+    pub fn package(&self) -> &LocalPackage {
+        &self.package
+    }
+
+    /// The NAR hashes of the two builds' install directories.
+    pub fn hashes(&self) -> &(Integrity, Integrity) {
+        &self.hashes
+    }
+
+    /// The files that differ between the two builds.
+    pub fn differences(&self) -> &[FileDifference] {
+        &self.differences
+    }
+
+    pub fn is_reproducible(&self) -> bool {
+        self.hashes.0 == self.hashes.1
+    }
+}
+
+/// A file, relative to the package's install directory,
+/// that differs between two builds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileDifference {
+    /// The file contents differ.
+    Content(PathBuf),
+    /// The file is executable in only one of the builds.
+    Permissions(PathBuf),
+    /// The file was only produced by the first build.
+    OnlyInFirst(PathBuf),
+    /// The file was only produced by the second build.
+    OnlyInSecond(PathBuf),
+}
+
+impl Display for FileDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Content(path) => write!(f, "{} (content differs)", path.display()),
+            Self::Permissions(path) => write!(f, "{} (permissions differ)", path.display()),
+            Self::OnlyInFirst(path) => write!(f, "{} (only in first build)", path.display()),
+            Self::OnlyInSecond(path) => write!(f, "{} (only in second build)", path.display()),
+        }
+    }
+}
+
+async fn do_check_reproducible(
+    args: CheckReproducible<'_>,
+) -> Result<Vec<ReproducibilityReport>, CheckReproducibleError> {
+    let config = args.config;
+    let workspace = args.workspace;
+    let workspace_tree = workspace.tree(config)?;
+    let lua = LuaInstallation::new_from_config(config).await?;
+    let features = args.features.unwrap_or_default();
+    let members = match &args.package {
+        Some(package) => vec![workspace.select_member(package)?],
+        None => workspace.members().iter().collect_vec(),
+    };
+    let mut reports = Vec::new();
+    for project in members {
+        let (first_dir, second_dir) = (fs::tempfile::tempdir()?, fs::tempfile::tempdir()?);
+        let (package, first) = build_in_tree(
+            project,
+            first_dir.path(),
+            &workspace_tree,
+            &lua,
+            &features,
+            config,
+        )
+        .await?;
+        let (_, second) = build_in_tree(
+            project,
+            second_dir.path(),
+            &workspace_tree,
+            &lua,
+            &features,
+            config,
+        )
+        .await?;
+        let hashes = (first.hash().await?, second.hash().await?);
+        let differences = if hashes.0 == hashes.1 {
+            Vec::new()
+        } else {
+            diff_dirs(&first, &second).await?
+        };
+        reports.push(ReproducibilityReport {
+            package,
+            hashes,
+            differences,
+        });
+    }
+    Ok(reports)
+}
+
+/// Builds a project in a tree at `root`, which shares the build dependencies
+/// of the workspace tree, and returns the package's install directory.
+async fn build_in_tree(
+    project: &Project,
+    root: &Path,
+    workspace_tree: &tree::Tree,
+    lua: &LuaInstallation,
+    features: &[String],
+    config: &Config,
+) -> Result<(LocalPackage, PathBuf), CheckReproducibleError> {
+    let project_toml = project
+        .toml()
+        .into_local()?
+        .with_declared_features(features);
+    let config = config
+        .clone()
+        .with_variables(project_toml.feature_variables());
+    let tree = workspace_tree.with_root(root.to_path_buf(), &config)?;
+    let package = Build::new()
+        .rockspec(&project_toml)
+        .lua(lua)
+        .tree(&tree)
+        .entry_type(tree::EntryType::Entrypoint)
+        .config(&config)
+        .behaviour(BuildBehaviour::Force)
+        .features(project_toml.enabled_features().to_vec())
+        .build()
+        .await?;
+    let rock_path = tree.entrypoint(&package)?.rock_path;
+    Ok((package, rock_path))
+}
+
+/// Compares the files in two directories.
+async fn diff_dirs(first: &Path, second: &Path) -> io::Result<Vec<FileDifference>> {
+    let first_files = relative_files(first)?;
+    let second_files = relative_files(second)?;
+    let mut differences = Vec::new();
+    for file in first_files.union(&second_files) {
+        match (first_files.contains(file), second_files.contains(file)) {
+            (true, false) => differences.push(FileDifference::OnlyInFirst(file.clone())),
+            (false, true) => differences.push(FileDifference::OnlyInSecond(file.clone())),
+            _ => {
+                let (first_file, second_file) = (first.join(file), second.join(file));
+                if first_file.hash().await? != second_file.hash().await? {
+                    differences.push(FileDifference::Content(file.clone()));
+                } else if reproducible::is_executable(&std::fs::metadata(&first_file)?)
+                    != reproducible::is_executable(&std::fs::metadata(&second_file)?)
+                {
+                    differences.push(FileDifference::Permissions(file.clone()));
+                }
+            }
+        }
+    }
+    Ok(differences)
+}
+
+fn relative_files(dir: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_ok(|entry| entry.file_type().is_file())
+        .map_ok(|entry| {
+            entry
+                .path()
+                .strip_prefix(dir)
+                .unwrap_or(entry.path())
+                .to_path_buf()
+        })
+        .map(|entry| entry.map_err(io::Error::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+
+    #[tokio::test]
+    async fn diff_build_outputs() {
+        let first = assert_fs::TempDir::new().unwrap();
+        let second = assert_fs::TempDir::new().unwrap();
+        first.child("src/foo.lua").write_str("return 1").unwrap();
+        second.child("src/foo.lua").write_str("return 1").unwrap();
+        first
+            .child("lib/foo.so")
+            .write_str("built at 12:00")
+            .unwrap();
+        second
+            .child("lib/foo.so")
+            .write_str("built at 12:01")
+            .unwrap();
+        first.child("etc/first.txt").write_str("").unwrap();
+        second.child("etc/second.txt").write_str("").unwrap();
+        let differences = diff_dirs(first.path(), second.path()).await.unwrap();
+        assert_eq!(
+            differences,
+            vec![
+                FileDifference::OnlyInFirst("etc/first.txt".into()),
+                FileDifference::OnlyInSecond("etc/second.txt".into()),
+                FileDifference::Content("lib/foo.so".into()),
+            ]
+        );
+    }
+}
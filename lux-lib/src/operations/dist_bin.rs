@@ -328,6 +328,7 @@ async fn compile_binary(
 ) -> Result<(), DistProjectBinError> {
     let mut build = cc::Build::new();
     let host = target_lexicon::Triple::host().to_string();
+    let target = config.target().to_string();
 
     let intermediate_dir = fs::tempfile::tempdir()?;
     build
@@ -336,7 +337,7 @@ async fn compile_binary(
         .cargo_warnings(false)
         .warnings(config.verbose())
         .host(&host)
-        .target(&host)
+        .target(&target)
         .opt_level(config.build_profile().opt_level())
         .out_dir(&intermediate_dir);
 
@@ -260,14 +260,18 @@ async fn download_remote_rock(
                     source,
                     url: format!("{}/{}", url, rockspec_name),
                 })?;
-            let bytes = crate::reqwest::https_client(config)?
-                .get(url.clone())
-                .apply_access_token(config, &url)
-                .send()
-                .await?
-                .error_for_status()?
-                .bytes()
-                .await?;
+            let bytes = if url.scheme() == "file" {
+                Bytes::from(fs::tokio::read(local_path(&url)?).await?)
+            } else {
+                crate::reqwest::server_client(&url, config)?
+                    .get(url.clone())
+                    .apply_access_token(config, &url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?
+            };
             let content = String::from_utf8(bytes.into())?;
             let rockspec = DownloadedRockspec {
                 rockspec: RemoteLuaRockspec::new(&content)
@@ -449,6 +453,11 @@ pub enum DownloadSrcRockError {
     Request(#[from] RequestError),
     #[error("failed to parse source rock URL")]
     Parse(#[from] ParseError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("'{0}' is not a valid local directory URL")]
+    LocalPath(Url),
 }
 
 impl From<reqwest::Error> for DownloadSrcRockError {
@@ -478,15 +487,28 @@ pub(crate) async fn download_binary_rock(
     server_url: &Url,
     config: &Config,
 ) -> Result<DownloadedPackedRockBytes, DownloadSrcRockError> {
-    let ext = format!("{}.rock", luarocks::current_platform_luarocks_identifier());
-    ArchiveDownload::new()
-        .package(package)
-        .server_url(server_url)
-        .config(config)
-        .ext(&ext)
-        .fallback_ext("all.rock")
-        .download()
-        .await
+    match luarocks::binary_rock_arch(config) {
+        Some(arch) => {
+            let ext = format!("{arch}.rock");
+            ArchiveDownload::new()
+                .package(package)
+                .server_url(server_url)
+                .config(config)
+                .ext(&ext)
+                .fallback_ext("all.rock")
+                .download()
+                .await
+        }
+        None => {
+            ArchiveDownload::new()
+                .package(package)
+                .server_url(server_url)
+                .config(config)
+                .ext("all.rock")
+                .download()
+                .await
+        }
+    }
 }
 
 #[tracing::instrument(name = "Downloading package", skip_all)]
@@ -556,38 +578,67 @@ async fn download_impl(
     let full_rock_name = mk_packed_rock_name(package.name(), package.version(), ext);
     tracing::debug!(message = format!("📥 Downloading {full_rock_name}").as_str());
     let url = server_url.join(&full_rock_name)?;
-    let response = crate::reqwest::https_client(args.config)?
+    if server_url.scheme() == "file" {
+        let (file_name, url) = match args.fallback_ext {
+            Some(ext) if !local_path(&url)?.is_file() => {
+                let full_rock_name = mk_packed_rock_name(package.name(), package.version(), ext);
+                let url = server_url.join(&full_rock_name)?;
+                (full_rock_name, url)
+            }
+            _ => (full_rock_name, url),
+        };
+        let bytes = fs::tokio::read(local_path(&url)?).await?;
+        return Ok(DownloadedPackedRockBytes {
+            name: package.name().clone(),
+            version: package.version().clone(),
+            bytes: bytes.into(),
+            file_name,
+            url,
+        });
+    }
+    let response = crate::reqwest::server_client(server_url, args.config)?
         .get(url.clone())
         .apply_access_token(args.config, &url)
         .send()
         .await?;
-    let bytes = if response.status().is_success() {
-        response.bytes().await
+    let (bytes, file_name, url) = if response.status().is_success() {
+        (response.bytes().await?, full_rock_name, url)
     } else {
         match args.fallback_ext {
             Some(ext) => {
                 let full_rock_name = mk_packed_rock_name(package.name(), package.version(), ext);
                 let url = server_url.join(&full_rock_name)?;
-                crate::reqwest::https_client(args.config)?
+                let bytes = crate::reqwest::server_client(server_url, args.config)?
                     .get(url.clone())
                     .apply_access_token(args.config, &url)
                     .send()
                     .await?
                     .error_for_status()?
                     .bytes()
-                    .await
+                    .await?;
+                (bytes, full_rock_name, url)
             }
-            None => response.error_for_status()?.bytes().await,
+            None => (
+                response.error_for_status()?.bytes().await?,
+                full_rock_name,
+                url,
+            ),
         }
-    }?;
+    };
     Ok(DownloadedPackedRockBytes {
         name: package.name().clone(),
         version: package.version().clone(),
         bytes,
-        file_name: full_rock_name,
+        file_name,
         url,
     })
 }
+/// The path of a file on a local rocks directory server.
+fn local_path(url: &Url) -> Result<PathBuf, DownloadSrcRockError> {
+    url.to_file_path()
+        .map_err(|_| DownloadSrcRockError::LocalPath(url.clone()))
+}
+
 fn mk_packed_rock_name(name: &PackageName, version: &PackageVersion, ext: &str) -> String {
     format!("{name}-{version}.{ext}")
 }
@@ -622,3 +673,75 @@ pub(crate) async fn unpack_rockspec(
         .map_err(|err| SearchAndDownloadError::Rockspec(Box::new(err)))?;
     Ok(rockspec)
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+
+    use crate::{config::ConfigBuilder, lua_version::LuaVersion};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn download_from_local_dir() {
+        let rocks_dir = assert_fs::TempDir::new().unwrap();
+        rocks_dir
+            .child("manifest-5.1")
+            .write_str(
+                r#"
+repository = {
+   luatest = {
+      ["0.2-1"] = {
+         { arch = "src" }
+      }
+   }
+}
+"#,
+            )
+            .unwrap();
+        std::fs::copy(
+            format!(
+                "{}/resources/test/luatest-0.2-1.src.rock",
+                env!("CARGO_MANIFEST_DIR")
+            ),
+            rocks_dir.join("luatest-0.2-1.src.rock"),
+        )
+        .unwrap();
+        let config = ConfigBuilder::new()
+            .unwrap()
+            .server(Some(Url::from_directory_path(rocks_dir.path()).unwrap()))
+            .extra_servers(Some(Vec::new()))
+            .lua_version(Some(LuaVersion::Lua51))
+            .build()
+            .unwrap();
+        let package_req = "luatest".parse().unwrap();
+        let rockspec = Download::new(&package_req, &config)
+            .download_rockspec()
+            .await
+            .unwrap();
+        assert_eq!(rockspec.rockspec.package().to_string(), "luatest");
+        let rock = Download::new(&package_req, &config)
+            .search_and_download_src_rock()
+            .await
+            .unwrap();
+        assert_eq!(rock.file_name, "luatest-0.2-1.src.rock");
+    }
+
+    #[tokio::test]
+    async fn download_binary_rock_fallback_from_local_dir() {
+        let rocks_dir = assert_fs::TempDir::new().unwrap();
+        rocks_dir
+            .child("luatest-0.2-1.all.rock")
+            .write_str("all")
+            .unwrap();
+        let server_url = Url::from_directory_path(rocks_dir.path()).unwrap();
+        let config = ConfigBuilder::new().unwrap().build().unwrap();
+        let package = "luatest@0.2-1".parse().unwrap();
+        let rock = download_binary_rock(&package, &server_url, &config)
+            .await
+            .unwrap();
+        assert_eq!(rock.file_name, "luatest-0.2-1.all.rock");
+        assert_eq!(rock.url, server_url.join("luatest-0.2-1.all.rock").unwrap());
+        assert_eq!(rock.bytes.as_ref(), b"all");
+    }
+}
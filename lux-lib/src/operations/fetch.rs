@@ -38,6 +38,9 @@ pub struct FetchSrc<'a, R: Rockspec> {
     config: &'a Config,
     #[builder(setters(vis = "pub(crate)"))]
     source_url: Option<RemotePackageSourceUrl>,
+    /// Copy source archives to the `dest_dir` as-is, instead of unpacking them.
+    #[builder(setters(vis = "pub(crate)"), default)]
+    keep_archive: bool,
 }
 
 #[derive(Debug)]
@@ -78,7 +81,16 @@ where
             },
             None => rock_source.source_spec.clone(),
         };
-        match fetch_src_impl(source_spec, rockspec, rock_source, dest_dir, config).await {
+        match fetch_src_impl(
+            source_spec,
+            rockspec,
+            rock_source,
+            dest_dir,
+            fetch.keep_archive,
+            config,
+        )
+        .await
+        {
             Err(err)
                 if fetch
                     .source_url
@@ -238,6 +250,7 @@ async fn fetch_src_impl<R: Rockspec>(
     rockspec: &R,
     rock_source: &RemoteRockSource,
     dest_dir: &Path,
+    keep_archive: bool,
     config: &Config,
 ) -> Result<RemotePackageSourceMetadata, FetchSrcError> {
     if let Some(vendor_dir) = config.vendor_dir() {
@@ -340,16 +353,20 @@ async fn fetch_src_impl<R: Rockspec>(
                     }
                 })
                 .unwrap_or(url.to_string());
-            let cursor = Cursor::new(response);
-            let mime_type = infer::get(cursor.get_ref()).map(|file_type| file_type.mime_type());
-            operations::unpack::unpack(
-                mime_type,
-                cursor,
-                rock_source.unpack_dir.is_none(),
-                file_name,
-                dest_dir,
-            )
-            .await?;
+            if keep_archive {
+                fs::tokio::write(dest_dir.join(file_name), &response).await?;
+            } else {
+                let cursor = Cursor::new(response);
+                let mime_type = infer::get(cursor.get_ref()).map(|file_type| file_type.mime_type());
+                operations::unpack::unpack(
+                    mime_type,
+                    cursor,
+                    rock_source.unpack_dir.is_none(),
+                    file_name,
+                    dest_dir,
+                )
+                .await?;
+            }
             RemotePackageSourceMetadata {
                 hash,
                 source_url: RemotePackageSourceUrl::Url { url: url.clone() },
@@ -375,14 +392,18 @@ async fn fetch_src_impl<R: Rockspec>(
                     .map(|os_str| os_str.to_string_lossy())
                     .unwrap_or(path.to_string_lossy())
                     .to_string();
-                operations::unpack::unpack(
-                    mime_type,
-                    file,
-                    rock_source.unpack_dir.is_none(),
-                    file_name,
-                    dest_dir,
-                )
-                .await?;
+                if keep_archive {
+                    fs::tokio::write(dest_dir.join(file_name), &buffer).await?;
+                } else {
+                    operations::unpack::unpack(
+                        mime_type,
+                        file,
+                        rock_source.unpack_dir.is_none(),
+                        file_name,
+                        dest_dir,
+                    )
+                    .await?;
+                }
                 path.hash().await.map_err(FetchSrcError::Hash)?
             };
             RemotePackageSourceMetadata {
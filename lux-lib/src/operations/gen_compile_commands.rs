@@ -0,0 +1,122 @@
+use crate::build::compile_commands::{CompileCommand, COMPILE_COMMANDS_FILE_NAME};
+use crate::config::Config;
+use crate::fs;
+use crate::workspace::Workspace;
+use bon::Builder;
+use itertools::Itertools;
+use miette::Diagnostic;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum GenCompileCommandsError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] fs::FsError),
+    #[error("failed to serialize compile commands:\n{0}")]
+    Serialize(String),
+}
+
+/// Generates a `compile_commands.json` in the workspace root,
+/// so that tools like `clangd` can find the Lua headers, external dependency
+/// include directories and defines used to compile the members' C sources.
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub(crate) struct GenCompileCommands<'a> {
+    config: &'a Config,
+    workspace: &'a Workspace,
+    /// The roots of the members that were built.
+    /// Existing compile commands for files in these directories are replaced.
+    members: Vec<PathBuf>,
+    compile_commands: Vec<CompileCommand>,
+}
+
+impl<State> GenCompileCommandsBuilder<'_, State>
+where
+    State: gen_compile_commands_builder::State + gen_compile_commands_builder::IsComplete,
+{
+    pub async fn generate_compile_commands(self) -> Result<(), GenCompileCommandsError> {
+        do_generate_compile_commands(self._build()).await
+    }
+}
+
+async fn do_generate_compile_commands(
+    args: GenCompileCommands<'_>,
+) -> Result<(), GenCompileCommandsError> {
+    if !args.config.generate_compile_commands() {
+        return Ok(());
+    }
+    let compile_commands_path = args.workspace.root().join(COMPILE_COMMANDS_FILE_NAME);
+    let existing = if compile_commands_path.is_file() {
+        let content = fs::tokio::read_to_string(&compile_commands_path).await?;
+        // We don't want to fail a build because of a corrupt compile_commands.json.
+        serde_json::from_str(&content).unwrap_or_default()
+    } else if args.compile_commands.is_empty() {
+        // Don't create a compile_commands.json for projects without C sources.
+        return Ok(());
+    } else {
+        Vec::new()
+    };
+    let compile_commands = merge_compile_commands(existing, args.compile_commands, &args.members);
+    let content = serde_json::to_string_pretty(&compile_commands)
+        .map_err(|err| GenCompileCommandsError::Serialize(err.to_string()))?;
+    fs::tokio::write(&compile_commands_path, content).await?;
+    Ok(())
+}
+
+/// Replaces the existing compile commands of the rebuilt members with the new ones,
+/// keeping the ones of members that weren't built.
+fn merge_compile_commands(
+    existing: Vec<CompileCommand>,
+    new: Vec<CompileCommand>,
+    members: &[PathBuf],
+) -> Vec<CompileCommand> {
+    existing
+        .into_iter()
+        .filter(|compile_command| {
+            !members
+                .iter()
+                .any(|member| compile_command.file.starts_with(member))
+        })
+        .chain(new)
+        .sorted_by(|a, b| a.file.cmp(&b.file))
+        .dedup()
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile_command(file: &str) -> CompileCommand {
+        CompileCommand {
+            directory: "/workspace".into(),
+            file: file.into(),
+            arguments: vec!["cc".into(), "-c".into(), file.into()],
+            command: None,
+            output: None,
+        }
+    }
+
+    #[test]
+    fn merge_replaces_rebuilt_members() {
+        let existing = vec![
+            compile_command("/workspace/foo/src/foo.c"),
+            compile_command("/workspace/foo/src/removed.c"),
+            compile_command("/workspace/bar/src/bar.c"),
+        ];
+        let new = vec![
+            compile_command("/workspace/foo/src/new.c"),
+            compile_command("/workspace/foo/src/foo.c"),
+        ];
+        let merged = merge_compile_commands(existing, new, &["/workspace/foo".into()]);
+        assert_eq!(
+            merged,
+            vec![
+                compile_command("/workspace/bar/src/bar.c"),
+                compile_command("/workspace/foo/src/foo.c"),
+                compile_command("/workspace/foo/src/new.c"),
+            ]
+        );
+    }
+}
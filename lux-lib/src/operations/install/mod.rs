@@ -23,7 +23,7 @@ use crate::{
     },
     package::{PackageName, PackageNameList, PackageReq},
     remote_package_db::{RemotePackageDB, RemotePackageDBError},
-    rockspec::Rockspec,
+    rockspec::{lua_dependency::LuaDependencySpec, Rockspec},
     tree::{self, InstallTree, Tree, TreeError},
     workspace::{Workspace, WorkspaceTreeError},
 };
@@ -60,6 +60,8 @@ where
     #[builder(setters(name = "_tree", vis = ""))]
     tree: T,
     package_db: Option<RemotePackageDB>,
+    /// Packages to force during dependency resolution, by package name.
+    overrides: Option<HashMap<PackageName, LuaDependencySpec>>,
 }
 
 impl<'a, State> InstallBuilder<'a, Tree, State>
@@ -218,6 +220,7 @@ where
         config,
         packages,
         package_db,
+        Arc::new(install.overrides.unwrap_or_default()),
         lockfile.clone(),
         build_lockfile.clone(),
         dep_tx,
@@ -338,10 +341,12 @@ where
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_resolve_worker(
     config: &Config,
     packages: Vec<PackageInstallSpec>,
     package_db: Arc<RemotePackageDB>,
+    overrides: Arc<HashMap<PackageName, LuaDependencySpec>>,
     lockfile: Lockfile<ReadOnly>,
     build_lockfile: Lockfile<ReadOnly>,
     dep_tx: UnboundedSender<PackageInstallData>,
@@ -357,6 +362,7 @@ fn spawn_resolve_worker(
                 .build_dependencies_tx(build_dep_tx)
                 .packages(packages)
                 .package_db(package_db)
+                .overrides(overrides)
                 .lockfile(lockfile)
                 .build_lockfile(build_lockfile)
                 .config(&config)
@@ -472,7 +478,8 @@ async fn install_package<T>(
 where
     T: InstallTree + Sync,
 {
-    match data.downloaded_rock {
+    let overridden = data.spec.overridden();
+    let mut pkg = match data.downloaded_rock {
         RemoteRockDownload::RockspecOnly { rockspec_download } => {
             install_rockspec(
                 rockspec_download,
@@ -481,12 +488,13 @@ where
                 data.build_behaviour,
                 data.pin,
                 data.opt,
+                data.spec.features().to_vec(),
                 data.entry_type,
                 lua,
                 tree,
                 config,
             )
-            .await
+            .await?
         }
         RemoteRockDownload::BinaryRock {
             rockspec_download,
@@ -499,11 +507,12 @@ where
                 data.build_behaviour,
                 data.pin,
                 data.opt,
+                data.spec.features().to_vec(),
                 data.entry_type,
                 config,
                 tree,
             )
-            .await
+            .await?
         }
         RemoteRockDownload::SrcRock {
             rockspec_download,
@@ -521,14 +530,17 @@ where
                 data.build_behaviour,
                 data.pin,
                 data.opt,
+                data.spec.features().to_vec(),
                 data.entry_type,
                 lua,
                 tree,
                 config,
             )
-            .await
+            .await?
         }
-    }
+    };
+    pkg.spec.overridden = overridden;
+    Ok(pkg)
 }
 
 fn worker_result(
@@ -656,6 +668,7 @@ async fn install_rockspec<T>(
     behaviour: BuildBehaviour,
     pin: PinnedState,
     opt: OptState,
+    features: Vec<String>,
     entry_type: tree::EntryType,
     lua: &LuaInstallation,
     tree: &T,
@@ -668,7 +681,9 @@ where
     let rockspec = rockspec_download.rockspec;
     let source = rockspec_download.source;
 
-    if let Some(BuildBackendSpec::LuaRock(_)) = &rockspec.build().current_platform().build_backend {
+    if let Some(BuildBackendSpec::LuaRock(_)) =
+        &rockspec.build().for_target(&config.target()).build_backend
+    {
         let luarocks_tree = tree.build_tree(config)?;
         let luarocks = LuaRocksInstallation::new(config, luarocks_tree)?;
         luarocks.ensure_installed(lua).await?;
@@ -689,6 +704,7 @@ where
         .opt(opt)
         .constraint(constraint)
         .behaviour(behaviour)
+        .features(features)
         .source(source)
         .source_spec(source_spec)
         .build()
@@ -714,6 +730,7 @@ async fn install_binary_rock(
     behaviour: BuildBehaviour,
     pin: PinnedState,
     opt: OptState,
+    features: Vec<String>,
     entry_type: tree::EntryType,
     config: &Config,
     tree: &impl InstallTree,
@@ -732,6 +749,7 @@ async fn install_binary_rock(
     .opt(opt)
     .constraint(constraint)
     .behaviour(behaviour)
+    .features(features)
     .install()
     .await
     .map_err(|err| InstallError::InstallBinaryRock(package, err))?;
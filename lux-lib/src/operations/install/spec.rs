@@ -26,4 +26,7 @@ pub struct PackageInstallSpec {
     /// Optional constraint, carried over from a previous install,
     /// e.g. defined in a lockfile.
     pub(crate) constraint: Option<LockConstraint>,
+    /// Optional features of the package to enable.
+    #[builder(default)]
+    pub(crate) features: Vec<String>,
 }
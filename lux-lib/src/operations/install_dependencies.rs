@@ -1,13 +1,17 @@
+use std::{collections::HashMap, path::Path};
+
 use bon::Builder;
 use itertools::Itertools;
 
 use crate::{
+    build::transpile,
     config::Config,
     lua_installation::LuaInstallation,
     luarocks::luarocks_installation::LuaRocksInstallation,
     operations::{Install, InstallError},
+    package::{PackageName, PackageReq},
     project::project_toml::LocalProjectToml,
-    rockspec::Rockspec,
+    rockspec::{lua_dependency::LuaDependencySpec, Rockspec},
     tree::{self, InstallTree},
 };
 
@@ -21,6 +25,9 @@ where
 {
     dependencies: Vec<PackageInstallSpec>,
     build_dependencies: Vec<PackageInstallSpec>,
+    /// Packages to force during dependency resolution, by package name.
+    #[builder(default)]
+    overrides: HashMap<PackageName, LuaDependencySpec>,
     tree: &'a T,
 
     lua: &'a LuaInstallation,
@@ -45,6 +52,7 @@ impl<
         if !build_dependencies.is_empty() {
             luarocks.ensure_installed(lua).await?;
             Install::new(config)
+                .overrides(args.overrides.clone())
                 .packages(build_dependencies.into_iter().unique().collect_vec())
                 .tree(build_tree.clone())
                 .install()
@@ -52,6 +60,7 @@ impl<
         }
         // for some reason, cargo can't infer the type
         Install::new(config)
+            .overrides(args.overrides)
             .packages(dependencies.into_iter().unique().collect_vec())
             .tree(tree.clone())
             .install()
@@ -62,6 +71,7 @@ impl<
 
 pub(crate) fn prepare_dependencies_for_build(
     project_toml: &LocalProjectToml,
+    project_root: &Path,
     workspace_tree: &impl InstallTree,
     dependencies_to_install: &mut Vec<PackageInstallSpec>,
     build_dependencies_to_install: &mut Vec<PackageInstallSpec>,
@@ -91,12 +101,13 @@ pub(crate) fn prepare_dependencies_for_build(
                 .pin(*dep.pin())
                 .opt(*dep.opt())
                 .maybe_source(dep.source().clone())
+                .features(dep.features().to_vec())
                 .build()
         })
         .for_each(|dep| dependencies_to_install.push(dep));
 
     build_dependencies
-        .into_iter()
+        .iter()
         .filter(|dep| {
             workspace_tree
                 .match_rocks(dep.package_req())
@@ -107,7 +118,23 @@ pub(crate) fn prepare_dependencies_for_build(
                 .pin(*dep.pin())
                 .opt(*dep.opt())
                 .maybe_source(dep.source().clone())
+                .features(dep.features().to_vec())
                 .build()
         })
         .for_each(|dep| build_dependencies_to_install.push(dep));
+
+    // The compilers for modules that transpile to Lua, unless declared as build dependencies
+    transpile::compiler_packages(project_toml, Some(project_root))
+        .into_iter()
+        .filter(|compiler| !build_dependencies.iter().any(|dep| dep.name() == compiler))
+        .map(PackageReq::from)
+        .filter(|package_req| {
+            workspace_tree
+                .match_rocks(package_req)
+                .is_ok_and(|rock_match| !rock_match.is_found())
+        })
+        .map(|package_req| {
+            PackageInstallSpec::new(package_req, tree::EntryType::Entrypoint).build()
+        })
+        .for_each(|dep| build_dependencies_to_install.push(dep));
 }
@@ -81,6 +81,7 @@ impl<
         let project_toml = project.toml().into_local()?;
         prepare_dependencies_for_build(
             &project_toml,
+            project.root(),
             tree,
             &mut dependencies_to_install,
             &mut build_dependencies_to_install,
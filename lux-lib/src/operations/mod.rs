@@ -1,12 +1,15 @@
 #![allow(ambiguous_glob_reexports)]
 
+mod admin;
 mod build_lua;
 mod build_workspace;
+mod check_reproducible;
 mod dist_bin;
 mod download;
 mod exec;
 mod fetch;
 mod fetch_vendored;
+mod gen_compile_commands;
 mod gen_luarc;
 pub mod install;
 mod install_dependencies;
@@ -16,20 +19,27 @@ mod pin;
 mod resolve;
 mod run;
 mod run_lua;
+mod search;
+#[cfg(feature = "serve")]
+mod serve;
 mod sync;
 mod test;
 mod uninstall;
 mod unpack;
 mod update;
 mod vendor;
+mod workspace_exec;
 
+pub use admin::*;
 pub use build_lua::*;
 pub use build_workspace::*;
+pub use check_reproducible::*;
 pub use dist_bin::*;
 pub use download::*;
 pub use exec::*;
 pub use fetch::*;
 pub(crate) use fetch_vendored::*;
+pub use gen_compile_commands::*;
 pub use gen_luarc::*;
 pub use install::*;
 pub(crate) use install_dependencies::*;
@@ -38,9 +48,13 @@ pub use pack::*;
 pub use pin::*;
 pub use run::*;
 pub use run_lua::*;
+pub use search::*;
+#[cfg(feature = "serve")]
+pub use serve::*;
 pub use sync::*;
 pub use test::*;
 pub use uninstall::*;
 pub use unpack::*;
 pub use update::*;
 pub use vendor::*;
+pub use workspace_exec::*;
@@ -1,7 +1,11 @@
+use crate::build::reproducible;
 use crate::build::utils;
 use crate::build::utils::c_dylib_extension;
+use crate::config::Config;
 use crate::fs;
 use crate::lockfile::LocalPackage;
+use crate::lua_rockspec::RemoteLuaRockspec;
+use crate::lua_rockspec::RockSourceSpec;
 use crate::luarocks;
 use crate::luarocks::rock_manifest::DirOrFileEntry;
 use crate::luarocks::rock_manifest::RockManifest;
@@ -11,6 +15,9 @@ use crate::luarocks::rock_manifest::RockManifestDoc;
 use crate::luarocks::rock_manifest::RockManifestLib;
 use crate::luarocks::rock_manifest::RockManifestLua;
 use crate::luarocks::rock_manifest::RockManifestRoot;
+use crate::operations;
+use crate::operations::FetchSrcError;
+use crate::rockspec::Rockspec;
 use crate::tree::InstallTree;
 use crate::tree::RockLayout;
 use crate::tree::Tree;
@@ -26,14 +33,11 @@ use std::io::Read;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use target_lexicon::Triple;
 use thiserror::Error;
 use walkdir::WalkDir;
-use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
-
 /// A binary rock packer
 #[derive(Builder)]
 #[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
@@ -44,6 +48,14 @@ pub struct Pack {
     tree: Tree,
     #[builder(start_fn)]
     package: LocalPackage,
+    /// The target the package was compiled for,
+    /// which determines the architecture binary rocks are tagged with.
+    #[builder(default = Triple::host())]
+    target: Triple,
+    /// A rockspec to pack instead of the installed one (e.g. with a bumped revision).
+    /// The packed rock is named after this rockspec's version.
+    #[builder(setters(vis = "pub(crate)"))]
+    rockspec: Option<RemoteLuaRockspec>,
 }
 
 impl<State> PackBuilder<State>
@@ -55,6 +67,41 @@ where
     }
 }
 
+impl Pack {
+    /// Create a source rock packer, which fetches a rockspec's source
+    /// and packs it together with the rockspec into a `.src.rock` archive.
+    pub fn src<'a, R: Rockspec>(
+        dest_dir: PathBuf,
+        rockspec: &'a R,
+        config: &'a Config,
+    ) -> PackSrcBuilder<'a, R> {
+        PackSrc::new(dest_dir, rockspec, config)
+    }
+}
+
+/// A source rock packer.
+/// Like luarocks, we pack source archives as-is and other sources
+/// as a directory, alongside the rockspec.
+#[derive(Builder)]
+#[builder(start_fn(name = new, vis = ""), finish_fn(name = _build, vis = ""))]
+pub struct PackSrc<'a, R: Rockspec> {
+    #[builder(start_fn)]
+    dest_dir: PathBuf,
+    #[builder(start_fn)]
+    rockspec: &'a R,
+    #[builder(start_fn)]
+    config: &'a Config,
+}
+
+impl<R: Rockspec, State> PackSrcBuilder<'_, R, State>
+where
+    State: pack_src_builder::State + pack_src_builder::IsComplete,
+{
+    pub async fn pack(self) -> Result<PathBuf, PackError> {
+        do_pack_src(self._build()).await
+    }
+}
+
 #[derive(Error, Debug, Diagnostic)]
 #[error("failed to pack rock")]
 pub enum PackError {
@@ -66,19 +113,31 @@ pub enum PackError {
     Walkdir(#[from] walkdir::Error),
     #[error("expected a `package.rockspec` in the package root.")]
     MissingRockspec,
+    #[error("error generating rockspec: {0}")]
+    Rockspec(String),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    FetchSrc(#[from] FetchSrcError),
 }
 
 async fn do_pack(args: Pack) -> Result<PathBuf, PackError> {
     let package = args.package;
     let tree = args.tree;
     let layout = tree.entrypoint_layout(&package);
+    let version = match &args.rockspec {
+        Some(rockspec) => rockspec.version().clone(),
+        None => package.version().clone(),
+    };
     let suffix = if is_binary_rock(&layout) {
-        format!("{}.rock", luarocks::current_platform_luarocks_identifier())
+        format!(
+            "{}.rock",
+            luarocks::target_platform_luarocks_identifier(&args.target)
+        )
     } else {
         "all.rock".into()
     };
-    let file_name = format!("{}-{}.{}", package.name(), package.version(), suffix);
-    let temp_file_name = format!("{}-{}.{}.part", package.name(), package.version(), suffix);
+    let file_name = format!("{}-{}.{}", package.name(), version, suffix);
+    let temp_file_name = format!("{}-{}.{}.part", package.name(), version, suffix);
     let temp_output_path = args.dest_dir.join(temp_file_name);
     let output_path = args.dest_dir.join(file_name);
     let file = fs::sync::create(&temp_output_path)?;
@@ -105,9 +164,19 @@ async fn do_pack(args: Pack) -> Result<PathBuf, PackError> {
     if !layout.rockspec_path().is_file() {
         return Err(PackError::MissingRockspec);
     }
-    let packed_rockspec_name = format!("{}-{}.rockspec", package.name(), package.version());
+    let packed_rockspec_name = format!("{}-{}.rockspec", package.name(), version);
     let renamed_rockspec_entry = temp_root_dir.path().join(packed_rockspec_name);
-    fs::tokio::copy(layout.rockspec_path(), &renamed_rockspec_entry).await?;
+    match &args.rockspec {
+        Some(rockspec) => {
+            let content = rockspec
+                .to_lua_remote_rockspec_string()
+                .map_err(|err| PackError::Rockspec(err.to_string()))?;
+            fs::tokio::write(&renamed_rockspec_entry, content).await?;
+        }
+        None => {
+            fs::tokio::copy(layout.rockspec_path(), &renamed_rockspec_entry).await?;
+        }
+    }
     let root_entries = add_rock_entries(&mut zip, temp_root_dir.path(), "".into())?;
     let mut bin_entries = HashMap::new();
     for relative_binary_path in package.spec.binaries() {
@@ -141,13 +210,74 @@ async fn do_pack(args: Pack) -> Result<PathBuf, PackError> {
         },
     };
     let manifest_str = rock_manifest.to_lua_string();
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let options =
+        reproducible::zip_file_options(false).compression_method(zip::CompressionMethod::Stored);
     zip.start_file("rock_manifest", options)?;
     zip.write_all(manifest_str.as_bytes())?;
     fs::tokio::rename(&temp_output_path, &output_path).await?;
     Ok(output_path)
 }
 
+async fn do_pack_src<R: Rockspec>(args: PackSrc<'_, R>) -> Result<PathBuf, PackError> {
+    let rockspec = args.rockspec;
+    let rockspec_content = rockspec
+        .to_lua_remote_rockspec_string()
+        .map_err(|err| PackError::Rockspec(err.to_string()))?;
+    let base_name = format!("{}-{}", rockspec.package(), rockspec.version());
+    let temp_root_dir = fs::tempfile::tempdir()?;
+    // Like luarocks, we add the rockspec to the root of the archive.
+    fs::tokio::write(
+        temp_root_dir.path().join(format!("{base_name}.rockspec")),
+        rockspec_content,
+    )
+    .await?;
+
+    let rock_source = rockspec.source().current_platform();
+    let source_dir = match &rock_source.source_spec {
+        // Archives are packed as-is, so that luarocks can unpack them when building.
+        RockSourceSpec::Url(_) => temp_root_dir.path().to_path_buf(),
+        RockSourceSpec::File(path) if path.is_file() => temp_root_dir.path().to_path_buf(),
+        RockSourceSpec::Git(git) => temp_root_dir.path().join(
+            rock_source
+                .unpack_dir
+                .clone()
+                .unwrap_or_else(|| default_git_source_dir(&git.url.to_string())),
+        ),
+        RockSourceSpec::File(path) => temp_root_dir.path().join(
+            rock_source
+                .unpack_dir
+                .clone()
+                .or_else(|| path.file_name().map(PathBuf::from))
+                .unwrap_or_else(|| PathBuf::from(&base_name)),
+        ),
+    };
+    fs::tokio::create_dir_all(&source_dir).await?;
+    operations::FetchSrc::new(&source_dir, rockspec, args.config)
+        .keep_archive(true)
+        .fetch()
+        .await?;
+
+    let output_path = args.dest_dir.join(format!("{base_name}.src.rock"));
+    let temp_output_path = args.dest_dir.join(format!("{base_name}.src.rock.part"));
+    let file = fs::sync::create(&temp_output_path)?;
+    let mut zip = ZipWriter::new(file);
+    add_rock_entries(&mut zip, temp_root_dir.path(), "".into())?;
+    zip.finish()?;
+    fs::tokio::rename(&temp_output_path, &output_path).await?;
+    Ok(output_path)
+}
+
+/// The directory luarocks clones git sources into if there is no `source.dir`,
+/// i.e. the repository name without a `.git` suffix.
+fn default_git_source_dir(url: &str) -> PathBuf {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(url);
+    PathBuf::from(name.strip_suffix(".git").unwrap_or(name))
+}
+
 fn is_binary_rock(layout: &RockLayout) -> bool {
     if !&layout.lib.is_dir() {
         return false;
@@ -170,14 +300,18 @@ fn add_rock_entries(
 ) -> Result<HashMap<PathBuf, DirOrFileEntry>, PackError> {
     let mut result = HashMap::new();
     if source_dir.is_dir() {
-        for file in WalkDir::new(source_dir).into_iter().filter_map_ok(|entry| {
-            let file = entry.into_path();
-            if file.is_file() {
-                Some(file)
-            } else {
-                None
-            }
-        }) {
+        for file in WalkDir::new(source_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map_ok(|entry| {
+                let file = entry.into_path();
+                if file.is_file() {
+                    Some(file)
+                } else {
+                    None
+                }
+            })
+        {
             let file = file?;
             let (relative_path, digest) = add_rock_entry(zip, file, source_dir, &zip_dir)?;
             add_dir_or_file_entry(&mut result, &relative_path, digest);
@@ -200,12 +334,8 @@ fn add_rock_entry(
     f.read_to_end(&mut buffer)?;
     let digest = md5::compute(&buffer);
 
-    #[cfg(target_family = "unix")]
-    let options = SimpleFileOptions::default()
-        .compression_method(zip::CompressionMethod::Stored)
-        .unix_permissions(f.metadata()?.permissions().mode());
-    #[cfg(target_family = "windows")]
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let options = reproducible::zip_file_options(reproducible::is_executable(&f.metadata()?))
+        .compression_method(zip::CompressionMethod::Stored);
 
     zip.start_file(zip_dir.join(&relative_path).to_string_lossy(), options)?;
     zip.write_all(&buffer)?;
@@ -241,3 +371,131 @@ fn add_dir_or_file_entry(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+    use std::io::Cursor;
+
+    fn sample_project(source_url: &str) -> assert_fs::TempDir {
+        let project_root = assert_fs::TempDir::new().unwrap();
+        project_root
+            .child("lux.toml")
+            .write_str(&format!(
+                r#"
+package = "sample-project"
+version = "0.1.0"
+lua = ">=5.1"
+
+[source]
+url = "{source_url}"
+
+[build]
+type = "builtin"
+"#,
+            ))
+            .unwrap();
+        project_root
+    }
+
+    #[tokio::test]
+    async fn pack_src_rock_with_source_archive() {
+        let source_dir = assert_fs::TempDir::new().unwrap();
+        let source_archive = source_dir.child("sample-project-0.1.0.zip");
+        let mut zip = ZipWriter::new(File::create(source_archive.path()).unwrap());
+        zip.start_file(
+            "sample-project-0.1.0/src/main.lua",
+            reproducible::zip_file_options(false),
+        )
+        .unwrap();
+        zip.write_all(b"return {}").unwrap();
+        zip.finish().unwrap();
+        let project_root = sample_project(&format!("file://{}", source_archive.display()));
+        let project = Project::from_exact(project_root.path()).unwrap().unwrap();
+        let rockspec = project.toml().into_remote(None).unwrap();
+        let config = crate::config::ConfigBuilder::new()
+            .unwrap()
+            .build()
+            .unwrap();
+        let dest_dir = assert_fs::TempDir::new().unwrap();
+        let src_rock = Pack::src(dest_dir.to_path_buf(), &rockspec, &config)
+            .pack()
+            .await
+            .unwrap();
+        assert_eq!(
+            src_rock.file_name().unwrap().to_string_lossy(),
+            "sample-project-0.1.0-1.src.rock"
+        );
+        let archive = zip::ZipArchive::new(File::open(&src_rock).unwrap()).unwrap();
+        let file_names = archive.file_names().sorted().collect_vec();
+        assert_eq!(
+            file_names,
+            vec![
+                "sample-project-0.1.0-1.rockspec",
+                "sample-project-0.1.0.zip"
+            ]
+        );
+
+        // The source archive is unpacked when building from the src.rock
+        let unpack_dir = assert_fs::TempDir::new().unwrap();
+        let bytes = std::fs::read(&src_rock).unwrap();
+        operations::unpack(
+            Some("application/zip"),
+            Cursor::new(bytes),
+            true,
+            "src.rock".into(),
+            unpack_dir.path(),
+        )
+        .await
+        .unwrap();
+        assert!(unpack_dir
+            .path()
+            .join("sample-project-0.1.0/src/main.lua")
+            .is_file());
+    }
+
+    #[tokio::test]
+    async fn pack_src_rock_with_source_directory() {
+        let source_dir = assert_fs::TempDir::new().unwrap();
+        source_dir
+            .child("sample-project/src/main.lua")
+            .write_str("return {}")
+            .unwrap();
+        let source_dir = source_dir.path().join("sample-project");
+        let project_root = sample_project(&format!("file://{}", source_dir.display()));
+        let project = Project::from_exact(project_root.path()).unwrap().unwrap();
+        let rockspec = project.toml().into_remote(None).unwrap();
+        let config = crate::config::ConfigBuilder::new()
+            .unwrap()
+            .build()
+            .unwrap();
+        let dest_dir = assert_fs::TempDir::new().unwrap();
+        let src_rock = Pack::src(dest_dir.to_path_buf(), &rockspec, &config)
+            .pack()
+            .await
+            .unwrap();
+        let archive = zip::ZipArchive::new(File::open(&src_rock).unwrap()).unwrap();
+        let file_names = archive.file_names().sorted().collect_vec();
+        assert_eq!(
+            file_names,
+            vec![
+                "sample-project-0.1.0-1.rockspec",
+                "sample-project/src/main.lua"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_git_source_dir() {
+        assert_eq!(
+            default_git_source_dir("https://github.com/lumen-oss/lux.git"),
+            PathBuf::from("lux")
+        );
+        assert_eq!(
+            default_git_source_dir("git@github.com:lumen-oss/lux"),
+            PathBuf::from("lux")
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt::Display, sync::Arc};
+use std::{collections::HashMap, fmt::Display, sync::Arc};
 
 use async_recursion::async_recursion;
 use bon::Builder;
@@ -10,7 +10,7 @@ use tokio::sync::mpsc::UnboundedSender;
 use tracing::Instrument;
 
 use crate::{
-    build::BuildBehaviour,
+    build::{transpile, BuildBehaviour},
     config::Config,
     lockfile::{
         LocalPackageId, LocalPackageSpec, Lockfile, LockfilePermissions, OptState, PinnedState,
@@ -19,7 +19,7 @@ use crate::{
     operations::{FetchVendored, FetchVendoredError},
     package::{PackageName, PackageReq},
     remote_package_db::RemotePackageDB,
-    rockspec::Rockspec,
+    rockspec::{lua_dependency::LuaDependencySpec, Rockspec},
     tree,
 };
 
@@ -68,6 +68,9 @@ where
     build_dependencies_tx: UnboundedSender<PackageInstallData>,
     packages: Vec<PackageInstallSpec>,
     package_db: Arc<RemotePackageDB>,
+    /// Packages to force wherever they appear in the dependency graph, by package name.
+    #[builder(default)]
+    overrides: Arc<HashMap<PackageName, LuaDependencySpec>>,
     parent_packages: Option<Arc<Vec<PackageName>>>,
     lockfile: Option<Arc<Lockfile<P>>>,
     build_lockfile: Option<Arc<Lockfile<P>>>,
@@ -87,6 +90,59 @@ where
     }
 }
 
+/// Replace a package with its override, if there is one.
+/// The override's version requirement becomes the lock constraint,
+/// so that the locked version always satisfies the recorded constraint.
+fn apply_override(
+    spec: PackageInstallSpec,
+    overrides: &HashMap<PackageName, LuaDependencySpec>,
+    package_db: &RemotePackageDB,
+) -> PackageInstallSpec {
+    let Some(dep) = overrides.get(spec.package.name()) else {
+        return spec;
+    };
+    if override_matches_upstream(&spec, dep, package_db) {
+        tracing::warn!(
+            "the override for '{}' ({}) no longer differs from upstream ({}).\n    Consider removing it from the `[overrides]` table.",
+            dep.name(),
+            override_display(dep),
+            spec.package,
+        );
+    }
+    PackageInstallSpec {
+        package: dep.package_req().clone(),
+        source: dep.source().clone().or(spec.source),
+        constraint: Some(dep.version_req().clone().into()),
+        ..spec
+    }
+}
+
+/// Whether an override resolves to the same package as the requirement it replaces.
+fn override_matches_upstream(
+    spec: &PackageInstallSpec,
+    dep: &LuaDependencySpec,
+    package_db: &RemotePackageDB,
+) -> bool {
+    match (dep.source(), &spec.source) {
+        (None, None) => {
+            let upstream = package_db.latest_match(&spec.package, None);
+            let overridden = package_db.latest_match(dep.package_req(), None);
+            upstream.is_some_and(|upstream| overridden.is_some_and(|pkg| pkg == upstream))
+        }
+        (Some(source), Some(upstream_source)) => {
+            source == upstream_source && dep.version_req() == spec.package.version_req()
+        }
+        _ => false,
+    }
+}
+
+fn override_display(dep: &LuaDependencySpec) -> String {
+    match dep.source() {
+        Some(source) => format!("{} from {source}", dep.package_req()),
+        None => dep.package_req().to_string(),
+    }
+}
+
 /// The names of the build dependencies to install, including the
 /// build backend rock (if any), excluding the luarocks build backends
 /// that Lux implements natively.
@@ -110,6 +166,12 @@ pub(crate) fn build_dependencies_to_install<R: Rockspec>(rockspec: &R) -> Vec<Pa
         let full_backend_name = format!("luarocks-build-{backend}");
         names.insert(0, PackageName::new(full_backend_name));
     }
+
+    let compilers = transpile::compiler_packages(rockspec, None)
+        .into_iter()
+        .filter(|compiler| !names.contains(compiler))
+        .collect_vec();
+    names.extend(compilers);
     names
 }
 
@@ -127,12 +189,14 @@ where
     let packages = args.packages;
     let parent_packages = args.parent_packages.unwrap_or_default();
     let package_db = args.package_db;
+    let overrides = args.overrides;
     let lockfile = args.lockfile;
     let build_lockfile = args.build_lockfile;
     let config = args.config;
     futures::stream::iter(
         packages
             .into_iter()
+            .map(|spec| apply_override(spec, &overrides, &package_db))
             // If there is a lockfile, exclude packages that are already installed
             .filter(
                 |PackageInstallSpec {
@@ -156,12 +220,14 @@ where
                      entry_type,
                      constraint,
                      source,
+                     features,
                  }| {
                     let config = config.clone();
                     let dependencies_tx = dependencies_tx.clone();
                     let build_dependencies_tx = build_dependencies_tx.clone();
                     let parent_packages = Arc::clone(&parent_packages);
                     let package_db = Arc::clone(&package_db);
+                    let overrides = Arc::clone(&overrides);
                     let lockfile = match &lockfile {
                         Some(lockfile) => Some(Arc::clone(lockfile)),
                         None => None,
@@ -269,6 +335,7 @@ where
                                             .collect_vec(),
                                     ))
                                     .package_db(package_db.clone())
+                                    .overrides(overrides.clone())
                                     .maybe_lockfile(build_lockfile.clone())
                                     .maybe_build_lockfile(build_lockfile.clone())
                                     .config(&config)
@@ -301,6 +368,7 @@ where
                                         .pin(pin)
                                         .opt(opt)
                                         .maybe_source(dep.source().clone())
+                                        .features(dep.features().to_vec())
                                         .build()
                                 })
                                 .collect_vec();
@@ -317,6 +385,7 @@ where
                                         .collect_vec(),
                                 ))
                                 .package_db(package_db)
+                                .overrides(overrides.clone())
                                 .maybe_lockfile(lockfile)
                                 .maybe_build_lockfile(build_lockfile)
                                 .config(&config)
@@ -324,7 +393,7 @@ where
                                 .await?;
 
                             let rockspec = downloaded_rock.rockspec();
-                            let local_spec = LocalPackageSpec::new(
+                            let mut local_spec = LocalPackageSpec::new(
                                 rockspec.package(),
                                 rockspec.version(),
                                 constraint,
@@ -332,7 +401,9 @@ where
                                 &pin,
                                 &opt,
                                 rockspec.binaries(),
+                                features,
                             );
+                            local_spec.overridden = overrides.contains_key(rockspec.package());
 
                             let install_spec = PackageInstallData {
                                 build_behaviour,
@@ -362,3 +433,151 @@ where
     .flatten()
     .try_collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::{
+        git::GitSource,
+        lua_rockspec::{RemoteLuaRockspec, RockSourceSpec},
+        manifest::{Manifest, ManifestMetadata},
+        package::RemotePackageTypeFilterSpec,
+    };
+
+    use super::*;
+
+    fn package_db() -> RemotePackageDB {
+        let manifest = r#"
+repository = {
+   foo = {
+      ["1.0.0-1"] = {
+         { arch = "rockspec" },
+      },
+      ["2.0.0-1"] = {
+         { arch = "rockspec" },
+      },
+   },
+}
+"#;
+        let server_url: Url = "https://example.com/".parse().unwrap();
+        RemotePackageDB::from(Manifest::new(
+            server_url,
+            ManifestMetadata::new(manifest).unwrap(),
+            RemotePackageTypeFilterSpec::default(),
+        ))
+    }
+
+    fn overrides(dep: LuaDependencySpec) -> HashMap<PackageName, LuaDependencySpec> {
+        HashMap::from([(dep.name().clone(), dep)])
+    }
+
+    #[test]
+    fn apply_override_records_override_constraint() {
+        let package_db = package_db();
+        let spec = PackageInstallSpec::new(
+            PackageReq::parse("foo>=2").unwrap(),
+            tree::EntryType::DependencyOnly,
+        )
+        .build();
+        let dep: LuaDependencySpec = PackageReq::parse("foo==1.0.0").unwrap().into();
+        let overridden = apply_override(spec, &overrides(dep.clone()), &package_db);
+        assert_eq!(&overridden.package, dep.package_req());
+        assert_eq!(
+            overridden.constraint,
+            Some(dep.version_req().clone().into())
+        );
+        let unrelated = PackageInstallSpec::new(
+            PackageReq::parse("bar>=2").unwrap(),
+            tree::EntryType::DependencyOnly,
+        )
+        .build();
+        assert_eq!(
+            apply_override(unrelated.clone(), &overrides(dep), &package_db),
+            unrelated
+        );
+    }
+
+    #[test]
+    fn override_matches_upstream_compares_sources() {
+        let package_db = package_db();
+        let git = |rev: &str| {
+            RockSourceSpec::Git(GitSource {
+                url: "https://github.com/example/foo.git".parse().unwrap(),
+                checkout_ref: Some(rev.into()),
+            })
+        };
+        let spec = |source: Option<RockSourceSpec>| {
+            PackageInstallSpec::new(
+                PackageReq::parse("foo>=1").unwrap(),
+                tree::EntryType::DependencyOnly,
+            )
+            .maybe_source(source)
+            .build()
+        };
+        let dep = |req: &str, source: Option<RockSourceSpec>| LuaDependencySpec {
+            source,
+            ..PackageReq::parse(req).unwrap().into()
+        };
+
+        // Both resolve to foo@2.0.0-1
+        assert!(override_matches_upstream(
+            &spec(None),
+            &dep("foo>=2", None),
+            &package_db
+        ));
+        assert!(!override_matches_upstream(
+            &spec(None),
+            &dep("foo==1.0.0", None),
+            &package_db
+        ));
+        assert!(!override_matches_upstream(
+            &spec(None),
+            &dep("foo>=1", Some(git("fix"))),
+            &package_db
+        ));
+        assert!(override_matches_upstream(
+            &spec(Some(git("fix"))),
+            &dep("foo>=1", Some(git("fix"))),
+            &package_db
+        ));
+        assert!(!override_matches_upstream(
+            &spec(Some(git("v1.0.0"))),
+            &dep("foo>=1", Some(git("fix"))),
+            &package_db
+        ));
+    }
+
+    #[test]
+    fn build_dependencies_include_transpiler() {
+        let rockspec = RemoteLuaRockspec::new(
+            r#"
+package = "foo"
+version = "1.0.0-1"
+source = {
+    url = "https://example.com/foo.tar.gz",
+}
+build_dependencies = {
+    "tl >= 0.15",
+}
+build = {
+    type = "builtin",
+    modules = {
+        foo = "src/foo.tl",
+        bar = "src/bar.fnl",
+        baz = "src/baz.tl",
+        qux = "src/qux.lua",
+    },
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            build_dependencies_to_install(&rockspec),
+            vec![
+                PackageName::new("tl".into()),
+                PackageName::new("fennel".into())
+            ]
+        );
+    }
+}
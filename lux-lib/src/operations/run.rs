@@ -1,4 +1,8 @@
-use std::{ops::Deref, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
 
 use bon::Builder;
 use itertools::Itertools;
@@ -14,7 +18,7 @@ use crate::{
     operations::run_lua::RunLua,
     package::PackageName,
     path::{Paths, PathsError},
-    project::project_toml::LocalProjectTomlValidationError,
+    project::project_toml::{LocalProjectTomlValidationError, ScriptSpec},
     tree::InstallTree,
     workspace::{Workspace, WorkspaceError, WorkspaceTreeError},
 };
@@ -82,6 +86,23 @@ pub enum RunError {
     Paths(#[from] PathsError),
     #[error("No `run` field found in `lux.toml`")]
     NoRunField,
+    #[error("no script named '{0}' found in the `[scripts]` table of `lux.toml`")]
+    ScriptNotFound(String),
+    #[error("script '{0}' is part of a cycle of `pre`/`post` scripts")]
+    ScriptCycle(String),
+    #[error("unable to parse the command of script '{script}': {source}")]
+    ScriptParse {
+        script: String,
+        source: shell_words::ParseError,
+    },
+    #[error("script '{0}' has an empty `command`")]
+    EmptyScript(String),
+    #[error("script '{script}' ({command}) exited with non-zero exit code: {}", exit_code.map(|code| code.to_string()).unwrap_or("unknown".into()))]
+    ScriptNonZeroExitCode {
+        script: String,
+        command: String,
+        exit_code: Option<i32>,
+    },
 }
 
 #[derive(Builder)]
@@ -128,6 +149,191 @@ where
     }
 }
 
+/// Run a named script from the `[scripts]` table of a project's `lux.toml`,
+/// including its `pre` and `post` scripts.
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct RunScript<'a> {
+    workspace: &'a Workspace,
+    package: Option<PackageName>,
+    #[builder(into)]
+    name: String,
+    dir: Option<PathBuf>,
+    /// Extra arguments, appended to the command of the named script only.
+    args: &'a [String],
+    config: &'a Config,
+    disable_loader: Option<bool>,
+}
+
+impl<State> RunScriptBuilder<'_, State>
+where
+    State: run_script_builder::State + run_script_builder::IsComplete,
+{
+    pub async fn run(self) -> Result<(), RunError> {
+        let run = self._build();
+        let workspace = run.workspace;
+        let config = run.config;
+        let project = workspace.single_member_or_select(&run.package)?;
+        let toml = project.toml().into_local()?;
+        let scripts = toml.scripts();
+
+        let steps = script_commands(scripts, &run.name, run.args, || {
+            Ok(LuaBinary::new(workspace.lua_version(config)?, config)
+                .try_into()
+                .map_err(RunLuaError::from)?)
+        })?;
+
+        let tree = workspace.tree(config)?;
+        let paths = Paths::new(&tree)?;
+        let lua_init = if run.disable_loader.unwrap_or(false) {
+            None
+        } else if tree.version().lux_lib_dir().is_none() {
+            tracing::warn!("lux-lua library not found.\n    Cannot use the `lux.loader`.\n    To suppress this warning, set the `--no-loader` option.");
+            None
+        } else {
+            Some(paths.init())
+        };
+        let root_dir = run.dir.unwrap_or(project.root().to_path_buf());
+
+        for step in steps {
+            tracing::info!(
+                "> {}: {}",
+                step.name,
+                step.script.command().unwrap_or_default()
+            );
+            exec(
+                step.name,
+                step.script,
+                &step.program,
+                &step.args,
+                &root_dir,
+                &paths,
+                lua_init.clone(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A step of a named script, with its command parsed.
+pub(crate) struct ScriptCommand<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) script: &'a ScriptSpec,
+    pub(crate) program: PathBuf,
+    pub(crate) args: Vec<String>,
+}
+
+/// Resolves the commands to run for a script and its `pre` and `post` scripts, in order.
+/// Scripts without a `command` are skipped, and a `lua` program is resolved with `lua_binary`.
+/// The extra arguments are appended to the command of the named script only.
+pub(crate) fn script_commands<'a>(
+    scripts: &'a BTreeMap<String, ScriptSpec>,
+    name: &str,
+    extra_args: &[String],
+    lua_binary: impl Fn() -> Result<PathBuf, RunError>,
+) -> Result<Vec<ScriptCommand<'a>>, RunError> {
+    let mut commands = Vec::new();
+    for step in script_steps(scripts, name)? {
+        let script = &scripts[step];
+        let Some(command) = script.command() else {
+            continue;
+        };
+        let mut words = shell_words::split(command).map_err(|err| RunError::ScriptParse {
+            script: step.clone(),
+            source: err,
+        })?;
+        if words.is_empty() {
+            return Err(RunError::EmptyScript(step.clone()));
+        }
+        let program = words.remove(0);
+        let program = if program == "lua" {
+            lua_binary()?
+        } else {
+            PathBuf::from(program)
+        };
+        if step == name {
+            words.extend(extra_args.iter().cloned());
+        }
+        commands.push(ScriptCommand {
+            name: step,
+            script,
+            program,
+            args: words,
+        });
+    }
+    Ok(commands)
+}
+
+/// Resolves the order in which to run a script and its `pre` and `post` scripts.
+fn script_steps<'a>(
+    scripts: &'a BTreeMap<String, ScriptSpec>,
+    name: &str,
+) -> Result<Vec<&'a String>, RunError> {
+    fn visit<'a>(
+        scripts: &'a BTreeMap<String, ScriptSpec>,
+        name: &str,
+        stack: &mut Vec<&'a String>,
+        steps: &mut Vec<&'a String>,
+    ) -> Result<(), RunError> {
+        let (name, script) = scripts
+            .get_key_value(name)
+            .ok_or_else(|| RunError::ScriptNotFound(name.to_string()))?;
+        if stack.contains(&name) {
+            return Err(RunError::ScriptCycle(name.clone()));
+        }
+        // A script shared by several steps only runs once, at its first position.
+        if steps.contains(&name) {
+            return Ok(());
+        }
+        stack.push(name);
+        for pre in script.pre() {
+            visit(scripts, pre, stack, steps)?;
+        }
+        steps.push(name);
+        for post in script.post() {
+            visit(scripts, post, stack, steps)?;
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    let mut steps = Vec::new();
+    visit(scripts, name, &mut Vec::new(), &mut steps)?;
+    Ok(steps)
+}
+
+async fn exec(
+    name: &str,
+    script: &ScriptSpec,
+    program: &Path,
+    args: &[String],
+    root_dir: &Path,
+    paths: &Paths,
+    lua_init: Option<String>,
+) -> Result<(), RunError> {
+    match Command::new(program)
+        .current_dir(root_dir)
+        .args(args)
+        .env("PATH", paths.path_prepended().joined())
+        .env("LUA_INIT", lua_init.unwrap_or_default())
+        .env("LUA_PATH", paths.package_path().joined())
+        .env("LUA_CPATH", paths.package_cpath().joined())
+        .envs(script.env())
+        .status()
+        .await?
+        .code()
+    {
+        Some(0) => Ok(()),
+        code => Err(RunError::ScriptNonZeroExitCode {
+            script: name.to_string(),
+            command: script.command().unwrap_or_default().to_string(),
+            exit_code: code,
+        }),
+    }
+}
+
 async fn run_with_local_lua(
     workspace: &Workspace,
     root_dir: Option<PathBuf>,
@@ -196,3 +402,102 @@ async fn run_with_command(
         .into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(pre: &[&str], post: &[&str]) -> ScriptSpec {
+        ScriptSpec {
+            command: Some("true".into()),
+            pre: pre.iter().map(|s| s.to_string()).collect(),
+            post: post.iter().map(|s| s.to_string()).collect(),
+            ..ScriptSpec::default()
+        }
+    }
+
+    #[test]
+    fn test_script_steps() {
+        let scripts = BTreeMap::from([
+            ("build".to_string(), script(&["clean"], &[])),
+            ("clean".to_string(), script(&[], &[])),
+            ("notify".to_string(), script(&[], &[])),
+            ("deploy".to_string(), script(&["build"], &["notify"])),
+        ]);
+        let steps = script_steps(&scripts, "deploy").unwrap();
+        assert_eq!(steps, vec!["clean", "build", "deploy", "notify"]);
+        assert!(matches!(
+            script_steps(&scripts, "missing"),
+            Err(RunError::ScriptNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_script_steps_dedup() {
+        let scripts = BTreeMap::from([
+            ("install".to_string(), script(&[], &[])),
+            ("lint".to_string(), script(&["install"], &["report"])),
+            ("test".to_string(), script(&["install"], &["report"])),
+            ("report".to_string(), script(&[], &[])),
+            ("check".to_string(), script(&["lint", "test"], &["report"])),
+        ]);
+        let steps = script_steps(&scripts, "check").unwrap();
+        assert_eq!(steps, vec!["install", "lint", "report", "test", "check"]);
+    }
+
+    #[test]
+    fn test_script_steps_cycle() {
+        let scripts = BTreeMap::from([
+            ("a".to_string(), script(&["b"], &[])),
+            ("b".to_string(), script(&[], &["a"])),
+        ]);
+        assert!(matches!(
+            script_steps(&scripts, "a"),
+            Err(RunError::ScriptCycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_script_commands() {
+        let scripts = BTreeMap::from([
+            (
+                "build".to_string(),
+                ScriptSpec {
+                    command: Some("lua build.lua --release".into()),
+                    ..ScriptSpec::default()
+                },
+            ),
+            ("group".to_string(), ScriptSpec::default()),
+            (
+                "test".to_string(),
+                ScriptSpec {
+                    command: Some("busted 'spec dir'".into()),
+                    pre: vec!["build".into(), "group".into()],
+                    ..ScriptSpec::default()
+                },
+            ),
+        ]);
+        let extra_args = vec!["-v".to_string()];
+        let commands =
+            script_commands(&scripts, "test", &extra_args, || Ok("/bin/lua5.4".into())).unwrap();
+        let commands = commands
+            .iter()
+            .map(|command| (command.name, command.program.clone(), command.args.clone()))
+            .collect_vec();
+        assert_eq!(
+            commands,
+            vec![
+                (
+                    "build",
+                    PathBuf::from("/bin/lua5.4"),
+                    vec!["build.lua".into(), "--release".into()]
+                ),
+                (
+                    "test",
+                    PathBuf::from("busted"),
+                    vec!["spec dir".into(), "-v".into()]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,609 @@
+use std::collections::HashSet;
+
+use bon::Builder;
+use futures::StreamExt;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    manifest::{search_index_cache_path, search_index_from_server, SearchIndex, SearchMetadata},
+    operations::Download,
+    package::{PackageName, PackageSpec, PackageVersion, PackageVersionReq, RemotePackageType},
+    remote_package_db::{RemotePackageDB, RemotePackageDBError},
+    rockspec::Rockspec,
+};
+
+/// The maximum number of rockspecs that are downloaded in a single search
+/// to find the metadata of packages whose names do not match all search terms,
+/// or that need to be filtered by label.
+/// If the limit is reached, the [`SearchResults`] are marked as truncated.
+pub const MAX_METADATA_DOWNLOADS: usize = 100;
+
+/// Search for packages by name, summary, labels and license.
+///
+/// Package names are matched fuzzily.
+/// The metadata of the best matches is downloaded lazily from their rockspecs and cached,
+/// so the summaries, labels and licenses of packages whose names do not match
+/// are only searched if they have been downloaded before,
+/// or if the server provides a `search-index.json`.
+/// At most [`MAX_METADATA_DOWNLOADS`] rockspecs are downloaded per search.
+///
+/// To search for packages that support a specific Lua version,
+/// use a package database with that Lua version's manifests.
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct Search<'a> {
+    /// Whitespace-separated search terms, all of which must match.
+    /// If empty, all packages match.
+    #[builder(start_fn)]
+    query: String,
+    #[builder(start_fn)]
+    config: &'a Config,
+    /// The package database to search.
+    /// Instantiated from the config if not set.
+    package_db: Option<&'a RemotePackageDB>,
+    /// Only consider package versions that match this requirement.
+    version_req: Option<PackageVersionReq>,
+    /// Only include packages that have all of these labels.
+    #[builder(default)]
+    labels: Vec<String>,
+    /// Only include packages that have a binary rock for the current platform.
+    #[builder(default)]
+    has_binary: bool,
+    /// The maximum number of results.
+    /// Default: `20`
+    #[builder(default = 20)]
+    limit: usize,
+}
+
+impl<State> SearchBuilder<'_, State>
+where
+    State: search_builder::State + search_builder::IsComplete,
+{
+    /// Search for packages, returning the best matches first.
+    pub async fn search(self) -> Result<SearchResults, RemotePackageDBError> {
+        let args = self._build();
+        match args.package_db {
+            Some(package_db) => Ok(search(&args, package_db).await),
+            None => {
+                let package_db = RemotePackageDB::from_config(args.config).await?;
+                Ok(search(&args, &package_db).await)
+            }
+        }
+    }
+}
+
+/// The packages that match a search.
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchResults {
+    /// The matching packages, best matches first.
+    pub packages: Vec<SearchResult>,
+    /// Whether packages that may match are missing from the results,
+    /// because their metadata could not be searched without exceeding
+    /// [`MAX_METADATA_DOWNLOADS`].
+    pub truncated: bool,
+}
+
+/// A package that matches a search.
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchResult {
+    pub name: PackageName,
+    /// The latest version that matches the version requirement.
+    pub version: PackageVersion,
+    /// All versions that match the version requirement, latest first.
+    pub versions: Vec<PackageVersion>,
+    /// Whether there is a binary rock of the latest version for the current platform.
+    pub has_binary: bool,
+    /// The metadata of the package, if it could be determined.
+    #[serde(flatten)]
+    pub metadata: Option<SearchMetadata>,
+    /// How well the package matches the search terms. Higher is better.
+    pub score: u32,
+}
+
+/// A package from the manifests that may match the search.
+struct Candidate<'a> {
+    name: &'a PackageName,
+    versions: Vec<&'a PackageVersion>,
+    has_binary: bool,
+}
+
+impl Candidate<'_> {
+    fn latest(&self) -> &PackageVersion {
+        self.versions[0]
+    }
+}
+
+enum Rank {
+    Match(u32),
+    /// The package's name matches some of the search terms,
+    /// but its metadata is needed to determine whether it matches the search.
+    NeedsMetadata(u32),
+    NoMatch,
+}
+
+async fn search(args: &Search<'_>, package_db: &RemotePackageDB) -> SearchResults {
+    let config = args.config;
+    let terms = args
+        .query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect_vec();
+    let labels = args
+        .labels
+        .iter()
+        .map(|label| label.to_lowercase())
+        .collect_vec();
+    let version_req = args
+        .version_req
+        .clone()
+        .unwrap_or_else(PackageVersionReq::any);
+
+    let cache_path = search_index_cache_path(config);
+    let mut cache = SearchIndex::load(&cache_path).await;
+    let mut index = cache.clone();
+    for manifest in package_db.manifests() {
+        if let Some(server_index) = search_index_from_server(manifest.server_url(), config).await {
+            index.extend(server_index);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for manifest in package_db.manifests() {
+        for (name, versions) in manifest.metadata().repository.iter() {
+            if seen.contains(name) {
+                continue;
+            }
+            let versions = versions
+                .iter()
+                .filter(|(version, _)| version_req.matches(version))
+                .sorted_by(|(a, _), (b, _)| Ord::cmp(b, a))
+                .collect_vec();
+            let Some((_, package_types)) = versions.first() else {
+                continue;
+            };
+            let has_binary = package_types.contains(&RemotePackageType::Binary);
+            if args.has_binary && !has_binary {
+                continue;
+            }
+            seen.insert(name);
+            candidates.push(Candidate {
+                name,
+                versions: versions.into_iter().map(|(version, _)| version).collect(),
+                has_binary,
+            });
+        }
+    }
+
+    let metadata = |index: &SearchIndex, candidate: &Candidate<'_>| {
+        index
+            .get(candidate.name, candidate.latest())
+            .or_else(|| index.latest(candidate.name))
+            .cloned()
+    };
+
+    let mut attempted = HashSet::new();
+    loop {
+        let ranked = candidates
+            .iter()
+            .map(|candidate| {
+                let metadata = metadata(&index, candidate);
+                let rank = rank(&terms, &labels, candidate.name, metadata.as_ref());
+                (candidate, metadata, rank)
+            })
+            .collect_vec();
+        let matches = ranked
+            .iter()
+            .filter_map(|(candidate, metadata, rank)| match rank {
+                Rank::Match(score) => Some((*score, candidate, metadata)),
+                _ => None,
+            })
+            .sorted_by(|(a_score, a, _), (b_score, b, _)| {
+                b_score.cmp(a_score).then_with(|| a.name.cmp(b.name))
+            })
+            .collect_vec();
+        // Download the metadata of the best matches, so that their summaries can be shown,
+        // and of packages that may match if there are not enough matches.
+        let mut missing = matches
+            .iter()
+            .take(args.limit)
+            .filter(|(_, _, metadata)| metadata.is_none())
+            .map(|(_, candidate, _)| **candidate)
+            .collect_vec();
+        if matches.len() < args.limit {
+            missing.extend(
+                ranked
+                    .iter()
+                    .filter_map(|(candidate, _, rank)| match rank {
+                        Rank::NeedsMetadata(score) => Some((*score, *candidate)),
+                        _ => None,
+                    })
+                    .sorted_by(|(a_score, a), (b_score, b)| {
+                        b_score.cmp(a_score).then_with(|| a.name.cmp(b.name))
+                    })
+                    .map(|(_, candidate)| candidate),
+            );
+        }
+        let missing = missing
+            .into_iter()
+            .filter(|candidate| !attempted.contains(candidate.name))
+            .take(
+                args.limit
+                    .min(MAX_METADATA_DOWNLOADS.saturating_sub(attempted.len())),
+            )
+            .collect_vec();
+        if missing.is_empty() {
+            // Packages that may match, whose metadata we did not download
+            let truncated = matches.len() < args.limit
+                && ranked.iter().any(|(candidate, _, rank)| {
+                    matches!(rank, Rank::NeedsMetadata(_)) && !attempted.contains(candidate.name)
+                });
+            let packages = matches
+                .into_iter()
+                .take(args.limit)
+                .map(|(score, candidate, metadata)| SearchResult {
+                    name: candidate.name.clone(),
+                    version: candidate.latest().clone(),
+                    versions: candidate.versions.iter().copied().cloned().collect(),
+                    has_binary: candidate.has_binary,
+                    metadata: metadata.clone(),
+                    score,
+                })
+                .collect_vec();
+            if let Err(err) = cache.save(&cache_path).await {
+                tracing::debug!("failed to write the search index cache: {err}");
+            }
+            return SearchResults {
+                packages,
+                truncated,
+            };
+        }
+        attempted.extend(missing.iter().map(|candidate| candidate.name));
+        let downloaded = futures::stream::iter(missing.into_iter().map(|candidate| {
+            let package = PackageSpec::new(candidate.name.clone(), candidate.latest().clone());
+            async move {
+                let package_req = package.clone().into_package_req();
+                let metadata = Download::new(&package_req, config)
+                    .package_db(package_db)
+                    .download_rockspec()
+                    .await
+                    .inspect_err(|err| {
+                        tracing::debug!("failed to download the rockspec of {package}: {err}")
+                    })
+                    .ok()
+                    .map(|download| SearchMetadata::from(download.rockspec.description()));
+                (package, metadata)
+            }
+        }))
+        .buffer_unordered(config.max_jobs())
+        .collect::<Vec<_>>()
+        .await;
+        for (package, metadata) in downloaded {
+            let (name, version) = (package.name().clone(), package.version().clone());
+            match metadata {
+                Some(metadata) => {
+                    cache.insert(name.clone(), version.clone(), metadata.clone());
+                    index.insert(name, version, metadata);
+                }
+                // Rank packages whose rockspec could not be downloaded by their name only
+                None => index.insert(name, version, SearchMetadata::default()),
+            }
+        }
+    }
+}
+
+fn rank(
+    terms: &[String],
+    labels: &[String],
+    name: &PackageName,
+    metadata: Option<&SearchMetadata>,
+) -> Rank {
+    let name = name.to_string();
+    let name_scores = terms
+        .iter()
+        .map(|term| name_score(term, &name))
+        .collect_vec();
+    match metadata {
+        None => {
+            let score = name_scores.iter().sum();
+            if labels.is_empty() && name_scores.iter().all(|score| *score > 0) {
+                Rank::Match(score)
+            } else if terms.is_empty() || score > 0 {
+                Rank::NeedsMetadata(score)
+            } else {
+                Rank::NoMatch
+            }
+        }
+        Some(metadata) => {
+            let has_labels = labels.iter().all(|label| {
+                metadata
+                    .labels
+                    .iter()
+                    .any(|other| other.to_lowercase() == *label)
+            });
+            if !has_labels {
+                return Rank::NoMatch;
+            }
+            let scores = terms
+                .iter()
+                .zip(name_scores)
+                .map(|(term, name_score)| name_score.max(metadata_score(term, metadata)))
+                .collect_vec();
+            if scores.contains(&0) {
+                Rank::NoMatch
+            } else {
+                Rank::Match(scores.into_iter().sum())
+            }
+        }
+    }
+}
+
+/// How well a search term matches a package name, or `0` if it does not match.
+fn name_score(term: &str, name: &str) -> u32 {
+    let name = name.to_lowercase();
+    if name == term {
+        100
+    } else if name.starts_with(term) {
+        80
+    } else if name.contains(term) {
+        60
+    } else {
+        fuzzy_score(term, &name)
+    }
+}
+
+/// Score inexact matches, i.e. typos and abbreviations of a package name.
+fn fuzzy_score(term: &str, name: &str) -> u32 {
+    let term_len = term.chars().count();
+    let max_distance = match term_len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    };
+    let distance = edit_distance(term, name);
+    let typo_score = if max_distance > 0 && distance <= max_distance {
+        50 - 10 * distance as u32
+    } else {
+        0
+    };
+    // The term's characters appear in the name in order, e.g. "cjsn" in "lua-cjson"
+    let abbreviation_score = if term_len >= 3 {
+        let mut name_chars = name.chars();
+        let is_subsequence = term
+            .chars()
+            .all(|c| name_chars.by_ref().any(|name_char| name_char == c));
+        if is_subsequence {
+            40u32
+                .saturating_sub(5 * (name.chars().count() - term_len) as u32)
+                .max(10)
+        } else {
+            0
+        }
+    } else {
+        0
+    };
+    typo_score.max(abbreviation_score)
+}
+
+/// The number of insertions, deletions, substitutions and transpositions of adjacent characters
+/// needed to turn one string into another.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect_vec();
+    let b = b.chars().collect_vec();
+    // distances[i][j] is the distance between the first i characters of a and the first j of b
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// How well a search term matches a package's metadata, or `0` if it does not match.
+fn metadata_score(term: &str, metadata: &SearchMetadata) -> u32 {
+    let contains = |text: &Option<String>| {
+        text.as_ref()
+            .is_some_and(|text| text.to_lowercase().contains(term))
+    };
+    if metadata
+        .labels
+        .iter()
+        .any(|label| label.to_lowercase() == term)
+    {
+        30
+    } else if contains(&metadata.summary) {
+        20
+    } else if contains(&metadata.license) {
+        15
+    } else if contains(&metadata.detailed) {
+        10
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use assert_fs::{prelude::*, TempDir};
+    use url::Url;
+
+    use crate::{config::ConfigBuilder, lua_version::LuaVersion, operations::MakeManifest};
+
+    use super::*;
+
+    const PLUGIN_ROCKSPEC: &str = r#"
+rockspec_format = "3.0"
+package = "nvim-plugin"
+version = "1.0.0-1"
+source = {
+   url = "https://example.com/nvim-plugin-1.0.0.tar.gz",
+}
+description = {
+   summary = "Does things in your editor",
+   license = "GPL-3.0",
+   labels = { "neovim" },
+}
+dependencies = {
+   "lua >= 5.1",
+}
+"#;
+
+    fn rocks_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/test");
+        for file in [
+            "lua-cjson-2.1.0-1.rockspec",
+            "luatest-0.2-1.src.rock",
+            "sample-project-0.1.0-1.all.rock",
+        ] {
+            std::fs::copy(resources.join(file), dir.join(file)).unwrap();
+        }
+        dir.child("nvim-plugin-1.0.0-1.rockspec")
+            .write_str(PLUGIN_ROCKSPEC)
+            .unwrap();
+        MakeManifest::new(dir.to_path_buf()).make().unwrap();
+        dir
+    }
+
+    fn config(rocks_dir: &TempDir, cache_dir: &TempDir) -> Config {
+        ConfigBuilder::new()
+            .unwrap()
+            .server(Some(Url::from_directory_path(rocks_dir.path()).unwrap()))
+            .extra_servers(Some(Vec::new()))
+            .cache_dir(Some(cache_dir.to_path_buf()))
+            .lua_version(Some(LuaVersion::Lua51))
+            .build()
+            .unwrap()
+    }
+
+    fn names(results: &[SearchResult]) -> Vec<String> {
+        results
+            .iter()
+            .map(|result| result.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("luatest", "luatest"), 0);
+        assert_eq!(edit_distance("luatset", "luatest"), 1);
+        assert_eq!(edit_distance("lutest", "luatest"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn score_names() {
+        assert_eq!(name_score("lua-cjson", "lua-cjson"), 100);
+        assert_eq!(name_score("lua", "lua-cjson"), 80);
+        assert_eq!(name_score("json", "lua-cjson"), 60);
+        assert!(name_score("cjsn", "lua-cjson") > 0);
+        assert!(name_score("luatset", "luatest") > 0);
+        assert_eq!(name_score("toml", "lua-cjson"), 0);
+    }
+
+    #[tokio::test]
+    async fn search_server_index() {
+        let rocks_dir = rocks_dir();
+        let cache_dir = TempDir::new().unwrap();
+        let config = config(&rocks_dir, &cache_dir);
+        let package_db = RemotePackageDB::from_config(&config).await.unwrap();
+
+        let results = Search::new("json".into(), &config)
+            .package_db(&package_db)
+            .search()
+            .await
+            .unwrap();
+        assert_eq!(names(&results.packages), vec!["lua-cjson"]);
+        assert_eq!(
+            results.packages[0]
+                .metadata
+                .as_ref()
+                .and_then(|metadata| metadata.summary.as_deref()),
+            Some("A fast JSON encoding/parsing module")
+        );
+
+        // Summaries and licenses are searched
+        let results = Search::new("editor".into(), &config)
+            .package_db(&package_db)
+            .search()
+            .await
+            .unwrap();
+        assert_eq!(names(&results.packages), vec!["nvim-plugin"]);
+        let results = Search::new("lua mit".into(), &config)
+            .package_db(&package_db)
+            .search()
+            .await
+            .unwrap();
+        assert_eq!(names(&results.packages), vec!["lua-cjson", "luatest"]);
+
+        let results = Search::new(String::new(), &config)
+            .package_db(&package_db)
+            .labels(vec!["Neovim".into()])
+            .search()
+            .await
+            .unwrap();
+        assert_eq!(names(&results.packages), vec!["nvim-plugin"]);
+
+        let results = Search::new(String::new(), &config)
+            .package_db(&package_db)
+            .has_binary(true)
+            .search()
+            .await
+            .unwrap();
+        assert_eq!(names(&results.packages), vec!["sample-project"]);
+    }
+
+    #[tokio::test]
+    async fn search_downloads_and_caches_metadata() {
+        let rocks_dir = rocks_dir();
+        std::fs::remove_file(rocks_dir.join("search-index.json")).unwrap();
+        let cache_dir = TempDir::new().unwrap();
+        let config = config(&rocks_dir, &cache_dir);
+        let package_db = RemotePackageDB::from_config(&config).await.unwrap();
+
+        let results = Search::new("luatset".into(), &config)
+            .package_db(&package_db)
+            .search()
+            .await
+            .unwrap();
+        assert_eq!(names(&results.packages), vec!["luatest"]);
+        assert_eq!(
+            results.packages[0]
+                .metadata
+                .as_ref()
+                .and_then(|metadata| metadata.summary.as_deref()),
+            Some("A Lua test runner")
+        );
+        let cache = SearchIndex::load(&search_index_cache_path(&config)).await;
+        assert!(cache.latest(&"luatest".into()).is_some());
+
+        // Filtering by label downloads the metadata of packages that may match
+        let results = Search::new(String::new(), &config)
+            .package_db(&package_db)
+            .labels(vec!["neovim".into()])
+            .search()
+            .await
+            .unwrap();
+        assert_eq!(names(&results.packages), vec!["nvim-plugin"]);
+        assert!(!results.truncated);
+    }
+}
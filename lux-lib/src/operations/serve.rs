@@ -0,0 +1,1022 @@
+use std::convert::Infallible;
+use std::io::{self, SeekFrom};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use bon::Builder;
+use bytes::{Bytes, BytesMut};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full, Limited, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use miette::Diagnostic;
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+use crate::fs;
+use crate::lua_rockspec::RemoteLuaRockspec;
+use crate::manifest::{
+    is_manifest_name, zip_manifest, ManifestIndex, ManifestIndexError, RockFile,
+    SEARCH_INDEX_FILE_NAME,
+};
+use crate::package::PackageSpec;
+use crate::rockspec::Rockspec;
+use crate::upload::ApiKey;
+use crate::TOOL_VERSION;
+
+/// The maximum size of an uploaded rockspec or packed rock.
+const MAX_UPLOAD_SIZE: usize = 128 * 1024 * 1024;
+
+/// The size of the chunks that served files are streamed in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+type ResponseBody = UnsyncBoxBody<Bytes, io::Error>;
+
+type ServerResponse = Response<ResponseBody>;
+
+/// Serve a directory of rockspecs and packed rocks over HTTP.
+///
+/// Manifests, a `search-index.json` and an `index.html` are generated on the fly,
+/// so the directory does not need to be indexed with [`MakeManifest`](crate::operations::MakeManifest).
+/// If an API key is set, the server also accepts uploads via the luarocks upload API,
+/// e.g. from [`ProjectUpload`](crate::upload::ProjectUpload).
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct Serve {
+    #[builder(start_fn)]
+    dir: PathBuf,
+    /// The address to listen on.
+    /// Default: `127.0.0.1:8080`
+    #[builder(default = SocketAddr::from((Ipv4Addr::LOCALHOST, 8080)))]
+    addr: SocketAddr,
+    /// The API key to authenticate uploads with.
+    /// Uploads are disabled if this is not set.
+    upload_api_key: Option<ApiKey>,
+}
+
+impl<State> ServeBuilder<State>
+where
+    State: serve_builder::State + serve_builder::IsComplete,
+{
+    /// Bind to the address, returning a server that is ready to be run.
+    pub async fn bind(self) -> Result<RocksServer, ServeError> {
+        let args = self._build();
+        if !args.dir.is_dir() {
+            return Err(ServeError::NotADirectory(args.dir));
+        }
+        let listener = TcpListener::bind(args.addr)
+            .await
+            .map_err(|err| ServeError::Bind(args.addr, err))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|err| ServeError::Bind(args.addr, err))?;
+        Ok(RocksServer {
+            listener,
+            addr,
+            state: Arc::new(ServerState {
+                dir: args.dir,
+                upload_api_key: args.upload_api_key,
+                index: Mutex::default(),
+                versions: Mutex::default(),
+            }),
+        })
+    }
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[non_exhaustive]
+pub enum ServeError {
+    #[error("'{0}' is not a directory")]
+    NotADirectory(PathBuf),
+    #[error("failed to listen on {0}")]
+    Bind(SocketAddr, #[source] io::Error),
+}
+
+/// A rocks server that is bound to an address.
+pub struct RocksServer {
+    listener: TcpListener,
+    addr: SocketAddr,
+    state: Arc<ServerState>,
+}
+
+impl RocksServer {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The URL to use as the server, e.g. in the `server` config option.
+    pub fn url(&self) -> Url {
+        // A socket address is always a valid URL host and port
+        Url::parse(&format!("http://{}/", self.addr)).unwrap_or_else(|_| unreachable!())
+    }
+
+    /// Serve requests until the task is cancelled.
+    pub async fn run(self) {
+        loop {
+            let stream = match self.listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("failed to accept connection: {err}");
+                    continue;
+                }
+            };
+            let state = Arc::clone(&self.state);
+            tokio::spawn(async move {
+                let service = service_fn(move |request| handle(Arc::clone(&state), request));
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    tracing::debug!("connection error: {err}");
+                }
+            });
+        }
+    }
+}
+
+struct ServerState {
+    dir: PathBuf,
+    upload_api_key: Option<ApiKey>,
+    /// The last generated index, along with the directory listing it was generated from.
+    index: Mutex<Option<(DirListing, Arc<ManifestIndex>)>>,
+    /// Package versions that have been assigned an ID by the upload API.
+    /// The ID of a version is its index + 1.
+    versions: Mutex<Vec<PackageSpec>>,
+}
+
+/// The rockspecs and packed rocks in a directory, with their sizes and modification times.
+#[derive(PartialEq, Eq)]
+struct DirListing {
+    files: Vec<(String, u64, SystemTime)>,
+    /// The latest modification time of the directory and its rocks.
+    last_modified: SystemTime,
+}
+
+impl DirListing {
+    fn read(dir: &Path) -> Result<Self, fs::FsError> {
+        let mut last_modified = fs::sync::metadata(dir)?
+            .modified()
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut files = Vec::new();
+        for entry in fs::sync::read_dir(dir)?.filter_map(Result::ok) {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !(file_name.ends_with(".rockspec") || file_name.ends_with(".rock")) {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                last_modified = last_modified.max(modified);
+                files.push((file_name, metadata.len(), modified));
+            }
+        }
+        files.sort();
+        Ok(Self {
+            files,
+            last_modified,
+        })
+    }
+}
+
+impl ServerState {
+    /// The index of the directory, which is only regenerated if rocks have changed.
+    fn index(&self) -> Result<(Arc<ManifestIndex>, SystemTime), ManifestIndexError> {
+        let listing = DirListing::read(&self.dir)?;
+        let mut cached = self.index.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some((cached_listing, index)) = cached.as_ref() {
+            if *cached_listing == listing {
+                return Ok((Arc::clone(index), listing.last_modified));
+            }
+        }
+        let index = Arc::new(ManifestIndex::scan(&self.dir)?);
+        let last_modified = listing.last_modified;
+        *cached = Some((listing, Arc::clone(&index)));
+        Ok((index, last_modified))
+    }
+
+    fn version_id(&self, package: &PackageSpec) -> usize {
+        let mut versions = self.versions.lock().unwrap_or_else(|err| err.into_inner());
+        match versions.iter().position(|version| version == package) {
+            Some(idx) => idx + 1,
+            None => {
+                versions.push(package.clone());
+                versions.len()
+            }
+        }
+    }
+
+    fn version(&self, id: usize) -> Option<PackageSpec> {
+        let versions = self.versions.lock().unwrap_or_else(|err| err.into_inner());
+        id.checked_sub(1).and_then(|idx| versions.get(idx)).cloned()
+    }
+}
+
+async fn handle(
+    state: Arc<ServerState>,
+    request: Request<Incoming>,
+) -> Result<ServerResponse, Infallible> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let response = match path.strip_prefix("/api/") {
+        Some(endpoint) => {
+            let endpoint = endpoint.to_string();
+            handle_api(&state, &endpoint, request).await
+        }
+        None if method == Method::GET || method == Method::HEAD => {
+            serve_file(state, &path, request.headers()).await
+        }
+        None => status(StatusCode::METHOD_NOT_ALLOWED),
+    };
+    // Don't log the request path of API calls, as it contains the API key
+    if path.starts_with("/api/1/") {
+        tracing::debug!("{method} /api/1/... {}", response.status());
+    } else {
+        tracing::debug!("{method} {path} {}", response.status());
+    }
+    Ok(response)
+}
+
+async fn serve_file(state: Arc<ServerState>, path: &str, headers: &HeaderMap) -> ServerResponse {
+    let name = match path.trim_start_matches('/') {
+        "" => "index.html".to_string(),
+        name if name.contains(['/', '\\']) || name.starts_with('.') => {
+            return status(StatusCode::NOT_FOUND)
+        }
+        name => name.to_string(),
+    };
+    let is_generated = name == "index.html"
+        || name == SEARCH_INDEX_FILE_NAME
+        || is_manifest_name(name.strip_suffix(".zip").unwrap_or(&name));
+    let content = if is_generated {
+        let state = Arc::clone(&state);
+        match tokio::task::spawn_blocking(move || generate(&state, &name)).await {
+            Ok(Ok(content)) => content,
+            Ok(Err(err)) => {
+                tracing::error!("failed to generate {path}: {err}");
+                return status(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            Err(_) => return status(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    } else {
+        let Ok(file) = tokio::fs::File::open(state.dir.join(&name)).await else {
+            return status(StatusCode::NOT_FOUND);
+        };
+        let metadata = match file.metadata().await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return status(StatusCode::NOT_FOUND),
+        };
+        Content {
+            body: ContentBody::File(file),
+            len: metadata.len(),
+            content_type: content_type(&name),
+            last_modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        }
+    };
+    content.into_response(headers).await
+}
+
+/// Generate `index.html`, `search-index.json` or a (zipped) manifest.
+fn generate(state: &ServerState, name: &str) -> Result<Content, ManifestIndexError> {
+    let (index, last_modified) = state.index()?;
+    let (bytes, content_type) = if name == "index.html" {
+        (index.to_html().into_bytes(), "text/html; charset=utf-8")
+    } else if name == SEARCH_INDEX_FILE_NAME {
+        (
+            index.to_search_index().to_json().into_bytes(),
+            "application/json",
+        )
+    } else if let Some(manifest_name) = name.strip_suffix(".zip") {
+        let manifest = index.render_manifest(manifest_name).unwrap_or_default();
+        let zipped = zip_manifest(manifest_name, &manifest)
+            .map_err(|err| ManifestIndexError::ZipWrite(name.into(), err))?;
+        (zipped, "application/zip")
+    } else {
+        let manifest = index.render_manifest(name).unwrap_or_default();
+        (manifest.into_bytes(), "text/plain; charset=utf-8")
+    };
+    Ok(Content {
+        len: bytes.len() as u64,
+        body: ContentBody::Bytes(bytes.into()),
+        content_type,
+        last_modified,
+    })
+}
+
+fn content_type(file_name: &str) -> &'static str {
+    if file_name.ends_with(".rockspec") {
+        "text/plain; charset=utf-8"
+    } else if file_name.ends_with(".html") {
+        "text/html; charset=utf-8"
+    } else if file_name.ends_with(".zip") {
+        "application/zip"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+struct Content {
+    body: ContentBody,
+    len: u64,
+    content_type: &'static str,
+    last_modified: SystemTime,
+}
+
+enum ContentBody {
+    /// Generated content.
+    Bytes(Bytes),
+    /// A file in the rocks directory, which is streamed.
+    File(tokio::fs::File),
+}
+
+impl ContentBody {
+    /// The response body with `len` bytes of the content, starting at `start`.
+    async fn slice(self, start: u64, len: u64) -> io::Result<ResponseBody> {
+        match self {
+            Self::Bytes(bytes) => Ok(full(bytes.slice(start as usize..(start + len) as usize))),
+            Self::File(mut file) => {
+                file.seek(SeekFrom::Start(start)).await?;
+                Ok(stream_body(file.take(len)))
+            }
+        }
+    }
+}
+
+impl Content {
+    /// Respond with the content, taking conditional and range request headers into account.
+    async fn into_response(self, headers: &HeaderMap) -> ServerResponse {
+        // Derived from the size and modification time, so that files don't need to be hashed
+        let modified = self
+            .last_modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let etag = format!("\"{:x}-{:x}\"", self.len, modified.as_nanos());
+        let last_modified = httpdate::fmt_http_date(self.last_modified);
+        let header_str = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+        };
+
+        let not_modified = match header_str(header::IF_NONE_MATCH) {
+            Some(if_none_match) => if_none_match
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag),
+            None => header_str(header::IF_MODIFIED_SINCE)
+                .and_then(|since| httpdate::parse_http_date(since).ok())
+                .zip(httpdate::parse_http_date(&last_modified).ok())
+                .is_some_and(|(since, last_modified)| last_modified <= since),
+        };
+
+        let mut response = Response::builder()
+            .header(header::ETAG, &etag)
+            .header(header::LAST_MODIFIED, &last_modified)
+            .header(header::ACCEPT_RANGES, "bytes");
+        if not_modified {
+            return response
+                .status(StatusCode::NOT_MODIFIED)
+                .body(full(Bytes::new()))
+                .unwrap_or_else(|_| status(StatusCode::INTERNAL_SERVER_ERROR));
+        }
+
+        let len = self.len;
+        let if_range_matches = header_str(header::IF_RANGE)
+            .is_none_or(|if_range| if_range == etag || if_range == last_modified);
+        let range = header_str(header::RANGE)
+            .filter(|_| if_range_matches)
+            .and_then(|range| parse_range(range, len));
+        response = response.header(header::CONTENT_TYPE, self.content_type);
+        let (response, start, end) = match range {
+            None => (response.status(StatusCode::OK), 0, len),
+            Some(Ok((start, end))) => (
+                response
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}")),
+                start,
+                end + 1,
+            ),
+            Some(Err(())) => {
+                return response
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+                    .body(full(Bytes::new()))
+                    .unwrap_or_else(|_| status(StatusCode::INTERNAL_SERVER_ERROR))
+            }
+        };
+        let body = match self.body.slice(start, end - start).await {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::error!("failed to read content: {err}");
+                return status(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+        response
+            .header(header::CONTENT_LENGTH, end - start)
+            .body(body)
+            .unwrap_or_else(|_| status(StatusCode::INTERNAL_SERVER_ERROR))
+    }
+}
+
+/// Stream the content of a reader in chunks, so that large rocks aren't read into memory.
+fn stream_body(reader: impl AsyncRead + Send + Unpin + 'static) -> ResponseBody {
+    let chunks = futures::stream::try_unfold(reader, |mut reader| async move {
+        let mut chunk = BytesMut::with_capacity(CHUNK_SIZE);
+        let read = reader.read_buf(&mut chunk).await?;
+        Ok((read > 0).then(|| (Frame::data(chunk.freeze()), reader)))
+    });
+    StreamBody::new(chunks).boxed_unsync()
+}
+
+fn full(bytes: impl Into<Bytes>) -> ResponseBody {
+    Full::new(bytes.into())
+        .map_err(|never| match never {})
+        .boxed_unsync()
+}
+
+/// Parse a single byte range of the form `bytes=<start>-<end>`, `bytes=<start>-` or `bytes=-<suffix-length>`,
+/// returning the inclusive start and end offsets.
+/// Returns `None` if the range should be ignored (i.e. it is malformed or has multiple ranges),
+/// and `Some(Err(()))` if it cannot be satisfied.
+fn parse_range(range: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let (start, end) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+    if end.contains(',') {
+        return None;
+    }
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 || len == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+    if start >= len {
+        Some(Err(()))
+    } else {
+        Some(Ok((start, end)))
+    }
+}
+
+/// Handle a request to the luarocks API, e.g. from `luarocks upload` or `lx upload`.
+async fn handle_api(
+    state: &ServerState,
+    endpoint: &str,
+    request: Request<Incoming>,
+) -> ServerResponse {
+    if endpoint == "tool_version" {
+        return json_response(StatusCode::OK, json!({ "version": TOOL_VERSION }));
+    }
+    let Some(api_key) = &state.upload_api_key else {
+        return errors(
+            StatusCode::NOT_FOUND,
+            "uploads are not enabled on this server",
+        );
+    };
+    let Some((key, method)) = endpoint
+        .strip_prefix("1/")
+        .and_then(|endpoint| endpoint.split_once('/'))
+    else {
+        return status(StatusCode::NOT_FOUND);
+    };
+    // Compare in constant time, so that the key can't be guessed from response times
+    if !bool::from(key.as_bytes().ct_eq(unsafe { api_key.get() }.as_bytes())) {
+        return errors(StatusCode::FORBIDDEN, "invalid API key");
+    }
+    let query: Vec<(String, String)> = request
+        .uri()
+        .query()
+        .map(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let query_param = |name: &str| {
+        query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    match (request.method().clone(), method) {
+        (Method::GET, "status") => json_response(StatusCode::OK, json!({ "user_id": 1 })),
+        (Method::GET, "verify_tfa") => errors(
+            StatusCode::BAD_REQUEST,
+            "two-factor authentication is not supported by this server",
+        ),
+        (Method::GET, "check_rockspec") => {
+            let (Some(package), Some(version)) = (query_param("package"), query_param("version"))
+            else {
+                return errors(StatusCode::BAD_REQUEST, "missing package or version");
+            };
+            let Ok(package) = PackageSpec::parse(package, version) else {
+                return json_response(StatusCode::OK, json!({}));
+            };
+            let rockspec = format!("{}-{}.rockspec", package.name(), package.version());
+            if state.dir.join(rockspec).is_file() {
+                version_response(state, &package)
+            } else {
+                json_response(StatusCode::OK, json!({}))
+            }
+        }
+        (Method::POST, "upload") => match read_upload(request, "rockspec_file").await {
+            Ok((rock, bytes)) if rock.is_rockspec() => {
+                let rockspec = String::from_utf8_lossy(&bytes);
+                match RemoteLuaRockspec::new(&rockspec) {
+                    Ok(rockspec)
+                        if rockspec.package() == rock.package().name()
+                            && rockspec.version() == rock.package().version() =>
+                    {
+                        match write_upload(state, &rock, &bytes).await {
+                            Ok(()) => version_response(state, rock.package()),
+                            Err(response) => response,
+                        }
+                    }
+                    Ok(_) => errors(
+                        StatusCode::BAD_REQUEST,
+                        "the rockspec's package and version do not match its file name",
+                    ),
+                    Err(err) => {
+                        errors(StatusCode::BAD_REQUEST, &format!("invalid rockspec: {err}"))
+                    }
+                }
+            }
+            Ok(_) => errors(StatusCode::BAD_REQUEST, "expected a rockspec"),
+            Err(response) => response,
+        },
+        (Method::POST, method) if method.starts_with("upload_rock/") => {
+            let Some(package) = method
+                .trim_start_matches("upload_rock/")
+                .parse()
+                .ok()
+                .and_then(|id| state.version(id))
+            else {
+                return errors(StatusCode::NOT_FOUND, "unknown version");
+            };
+            match read_upload(request, "rock_file").await {
+                Ok((rock, bytes)) if !rock.is_rockspec() && *rock.package() == package => {
+                    match write_upload(state, &rock, &bytes).await {
+                        Ok(()) => json_response(StatusCode::OK, json!({})),
+                        Err(response) => response,
+                    }
+                }
+                Ok(_) => errors(
+                    StatusCode::BAD_REQUEST,
+                    &format!("expected a packed rock of {package}"),
+                ),
+                Err(response) => response,
+            }
+        }
+        _ => status(StatusCode::NOT_FOUND),
+    }
+}
+
+fn version_response(state: &ServerState, package: &PackageSpec) -> ServerResponse {
+    json_response(
+        StatusCode::OK,
+        json!({
+            "module": { "name": package.name().to_string() },
+            "version": {
+                "id": state.version_id(package),
+                "version": package.version().to_string(),
+            },
+        }),
+    )
+}
+
+/// Read an uploaded file from a `multipart/form-data` request.
+async fn read_upload(
+    request: Request<Incoming>,
+    field: &str,
+) -> Result<(RockFile, Bytes), ServerResponse> {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let body = Limited::new(request.into_body(), MAX_UPLOAD_SIZE)
+        .collect()
+        .await
+        .map_err(|_| errors(StatusCode::PAYLOAD_TOO_LARGE, "upload too large"))?
+        .to_bytes();
+    let part = parse_multipart(&content_type, &body)
+        .ok_or_else(|| errors(StatusCode::BAD_REQUEST, "invalid multipart/form-data body"))?
+        .into_iter()
+        .find(|part| part.name == field)
+        .ok_or_else(|| errors(StatusCode::BAD_REQUEST, &format!("missing field '{field}'")))?;
+    let rock = part
+        .file_name
+        .as_deref()
+        .filter(|file_name| !file_name.contains(['/', '\\']))
+        .and_then(RockFile::from_file_name)
+        .ok_or_else(|| errors(StatusCode::BAD_REQUEST, "invalid file name"))?;
+    Ok((rock, body.slice_ref(part.data)))
+}
+
+async fn write_upload(
+    state: &ServerState,
+    rock: &RockFile,
+    bytes: &[u8],
+) -> Result<(), ServerResponse> {
+    let file_name = rock.file_name();
+    let path = state.dir.join(&file_name);
+    if path.exists() {
+        return Err(errors(
+            StatusCode::BAD_REQUEST,
+            &format!("{file_name} already exists"),
+        ));
+    }
+    fs::tokio::write(&path, bytes).await.map_err(|err| {
+        tracing::error!("{err}");
+        errors(StatusCode::INTERNAL_SERVER_ERROR, "failed to write upload")
+    })?;
+    // Make sure the upload can be indexed, so that it can't break the manifests
+    if let Err(err) = ManifestIndex::default().insert(&path) {
+        let _ = fs::tokio::remove_file(&path).await;
+        return Err(errors(StatusCode::BAD_REQUEST, &err.to_string()));
+    }
+    tracing::info!("received {file_name}");
+    Ok(())
+}
+
+struct FormPart<'a> {
+    name: String,
+    file_name: Option<String>,
+    data: &'a [u8],
+}
+
+/// Parse a `multipart/form-data` body.
+fn parse_multipart<'a>(content_type: &str, body: &'a [u8]) -> Option<Vec<FormPart<'a>>> {
+    let boundary = content_type
+        .split(';')
+        .map(str::trim)
+        .find_map(|param| param.strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{boundary}");
+    let part_delimiter = format!("\r\n{delimiter}");
+    let mut rest = &body[find(body, delimiter.as_bytes())? + delimiter.len()..];
+    let mut parts = Vec::new();
+    while !rest.starts_with(b"--") {
+        rest = rest.strip_prefix(b"\r\n")?;
+        let end = find(rest, part_delimiter.as_bytes())?;
+        let part = &rest[..end];
+        rest = &rest[end + part_delimiter.len()..];
+        let header_end = find(part, b"\r\n\r\n")?;
+        let headers = std::str::from_utf8(&part[..header_end]).ok()?;
+        let disposition = headers.split("\r\n").find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-disposition")
+                .then_some(value)
+        })?;
+        let param = |key: &str| {
+            disposition
+                .split(';')
+                .map(str::trim)
+                .find_map(|param| param.strip_prefix(key)?.strip_prefix('='))
+                .map(|value| value.trim_matches('"').to_string())
+        };
+        parts.push(FormPart {
+            name: param("name")?,
+            file_name: param("filename"),
+            data: &part[header_end + 4..],
+        });
+    }
+    Some(parts)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn status(status: StatusCode) -> ServerResponse {
+    let mut response = Response::new(full(
+        status.canonical_reason().unwrap_or_default().to_string(),
+    ));
+    *response.status_mut() = status;
+    response
+}
+
+fn json_response(status: StatusCode, value: serde_json::Value) -> ServerResponse {
+    let mut response = Response::new(full(value.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn errors(status: StatusCode, error: &str) -> ServerResponse {
+    json_response(status, json!({ "errors": [error] }))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+    use reqwest::multipart::{Form, Part};
+
+    use crate::manifest::ManifestMetadata;
+
+    use super::*;
+
+    fn rocks_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/test");
+        for file in ["lua-cjson-2.1.0-1.rockspec", "luatest-0.2-1.src.rock"] {
+            std::fs::copy(resources.join(file), dir.join(file)).unwrap();
+        }
+        dir
+    }
+
+    async fn start_server(dir: &Path, upload_api_key: Option<&str>) -> Url {
+        let server = Serve::new(dir.to_path_buf())
+            .addr(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .maybe_upload_api_key(upload_api_key.map(ApiKey::from))
+            .bind()
+            .await
+            .unwrap();
+        let url = server.url();
+        tokio::spawn(server.run());
+        url
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(parse_range("bytes=0-3", 10), Some(Ok((0, 3))));
+        assert_eq!(parse_range("bytes=4-", 10), Some(Ok((4, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Some(Ok((7, 9))));
+        assert_eq!(parse_range("bytes=5-100", 10), Some(Ok((5, 9))));
+        assert_eq!(parse_range("bytes=10-", 10), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-1,3-4", 10), None);
+        assert_eq!(parse_range("lines=0-1", 10), None);
+    }
+
+    #[tokio::test]
+    async fn serve_generated_manifests() {
+        let dir = rocks_dir();
+        let url = start_server(dir.path(), None).await;
+        let client = reqwest::Client::new();
+
+        let manifest = client
+            .get(url.join("manifest-5.1").unwrap())
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let metadata = ManifestMetadata::new(&manifest).unwrap();
+        assert!(metadata.has_rock(&"lua-cjson".into()));
+        assert!(metadata.has_rock(&"luatest".into()));
+
+        let zipped = client
+            .get(url.join("manifest-5.1.zip").unwrap())
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let mut archive = zip::ZipArchive::new(io::Cursor::new(zipped)).unwrap();
+        let mut unzipped = String::new();
+        io::Read::read_to_string(&mut archive.by_name("manifest-5.1").unwrap(), &mut unzipped)
+            .unwrap();
+        assert_eq!(unzipped, manifest);
+
+        let index = client.get(url.clone()).send().await.unwrap();
+        assert_eq!(index.status(), StatusCode::OK);
+        assert!(index
+            .text()
+            .await
+            .unwrap()
+            .contains("luatest-0.2-1.src.rock"));
+
+        let search_index = client
+            .get(url.join("search-index.json").unwrap())
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(search_index.contains("A fast JSON encoding/parsing module"));
+
+        std::fs::remove_file(dir.path().join("luatest-0.2-1.src.rock")).unwrap();
+        let manifest = client
+            .get(url.join("manifest").unwrap())
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let metadata = ManifestMetadata::new(&manifest).unwrap();
+        assert!(!metadata.has_rock(&"luatest".into()));
+
+        let not_found = client
+            .get(url.join("../secret").unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(not_found.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn serve_conditional_and_range_requests() {
+        let dir = rocks_dir();
+        let url = start_server(dir.path(), None).await;
+        let client = reqwest::Client::new();
+        let rockspec_url = url.join("lua-cjson-2.1.0-1.rockspec").unwrap();
+        let rockspec = std::fs::read(dir.path().join("lua-cjson-2.1.0-1.rockspec")).unwrap();
+
+        let response = client.get(rockspec_url.clone()).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+        let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+        assert_eq!(response.bytes().await.unwrap(), rockspec);
+
+        let response = client
+            .get(rockspec_url.clone())
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = client
+            .get(rockspec_url.clone())
+            .header(header::IF_MODIFIED_SINCE, last_modified)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = client
+            .get(rockspec_url.clone())
+            .header(header::RANGE, "bytes=2-5")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()[header::CONTENT_RANGE],
+            format!("bytes 2-5/{}", rockspec.len())
+        );
+        assert_eq!(response.bytes().await.unwrap(), rockspec[2..=5]);
+
+        let response = client
+            .get(rockspec_url.clone())
+            .header(header::RANGE, format!("bytes={}-", rockspec.len()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        std::fs::write(
+            dir.path().join("lua-cjson-2.1.0-1.rockspec"),
+            [rockspec.as_slice(), b"-- changed"].concat(),
+        )
+        .unwrap();
+        let response = client
+            .get(rockspec_url)
+            .header(header::IF_NONE_MATCH, etag)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.bytes().await.unwrap().ends_with(b"-- changed"));
+    }
+
+    #[tokio::test]
+    async fn serve_uploads() {
+        let dir = TempDir::new().unwrap();
+        let url = start_server(dir.path(), Some("test-key")).await;
+        let client = reqwest::Client::new();
+        let api_url = |endpoint: &str| url.join(&format!("api/1/test-key/{endpoint}")).unwrap();
+
+        let response = client
+            .get(url.join("api/1/wrong-key/status").unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .get(url.join("api/1/test-key-suffix/status").unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = client
+            .get(api_url("verify_tfa"))
+            .query(&[("code", "123456")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.json::<serde_json::Value>().await.unwrap()["errors"].is_array());
+
+        let check = |client: reqwest::Client| async move {
+            client
+                .get(api_url("check_rockspec"))
+                .query(&[("package", "luatest"), ("version", "0.2-1")])
+                .send()
+                .await
+                .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap()
+        };
+        assert_eq!(check(client.clone()).await, json!({}));
+
+        let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/test");
+        let rock = std::fs::read(resources.join("luatest-0.2-1.src.rock")).unwrap();
+        let mut rockspec = String::new();
+        let mut archive = zip::ZipArchive::new(io::Cursor::new(rock.clone())).unwrap();
+        io::Read::read_to_string(
+            &mut archive.by_name("luatest-0.2-1.rockspec").unwrap(),
+            &mut rockspec,
+        )
+        .unwrap();
+        let form = Form::new().part(
+            "rockspec_file",
+            Part::text(rockspec.clone()).file_name("luatest-0.2-1.rockspec"),
+        );
+        let response: serde_json::Value = client
+            .post(api_url("upload"))
+            .multipart(form)
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let id = response["version"]["id"].as_u64().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("luatest-0.2-1.rockspec")).unwrap(),
+            rockspec
+        );
+        assert_eq!(check(client.clone()).await["version"]["id"], id);
+
+        let upload_rock = |file_name: &'static str, bytes: Vec<u8>| {
+            let form = Form::new().part("rock_file", Part::bytes(bytes).file_name(file_name));
+            client
+                .post(api_url(&format!("upload_rock/{id}")))
+                .multipart(form)
+                .send()
+        };
+        let response = upload_rock("other-1.0.0-1.src.rock", rock.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = upload_rock("luatest-0.2-1.all.rock", b"not a rock".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!dir.path().join("luatest-0.2-1.all.rock").exists());
+        upload_rock("luatest-0.2-1.src.rock", rock)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+
+        let manifest = client
+            .get(url.join("manifest").unwrap())
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(manifest.contains(r#"arch = "src""#));
+    }
+}
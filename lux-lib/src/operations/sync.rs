@@ -2,7 +2,7 @@ use std::io;
 
 use super::{Install, InstallError, PackageInstallSpec, RemoveError, Uninstall};
 use crate::{
-    build::BuildBehaviour,
+    build::{transpile, BuildBehaviour},
     config::Config,
     fs,
     lockfile::{
@@ -13,7 +13,7 @@ use crate::{
     operations::{self, GenLuaRcError},
     package::{PackageName, PackageReq},
     project::{project_toml::LocalProjectTomlValidationError, ProjectError},
-    rockspec::Rockspec,
+    rockspec::{lua_dependency::LuaDependencySpec, Rockspec},
     tree::{self, InstallTree, TreeError},
     workspace::{Workspace, WorkspaceError, WorkspaceTreeError},
 };
@@ -39,6 +39,8 @@ pub struct Sync<'a> {
     /// When `true`, skip filesystem existence checks and rely on the install tree's lockfile
     /// alone.
     fast: Option<bool>,
+    /// Optional features to enable in the workspace members that declare them.
+    features: Option<Vec<String>>,
 }
 
 impl<State> SyncBuilder<'_, State>
@@ -183,6 +185,7 @@ async fn do_sync(
     let mut workspace_lockfile = args.workspace.lockfile()?.write_guard();
     let dest_lockfile = tree.lockfile()?;
 
+    let features = args.features.unwrap_or_default();
     let mut packages = Vec::new();
     for project in args.workspace.members() {
         match lock_type {
@@ -190,18 +193,23 @@ async fn do_sync(
                 project
                     .toml()
                     .into_local()?
+                    .with_declared_features(&features)
                     .dependencies()
                     .current_platform()
                     .clone(),
             ),
-            LocalPackageLockType::Build => packages.extend(
-                project
-                    .toml()
-                    .into_local()?
-                    .build_dependencies()
-                    .current_platform()
-                    .clone(),
-            ),
+            LocalPackageLockType::Build => {
+                let project_toml = project.toml().into_local()?;
+                let build_dependencies = project_toml.build_dependencies().current_platform();
+                // The compilers for modules that transpile to Lua, unless declared
+                let compilers = transpile::compiler_packages(&project_toml, Some(project.root()))
+                    .into_iter()
+                    .filter(|compiler| !build_dependencies.iter().any(|dep| dep.name() == compiler))
+                    .map(|compiler| LuaDependencySpec::from(PackageReq::from(compiler)))
+                    .collect_vec();
+                packages.extend(build_dependencies.clone());
+                packages.extend(compilers);
+            }
             LocalPackageLockType::Test => packages.extend(
                 project
                     .toml()
@@ -212,11 +220,29 @@ async fn do_sync(
             ),
         }
     }
+    // Overridden entrypoints are locked with the override's constraint
+    let overrides = args.workspace.overrides()?;
     let packages = packages
         .into_iter()
         .chain(args.extra_packages.into_iter().map_into())
+        .map(|dep: LuaDependencySpec| match overrides.get(dep.name()) {
+            Some(override_dep) => LuaDependencySpec {
+                package_req: override_dep.package_req().clone(),
+                source: override_dep.source().clone().or(dep.source),
+                ..dep
+            },
+            None => dep,
+        })
         .collect_vec();
 
+    // Re-resolve entrypoints whose dependencies no longer satisfy the `[overrides]`
+    for pkg in workspace_lockfile
+        .local_pkg_lock(lock_type)
+        .entrypoints_violating_overrides(&overrides)
+    {
+        workspace_lockfile.remove(&pkg, lock_type);
+    }
+
     let strategy = if args.fast.unwrap_or(false) {
         SyncStrategy::LockfileOnly
     } else {
@@ -259,6 +285,7 @@ async fn do_sync(
                 .pin(pkg.pinned())
                 .opt(pkg.opt())
                 .constraint(pkg.constraint())
+                .features(pkg.features().to_vec())
                 .build()
         })
         .unique()
@@ -271,6 +298,7 @@ async fn do_sync(
 
     Install::new(args.config)
         .package_db(package_db)
+        .overrides(overrides.clone())
         .packages(packages_to_install)
         .tree(tree.clone())
         .install()
@@ -315,12 +343,14 @@ async fn do_sync(
                     .pin(*dep.pin())
                     .opt(*dep.opt())
                     .maybe_source(dep.source.clone())
+                    .features(dep.features().to_vec())
                     .build()
             })
             .unique()
             .collect();
 
         let added = Install::new(args.config)
+            .overrides(overrides)
             .packages(missing_packages)
             .tree(tree.clone())
             .install()
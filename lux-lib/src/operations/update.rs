@@ -3,11 +3,13 @@ use std::io;
 use crate::{
     config::Config,
     lockfile::{
-        LocalPackage, LocalPackageLockType, Lockfile, PinnedState, ReadOnly, ReadWrite,
-        WorkspaceLockfile,
+        LocalPackage, LocalPackageLockType, LockConstraint, Lockfile, PinnedState, ReadOnly,
+        ReadWrite, WorkspaceLockfile,
     },
     lua_version::{LuaVersion, LuaVersionUnset},
-    package::{PackageReq, RockConstraintUnsatisfied},
+    package::{
+        PackageReq, PackageVersion, PackageVersionReq, RockConstraintUnsatisfied, UpdateKind,
+    },
     remote_package_db::{RemotePackageDB, RemotePackageDBError},
     remote_package_source::RemotePackageSource,
     tree::{self, InstallTree, Tree, TreeError},
@@ -82,6 +84,14 @@ pub struct Update<'a> {
     validate_integrity: Option<bool>,
 
     package_db: Option<RemotePackageDB>,
+
+    /// The largest kind of update to apply,
+    /// e.g. [`UpdateKind::Minor`] to only apply SemVer compatible updates.
+    /// By default, packages are updated to the latest version that satisfies
+    /// the constraint they were installed with.
+    /// In the user tree, [`UpdateKind::Major`] also updates packages beyond that constraint.
+    /// In a workspace, the constraints in `lux.toml` are always respected.
+    update_kind: Option<UpdateKind>,
 }
 
 impl<State: update_builder::State> UpdateBuilder<'_, State> {
@@ -157,6 +167,7 @@ async fn update_workspace(
         package_db.clone(),
         args.config,
         &args.packages,
+        args.update_kind,
     )
     .await?
     .into_iter()
@@ -175,6 +186,7 @@ async fn update_workspace(
         package_db.clone(),
         args.config,
         &args.test_dependencies,
+        args.update_kind,
     )
     .await?
     .into_iter()
@@ -194,6 +206,7 @@ async fn update_workspace(
         package_db.clone(),
         args.config,
         &args.build_dependencies,
+        args.update_kind,
     )
     .await?
     .into_iter()
@@ -214,6 +227,7 @@ async fn update_dependency_tree(
     package_db: RemotePackageDB,
     config: &Config,
     packages: &Option<Vec<PackageReq>>,
+    update_kind: Option<UpdateKind>,
 ) -> Result<Vec<LocalPackage>, UpdateError> {
     let lockfile = tree.lockfile()?;
     let dependencies = updatable_packages(&lockfile)
@@ -221,17 +235,19 @@ async fn update_dependency_tree(
         .filter(|pkg| is_included(pkg, packages))
         .collect_vec();
     let updated_lockfile = tree.lockfile()?;
-    let updated_dependencies = update(dependencies, package_db, tree, &lockfile, config).await?;
+    let policy = UpdatePolicy {
+        max_kind: update_kind.unwrap_or(UpdateKind::Major),
+        respect_constraints: true,
+    };
+    let updated_dependencies =
+        update(dependencies, package_db, tree, &lockfile, config, policy).await?;
     if !updated_dependencies.is_empty() {
         project_lockfile.sync(updated_lockfile.local_pkg_lock(), &lock_type);
     }
     Ok(updated_dependencies)
 }
 
-fn is_included(
-    (pkg, _): &(LocalPackage, PackageReq),
-    package_reqs: &Option<Vec<PackageReq>>,
-) -> bool {
+fn is_included(pkg: &LocalPackage, package_reqs: &Option<Vec<PackageReq>>) -> bool {
     package_reqs.is_none()
         || package_reqs.as_ref().is_some_and(|packages| {
             packages
@@ -252,29 +268,49 @@ async fn update_install_tree(
         .into_iter()
         .filter(|pkg| is_included(pkg, &args.packages))
         .collect_vec();
-    update(packages, package_db, tree, &lockfile, args.config).await
+    let policy = UpdatePolicy {
+        max_kind: args.update_kind.unwrap_or(UpdateKind::Major),
+        respect_constraints: args.update_kind != Some(UpdateKind::Major),
+    };
+    update(packages, package_db, tree, &lockfile, args.config, policy).await
+}
+
+#[derive(Clone, Copy)]
+struct UpdatePolicy {
+    max_kind: UpdateKind,
+    respect_constraints: bool,
+}
+
+impl UpdatePolicy {
+    fn latest_update(
+        &self,
+        package: &LocalPackage,
+        package_db: &RemotePackageDB,
+    ) -> Option<PackageVersion> {
+        let constraint = match package.constraint() {
+            LockConstraint::Constrained(req) if self.respect_constraints => req,
+            _ => PackageVersionReq::any(),
+        };
+        package
+            .to_package()
+            .latest_update_with(&constraint, self.max_kind, package_db)
+    }
 }
 
 async fn update(
-    packages: Vec<(LocalPackage, PackageReq)>,
+    packages: Vec<LocalPackage>,
     package_db: RemotePackageDB,
     tree: Tree,
     lockfile: &Lockfile<ReadOnly>,
     config: &Config,
+    policy: UpdatePolicy,
 ) -> Result<Vec<LocalPackage>, UpdateError> {
     let updatable = packages
-        .clone()
         .into_iter()
-        .filter_map(|(package, constraint)| {
-            match package
-                .to_package()
-                .has_update_with(&constraint, &package_db)
-            {
-                Ok(Some(_)) if package.pinned() == PinnedState::Unpinned => {
-                    Some((package, constraint))
-                }
-                _ => None,
-            }
+        .filter(|package| package.pinned() == PinnedState::Unpinned)
+        .filter_map(|package| {
+            let version = policy.latest_update(&package, &package_db)?;
+            Some((package, version))
         })
         .collect_vec();
     if updatable.is_empty() {
@@ -300,7 +336,7 @@ async fn update(
     }
 }
 
-fn updatable_packages(lockfile: &Lockfile<ReadOnly>) -> Vec<(LocalPackage, PackageReq)> {
+fn updatable_packages(lockfile: &Lockfile<ReadOnly>) -> Vec<LocalPackage> {
     lockfile
         .rocks()
         .values()
@@ -318,12 +354,12 @@ fn updatable_packages(lockfile: &Lockfile<ReadOnly>) -> Vec<(LocalPackage, Packa
                     RemotePackageSource::Test => false,
                 }
         })
-        .map(|package| (package.clone(), package.to_package().into_package_req()))
+        .cloned()
         .collect_vec()
 }
 
 fn mk_install_spec(
-    (package, req): &(LocalPackage, PackageReq),
+    (package, version): &(LocalPackage, PackageVersion),
     lockfile: &Lockfile<ReadOnly>,
 ) -> PackageInstallSpec {
     let entry_type = if lockfile.is_entrypoint(&package.id()) {
@@ -331,8 +367,19 @@ fn mk_install_spec(
     } else {
         tree::EntryType::DependencyOnly
     };
-    PackageInstallSpec::new(req.clone(), entry_type)
+    let req = PackageReq {
+        name: package.name().clone(),
+        version_req: version.into_version_req(),
+    };
+    // Keep the original constraint, unless the update goes beyond it
+    let constraint = match package.constraint() {
+        LockConstraint::Constrained(req) if !req.matches(version) => LockConstraint::Unconstrained,
+        constraint => constraint,
+    };
+    PackageInstallSpec::new(req, entry_type)
+        .constraint(constraint)
         .pin(PinnedState::Unpinned)
         .opt(package.opt())
+        .features(package.features().to_vec())
         .build()
 }
@@ -305,7 +305,14 @@ async fn vendor_rockspec_sources(
             source_url: _,
         }) => {
             let cursor = Cursor::new(bytes);
-            operations::unpack_src_rock(cursor, package_vendor_dir).await?;
+            operations::unpack(
+                Some("application/zip"),
+                cursor,
+                true,
+                "src.rock".into(),
+                &package_vendor_dir,
+            )
+            .await?;
         }
         RemotePackageSourceSpec::RockSpec(source_url) => {
             operations::FetchSrc::new(&package_vendor_dir, &rockspec, config)
@@ -0,0 +1,368 @@
+//! Run commands across the members of a workspace.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+};
+
+use bon::Builder;
+use futures::StreamExt;
+use itertools::Itertools;
+use miette::Diagnostic;
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+};
+
+use crate::{
+    config::Config,
+    lua_installation::{LuaBinary, LuaBinaryError},
+    lua_rockspec::LuaVersionError,
+    package::PackageName,
+    path::{Paths, PathsError},
+    project::{project_toml::LocalProjectTomlValidationError, Project},
+    tree::{InstallTree, TreeError},
+    workspace::{Workspace, WorkspaceTreeError},
+};
+
+use super::{
+    run::{script_commands, RunError},
+    BuildWorkspace, BuildWorkspaceError, RunLuaError,
+};
+
+/// The command to run in each workspace member.
+#[derive(Debug, Clone)]
+pub enum MemberCommand {
+    /// Execute an arbitrary command.
+    Exec { command: String, args: Vec<String> },
+    /// Run each member's `[run]` command, or the named script from
+    /// each member's `[scripts]` table, with extra arguments.
+    Run {
+        script: Option<String>,
+        args: Vec<String>,
+    },
+}
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum WorkspaceExecError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    BuildWorkspace(#[from] BuildWorkspaceError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceTree(#[from] WorkspaceTreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Tree(#[from] TreeError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Paths(#[from] PathsError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    LuaVersion(#[from] LuaVersionError),
+    #[error("command failed in {} workspace member(s):\n{}", .0.len(), .0.iter().map(|err| err.to_string()).join("\n"))]
+    #[diagnostic(help("check the output of the failed members for errors."))]
+    MembersFailed(Vec<MemberExecError>),
+}
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum MemberExecError {
+    #[error("{package}: failed to run '{cmd}': {source}")]
+    RunCommandFailed {
+        package: PackageName,
+        cmd: String,
+        source: io::Error,
+    },
+    #[error(
+        "{package}: '{cmd}' exited with non-zero exit code: {}",
+        exit_code.map(|code| code.to_string()).unwrap_or("unknown".into())
+    )]
+    NonZeroExitCode {
+        package: PackageName,
+        cmd: String,
+        exit_code: Option<i32>,
+    },
+    #[error("{0}: no `run` field found in `lux.toml`")]
+    NoRunField(PackageName),
+    #[error("{0}: {1}")]
+    Script(PackageName, Box<RunError>),
+    #[error("{0}: {1}")]
+    Toml(PackageName, Box<LocalProjectTomlValidationError>),
+    #[error("{0}: {1}")]
+    LuaVersion(PackageName, LuaVersionError),
+    #[error("{0}: {1}")]
+    LuaBinary(PackageName, LuaBinaryError),
+}
+
+/// Run a command in each of the selected workspace members,
+/// with the workspace's `PATH`, `LUA_PATH`, `LUA_CPATH` and `LUA_INIT` set up.
+#[derive(Builder)]
+#[builder(start_fn = new, finish_fn(name = _build, vis = ""))]
+pub struct WorkspaceExec<'a> {
+    #[builder(start_fn)]
+    workspace: &'a Workspace,
+    #[builder(start_fn)]
+    config: &'a Config,
+    #[builder(start_fn)]
+    command: MemberCommand,
+
+    /// The members to run the command in.
+    /// Defaults to all workspace members.
+    members: Option<Vec<&'a Project>>,
+
+    /// The maximum number of members to run the command in concurrently.
+    /// If greater than 1, each line of output is prefixed with the member's package name.
+    /// Defaults to 1.
+    parallel: Option<usize>,
+
+    /// Stop after the first member in which the command fails.
+    fail_fast: Option<bool>,
+
+    /// Do not add `require('lux').loader()` to `LUA_INIT`.
+    disable_loader: Option<bool>,
+}
+
+impl<State> WorkspaceExecBuilder<'_, State>
+where
+    State: workspace_exec_builder::State + workspace_exec_builder::IsComplete,
+{
+    pub async fn exec(self) -> Result<(), WorkspaceExecError> {
+        do_exec(self._build()).await
+    }
+}
+
+/// The commands that are ready to be spawned for a workspace member, in order.
+struct MemberProcess {
+    package: PackageName,
+    cwd: PathBuf,
+    steps: Vec<MemberStep>,
+}
+
+struct MemberStep {
+    cmd: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+}
+
+async fn do_exec(args: WorkspaceExec<'_>) -> Result<(), WorkspaceExecError> {
+    let workspace = args.workspace;
+    let config = args.config;
+    let members = args
+        .members
+        .unwrap_or_else(|| workspace.members().iter().collect_vec());
+    if members.is_empty() {
+        tracing::info!("no workspace members selected");
+        return Ok(());
+    }
+
+    BuildWorkspace::new(workspace, config)
+        .no_lock(false)
+        .only_deps(false)
+        .members(members.clone())
+        .build()
+        .await?;
+
+    let lua_version = workspace.lua_version(config)?;
+    let user_tree = config.user_tree(lua_version)?;
+    let tree = workspace.tree(config)?;
+    let mut paths = Paths::new(&user_tree)?;
+    paths.prepend(&Paths::new(&tree)?);
+
+    let lua_init = if args.disable_loader.unwrap_or(false) {
+        None
+    } else if tree.version().lux_lib_dir().is_none() {
+        tracing::warn!(
+            r#"lux-lua library not found.
+Cannot use the `lux.loader`.
+To suppress this warning, set the `--no-loader` option."#
+        );
+        None
+    } else {
+        Some(paths.init())
+    };
+
+    let parallel = args.parallel.unwrap_or(1).max(1);
+    let fail_fast = args.fail_fast.unwrap_or(false);
+    let prefix_output = parallel > 1;
+
+    let mut errors = Vec::new();
+    let mut processes = Vec::new();
+    for project in members {
+        match member_process(project, &args.command, config) {
+            Ok(process) => processes.push(process),
+            Err(err) if fail_fast => return Err(WorkspaceExecError::MembersFailed(vec![err])),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    let mut results = futures::stream::iter(processes.into_iter().map(|process| {
+        let paths = &paths;
+        let lua_init = &lua_init;
+        async move {
+            for step in process.steps {
+                let mut cmd = Command::new(&step.cmd);
+                cmd.current_dir(&process.cwd)
+                    .args(&step.args)
+                    .env("PATH", paths.path_prepended().joined())
+                    .env("LUA_INIT", lua_init.clone().unwrap_or_default())
+                    .env("LUA_PATH", paths.package_path().joined())
+                    .env("LUA_CPATH", paths.package_cpath().joined())
+                    .envs(&step.env)
+                    .kill_on_drop(true);
+                let status = if prefix_output {
+                    run_prefixed(cmd, &process.package).await
+                } else {
+                    cmd.status().await
+                };
+                match status {
+                    Ok(status) if status.success() => {}
+                    Ok(status) => {
+                        return Err(MemberExecError::NonZeroExitCode {
+                            package: process.package,
+                            cmd: step.cmd,
+                            exit_code: status.code(),
+                        })
+                    }
+                    Err(source) => {
+                        return Err(MemberExecError::RunCommandFailed {
+                            package: process.package,
+                            cmd: step.cmd,
+                            source,
+                        })
+                    }
+                }
+            }
+            Ok(())
+        }
+    }))
+    .buffer_unordered(parallel);
+
+    while let Some(result) = results.next().await {
+        if let Err(err) = result {
+            errors.push(err);
+            if fail_fast {
+                break;
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(WorkspaceExecError::MembersFailed(errors))
+    }
+}
+
+fn member_process(
+    project: &Project,
+    command: &MemberCommand,
+    config: &Config,
+) -> Result<MemberProcess, MemberExecError> {
+    let package = project.toml().package().clone();
+    let cwd = project.root().to_path_buf();
+    match command {
+        MemberCommand::Exec { command, args } => Ok(MemberProcess {
+            package,
+            cwd,
+            steps: vec![MemberStep {
+                cmd: command.clone(),
+                args: args.clone(),
+                env: BTreeMap::new(),
+            }],
+        }),
+        MemberCommand::Run {
+            script: Some(script),
+            args: extra_args,
+        } => {
+            let toml = project
+                .toml()
+                .into_local()
+                .map_err(|err| MemberExecError::Toml(package.clone(), Box::new(err)))?;
+            let steps = script_commands(toml.scripts(), script, extra_args, || {
+                Ok(LuaBinary::new(project.lua_version(config)?, config)
+                    .try_into()
+                    .map_err(RunLuaError::from)?)
+            })
+            .map_err(|err| MemberExecError::Script(package.clone(), Box::new(err)))?
+            .into_iter()
+            .map(|step| MemberStep {
+                cmd: step.program.to_string_lossy().to_string(),
+                args: step.args,
+                env: step.script.env().clone(),
+            })
+            .collect_vec();
+            Ok(MemberProcess {
+                package,
+                cwd,
+                steps,
+            })
+        }
+        MemberCommand::Run {
+            script: None,
+            args: extra_args,
+        } => {
+            let toml = project
+                .toml()
+                .into_local()
+                .map_err(|err| MemberExecError::Toml(package.clone(), Box::new(err)))?;
+            let run_spec = toml
+                .run()
+                .ok_or_else(|| MemberExecError::NoRunField(package.clone()))?
+                .current_platform()
+                .clone();
+            let mut args: Vec<String> = run_spec.args.map(|args| args.into()).unwrap_or_default();
+            args.extend(extra_args.iter().cloned());
+            let cmd = match run_spec.command {
+                Some(command) => command.to_string(),
+                None => {
+                    let lua_version = project
+                        .lua_version(config)
+                        .map_err(|err| MemberExecError::LuaVersion(package.clone(), err))?;
+                    let lua_cmd: PathBuf = LuaBinary::new(lua_version, config)
+                        .try_into()
+                        .map_err(|err| MemberExecError::LuaBinary(package.clone(), err))?;
+                    lua_cmd.to_string_lossy().to_string()
+                }
+            };
+            Ok(MemberProcess {
+                package,
+                cwd,
+                steps: vec![MemberStep {
+                    cmd,
+                    args,
+                    env: BTreeMap::new(),
+                }],
+            })
+        }
+    }
+}
+
+/// Run a command, prefixing each line of its stdout and stderr with the package name.
+async fn run_prefixed(mut cmd: Command, package: &PackageName) -> io::Result<ExitStatus> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let prefix = format!("[{package}]");
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let (status, _, _) = tokio::join!(
+        child.wait(),
+        forward_prefixed(stdout, &prefix, io::stdout),
+        forward_prefixed(stderr, &prefix, io::stderr),
+    );
+    status
+}
+
+async fn forward_prefixed<R, W>(reader: Option<R>, prefix: &str, writer: fn() -> W)
+where
+    R: AsyncRead + Unpin,
+    W: Write,
+{
+    if let Some(reader) = reader {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let _ = writeln!(writer(), "{prefix} {line}");
+        }
+    }
+}
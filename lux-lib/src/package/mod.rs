@@ -10,7 +10,7 @@ mod version;
 pub use outdated::*;
 pub use version::{
     PackageVersion, PackageVersionParseError, PackageVersionReq, PackageVersionReqError, SpecRev,
-    VersionReqToVersionError,
+    UpdateKind, VersionReqToVersionError,
 };
 
 pub(crate) use version::SpecRevIterator;
@@ -123,8 +123,9 @@ impl RemotePackage {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
-pub(crate) enum RemotePackageType {
+/// The type of a package artefact on a remote server.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+pub enum RemotePackageType {
     Rockspec,
     Src,
     Binary,
@@ -152,6 +153,16 @@ impl PartialOrd for RemotePackageType {
     }
 }
 
+impl Display for RemotePackageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rockspec => f.write_str("rockspec"),
+            Self::Src => f.write_str("src"),
+            Self::Binary => f.write_str("binary"),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RemotePackageTypeFilterSpec {
     /// Include Rockspec
@@ -4,7 +4,9 @@ use crate::remote_package_db::RemotePackageDB;
 use miette::Diagnostic;
 use thiserror::Error;
 
-use super::{version::PackageVersion, PackageName, PackageReq, PackageSpec, PackageVersionReq};
+use super::{
+    version::PackageVersion, PackageName, PackageReq, PackageSpec, PackageVersionReq, UpdateKind,
+};
 
 #[derive(Error, Debug, Diagnostic)]
 #[error("rock {0} not found")]
@@ -56,6 +58,35 @@ impl PackageSpec {
             Ok(None)
         }
     }
+
+    /// Tries to find the latest version of a rock that is newer than this one,
+    /// whose update is at most of the given kind.
+    pub fn latest_update(
+        &self,
+        max_kind: UpdateKind,
+        package_db: &RemotePackageDB,
+    ) -> Option<PackageVersion> {
+        self.latest_update_with(&PackageVersionReq::any(), max_kind, package_db)
+    }
+
+    /// Tries to find the latest version of a rock that is newer than this one,
+    /// satisfies a constraint, and whose update is at most of the given kind.
+    pub fn latest_update_with(
+        &self,
+        constraint: &PackageVersionReq,
+        max_kind: UpdateKind,
+        package_db: &RemotePackageDB,
+    ) -> Option<PackageVersion> {
+        package_db
+            .versions(&self.name)
+            .into_iter()
+            .filter(|version| constraint.matches(version))
+            .find(|version| {
+                self.version
+                    .update_kind(version)
+                    .is_some_and(|kind| kind <= max_kind)
+            })
+    }
 }
 
 impl Display for PackageSpec {
@@ -71,8 +102,10 @@ mod tests {
     use url::Url;
 
     use crate::{
+        lockfile::LocalPackageLock,
         manifest::{Manifest, ManifestMetadata},
-        package::PackageSpec,
+        package::{PackageSpec, UpdateKind},
+        remote_package_db::RemotePackageDB,
     };
 
     #[test]
@@ -96,4 +129,91 @@ mod tests {
             Some("2.1.0-1".parse().unwrap())
         );
     }
+
+    #[test]
+    fn rock_latest_update_of_kind() {
+        let manifest = r#"
+repository = {
+   foo = {
+      ["1.0.0-1"] = { { arch = "rockspec" } },
+      ["1.0.1-1"] = { { arch = "rockspec" } },
+      ["1.2.0-1"] = { { arch = "rockspec" } },
+      ["2.0.0-1"] = { { arch = "rockspec" } },
+   },
+}
+"#;
+        let package_db = Manifest::new(
+            Url::parse("https://example.com").unwrap(),
+            ManifestMetadata::new(manifest).unwrap(),
+            Default::default(),
+        )
+        .into();
+        let test_package = PackageSpec::parse("foo".to_string(), "1.0.0-1".to_string()).unwrap();
+        assert_eq!(
+            test_package.latest_update(UpdateKind::Patch, &package_db),
+            Some("1.0.1-1".parse().unwrap())
+        );
+        assert_eq!(
+            test_package.latest_update(UpdateKind::Minor, &package_db),
+            Some("1.2.0-1".parse().unwrap())
+        );
+        assert_eq!(
+            test_package.latest_update(UpdateKind::Major, &package_db),
+            Some("2.0.0-1".parse().unwrap())
+        );
+        assert_eq!(
+            test_package.latest_update_with(
+                &"< 1.2".parse().unwrap(),
+                UpdateKind::Major,
+                &package_db
+            ),
+            Some("1.0.1-1".parse().unwrap())
+        );
+        let latest = PackageSpec::parse("foo".to_string(), "2.0.0-1".to_string()).unwrap();
+        assert_eq!(latest.latest_update(UpdateKind::Major, &package_db), None);
+    }
+
+    #[test]
+    fn rock_latest_update_from_lockfile() {
+        let rock = |version: &str| {
+            serde_json::json!({
+                "name": "foo",
+                "version": version,
+                "pinned": false,
+                "opt": false,
+                "dependencies": [],
+                "constraint": null,
+                "binaries": [],
+                "source": "luarocks_rockspec+https://luarocks.org/",
+                "source_url": null,
+                "hashes": {
+                    "rockspec": "sha256-WFKt1iWeyjO9A8SG0KUX8tkS9JvMqoVM8CKBUguuK0Y=",
+                    "source": "sha256-IjNkK1leVtYgbEjUqguVMjbdW+0BHAOCE0pazrVuF50="
+                }
+            })
+        };
+        let lock: LocalPackageLock = serde_json::from_value(serde_json::json!({
+            "rocks": {
+                "0e7601c45f13611fa5b85cb3ba46a554ad6fb6c4546776b310c9ebfc5581e663": rock("1.0.1-1"),
+                "3762e98c2b422658125cbb99ab9323b07bd53ee3f278bc5bb94440f43d4a4536": rock("1.2.0-1"),
+                "4e9592a499c9ced4f8ce366db9db7d9c0dd1424ea8d4c8c16c1550ea3a61a696": rock("2.0.0-1"),
+            },
+            "entrypoints": [],
+        }))
+        .unwrap();
+        let package_db: RemotePackageDB = lock.into();
+        let test_package = PackageSpec::parse("foo".to_string(), "1.0.0-1".to_string()).unwrap();
+        assert_eq!(
+            test_package.latest_update(UpdateKind::Patch, &package_db),
+            Some("1.0.1-1".parse().unwrap())
+        );
+        assert_eq!(
+            test_package.latest_update(UpdateKind::Minor, &package_db),
+            Some("1.2.0-1".parse().unwrap())
+        );
+        assert_eq!(
+            test_package.latest_update(UpdateKind::Major, &package_db),
+            Some("2.0.0-1".parse().unwrap())
+        );
+    }
 }
@@ -71,6 +71,33 @@ impl PackageVersion {
         }
     }
 
+    /// Classifies an update from this version to `newer`.
+    /// Returns `None` if `newer` is not newer than this version.
+    ///
+    /// Following SemVer conventions, bumping the minor version of a `0.x` version
+    /// is considered a major update.
+    /// Updates between non-SemVer versions are always considered major updates.
+    pub fn update_kind(&self, newer: &PackageVersion) -> Option<UpdateKind> {
+        if newer <= self {
+            return None;
+        }
+        match (self, newer) {
+            (PackageVersion::SemVer(current), PackageVersion::SemVer(newer)) => {
+                let (current, newer) = (&current.version, &newer.version);
+                if current.major != newer.major
+                    || (current.major == 0 && current.minor != newer.minor)
+                {
+                    Some(UpdateKind::Major)
+                } else if current.minor != newer.minor {
+                    Some(UpdateKind::Minor)
+                } else {
+                    Some(UpdateKind::Patch)
+                }
+            }
+            _ => Some(UpdateKind::Major),
+        }
+    }
+
     pub(crate) fn is_semver(&self) -> bool {
         matches!(self, PackageVersion::SemVer(_))
     }
@@ -84,6 +111,28 @@ impl PackageVersion {
     }
 }
 
+/// The kind of an update from one [`PackageVersion`] to a newer one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateKind {
+    /// A patch or revision update, e.g. `1.2.3-1` to `1.2.4-1`.
+    Patch,
+    /// A backwards compatible feature update, e.g. `1.2.3-1` to `1.3.0-1`.
+    Minor,
+    /// A potentially breaking update, e.g. `1.2.3-1` to `2.0.0-1`.
+    Major,
+}
+
+impl Display for UpdateKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateKind::Patch => "patch".fmt(f),
+            UpdateKind::Minor => "minor".fmt(f),
+            UpdateKind::Major => "major".fmt(f),
+        }
+    }
+}
+
 impl TryFrom<PackageVersionReq> for PackageVersion {
     type Error = VersionReqToVersionError;
 
@@ -913,4 +962,23 @@ mod tests {
             "==a144124839f027a2d0a95791936c478d047126fc"
         );
     }
+
+    #[test]
+    fn classify_updates() {
+        let update_kind = |current: &str, newer: &str| {
+            PackageVersion::parse(current)
+                .unwrap()
+                .update_kind(&PackageVersion::parse(newer).unwrap())
+        };
+        assert_eq!(update_kind("1.2.3-1", "1.2.3-1"), None);
+        assert_eq!(update_kind("1.2.3-1", "1.2.2-1"), None);
+        assert_eq!(update_kind("1.2.3-1", "1.2.3-2"), Some(UpdateKind::Patch));
+        assert_eq!(update_kind("1.2.3-1", "1.2.4-1"), Some(UpdateKind::Patch));
+        assert_eq!(update_kind("1.2-1", "1.3-1"), Some(UpdateKind::Minor));
+        assert_eq!(update_kind("1.2.3-1", "2.0.0-1"), Some(UpdateKind::Major));
+        assert_eq!(update_kind("0.1.0-1", "0.1.1-1"), Some(UpdateKind::Patch));
+        assert_eq!(update_kind("0.1.0-1", "0.2.0-1"), Some(UpdateKind::Major));
+        assert_eq!(update_kind("1.2.3-1", "scm-1"), Some(UpdateKind::Major));
+        assert!(UpdateKind::Patch < UpdateKind::Minor && UpdateKind::Minor < UpdateKind::Major);
+    }
 }
@@ -0,0 +1,173 @@
+//! Helpers for `lux.toml` fields that can be inherited from a workspace's
+//! `[workspace.package]` table, e.g. `license.workspace = true`.
+
+use serde::{de, Deserialize, Deserializer};
+use url::Url;
+
+use crate::{lua_rockspec::RockDescription, workspace::workspace_toml::WorkspacePackage};
+
+use super::project_toml::LocalProjectTomlValidationError;
+
+/// A `lux.toml` field that is either set explicitly,
+/// or inherited from the workspace with `field.workspace = true`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Inheritable<T> {
+    Value(T),
+    Workspace,
+}
+
+impl<T: Default> Default for Inheritable<T> {
+    fn default() -> Self {
+        Self::Value(T::default())
+    }
+}
+
+impl<T: Clone> Inheritable<T> {
+    /// Resolve this field, looking up the `inherited` value if it is inherited from the workspace.
+    /// Returns `None` if the workspace does not provide a value.
+    pub(crate) fn resolve<'a>(&'a self, inherited: Option<&'a T>) -> Option<&'a T> {
+        match self {
+            Self::Value(value) => Some(value),
+            Self::Workspace => inherited,
+        }
+    }
+
+    /// Like [`Self::resolve`], but fails if the workspace does not provide a value.
+    pub(crate) fn try_resolve(
+        &self,
+        field: &str,
+        inherited: Option<&T>,
+    ) -> Result<T, LocalProjectTomlValidationError> {
+        self.resolve(inherited).cloned().ok_or_else(|| {
+            LocalProjectTomlValidationError::MissingWorkspaceField(field.to_string())
+        })
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Inheritable<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct WorkspaceRef {
+            workspace: bool,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum InheritableHelper<T> {
+            Workspace(WorkspaceRef),
+            Value(T),
+        }
+
+        match InheritableHelper::deserialize(deserializer)? {
+            InheritableHelper::Workspace(WorkspaceRef { workspace: true }) => Ok(Self::Workspace),
+            InheritableHelper::Workspace(WorkspaceRef { workspace: false }) => Err(
+                de::Error::custom("`workspace = false` is not supported. Omit the field instead."),
+            ),
+            InheritableHelper::Value(value) => Ok(Self::Value(value)),
+        }
+    }
+}
+
+/// The `[description]` table of a `lux.toml`,
+/// whose shared metadata may be inherited from the workspace.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub(crate) struct ProjectDescription {
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub detailed: Option<String>,
+    #[serde(default)]
+    pub license: Option<Inheritable<String>>,
+    #[serde(default)]
+    pub homepage: Option<Inheritable<Url>>,
+    #[serde(default)]
+    pub issues_url: Option<Inheritable<Url>>,
+    #[serde(default)]
+    pub maintainer: Option<Inheritable<String>>,
+    #[serde(default)]
+    pub labels: Option<Inheritable<Vec<String>>>,
+}
+
+impl ProjectDescription {
+    pub(crate) fn try_resolve(
+        &self,
+        workspace_package: Option<&WorkspacePackage>,
+    ) -> Result<RockDescription, LocalProjectTomlValidationError> {
+        Ok(RockDescription {
+            summary: self.summary.clone(),
+            detailed: self.detailed.clone(),
+            license: self
+                .license
+                .as_ref()
+                .map(|license| {
+                    license.try_resolve(
+                        "description.license",
+                        workspace_package.and_then(|ws| ws.license.as_ref()),
+                    )
+                })
+                .transpose()?,
+            homepage: self
+                .homepage
+                .as_ref()
+                .map(|homepage| {
+                    homepage.try_resolve(
+                        "description.homepage",
+                        workspace_package.and_then(|ws| ws.homepage.as_ref()),
+                    )
+                })
+                .transpose()?,
+            issues_url: self
+                .issues_url
+                .as_ref()
+                .map(|issues_url| {
+                    issues_url.try_resolve(
+                        "description.issues_url",
+                        workspace_package.and_then(|ws| ws.issues_url.as_ref()),
+                    )
+                })
+                .transpose()?,
+            maintainer: self
+                .maintainer
+                .as_ref()
+                .map(|maintainer| {
+                    maintainer.try_resolve(
+                        "description.maintainer",
+                        workspace_package.and_then(|ws| ws.maintainer.as_ref()),
+                    )
+                })
+                .transpose()?,
+            labels: self
+                .labels
+                .as_ref()
+                .map(|labels| {
+                    labels.try_resolve(
+                        "description.labels",
+                        workspace_package.and_then(|ws| ws.labels.as_ref()),
+                    )
+                })
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl From<RockDescription> for ProjectDescription {
+    fn from(description: RockDescription) -> Self {
+        Self {
+            summary: description.summary,
+            detailed: description.detailed,
+            license: description.license.map(Inheritable::Value),
+            homepage: description.homepage.map(Inheritable::Value),
+            issues_url: description.issues_url.map(Inheritable::Value),
+            maintainer: description.maintainer.map(Inheritable::Value),
+            labels: Some(Inheritable::Value(description.labels)),
+        }
+    }
+}
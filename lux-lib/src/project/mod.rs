@@ -28,12 +28,13 @@ use crate::{
         PartialRockspecError, RemoteLuaRockspec,
     },
     lua_version::LuaVersion,
-    package::SpecRev,
+    package::{SpecRev, UpdateKind},
     remote_package_db::RemotePackageDB,
     rockspec::{
         lua_dependency::{DependencyType, LuaDependencySpec, LuaDependencyType},
         LuaVersionCompatibility,
     },
+    workspace::{self, workspace_toml::WorkspacePackage},
 };
 use crate::{
     lockfile::PinnedState,
@@ -41,6 +42,7 @@ use crate::{
 };
 
 pub(crate) mod gen;
+pub(crate) mod inherit;
 pub mod project_toml;
 
 pub use project_toml::PROJECT_TOML;
@@ -223,9 +225,26 @@ pub struct Project {
 }
 
 impl Project {
-    /// Load a project at the specified path, if it exists
+    /// Load a project at the specified path, if it exists.
+    /// If the project is a member of an enclosing workspace,
+    /// it inherits the workspace's `[workspace.package]` metadata.
     #[tracing::instrument(level = "trace", skip_all)]
     pub fn from_exact(start: impl AsRef<Path>) -> Result<Option<Self>, ProjectError> {
+        match Self::from_exact_without_workspace(&start)? {
+            Some(mut project) => {
+                let workspace_package = workspace::enclosing_workspace_package(project.root())?;
+                project.set_workspace_package(workspace_package);
+                Ok(Some(project))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Load a project at the specified path, if it exists,
+    /// without looking up the metadata it inherits from its workspace.
+    pub(crate) fn from_exact_without_workspace(
+        start: impl AsRef<Path>,
+    ) -> Result<Option<Self>, ProjectError> {
         if !start.as_ref().exists() {
             return Ok(None);
         }
@@ -352,11 +371,7 @@ impl Project {
 
         let toml_content = project_toml.to_string();
         fs::tokio::write(self.toml_path(), &toml_content).await?;
-        self.toml = PartialProjectToml::new(
-            self.toml_path().to_str().unwrap_or("<lux.toml>"),
-            &toml_content,
-            self.root.clone(),
-        )?;
+        self.reload_toml(&toml_content)?;
 
         Ok(())
     }
@@ -404,11 +419,7 @@ impl Project {
 
         let toml_content = project_toml.to_string();
         fs::tokio::write(self.toml_path(), &toml_content).await?;
-        self.toml = PartialProjectToml::new(
-            self.toml_path().to_str().unwrap_or("<lux.toml>"),
-            &toml_content,
-            self.root.clone(),
-        )?;
+        self.reload_toml(&toml_content)?;
 
         Ok(())
     }
@@ -450,18 +461,18 @@ impl Project {
 
         let toml_content = project_toml.to_string();
         fs::tokio::write(self.toml_path(), &toml_content).await?;
-        self.toml = PartialProjectToml::new(
-            self.toml_path().to_str().unwrap_or("<lux.toml>"),
-            &toml_content,
-            self.root.clone(),
-        )?;
+        self.reload_toml(&toml_content)?;
 
         Ok(())
     }
 
+    /// Upgrades the version constraints of dependencies in `lux.toml`,
+    /// to the latest version that is at most an update of the given kind.
+    /// The update kind is relative to the latest version that satisfies the current constraint.
     pub async fn upgrade(
         &mut self,
         dependencies: LuaDependencyType<&PackageName>,
+        update_kind: UpdateKind,
         package_db: &RemotePackageDB,
         config: &Config,
     ) -> Result<(), ProjectEditError> {
@@ -469,10 +480,18 @@ impl Project {
             toml_edit::DocumentMut::from_str(&fs::tokio::read_to_string(self.toml_path()).await?)?;
 
         prepare_dependency_tables(&mut project_toml);
-        let table = match dependencies {
-            LuaDependencyType::Regular(_) => &mut project_toml["dependencies"],
-            LuaDependencyType::Build(_) => &mut project_toml["build_dependencies"],
-            LuaDependencyType::Test(_) => &mut project_toml["test_dependencies"],
+        let (table, current_dependencies) = match dependencies {
+            LuaDependencyType::Regular(_) => {
+                (&mut project_toml["dependencies"], &self.toml().dependencies)
+            }
+            LuaDependencyType::Build(_) => (
+                &mut project_toml["build_dependencies"],
+                &self.toml().build_dependencies,
+            ),
+            LuaDependencyType::Test(_) => (
+                &mut project_toml["test_dependencies"],
+                &self.toml().test_dependencies,
+            ),
         };
 
         match dependencies {
@@ -481,10 +500,22 @@ impl Project {
             | LuaDependencyType::Test(deps) => {
                 let latest_rock_version_str =
                     |dep: &PackageName| -> Result<String, ProjectEditError> {
-                        Ok(package_db
-                            .latest_version(dep)
-                            .ok_or(ProjectEditError::LatestVersionNotFound(dep.clone()))?
-                            .to_string())
+                        let current_version = current_dependencies
+                            .iter()
+                            .flatten()
+                            .find(|current| current.name() == dep)
+                            .and_then(|current| {
+                                package_db.latest_match(current.package_req(), None)
+                            });
+                        let version = match current_version {
+                            Some(current) if update_kind < UpdateKind::Major => current
+                                .latest_update(update_kind, package_db)
+                                .unwrap_or_else(|| current.version().clone()),
+                            _ => package_db
+                                .latest_version(dep)
+                                .ok_or(ProjectEditError::LatestVersionNotFound(dep.clone()))?,
+                        };
+                        Ok(version.to_string())
                     };
                 for dep in deps {
                     let mut dep_item = table[dep.to_string()].clone();
@@ -542,17 +573,14 @@ impl Project {
 
         let toml_content = project_toml.to_string();
         fs::tokio::write(self.toml_path(), &toml_content).await?;
-        self.toml = PartialProjectToml::new(
-            self.toml_path().to_str().unwrap_or("<lux.toml>"),
-            &toml_content,
-            self.root.clone(),
-        )?;
+        self.reload_toml(&toml_content)?;
 
         Ok(())
     }
 
     pub async fn upgrade_all(
         &mut self,
+        update_kind: UpdateKind,
         package_db: &RemotePackageDB,
         config: &Config,
     ) -> Result<(), ProjectEditError> {
@@ -564,6 +592,7 @@ impl Project {
                 .collect_vec();
             self.upgrade(
                 LuaDependencyType::Regular(packages.iter().collect()),
+                update_kind,
                 package_db,
                 config,
             )
@@ -577,6 +606,7 @@ impl Project {
                 .collect_vec();
             self.upgrade(
                 LuaDependencyType::Build(packages.iter().collect()),
+                update_kind,
                 package_db,
                 config,
             )
@@ -590,6 +620,7 @@ impl Project {
                 .collect_vec();
             self.upgrade(
                 LuaDependencyType::Test(packages.iter().collect()),
+                update_kind,
                 package_db,
                 config,
             )
@@ -683,11 +714,61 @@ impl Project {
 
         let toml_content = project_toml.to_string();
         fs::tokio::write(self.toml_path(), &toml_content).await?;
-        self.toml = PartialProjectToml::new(
-            self.toml_path().to_str().unwrap_or("<lux.toml>"),
-            &toml_content,
-            self.root.clone(),
-        )?;
+        self.reload_toml(&toml_content)?;
+
+        Ok(())
+    }
+
+    /// Enable optional features of the given dependencies,
+    /// e.g. `foo = { version = "1", features = ["tls"] }`.
+    /// Features that are already enabled are kept.
+    pub async fn add_dependency_features(
+        &mut self,
+        dependencies: LuaDependencyType<&PackageName>,
+        features: &[String],
+    ) -> Result<(), ProjectEditError> {
+        if features.is_empty() {
+            return Ok(());
+        }
+        let mut project_toml =
+            toml_edit::DocumentMut::from_str(&fs::tokio::read_to_string(self.toml_path()).await?)?;
+
+        prepare_dependency_tables(&mut project_toml);
+        let table = match dependencies {
+            LuaDependencyType::Regular(_) => &mut project_toml["dependencies"],
+            LuaDependencyType::Build(_) => &mut project_toml["build_dependencies"],
+            LuaDependencyType::Test(_) => &mut project_toml["test_dependencies"],
+        };
+
+        match dependencies {
+            LuaDependencyType::Regular(ref deps)
+            | LuaDependencyType::Build(ref deps)
+            | LuaDependencyType::Test(ref deps) => {
+                for dep in deps {
+                    let dep_item = &mut table[dep.to_string()];
+                    if let Item::Value(toml_edit::Value::String(version)) = dep_item {
+                        let mut dep_entry = toml_edit::InlineTable::new();
+                        dep_entry.insert("version", version.value().as_str().into());
+                        *dep_item = toml_edit::value(dep_entry);
+                    }
+                    let enabled_features = dep_item
+                        .get("features")
+                        .and_then(|item| item.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|value| value.as_str())
+                        .map(|feature| feature.to_string())
+                        .chain(features.iter().cloned())
+                        .unique()
+                        .collect::<toml_edit::Array>();
+                    dep_item["features"] = toml_edit::value(enabled_features);
+                }
+            }
+        }
+
+        let toml_content = project_toml.to_string();
+        fs::tokio::write(self.toml_path(), &toml_content).await?;
+        self.reload_toml(&toml_content)?;
 
         Ok(())
     }
@@ -695,6 +776,23 @@ impl Project {
     pub fn project_files(&self) -> Vec<PathBuf> {
         project_files(&self.root().0)
     }
+
+    /// Set the metadata this project can inherit from its workspace's `[workspace.package]`.
+    pub(crate) fn set_workspace_package(&mut self, workspace_package: Option<WorkspacePackage>) {
+        self.toml.workspace_package = workspace_package;
+    }
+
+    /// Re-parse the `lux.toml` after an edit, keeping the inherited workspace metadata.
+    fn reload_toml(&mut self, toml_content: &str) -> Result<(), TomlDeError> {
+        let workspace_package = self.toml.workspace_package.take();
+        self.toml = PartialProjectToml::new(
+            self.toml_path().to_str().unwrap_or("<lux.toml>"),
+            toml_content,
+            self.root.clone(),
+        )?;
+        self.toml.workspace_package = workspace_package;
+        Ok(())
+    }
 }
 
 /// Get the files that Lux treats as project files
@@ -936,6 +1034,44 @@ mod tests {
         check(&reloaded_project);
     }
 
+    #[tokio::test]
+    async fn test_add_dependency_features() {
+        let sample_project = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/test/sample-projects/dependencies/");
+        let project_root = assert_fs::TempDir::new().unwrap();
+        project_root.copy_from(&sample_project, &["**"]).unwrap();
+        let project_root: PathBuf = project_root.path().into();
+        let mut project = Project::from_exact(&project_root).unwrap().unwrap();
+        let lua_cjson = "lua-cjson".into();
+        project
+            .add_dependency_features(
+                LuaDependencyType::Regular(vec![&lua_cjson]),
+                &["fast".into()],
+            )
+            .await
+            .unwrap();
+        project
+            .add_dependency_features(
+                LuaDependencyType::Regular(vec![&lua_cjson]),
+                &["fast".into(), "safe".into()],
+            )
+            .await
+            .unwrap();
+        let check = |project: &Project| {
+            let dependencies = project.toml().dependencies.clone().unwrap_or_default();
+            let dep = dependencies
+                .iter()
+                .find(|dep| dep.name() == &lua_cjson)
+                .unwrap();
+            assert_eq!(dep.features(), &["fast".to_string(), "safe".to_string()]);
+            assert!(!dep.version_req().is_any());
+        };
+        check(&project);
+        // check again after reloading lux.toml
+        let reloaded_project = Project::from_exact(&project_root).unwrap().unwrap();
+        check(&reloaded_project);
+    }
+
     #[tokio::test]
     async fn project_files_includes_cargo_directory() {
         let project_root = assert_fs::TempDir::new().unwrap();
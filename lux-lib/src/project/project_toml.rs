@@ -18,9 +18,13 @@ use crate::package::PackageNameList;
 use crate::package::SpecRev;
 use crate::project::TomlDeError;
 use crate::rockspec::lua_dependency::LuaDependencySpec;
+use crate::workspace::workspace_toml::WorkspacePackage;
 use crate::ROCKSPEC_FUEL_LIMIT;
 use std::io;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
 
 use crate::{
     config::Config,
@@ -46,6 +50,8 @@ use thiserror::Error;
 
 use super::gen::GenerateSourceError;
 use super::gen::RockSourceTemplate;
+use super::inherit::Inheritable;
+use super::inherit::ProjectDescription;
 use super::r#gen::GenerateVersionError;
 use super::r#gen::PackageVersionTemplate;
 use super::ProjectRoot;
@@ -73,9 +79,11 @@ struct DependencyTableEntry {
     path: Option<PathBuf>,
     #[serde(default)]
     rev: Option<String>,
+    #[serde(default)]
+    features: Vec<String>,
 }
 
-fn parse_map_to_dependency_vec_opt<'de, D>(
+pub(crate) fn parse_map_to_dependency_vec_opt<'de, D>(
     deserializer: D,
 ) -> Result<Option<Vec<LuaDependencySpec>>, D::Error>
 where
@@ -129,6 +137,7 @@ where
                             opt: OptState::from(entry.opt.unwrap_or(false)),
                             pin: PinnedState::from(entry.pin.unwrap_or(false)),
                             source,
+                            features: entry.features,
                         })
                     }
                 })
@@ -214,6 +223,18 @@ pub enum LocalProjectTomlValidationError {
     #[error("error generating rockspec version")]
     #[diagnostic(forward(0))]
     GenerateVersion(#[from] GenerateVersionError),
+    #[error("'{0}' is inherited from the workspace, but the workspace does not set it")]
+    #[diagnostic(help(
+        r#"add the field to the `[workspace.package]` table of your workspace's lux.toml,
+or set it in the project's lux.toml instead of `{0}.workspace = true`."#
+    ))]
+    MissingWorkspaceField(String),
+    #[error("script '{script}' references unknown script '{reference}'")]
+    #[diagnostic(help("declare '{reference}' in the `[scripts]` table of your lux.toml."))]
+    UnknownScript { script: String, reference: String },
+    #[error("unknown feature '{0}'")]
+    #[diagnostic(help("declare the feature in the `[features]` table of your lux.toml."))]
+    UnknownFeature(String),
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -243,9 +264,11 @@ pub struct PartialProjectToml {
     #[serde(default)]
     pub(crate) run: Option<RunSpec>,
     #[serde(default)]
-    pub(crate) lua: Option<PackageVersionReq>,
+    pub(crate) scripts: Option<BTreeMap<String, ScriptSpec>>,
+    #[serde(default)]
+    pub(crate) lua: Option<Inheritable<PackageVersionReq>>,
     #[serde(default)]
-    pub(crate) description: Option<RockDescription>,
+    pub(crate) description: Option<ProjectDescription>,
     #[serde(default)]
     pub(crate) supported_platforms: Option<HashMap<PlatformIdentifier, bool>>,
     #[serde(default, deserialize_with = "parse_map_to_dependency_vec_opt")]
@@ -257,15 +280,24 @@ pub struct PartialProjectToml {
     #[serde(default, deserialize_with = "parse_map_to_dependency_vec_opt")]
     pub(crate) test_dependencies: Option<Vec<LuaDependencySpec>>,
     #[serde(default, rename = "source")]
-    pub(crate) source_template: RockSourceTemplate,
+    pub(crate) source_template: Inheritable<RockSourceTemplate>,
     #[serde(default)]
     pub(crate) test: Option<TestSpecInternal>,
     #[serde(default)]
     pub(crate) deploy: Option<DeploySpec>,
+    #[serde(default)]
+    pub(crate) features: Option<HashMap<String, FeatureSpec>>,
+    /// Forced replacements for (transitive) dependencies
+    #[serde(default, deserialize_with = "parse_map_to_dependency_vec_opt")]
+    pub(crate) overrides: Option<Vec<LuaDependencySpec>>,
 
     /// Used to bind the project TOML to a project root
     #[serde(skip, default = "ProjectRoot::new")]
     pub(crate) project_root: ProjectRoot,
+
+    /// Metadata that can be inherited from the workspace's `[workspace.package]`.
+    #[serde(skip)]
+    pub(crate) workspace_package: Option<WorkspacePackage>,
 }
 
 impl HasIntegrity for PartialProjectToml {
@@ -290,7 +322,23 @@ impl PartialProjectToml {
 
     /// This project's Lua version requirement, if any
     pub fn lua(&self) -> Option<&PackageVersionReq> {
-        self.lua.as_ref()
+        self.lua.as_ref().and_then(|lua| {
+            lua.resolve(
+                self.workspace_package
+                    .as_ref()
+                    .and_then(|ws| ws.lua.as_ref()),
+            )
+        })
+    }
+
+    /// The rockspec source template, which may be inherited from the workspace.
+    fn source_template(&self) -> Result<RockSourceTemplate, LocalProjectTomlValidationError> {
+        self.source_template.try_resolve(
+            "source",
+            self.workspace_package
+                .as_ref()
+                .and_then(|ws| ws.source_template.as_ref()),
+        )
     }
 
     /// Convert the `PartialProjectToml` struct into a `LocalProjectToml` struct, making
@@ -337,6 +385,20 @@ impl PartialProjectToml {
             ));
         }
 
+        for (script, spec) in project_toml.scripts.iter().flatten() {
+            if let Some(reference) = spec.pre.iter().chain(spec.post.iter()).find(|reference| {
+                !project_toml
+                    .scripts
+                    .as_ref()
+                    .is_some_and(|scripts| scripts.contains_key(*reference))
+            }) {
+                return Err(LocalProjectTomlValidationError::UnknownScript {
+                    script: script.clone(),
+                    reference: reference.clone(),
+                });
+            }
+        }
+
         let validated = LocalProjectToml {
             internal: project_toml.clone(),
 
@@ -345,11 +407,24 @@ impl PartialProjectToml {
                 .version_template
                 .try_generate(&self.project_root, None)
                 .unwrap_or(PackageVersion::default_dev_version()),
-            lua: project_toml
-                .lua
-                .ok_or(LocalProjectTomlValidationError::NoLuaVersion)?,
-            description: project_toml.description.unwrap_or_default(),
+            lua: match &project_toml.lua {
+                Some(lua) => lua.try_resolve(
+                    "lua",
+                    self.workspace_package
+                        .as_ref()
+                        .and_then(|ws| ws.lua.as_ref()),
+                )?,
+                None => return Err(LocalProjectTomlValidationError::NoLuaVersion),
+            },
+            description: project_toml
+                .description
+                .as_ref()
+                .map(|description| description.try_resolve(self.workspace_package.as_ref()))
+                .transpose()?
+                .unwrap_or_default(),
+            source_template: self.source_template()?,
             run: project_toml.run.map(PerPlatform::new),
+            scripts: project_toml.scripts.clone().unwrap_or_default(),
             supported_platforms: PlatformSupport::parse(
                 &project_toml
                     .supported_platforms
@@ -399,6 +474,26 @@ impl PartialProjectToml {
             build: PerPlatform::new(BuildSpec::from_internal_spec(project_toml.build.clone())?),
             deploy: PerPlatform::new(project_toml.deploy.clone().unwrap_or_default()),
             rockspec_format: project_toml.rockspec_format.clone(),
+            features: project_toml
+                .features
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, feature)| {
+                    (
+                        name,
+                        FeatureSpec {
+                            dependencies: feature.dependencies.map(|deps| {
+                                deps.into_iter()
+                                    .map(|dep| self.resolve_lua_dependency_spec(dep))
+                                    .collect()
+                            }),
+                            ..feature
+                        },
+                    )
+                })
+                .collect(),
+            enabled_features: Vec::new(),
 
             source: PerPlatform::new(RemoteRockSource {
                 local: LocalRockSource::default(),
@@ -444,7 +539,7 @@ impl PartialProjectToml {
             .version_template
             .try_generate(&self.project_root, specrev)?;
         let source =
-            self.source_template
+            self.source_template()?
                 .try_generate(&self.project_root, &self.package, &version)?;
         let source = PerPlatform::new(RemoteRockSource::try_from(source).map_err(|err| {
             RemoteProjectTomlValidationError::LocalProjectTomlValidationError(
@@ -465,6 +560,29 @@ impl PartialProjectToml {
         &self.package
     }
 
+    /// Whether the `[features]` table declares the given feature.
+    pub(crate) fn declares_feature(&self, feature: &str) -> bool {
+        self.features
+            .as_ref()
+            .is_some_and(|features| features.contains_key(feature))
+    }
+
+    /// The names of all regular, build, test and feature dependencies.
+    pub(crate) fn all_dependency_names(&self) -> impl Iterator<Item = &PackageName> {
+        self.dependencies
+            .iter()
+            .chain(self.build_dependencies.iter())
+            .chain(self.test_dependencies.iter())
+            .chain(
+                self.features
+                    .iter()
+                    .flat_map(|features| features.values())
+                    .filter_map(|feature| feature.dependencies.as_ref()),
+            )
+            .flatten()
+            .map(|dep| dep.name())
+    }
+
     /// Returns the current package version, which may be generated from a template
     pub fn version(&self) -> Result<PackageVersion, GenerateVersionError> {
         self.version_template.try_generate(&self.project_root, None)
@@ -486,14 +604,19 @@ impl PartialProjectToml {
                             if dep.version_req().is_any() {
                                 None
                             } else {
-                                Some(dep.version_req().clone())
+                                Some(Inheritable::Value(dep.version_req().clone()))
                             }
                         })
                 })
                 .or(self.lua),
             build: other.build.unwrap_or(self.build),
             run: self.run,
-            description: other.description.or(self.description),
+            scripts: self.scripts,
+            overrides: self.overrides,
+            description: other
+                .description
+                .map(ProjectDescription::from)
+                .or(self.description),
             supported_platforms: other
                 .supported_platforms
                 .map(|platform_support| platform_support.platforms().clone())
@@ -513,12 +636,26 @@ impl PartialProjectToml {
             test: other.test.or(self.test),
             deploy: other.deploy.or(self.deploy),
             rockspec_format: other.rockspec_format.or(self.rockspec_format),
+            features: self.features,
 
-            // Keep the project root the same, as it is not part of the lua rockspec
+            // Keep the project root and workspace metadata the same,
+            // as they are not part of the lua rockspec
             project_root: self.project_root,
+            workspace_package: self.workspace_package,
         }
     }
 
+    /// The packages to force during dependency resolution, declared in the `[overrides]` table,
+    /// with local paths resolved relative to the project root.
+    pub(crate) fn overrides(&self) -> Vec<LuaDependencySpec> {
+        self.overrides
+            .iter()
+            .flatten()
+            .cloned()
+            .map(|dep| self.resolve_lua_dependency_spec(dep))
+            .collect()
+    }
+
     fn resolve_lua_dependency_spec(&self, dep: LuaDependencySpec) -> LuaDependencySpec {
         match &dep.source {
             Some(RockSourceSpec::File(path)) if path.is_dir() => dep,
@@ -570,8 +707,7 @@ impl LuaVersionCompatibility for PartialProjectToml {
     }
 
     fn supports_lua_version(&self, lua_version: &LuaVersion) -> bool {
-        self.lua
-            .as_ref()
+        self.lua()
             .is_none_or(|lua| lua.matches(&lua_version.as_version()))
     }
 
@@ -584,11 +720,7 @@ impl LuaVersionCompatibility for PartialProjectToml {
             ("5.1.0", LuaVersion::Lua51),
         ] {
             let possibility = unsafe { possibility.parse().unwrap_unchecked() };
-            if self
-                .lua
-                .as_ref()
-                .is_none_or(|lua| lua.matches(&possibility))
-            {
+            if self.lua().is_none_or(|lua| lua.matches(&possibility)) {
                 return Some(version);
             }
         }
@@ -596,6 +728,28 @@ impl LuaVersionCompatibility for PartialProjectToml {
     }
 }
 
+/// An optional group of dependencies and build variables,
+/// declared in the `[features]` table and enabled on demand.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FeatureSpec {
+    /// Extra dependencies to install when the feature is enabled
+    #[serde(default, deserialize_with = "parse_map_to_dependency_vec_opt")]
+    pub(crate) dependencies: Option<Vec<LuaDependencySpec>>,
+    /// Extra variables to substitute in the build when the feature is enabled
+    #[serde(default)]
+    pub(crate) variables: HashMap<String, String>,
+}
+
+impl FeatureSpec {
+    pub fn dependencies(&self) -> &[LuaDependencySpec] {
+        self.dependencies.as_deref().unwrap_or_default()
+    }
+
+    pub fn variables(&self) -> &HashMap<String, String> {
+        &self.variables
+    }
+}
+
 // TODO(vhyrro): Move this struct into a different directory.
 #[derive(Debug, Clone, Deserialize)]
 pub struct RunSpec {
@@ -605,6 +759,97 @@ pub struct RunSpec {
     pub(crate) args: Option<NonEmpty<String>>,
 }
 
+/// A named script in the `[scripts]` table, which can be run with `lx run <name>`.
+/// Either a command line, e.g. `serve = "lua src/server.lua --port 8080"`,
+/// or a table with a `command` and optional `env`, `pre` and `post` fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptSpec {
+    /// The command line to execute, split into words like a shell would.
+    /// If the program is `lua`, the project's Lua interpreter is used.
+    pub(crate) command: Option<String>,
+    /// A short description, shown by `lx run --list`
+    pub(crate) description: Option<String>,
+    /// Environment variables to set when running the command
+    pub(crate) env: BTreeMap<String, String>,
+    /// Scripts to run before the command
+    pub(crate) pre: Vec<String>,
+    /// Scripts to run after the command
+    pub(crate) post: Vec<String>,
+}
+
+impl ScriptSpec {
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    pub fn pre(&self) -> &[String] {
+        &self.pre
+    }
+
+    pub fn post(&self) -> &[String] {
+        &self.post
+    }
+}
+
+impl<'de> Deserialize<'de> for ScriptSpec {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct ScriptTable {
+            #[serde(default)]
+            command: Option<String>,
+            #[serde(default)]
+            description: Option<String>,
+            #[serde(default)]
+            env: BTreeMap<String, String>,
+            #[serde(default)]
+            pre: Vec<String>,
+            #[serde(default)]
+            post: Vec<String>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum ScriptEntry {
+            Command(String),
+            Table(ScriptTable),
+        }
+
+        match ScriptEntry::deserialize(deserializer)? {
+            ScriptEntry::Command(command) => Ok(Self {
+                command: Some(command),
+                ..Self::default()
+            }),
+            ScriptEntry::Table(ScriptTable {
+                command: None,
+                pre,
+                post,
+                ..
+            }) if pre.is_empty() && post.is_empty() => Err(de::Error::custom(
+                "a script must have a `command`, or `pre` or `post` scripts to run",
+            )),
+            ScriptEntry::Table(table) => Ok(Self {
+                command: table.command,
+                description: table.description,
+                env: table.env,
+                pre: table.pre,
+                post: table.post,
+            }),
+        }
+    }
+}
+
 /// The `lux.toml` file, after being properly deserialized.
 /// This struct may be used to build a local version of a project.
 /// To build a rockspec, use `RemoteProjectToml`.
@@ -615,6 +860,7 @@ pub struct LocalProjectToml {
     lua: PackageVersionReq,
     rockspec_format: Option<RockspecFormat>,
     run: Option<PerPlatform<RunSpec>>,
+    scripts: BTreeMap<String, ScriptSpec>,
     description: RockDescription,
     supported_platforms: PlatformSupport,
     dependencies: PerPlatform<Vec<LuaDependencySpec>>,
@@ -624,6 +870,9 @@ pub struct LocalProjectToml {
     test: PerPlatform<TestSpec>,
     build: PerPlatform<BuildSpec>,
     deploy: PerPlatform<DeploySpec>,
+    source_template: RockSourceTemplate,
+    features: HashMap<String, FeatureSpec>,
+    enabled_features: Vec<String>,
 
     // Used for simpler serialization
     internal: PartialProjectToml,
@@ -637,6 +886,71 @@ impl LocalProjectToml {
         self.run.as_ref()
     }
 
+    /// The named scripts declared in the `[scripts]` table.
+    pub fn scripts(&self) -> &BTreeMap<String, ScriptSpec> {
+        &self.scripts
+    }
+
+    /// The optional features declared in the `[features]` table.
+    pub fn features(&self) -> &HashMap<String, FeatureSpec> {
+        &self.features
+    }
+
+    /// The features enabled with [`Self::with_features`].
+    pub fn enabled_features(&self) -> &[String] {
+        &self.enabled_features
+    }
+
+    /// Enable the given features, adding their dependencies to this project's dependencies.
+    /// Fails if a feature is not declared in the `[features]` table.
+    pub fn with_features(
+        self,
+        features: &[String],
+    ) -> Result<Self, LocalProjectTomlValidationError> {
+        if let Some(unknown) = features
+            .iter()
+            .find(|name| !self.features.contains_key(*name))
+        {
+            return Err(LocalProjectTomlValidationError::UnknownFeature(
+                unknown.clone(),
+            ));
+        }
+        Ok(self.with_declared_features(features))
+    }
+
+    /// Like [`Self::with_features`], but ignores features this project does not declare.
+    /// Used for workspaces, in which features apply to the members that declare them.
+    pub(crate) fn with_declared_features(mut self, features: &[String]) -> Self {
+        for name in features.iter().sorted().dedup() {
+            let feature_dependencies = match self.features.get(name) {
+                Some(feature) => feature.dependencies().to_vec(),
+                None => continue,
+            };
+            for dependencies in std::iter::once(&mut self.dependencies.default)
+                .chain(self.dependencies.per_platform.values_mut())
+            {
+                // Feature dependencies replace regular dependencies with the same name
+                dependencies.retain(|dep| {
+                    !feature_dependencies
+                        .iter()
+                        .any(|feature_dep| feature_dep.name() == dep.name())
+                });
+                dependencies.extend(feature_dependencies.iter().cloned());
+            }
+            self.enabled_features.push(name.clone());
+        }
+        self
+    }
+
+    /// The build variables of the enabled features.
+    pub(crate) fn feature_variables(&self) -> HashMap<String, String> {
+        self.enabled_features
+            .iter()
+            .filter_map(|name| self.features.get(name))
+            .flat_map(|feature| feature.variables.clone())
+            .collect()
+    }
+
     /// Convert this project TOML to a Lua rockspec.
     /// Fails if there is no valid project root or if there are off-spec dependencies.
     pub fn to_lua_rockspec(&self) -> Result<LocalLuaRockspec, LuaRockspecError> {
@@ -822,10 +1136,9 @@ version = "{}""#,
             _ => {}
         }
 
-        let source =
-            self.internal
-                .source_template
-                .try_generate(project_root, &self.package, &version)?;
+        let source = self
+            .source_template
+            .try_generate(project_root, &self.package, &version)?;
         template.push(source.display_lua());
 
         template.push(self.internal.build.display_lua());
@@ -1037,7 +1350,7 @@ version = "{}""#,
             _ => {}
         }
 
-        let source = self.local.internal.source_template.try_generate(
+        let source = self.local.source_template.try_generate(
             project_root,
             &self.local.internal.package,
             self.version(),
@@ -1545,6 +1858,167 @@ mod tests {
             .unwrap_err();
     }
 
+    #[test]
+    fn project_toml_with_overrides() {
+        let project_toml = r#"
+        package = "app"
+        version = "1.0.0"
+        lua = "5.1"
+
+        [dependencies]
+        neorg = "8.8.1"
+
+        [overrides]
+        nvim-nio = "1.10.1"
+        pathlib-nvim = { version = "2.2.3", path = "vendor/pathlib.nvim" }
+        "#;
+
+        let root = ProjectRoot::default();
+        let project = PartialProjectToml::new(PROJECT_TOML, project_toml, root.clone()).unwrap();
+        let overrides = project.overrides();
+        assert_eq!(overrides.len(), 2);
+        let pathlib = overrides
+            .iter()
+            .find(|dep| dep.name().to_string() == "pathlib-nvim")
+            .unwrap();
+        assert_eq!(
+            pathlib.source(),
+            &Some(RockSourceSpec::File(root.join("vendor/pathlib.nvim")))
+        );
+    }
+
+    #[test]
+    fn project_toml_with_scripts() {
+        let project_toml = r#"
+        package = "server"
+        version = "1.0.0"
+        lua = "5.1"
+
+        [scripts]
+        serve = "lua src/server.lua --port 8080"
+        migrate = { command = "lua scripts/migrate.lua", env = { DB = "dev.sqlite" }, pre = ["serve"] }
+        all = { pre = ["migrate"], description = "Migrate the database" }
+
+        [build]
+        type = "builtin"
+        "#;
+
+        let project =
+            PartialProjectToml::new(PROJECT_TOML, project_toml, ProjectRoot::default()).unwrap();
+        let local = project.into_local().unwrap();
+        let scripts = local.scripts();
+        assert_eq!(scripts.len(), 3);
+        assert_eq!(
+            scripts["serve"].command(),
+            Some("lua src/server.lua --port 8080")
+        );
+        let migrate = &scripts["migrate"];
+        assert_eq!(migrate.env().get("DB").unwrap(), "dev.sqlite");
+        assert_eq!(migrate.pre(), &["serve".to_string()]);
+        assert_eq!(scripts["all"].command(), None);
+        assert_eq!(scripts["all"].description(), Some("Migrate the database"));
+
+        let project_toml = r#"
+        package = "server"
+        version = "1.0.0"
+        lua = "5.1"
+
+        [scripts]
+        serve = { command = "lua src/server.lua", pre = ["missing"] }
+
+        [build]
+        type = "builtin"
+        "#;
+        let project =
+            PartialProjectToml::new(PROJECT_TOML, project_toml, ProjectRoot::default()).unwrap();
+        assert!(matches!(
+            project.into_local(),
+            Err(super::LocalProjectTomlValidationError::UnknownScript { .. })
+        ));
+
+        let project_toml = r#"
+        package = "server"
+        version = "1.0.0"
+        lua = "5.1"
+
+        [scripts]
+        serve = { description = "Nothing to run" }
+        "#;
+        assert!(
+            PartialProjectToml::new(PROJECT_TOML, project_toml, ProjectRoot::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn project_toml_with_features() {
+        let project_toml = r#"
+        package = "http"
+        version = "1.0.0"
+        lua = "5.1"
+
+        [dependencies]
+        luasocket = "3.0"
+        json = { version = "1.0", features = ["fast"] }
+
+        [features.tls]
+        dependencies = { luasec = "1.3" }
+        variables = { HTTP_TLS = "1" }
+
+        [features.curl]
+        dependencies = { lua-curl = "0.3", luasocket = "3.1" }
+
+        [build]
+        type = "builtin"
+        "#;
+
+        let project =
+            PartialProjectToml::new(PROJECT_TOML, project_toml, ProjectRoot::default()).unwrap();
+        assert!(project.declares_feature("tls"));
+        assert!(!project.declares_feature("http2"));
+
+        let local = project.into_local().unwrap();
+        assert_eq!(local.features().len(), 2);
+        assert!(local.enabled_features().is_empty());
+        let json = local
+            .dependencies()
+            .current_platform()
+            .iter()
+            .find(|dep| dep.name() == &"json".into())
+            .unwrap();
+        assert_eq!(json.features(), &["fast".to_string()]);
+
+        let local = local.with_features(&["tls".into(), "curl".into()]).unwrap();
+        assert_eq!(
+            local.enabled_features(),
+            &["curl".to_string(), "tls".to_string()]
+        );
+        let dependencies = local.dependencies().current_platform();
+        assert_eq!(dependencies.len(), 4);
+        let luasocket = dependencies
+            .iter()
+            .find(|dep| dep.name() == &"luasocket".into())
+            .unwrap();
+        assert_eq!(luasocket.version_req().to_string(), "==3.1");
+        assert!(dependencies
+            .iter()
+            .any(|dep| dep.name() == &"luasec".into()));
+        assert_eq!(
+            local.feature_variables().get("HTTP_TLS"),
+            Some(&"1".to_string())
+        );
+
+        let err = PartialProjectToml::new(PROJECT_TOML, project_toml, ProjectRoot::default())
+            .unwrap()
+            .into_local()
+            .unwrap()
+            .with_features(&["http2".into()])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            super::LocalProjectTomlValidationError::UnknownFeature(feature) if feature == "http2"
+        ));
+    }
+
     #[test]
     fn project_toml_with_invalid_run_command() {
         for command in ["lua", "lua5.1", "lua5.2", "lua5.3", "lua5.4", "luajit"] {
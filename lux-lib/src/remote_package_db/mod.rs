@@ -3,11 +3,12 @@ use crate::{
     lockfile::{LocalPackageLock, LockfileIntegrityError},
     manifest::{Manifest, ManifestError},
     package::{
-        PackageName, PackageReq, PackageSpec, PackageVersion, RemotePackage,
+        PackageName, PackageReq, PackageSpec, PackageVersion, RemotePackage, RemotePackageType,
         RemotePackageTypeFilterSpec,
     },
 };
 use itertools::Itertools;
+use url::Url;
 
 use miette::Diagnostic;
 use thiserror::Error;
@@ -22,6 +23,14 @@ enum Impl {
     Lock(LocalPackageLock),
 }
 
+/// The versions of a package that a server provides.
+#[derive(Debug)]
+pub struct ServerPackageVersions<'a> {
+    pub server_url: &'a Url,
+    /// The versions, latest first, with the package types that are available for each.
+    pub versions: Vec<(&'a PackageVersion, Vec<RemotePackageType>)>,
+}
+
 #[derive(Error, Debug, Diagnostic)]
 pub enum RemotePackageDBError {
     #[error(transparent)]
@@ -105,6 +114,15 @@ impl RemotePackageDB {
         }
     }
 
+    /// The manifests of the remote servers, in the order in which they are searched.
+    /// Empty if the package database is a lockfile.
+    pub(crate) fn manifests(&self) -> &[Manifest] {
+        match &self.0 {
+            Impl::LuarocksManifests(manifests) => manifests,
+            Impl::Lock(_) => &[],
+        }
+    }
+
     /// Search for all packages that match the requirement.
     pub fn search(&self, package_req: &PackageReq) -> Vec<(&PackageName, Vec<&PackageVersion>)> {
         match &self.0 {
@@ -150,6 +168,53 @@ impl RemotePackageDB {
         }
     }
 
+    /// The versions of a package that each server provides.
+    /// Servers that do not provide the package are omitted.
+    pub fn versions_by_server(&self, name: &PackageName) -> Vec<ServerPackageVersions<'_>> {
+        self.manifests()
+            .iter()
+            .filter_map(|manifest| {
+                let versions = manifest.metadata().repository.get(name)?;
+                let versions = versions
+                    .iter()
+                    .map(|(version, package_types)| {
+                        (
+                            version,
+                            package_types.iter().cloned().sorted().rev().collect_vec(),
+                        )
+                    })
+                    .sorted_by(|(a, _), (b, _)| Ord::cmp(b, a))
+                    .collect_vec();
+                Some(ServerPackageVersions {
+                    server_url: manifest.server_url(),
+                    versions,
+                })
+            })
+            .collect()
+    }
+
+    /// All versions of a package that are available, latest first.
+    pub(crate) fn versions(&self, name: &PackageName) -> Vec<PackageVersion> {
+        match &self.0 {
+            Impl::LuarocksManifests(manifests) => manifests
+                .iter()
+                .filter_map(|manifest| manifest.metadata().repository.get(name))
+                .flat_map(|versions| versions.keys())
+                .cloned()
+                .collect_vec(),
+            Impl::Lock(lockfile) => lockfile
+                .rocks()
+                .values()
+                .filter(|package| package.name() == name)
+                .map(|package| package.version().clone())
+                .collect_vec(),
+        }
+        .into_iter()
+        .sorted_by(|a, b| Ord::cmp(b, a))
+        .dedup()
+        .collect_vec()
+    }
+
     /// Find the latest version for a package by name.
     pub(crate) fn latest_version(&self, rock_name: &PackageName) -> Option<PackageVersion> {
         self.latest_match(&rock_name.clone().into(), None)
@@ -180,3 +245,56 @@ impl From<LocalPackageLock> for RemotePackageDB {
         Self(Impl::Lock(lock))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::manifest::ManifestMetadata;
+
+    use super::*;
+
+    #[test]
+    fn versions_by_server() {
+        let manifest = r#"
+repository = {
+   foo = {
+      ["1.0.0-1"] = {
+         { arch = "rockspec" },
+         { arch = "src" },
+      },
+      ["2.0.0-1"] = {
+         { arch = "rockspec" },
+         { arch = "all" },
+      },
+   },
+}
+"#;
+        let server_url: Url = "https://example.com/".parse().unwrap();
+        let package_db = RemotePackageDB::from(Manifest::new(
+            server_url.clone(),
+            ManifestMetadata::new(manifest).unwrap(),
+            RemotePackageTypeFilterSpec::default(),
+        ));
+        let servers = package_db.versions_by_server(&"foo".into());
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].server_url, &server_url);
+        let versions = servers[0]
+            .versions
+            .iter()
+            .map(|(version, package_types)| (version.to_string(), package_types.clone()))
+            .collect_vec();
+        assert_eq!(
+            versions,
+            vec![
+                (
+                    "2.0.0-1".into(),
+                    vec![RemotePackageType::Binary, RemotePackageType::Rockspec]
+                ),
+                (
+                    "1.0.0-1".into(),
+                    vec![RemotePackageType::Rockspec, RemotePackageType::Src]
+                ),
+            ]
+        );
+        assert!(package_db.versions_by_server(&"bar".into()).is_empty());
+    }
+}
@@ -109,6 +109,28 @@ pub(crate) fn http_client(config: &Config) -> Result<&Client, reqwest::Error> {
     client(false, config)
 }
 
+/// Returns a pre-configured client for requests to a rocks server.
+/// Local servers (e.g. `lx serve`) may not be served over HTTPS,
+/// so this is an HTTP client if the server URL is an `http` URL on the loopback interface,
+/// or if plain HTTP servers are explicitly allowed in the config.
+pub(crate) fn server_client<'a>(
+    server_url: &Url,
+    config: &'a Config,
+) -> Result<&'a Client, reqwest::Error> {
+    let allow_http = server_url.scheme() == "http"
+        && (config.allow_insecure_servers() || is_loopback(server_url));
+    client(!allow_http, config)
+}
+
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
 mod private {
     pub trait Sealed {}
     impl Sealed for reqwest::RequestBuilder {}
@@ -198,4 +220,34 @@ mod tests {
             .unwrap();
         assert!(response.status().is_success());
     }
+
+    #[test]
+    fn test_loopback_server_urls() {
+        for url in [
+            "http://localhost:8080/",
+            "http://127.0.0.1:8080/",
+            "http://[::1]:8080/",
+        ] {
+            assert!(is_loopback(&Url::parse(url).unwrap()), "{url}");
+        }
+        for url in ["http://example.com/", "http://10.0.0.1/"] {
+            assert!(!is_loopback(&Url::parse(url).unwrap()), "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_client_rejects_remote_http() {
+        let config = crate::config::ConfigBuilder::new()
+            .unwrap()
+            .build()
+            .unwrap();
+        let url = Url::parse("http://rocks.example.com/").unwrap();
+        let err = server_client(&url, &config)
+            .unwrap()
+            .get(url.clone())
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_builder(), "{err:?}");
+    }
 }
@@ -26,6 +26,8 @@ pub struct LuaDependencySpec {
     pub(crate) pin: PinnedState,
     pub(crate) opt: OptState,
     pub(crate) source: Option<RockSourceSpec>,
+    /// Optional features of the dependency to enable.
+    pub(crate) features: Vec<String>,
 }
 
 impl LuaDependencySpec {
@@ -41,6 +43,9 @@ impl LuaDependencySpec {
     pub fn source(&self) -> &Option<RockSourceSpec> {
         &self.source
     }
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
     pub fn into_package_req(self) -> PackageReq {
         self.package_req
     }
@@ -62,6 +67,7 @@ impl From<PackageName> for LuaDependencySpec {
             pin: PinnedState::default(),
             opt: OptState::default(),
             source: None,
+            features: Vec::new(),
         }
     }
 }
@@ -73,6 +79,7 @@ impl From<PackageReq> for LuaDependencySpec {
             pin: PinnedState::default(),
             opt: OptState::default(),
             source: None,
+            features: Vec::new(),
         }
     }
 }
@@ -87,6 +94,7 @@ impl FromStr for LuaDependencySpec {
             pin: PinnedState::default(),
             opt: OptState::default(),
             source: None,
+            features: Vec::new(),
         })
     }
 }
@@ -145,6 +153,7 @@ impl<'de> Deserialize<'de> for LuaDependencySpec {
             pin: PinnedState::default(),
             opt: OptState::default(),
             source: None,
+            features: Vec::new(),
         })
     }
 }
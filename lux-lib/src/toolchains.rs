@@ -45,6 +45,7 @@ pub struct ToolchainReport {
     c_compiler: Tool,
     make: Tool,
     cmake: Tool,
+    meson: Tool,
     cargo: Tool,
     pkg_config: Tool,
 }
@@ -59,6 +60,9 @@ impl ToolchainReport {
     pub fn cmake(&self) -> &Tool {
         &self.cmake
     }
+    pub fn meson(&self) -> &Tool {
+        &self.meson
+    }
     pub fn cargo(&self) -> &Tool {
         &self.cargo
     }
@@ -71,6 +75,7 @@ impl ToolchainReport {
             c_compiler: check_c_compiler(),
             make: check_executable("make"),
             cmake: check_executable("cmake"),
+            meson: check_executable("meson"),
             cargo: check_executable("cargo"),
             pkg_config: check_executable("pkg-config"),
         }
@@ -170,6 +175,7 @@ mod tests {
             report.c_compiler(),
             report.make(),
             report.cmake(),
+            report.meson(),
             report.cargo(),
             report.pkg_config(),
         ];
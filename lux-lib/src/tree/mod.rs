@@ -190,6 +190,17 @@ impl Tree {
         })
     }
 
+    /// Create a tree at `root` that shares this tree's test and build dependency trees.
+    pub(crate) fn with_root(&self, root: PathBuf, config: &Config) -> Result<Self, TreeError> {
+        Self::new_with_paths(
+            root,
+            self.test_tree_dir.clone(),
+            self.build_tree_dir.clone(),
+            self.version.clone(),
+            config,
+        )
+    }
+
     pub fn match_rocks_and<F>(&self, req: &PackageReq, filter: F) -> Result<RockMatches, TreeError>
     where
         F: Fn(&LocalPackage) -> bool,
@@ -1,15 +1,18 @@
+use std::path::{Path, PathBuf};
 use std::{env, io};
 
-use crate::operations::SearchAndDownloadError;
-use crate::package::SpecRevIterator;
+use crate::lockfile::LocalPackage;
+use crate::manifest::{Manifest, ManifestError};
+use crate::operations::{Pack, PackError, SearchAndDownloadError};
+use crate::package::{PackageSpec, SpecRevIterator};
 use crate::project::project_toml::RemoteProjectTomlValidationError;
 use crate::remote_package_db::RemotePackageDB;
 use crate::rockspec::Rockspec;
+use crate::tree::Tree;
 use crate::TOOL_VERSION;
 use crate::{config::Config, project::Project};
 
 use bon::Builder;
-use itertools::Itertools;
 use miette::Diagnostic;
 use reqwest::multipart::{Form, Part};
 use reqwest::StatusCode;
@@ -37,6 +40,14 @@ pub struct ProjectUpload<'a> {
     sign_protocol: SignatureProtocol,
     config: &'a Config,
     package_db: &'a RemotePackageDB,
+    /// Whether to pack the project's sources into a `.src.rock`
+    /// and upload it alongside the rockspec.
+    with_src_rock: Option<bool>,
+    /// An installed build of the project (e.g. built with
+    /// [`BuildWorkspace`](crate::operations::BuildWorkspace)) and the tree it is installed to.
+    /// It is packed into a binary rock with the uploaded rockspec
+    /// and uploaded alongside it.
+    binary_rock: Option<(Tree, LocalPackage)>,
 }
 
 impl<State> ProjectUploadBuilder<'_, State>
@@ -176,6 +187,22 @@ if the issue persists, the server may be temporarily unavailable."#
     #[error("the 2FA code '{0}' was rejected by the server: {1}")]
     #[diagnostic(help("it may have expired; try again with a new code."))]
     TfaCodeRejected(String, String),
+    #[error("error packing rock")]
+    #[diagnostic(forward(0))]
+    Pack(#[from] PackError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Fs(#[from] crate::fs::FsError),
+    #[error("unable to read rock '{0}'")]
+    RockRead(PathBuf, #[source] io::Error),
+    #[error("expected a packed rock named '<package>-<version>.<arch>.rock', but got '{0}'")]
+    InvalidRockFileName(PathBuf),
+    #[error("server '{0}' did not report the ID of the uploaded version")]
+    #[diagnostic(help("make sure the server implements the luarocks.org upload API"))]
+    MissingVersionId(Url),
 }
 
 pub struct ApiKey(String);
@@ -195,6 +222,29 @@ impl ApiKey {
         ))
     }
 
+    /// Retrieves the API key for the named registry in the `[registries]` config section.
+    /// Looks up the registry's `api_key_env` environment variable,
+    /// then the `[access_tokens]` for the registry name and for its host,
+    /// and falls back to the `$LUX_API_KEY` environment variable.
+    pub fn for_registry(name: &str, config: &Config) -> Result<Self, ApiKeyUnspecified> {
+        let registry = config.registries().get(name);
+        registry
+            .and_then(|registry| registry.api_key_env())
+            .and_then(|var| env::var(var).ok())
+            .or_else(|| {
+                config
+                    .access_token(name)
+                    .or_else(|| {
+                        registry
+                            .and_then(|registry| registry.url().host_str())
+                            .and_then(|host| config.access_token(host))
+                    })
+                    .map(|token| unsafe { token.password() }.to_string())
+            })
+            .map(Self)
+            .map_or_else(Self::new, Ok)
+    }
+
     /// Creates an API key from a [`String`].
     ///
     /// # Safety
@@ -318,19 +368,82 @@ impl From<SignatureProtocol> for gpgme::Protocol {
 )]
 async fn upload_from_project(args: ProjectUpload<'_>) -> Result<(), UploadError> {
     let project = args.project;
-    let api_key = args.api_key.unwrap_or(ApiKey::new()?);
+    let api_key = match args.api_key {
+        Some(api_key) => api_key,
+        None => ApiKey::new()?,
+    };
     #[cfg(feature = "gpgme")]
     let protocol = args.sign_protocol;
     let config = args.config;
     let package_db = args.package_db;
 
-    let client = crate::reqwest::https_client(args.config)?;
+    let client = crate::reqwest::server_client(config.server(), config)?;
 
     helpers::ensure_tool_version(client, config.server()).await?;
     helpers::ensure_user_exists(client, &api_key, config.server()).await?;
 
-    let (rockspec, rockspec_content) =
-        helpers::generate_rockspec(project, client, &api_key, config, package_db).await?;
+    let with_src_rock = args.with_src_rock.unwrap_or(false);
+    let with_rocks = with_src_rock || args.binary_rock.is_some();
+
+    let (rockspec, rockspec_content, existing_version) =
+        helpers::generate_rockspec(project, client, &api_key, config, package_db, with_rocks)
+            .await?;
+
+    let temp_dir = crate::fs::tempfile::tempdir()?;
+    let mut rocks = Vec::new();
+    if with_src_rock {
+        let src_rock = Pack::src(temp_dir.path().to_path_buf(), &rockspec, config)
+            .pack()
+            .await?;
+        rocks.push(src_rock);
+    }
+    if let Some((tree, package)) = args.binary_rock {
+        // We pack the uploaded rockspec, whose revision may have been bumped,
+        // so that the rock's name and contents match the published version.
+        let lua_rockspec = rockspec
+            .to_lua_rockspec()
+            .map_err(|err| UploadError::Rockspec(err.to_string()))?;
+        let binary_rock = Pack::new(temp_dir.path().to_path_buf(), tree, package)
+            .target(config.target())
+            .rockspec(lua_rockspec)
+            .pack()
+            .await?;
+        rocks.push(binary_rock);
+    }
+
+    let tfa_token = match args.tfa_code {
+        Some(code) => {
+            Some(helpers::verify_tfa_code(client, config.server(), &api_key, &code).await?)
+        }
+        None => None,
+    };
+
+    let package = PackageSpec::new(rockspec.package().clone(), rockspec.version().clone());
+
+    if let Some(existing_version) = existing_version {
+        // The rockspec has already been published, so we only add the missing artefacts.
+        let version_id = existing_version
+            .id
+            .ok_or_else(|| UploadError::MissingVersionId(config.server().clone()))?;
+        let uploaded_arches = Manifest::package_arches(config.server(), &package, config).await?;
+        for rock in rocks {
+            let arch = helpers::rock_arch(&rock)?;
+            if uploaded_arches.contains(&arch) {
+                tracing::info!("{} has already been uploaded. Skipping.", rock.display());
+                continue;
+            }
+            helpers::upload_rock(
+                client,
+                config.server(),
+                &api_key,
+                version_id,
+                &rock,
+                tfa_token.as_ref(),
+            )
+            .await?;
+        }
+        return Ok(());
+    }
 
     #[cfg(not(feature = "gpgme"))]
     let signed: Option<String> = None;
@@ -375,23 +488,47 @@ async fn upload_from_project(args: ProjectUpload<'_>) -> Result<(), UploadError>
         .post(unsafe { helpers::url_for_method(config.server(), &api_key, "upload")? })
         .multipart(multipart);
 
-    if let Some(code) = args.tfa_code {
-        let token = helpers::verify_tfa_code(client, config.server(), &api_key, &code).await?;
+    if let Some(token) = &tfa_token {
         request = request.header(TFA_TOKEN_HEADER, unsafe { token.get() });
     }
 
-    let response = request.send().await?;
+    let response = helpers::check_response(config.server(), request.send().await?).await?;
 
-    let status = response.status();
-    if status.is_server_error() {
-        Err(UploadError::Server(config.server().clone(), status))
-    } else if status.is_success() {
-        Ok(())
-    } else {
-        let response = response.json::<LuarocksErrorResponse>().await?;
-        let errors = response.errors.into_iter().join("\n");
-        Err(UploadError::Client(config.server().clone(), errors))
+    if rocks.is_empty() {
+        return Ok(());
     }
+
+    let version_id = response
+        .json::<LuarocksUploadResponse>()
+        .await?
+        .version
+        .id
+        .ok_or_else(|| UploadError::MissingVersionId(config.server().clone()))?;
+    for rock in rocks {
+        helpers::upload_rock(
+            client,
+            config.server(),
+            &api_key,
+            version_id,
+            &rock,
+            tfa_token.as_ref(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Models the response to a successful rockspec upload or rockspec check
+#[derive(Deserialize)]
+struct LuarocksUploadResponse {
+    version: LuarocksVersion,
+}
+
+/// A published version of a package, to which rocks can be added
+#[derive(Deserialize)]
+struct LuarocksVersion {
+    #[serde(default)]
+    id: Option<u64>,
 }
 
 mod helpers {
@@ -405,7 +542,7 @@ mod helpers {
     use crate::upload::RockCheckError;
     use crate::upload::{ToolCheckError, UserCheckError};
     use itertools::Itertools;
-    use reqwest::Client;
+    use reqwest::{Client, Response};
     use ssri::Integrity;
     use url::Url;
 
@@ -457,7 +594,7 @@ mod helpers {
     ) -> Result<TfaToken, UploadError> {
         let response = client
             .get(unsafe { url_for_method(server_url, api_key, "verify_tfa")? })
-            .query(&("code", tfa_code.to_string()))
+            .query(&[("code", tfa_code)])
             .send()
             .await?;
         let status = response.status();
@@ -498,6 +635,73 @@ mod helpers {
         }
     }
 
+    /// Check the response to an upload, turning error statuses into [`UploadError`]s.
+    pub(crate) async fn check_response(
+        server_url: &Url,
+        response: Response,
+    ) -> Result<Response, UploadError> {
+        let status = response.status();
+        if status.is_server_error() {
+            Err(UploadError::Server(server_url.clone(), status))
+        } else if status.is_success() {
+            Ok(response)
+        } else {
+            let response = response.json::<LuarocksErrorResponse>().await?;
+            let errors = response.errors.into_iter().join("\n");
+            Err(UploadError::Client(server_url.clone(), errors))
+        }
+    }
+
+    /// The architecture of a packed rock (e.g. "src" or "linux-x86_64"),
+    /// as listed in the server's manifest.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn rock_arch(rock: &Path) -> Result<String, UploadError> {
+        rock_file_name(rock)?
+            .strip_suffix(".rock")
+            .and_then(|stem| stem.rsplit_once('.'))
+            .map(|(_, arch)| arch.to_string())
+            .ok_or_else(|| UploadError::InvalidRockFileName(rock.to_path_buf()))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn rock_file_name(rock: &Path) -> Result<String, UploadError> {
+        rock.file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .ok_or_else(|| UploadError::InvalidRockFileName(rock.to_path_buf()))
+    }
+
+    #[tracing::instrument(level = "trace", skip(client, api_key, rock, tfa_token))]
+    pub(crate) async fn upload_rock(
+        client: &Client,
+        server_url: &Url,
+        api_key: &ApiKey,
+        version_id: u64,
+        rock: &Path,
+        tfa_token: Option<&TfaToken>,
+    ) -> Result<(), UploadError> {
+        let file_name = rock_file_name(rock)?;
+        let bytes = tokio::fs::read(rock)
+            .await
+            .map_err(|err| UploadError::RockRead(rock.to_path_buf(), err))?;
+        let part = Part::bytes(bytes)
+            .file_name(file_name.clone())
+            .mime_str("application/octet-stream")?;
+        let mut request = client
+            .post(unsafe {
+                url_for_method(server_url, api_key, &format!("upload_rock/{version_id}"))?
+            })
+            .multipart(Form::new().part("rock_file", part));
+        if let Some(token) = tfa_token {
+            request = request.header(TFA_TOKEN_HEADER, unsafe { token.get() });
+        }
+        check_response(server_url, request.send().await?).await?;
+        tracing::info!("Uploaded {file_name}");
+        Ok(())
+    }
+
+    /// Generate a rockspec that has not yet been published.
+    /// If `allow_existing` is set and the server already has an identical rockspec,
+    /// returns it along with the existing version, so that missing rocks can be added.
     #[tracing::instrument(level = "trace", skip_all)]
     pub(crate) async fn generate_rockspec(
         project: &Project,
@@ -505,7 +709,8 @@ mod helpers {
         api_key: &ApiKey,
         config: &Config,
         package_db: &RemotePackageDB,
-    ) -> Result<(RemoteProjectToml, String), UploadError> {
+        allow_existing: bool,
+    ) -> Result<(RemoteProjectToml, String, Option<LuarocksVersion>), UploadError> {
         for specrev in SpecRevIterator::new() {
             let rockspec = project.toml().into_remote(Some(specrev))?;
 
@@ -516,7 +721,7 @@ mod helpers {
             if let PackageVersion::StringVer(ver) = rockspec.version() {
                 return Err(UploadError::UnsupportedVersion(ver.to_string()));
             }
-            if helpers::rock_exists(
+            if let Some(existing_version) = helpers::rock_exists(
                 client,
                 api_key,
                 rockspec.package(),
@@ -539,10 +744,13 @@ mod helpers {
                     .matches(&rockspec_content_hash)
                     .is_some()
                 {
+                    if allow_existing {
+                        return Ok((rockspec, rockspec_content, Some(existing_version)));
+                    }
                     return Err(UploadError::RockExists(config.server().clone()));
                 }
             } else {
-                return Ok((rockspec, rockspec_content));
+                return Ok((rockspec, rockspec_content, None));
             }
         }
         Err(UploadError::MaxSpecRevsExceeded)
@@ -555,7 +763,7 @@ mod helpers {
         name: &PackageName,
         version: &PackageVersion,
         server: &Url,
-    ) -> Result<bool, RockCheckError> {
+    ) -> Result<Option<LuarocksVersion>, RockCheckError> {
         let server_response_raw_json = client
             .get(unsafe { url_for_method(server, api_key, "check_rockspec")? })
             .query(&(
@@ -569,8 +777,14 @@ mod helpers {
             .await?;
         let response_map: Option<HashMap<String, serde_json::Value>> =
             serde_json::from_str(&server_response_raw_json).ok();
-        Ok(response_map.is_some_and(|response_map| {
-            response_map.contains_key("module") && response_map.contains_key("version")
+        Ok(response_map.and_then(|response_map| {
+            if response_map.contains_key("module") {
+                response_map.get("version").map(|version| LuarocksVersion {
+                    id: version.get("id").and_then(serde_json::Value::as_u64),
+                })
+            } else {
+                None
+            }
         }))
     }
 }
@@ -578,6 +792,16 @@ mod helpers {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::config::ConfigBuilder;
+    use crate::manifest::{Manifest, ManifestMetadata};
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+    use httptest::{
+        matchers::{all_of, contains, request, url_decoded},
+        responders::{json_encoded, status_code},
+        Expectation, Server,
+    };
+    use serial_test::serial;
+    use std::collections::HashMap;
 
     #[test]
     fn test_deserialize_tfa_success() {
@@ -620,4 +844,282 @@ mod test {
         let result = serde_json::from_str(response_str).unwrap();
         assert!(matches!(result, LuarocksErrorResponse { .. }));
     }
+
+    #[test]
+    fn test_deserialize_upload_response() {
+        let response_str = r#"{
+    "module": { "id": 1, "name": "foo" },
+    "version": { "id": 42, "version": "1.0.0-1" },
+    "module_url": "https://luarocks.org/modules/user/foo"
+}
+"#;
+        let result: LuarocksUploadResponse = serde_json::from_str(response_str).unwrap();
+        assert_eq!(result.version.id, Some(42));
+    }
+
+    #[test]
+    fn test_rock_arch() {
+        assert_eq!(
+            helpers::rock_arch(Path::new("/tmp/foo-1.0.0-1.src.rock")).unwrap(),
+            "src"
+        );
+        assert_eq!(
+            helpers::rock_arch(Path::new("/tmp/foo-1.0.0-1.linux-x86_64.rock")).unwrap(),
+            "linux-x86_64"
+        );
+        assert!(helpers::rock_arch(Path::new("/tmp/foo.rockspec")).is_err());
+    }
+
+    const TEST_API_KEY: &str = "test-api-key";
+
+    /// A mock luarocks-site server, with an `internal` registry pointing to it.
+    fn mock_registry() -> (Server, Config, Url) {
+        let server = Server::run();
+        let url = Url::parse(&server.url_str("/")).unwrap();
+        let config = toml::from_str::<ConfigBuilder>(&format!(
+            r#"
+[registries.internal]
+url = "{url}"
+api_key_env = "LUX_TEST_INTERNAL_API_KEY"
+tfa = false
+"#
+        ))
+        .unwrap()
+        .build()
+        .unwrap()
+        .for_registry("internal")
+        .unwrap();
+        (server, config, url)
+    }
+
+    fn api_path(endpoint: &str) -> String {
+        format!("/api/1/{TEST_API_KEY}/{endpoint}")
+    }
+
+    #[tokio::test]
+    async fn mock_ensure_tool_version() {
+        let (server, config, url) = mock_registry();
+        server.expect(
+            Expectation::matching(request::method_path("POST", "/api/tool_version"))
+                .respond_with(json_encoded(serde_json::json!({ "version": TOOL_VERSION }))),
+        );
+        let client = crate::reqwest::http_client(&config).unwrap();
+        helpers::ensure_tool_version(client, &url).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn mock_ensure_tool_version_outdated() {
+        let (server, config, url) = mock_registry();
+        server.expect(
+            Expectation::matching(request::method_path("POST", "/api/tool_version"))
+                .respond_with(json_encoded(serde_json::json!({ "version": "0.0.0" }))),
+        );
+        let client = crate::reqwest::http_client(&config).unwrap();
+        let result = helpers::ensure_tool_version(client, &url).await;
+        assert!(matches!(result, Err(ToolCheckError::ToolOutdated(..))));
+    }
+
+    #[tokio::test]
+    async fn mock_ensure_user_exists() {
+        let (server, config, url) = mock_registry();
+        server.expect(
+            Expectation::matching(request::method_path("GET", api_path("status")))
+                .respond_with(json_encoded(serde_json::json!({ "user_id": 1 }))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/api/1/unknown-key/status"))
+                .respond_with(status_code(403)),
+        );
+        let client = crate::reqwest::http_client(&config).unwrap();
+        helpers::ensure_user_exists(client, &ApiKey::from(TEST_API_KEY), &url)
+            .await
+            .unwrap();
+        let result = helpers::ensure_user_exists(client, &ApiKey::from("unknown-key"), &url).await;
+        assert!(matches!(result, Err(UserCheckError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn mock_verify_tfa_code() {
+        let (server, config, url) = mock_registry();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", api_path("verify_tfa")),
+                request::query(url_decoded(contains(("code", "123456")))),
+            ])
+            .respond_with(json_encoded(serde_json::json!({
+                "success": true,
+                "expires": 1782939987,
+                "tfa_token": "dummy_token",
+            }))),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", api_path("verify_tfa")),
+                request::query(url_decoded(contains(("code", "000000")))),
+            ])
+            .respond_with(json_encoded(serde_json::json!({
+                "errors": ["Invalid verification code"],
+            }))),
+        );
+        let client = crate::reqwest::http_client(&config).unwrap();
+        let api_key = ApiKey::from(TEST_API_KEY);
+        let token = helpers::verify_tfa_code(client, &url, &api_key, "123456")
+            .await
+            .unwrap();
+        assert_eq!(unsafe { token.get() }, "dummy_token");
+        let result = helpers::verify_tfa_code(client, &url, &api_key, "000000").await;
+        assert!(matches!(result, Err(UploadError::TfaCodeRejected(..))));
+    }
+
+    #[tokio::test]
+    async fn mock_upload_rock() {
+        let (server, config, url) = mock_registry();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", api_path("upload_rock/42")),
+                request::headers(contains((TFA_TOKEN_HEADER.to_lowercase(), "dummy_token"))),
+            ])
+            .respond_with(json_encoded(serde_json::json!({}))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("POST", api_path("upload_rock/43")))
+                .respond_with(
+                    status_code(400)
+                        .body(r#"{ "errors": ["Rock already exists"] }"#)
+                        .append_header("Content-Type", "application/json"),
+                ),
+        );
+        let client = crate::reqwest::http_client(&config).unwrap();
+        let api_key = ApiKey::from(TEST_API_KEY);
+        let temp = assert_fs::TempDir::new().unwrap();
+        let rock = temp.child("foo-1.0.0-1.src.rock");
+        rock.write_str("dummy rock").unwrap();
+        let tfa_token = TfaToken("dummy_token".into());
+        helpers::upload_rock(client, &url, &api_key, 42, rock.path(), Some(&tfa_token))
+            .await
+            .unwrap();
+        let result = helpers::upload_rock(client, &url, &api_key, 43, rock.path(), None).await;
+        assert!(matches!(result, Err(UploadError::Client(..))));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn mock_upload_to_registry() {
+        let (server, config, url) = mock_registry();
+        server.expect(
+            Expectation::matching(request::method_path("POST", "/api/tool_version"))
+                .respond_with(json_encoded(serde_json::json!({ "version": TOOL_VERSION }))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", api_path("status")))
+                .respond_with(json_encoded(serde_json::json!({ "user_id": 1 }))),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", api_path("check_rockspec")),
+                request::query(url_decoded(contains(("package", "sample-project")))),
+            ])
+            .respond_with(json_encoded(serde_json::json!({}))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("POST", api_path("upload")))
+                .times(1)
+                .respond_with(json_encoded(serde_json::json!({
+                    "module": { "id": 1 },
+                    "version": { "id": 7 },
+                }))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("POST", api_path("upload_rock/7")))
+                .times(1)
+                .respond_with(json_encoded(serde_json::json!({}))),
+        );
+
+        // The source is fetched to pack the src.rock
+        let source_dir = assert_fs::TempDir::new().unwrap();
+        source_dir
+            .child("src/main.lua")
+            .write_str("return {}")
+            .unwrap();
+        let project_root = assert_fs::TempDir::new().unwrap();
+        project_root
+            .child("lux.toml")
+            .write_str(&format!(
+                r#"
+package = "sample-project"
+version = "0.1.0"
+lua = ">=5.1"
+
+[source]
+url = "file://{}"
+
+[build]
+type = "builtin"
+"#,
+                source_dir.path().display()
+            ))
+            .unwrap();
+        project_root
+            .child("src/main.lua")
+            .write_str("return {}")
+            .unwrap();
+        let project = Project::from_exact(project_root.path()).unwrap().unwrap();
+
+        std::env::set_var("LUX_TEST_INTERNAL_API_KEY", TEST_API_KEY);
+        let api_key = ApiKey::for_registry("internal", &config).unwrap();
+        std::env::remove_var("LUX_TEST_INTERNAL_API_KEY");
+
+        let package_db: RemotePackageDB = Manifest::new(
+            url,
+            ManifestMetadata {
+                repository: HashMap::new(),
+            },
+            config.package_types().clone(),
+        )
+        .into();
+
+        #[cfg(feature = "gpgme")]
+        let upload = ProjectUpload::new().sign_protocol(SignatureProtocol::None);
+        #[cfg(not(feature = "gpgme"))]
+        let upload = ProjectUpload::new();
+        upload
+            .project(&project)
+            .api_key(api_key)
+            .config(&config)
+            .package_db(&package_db)
+            .with_src_rock(true)
+            .upload_to_luarocks()
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn api_key_for_registry() {
+        let config = toml::from_str::<ConfigBuilder>(
+            r#"
+[registries.internal]
+url = "https://rocks.internal.example.com/"
+api_key_env = "LUX_TEST_INTERNAL_API_KEY"
+
+[registries.other]
+url = "https://rocks.other.example.com/"
+
+[access_tokens]
+internal = "from-access-tokens"
+"rocks.other.example.com" = "from-host"
+"#,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        std::env::set_var("LUX_TEST_INTERNAL_API_KEY", "from-env");
+        let api_key = ApiKey::for_registry("internal", &config).unwrap();
+        assert_eq!(unsafe { api_key.get() }, "from-env");
+        std::env::remove_var("LUX_TEST_INTERNAL_API_KEY");
+        let api_key = ApiKey::for_registry("internal", &config).unwrap();
+        assert_eq!(unsafe { api_key.get() }, "from-access-tokens");
+        let api_key = ApiKey::for_registry("other", &config).unwrap();
+        assert_eq!(unsafe { api_key.get() }, "from-host");
+    }
 }
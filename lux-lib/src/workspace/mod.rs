@@ -1,4 +1,5 @@
 use std::{
+    collections::{HashMap, HashSet},
     io,
     ops::Deref,
     path::{Path, PathBuf},
@@ -7,13 +8,16 @@ use std::{
 use crate::{
     config::{Config, ConfigBuilder, ConfigError},
     fs,
+    git::utils::{self as git_utils, GitError},
     lockfile::{LockfileError, ReadOnly, WorkspaceLockfile},
     lua_rockspec::LuaVersionError,
+    lua_rockspec::RockSourceSpec,
     lua_version::LuaVersion,
     package::PackageName,
     project::{Project, ProjectError, TomlDeError, PROJECT_TOML},
+    rockspec::lua_dependency::LuaDependencySpec,
     tree::{InstallTree, Tree, TreeError},
-    workspace::workspace_toml::{WorkspaceMemberSpec, WorkspaceToml},
+    workspace::workspace_toml::{WorkspaceMemberSpec, WorkspacePackage, WorkspaceToml},
 };
 use glob::glob;
 use itertools::Itertools;
@@ -102,6 +106,16 @@ specify the package with '--package=[PACKAGE_NAME]'
         "make sure it is declared in your {WORKSPACE_TOML}'s '[workspace.members]'"
     ))]
     PackageNotFound(PackageName, WorkspaceRoot),
+    #[error("invalid package glob '{0}': {1}")]
+    PackageGlob(String, glob::PatternError),
+    #[error("workspace members declare conflicting overrides for '{0}'")]
+    #[diagnostic(help(
+        "declare the override in the `[workspace.overrides]` table of the workspace's {WORKSPACE_TOML}"
+    ))]
+    ConflictingOverrides(PackageName),
+    #[error("error detecting changed workspace members")]
+    #[diagnostic(forward(0))]
+    Git(#[from] GitError),
 }
 
 #[derive(Error, Debug, Diagnostic)]
@@ -120,6 +134,8 @@ pub enum WorkspaceTreeError {
 pub struct Workspace {
     root: WorkspaceRoot,
     members: NonEmpty<Project>,
+    /// Overrides declared in the `[workspace.overrides]` table.
+    overrides: Vec<LuaDependencySpec>,
 }
 
 #[derive(Error, Debug, Diagnostic)]
@@ -226,6 +242,110 @@ impl Workspace {
             .ok_or_else(|| WorkspaceError::PackageNotFound(package.clone(), workspace_root))
     }
 
+    /// Select the members whose package names match any of the given glob patterns.
+    pub fn select_members_matching(
+        &self,
+        patterns: &[String],
+    ) -> Result<Vec<&Project>, WorkspaceError> {
+        let patterns: Vec<glob::Pattern> = patterns
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map_err(|err| WorkspaceError::PackageGlob(pattern.clone(), err))
+            })
+            .try_collect()?;
+        Ok(self
+            .members()
+            .iter()
+            .filter(|project| {
+                patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(&project.toml().package().to_string()))
+            })
+            .collect_vec())
+    }
+
+    /// Select the members whose project files have changed since the git `reference`,
+    /// including workspace members that (transitively) depend on them.
+    pub fn select_members_changed_since(
+        &self,
+        reference: &str,
+    ) -> Result<Vec<&Project>, WorkspaceError> {
+        let changed_files = git_utils::changed_files_since(&self.root, reference)?;
+        let mut changed: HashSet<&PackageName> = HashSet::new();
+        for project in self.members() {
+            let root =
+                std::fs::canonicalize(project.root()).unwrap_or(project.root().to_path_buf());
+            let project_files: HashSet<PathBuf> = project.project_files().into_iter().collect();
+            let is_changed = changed_files.iter().any(|file| {
+                file.strip_prefix(&root).is_ok_and(|relative_path| {
+                    let file = project.root().join(relative_path);
+                    project_files.contains(&file) || !file.exists()
+                })
+            });
+            if is_changed {
+                changed.insert(project.toml().package());
+            }
+        }
+        // Add the dependants of changed members until we reach a fixpoint
+        loop {
+            let dependants = self
+                .members()
+                .iter()
+                .filter(|project| !changed.contains(project.toml().package()))
+                .filter(|project| {
+                    project
+                        .toml()
+                        .all_dependency_names()
+                        .any(|dep| changed.contains(dep))
+                })
+                .map(|project| project.toml().package())
+                .collect_vec();
+            if dependants.is_empty() {
+                break;
+            }
+            changed.extend(dependants);
+        }
+        Ok(self
+            .members()
+            .iter()
+            .filter(|project| changed.contains(project.toml().package()))
+            .collect_vec())
+    }
+
+    /// The packages to force during dependency resolution, by package name.
+    /// Overrides declared in the `[workspace.overrides]` table take precedence
+    /// over those declared in the members' `[overrides]` tables.
+    /// Fails if two members declare different overrides for the same package.
+    pub fn overrides(&self) -> Result<HashMap<PackageName, LuaDependencySpec>, WorkspaceError> {
+        let mut overrides: HashMap<PackageName, LuaDependencySpec> = HashMap::new();
+        for dep in self
+            .members()
+            .iter()
+            .flat_map(|project| project.toml().overrides())
+        {
+            match overrides.get(dep.name()) {
+                Some(existing)
+                    if existing.package_req() != dep.package_req()
+                        || existing.source() != dep.source() =>
+                {
+                    if !self.overrides.iter().any(|ws| ws.name() == dep.name()) {
+                        return Err(WorkspaceError::ConflictingOverrides(dep.name().clone()));
+                    }
+                }
+                _ => {
+                    overrides.insert(dep.name().clone(), dep);
+                }
+            }
+        }
+        overrides.extend(
+            self.overrides
+                .iter()
+                .map(|dep| (dep.name().clone(), dep.clone())),
+        );
+        Ok(overrides)
+    }
+
     /// Get the `lux.lock` lockfile path.
     pub fn lockfile_path(&self) -> PathBuf {
         self.root.join("lux.lock")
@@ -340,6 +460,7 @@ impl Workspace {
                 Ok(Some(Workspace {
                     root: WorkspaceRoot(root.to_path_buf()),
                     members: NonEmpty::new(project),
+                    overrides: Vec::new(),
                 }))
             }
         } else {
@@ -383,6 +504,7 @@ impl Workspace {
                                     Ok(Some(Workspace {
                                         root: WorkspaceRoot(root.to_path_buf()),
                                         members: NonEmpty::new(project),
+                                        overrides: Vec::new(),
                                     }))
                                 }
                             }
@@ -405,7 +527,7 @@ impl Workspace {
         let toml = WorkspaceToml::new(WORKSPACE_TOML, toml_content)
             .map_err(|source| WorkspaceError::TOML { source })?;
         let mut members = Vec::new();
-        for member in toml.workspace.members {
+        for member in toml.workspace.members.iter().cloned() {
             match member {
                 WorkspaceMemberSpec::RelativeProjectGlob(pattern) => {
                     let potential_paths = glob(root.join(pattern).to_slash_lossy().deref())
@@ -418,15 +540,21 @@ impl Workspace {
                         })
                         .try_collect::<_, Vec<_>, _>()?;
                     for project_path in potential_paths {
-                        if let Some(project) = Project::from_exact(&project_path)? {
+                        if let Some(mut project) =
+                            Project::from_exact_without_workspace(&project_path)?
+                        {
+                            project.set_workspace_package(toml.workspace.package.clone());
                             members.push(project)
                         }
                     }
                 }
                 WorkspaceMemberSpec::RelativeProjectPath(relative_project_path) => {
                     let project_path = root.join(relative_project_path);
-                    match Project::from_exact(&project_path)? {
-                        Some(project) => members.push(project),
+                    match Project::from_exact_without_workspace(&project_path)? {
+                        Some(mut project) => {
+                            project.set_workspace_package(toml.workspace.package.clone());
+                            members.push(project)
+                        }
                         None => return Err(WorkspaceError::WorkspaceNotFound(project_path)),
                     }
                 }
@@ -436,16 +564,70 @@ impl Workspace {
             Some(members) => Ok(Workspace {
                 root: WorkspaceRoot(root.to_path_buf()),
                 members,
+                overrides: toml
+                    .workspace
+                    .overrides
+                    .into_iter()
+                    .flatten()
+                    .map(|dep| match &dep.source {
+                        Some(RockSourceSpec::File(path)) if path.is_relative() => {
+                            LuaDependencySpec {
+                                source: Some(RockSourceSpec::File(root.join(path))),
+                                ..dep
+                            }
+                        }
+                        _ => dep,
+                    })
+                    .collect(),
             }),
             None => Err(WorkspaceError::EmptyWorkspace(root.to_path_buf())),
         }
     }
 }
 
+/// The `[workspace.package]` metadata of the workspace enclosing the project at `project_root`,
+/// if the project is one of the workspace's members.
+pub(crate) fn enclosing_workspace_package(
+    project_root: &Path,
+) -> Result<Option<WorkspacePackage>, ProjectError> {
+    let project_root = project_root
+        .canonicalize()
+        .unwrap_or(project_root.to_path_buf());
+    let is_project_root =
+        |path: &Path| path.canonicalize().unwrap_or(path.to_path_buf()) == project_root;
+    for dir in project_root.ancestors().skip(1) {
+        let toml_path = dir.join(WORKSPACE_TOML);
+        if !toml_path.is_file() {
+            continue;
+        }
+        let toml_content = fs::sync::read_to_string(&toml_path)?;
+        let toml_obj: Option<toml::Table> = toml::from_str(&toml_content).ok();
+        if !toml_obj.is_some_and(|toml| toml.contains_key("workspace")) {
+            continue;
+        }
+        let toml = WorkspaceToml::new(WORKSPACE_TOML, &toml_content)?;
+        let is_member = toml.workspace.members.iter().any(|member| match member {
+            WorkspaceMemberSpec::RelativeProjectGlob(pattern) => {
+                glob(dir.join(pattern).to_slash_lossy().deref())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Result::ok)
+                    .any(|path| is_project_root(&path))
+            }
+            WorkspaceMemberSpec::RelativeProjectPath(path) => is_project_root(&dir.join(path)),
+        });
+        return Ok(toml.workspace.package.filter(|_| is_member));
+    }
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::ConfigBuilder, fs, lua_version::LuaVersion};
+    use crate::{
+        config::ConfigBuilder, fs, lua_version::LuaVersion,
+        project::project_toml::LocalProjectTomlValidationError, rockspec::Rockspec,
+    };
     use std::path::PathBuf;
 
     use assert_fs::prelude::*;
@@ -534,6 +716,243 @@ members = [ "glob:projects/*" ]
         );
     }
 
+    #[tokio::test]
+    async fn inherit_workspace_package_metadata() {
+        let workspace_root = assert_fs::TempDir::new().unwrap();
+        workspace_root
+            .child(WORKSPACE_TOML)
+            .write_str(
+                r#"
+[workspace]
+members = ["foo"]
+
+[workspace.package]
+license = "MIT"
+maintainer = "lumen-labs"
+labels = ["lua"]
+lua = ">=5.1"
+
+[workspace.package.source]
+url = "https://example.com/$(PACKAGE)-$(VERSION).tar.gz"
+"#,
+            )
+            .unwrap();
+        workspace_root
+            .child("foo")
+            .child(PROJECT_TOML)
+            .write_str(
+                r#"
+package = "foo"
+version = "1.0.0"
+lua.workspace = true
+source.workspace = true
+
+[description]
+summary = "foo"
+license.workspace = true
+maintainer = "someone-else"
+labels.workspace = true
+"#,
+            )
+            .unwrap();
+
+        let workspace = Workspace::from(&workspace_root).unwrap().unwrap();
+        let foo = workspace.select_member(&"foo".into()).unwrap();
+        assert_eq!(foo.toml().lua().unwrap().to_string(), ">=5.1");
+        let remote = foo.toml().into_remote(None).unwrap();
+        let description = remote.description();
+        assert_eq!(description.license, Some("MIT".into()));
+        assert_eq!(description.maintainer, Some("someone-else".into()));
+        assert_eq!(description.labels, vec!["lua".to_string()]);
+        let rockspec = remote.to_lua_remote_rockspec_string().unwrap();
+        assert!(rockspec.contains("https://example.com/foo-1.0.0.tar.gz"));
+    }
+
+    #[tokio::test]
+    async fn inherit_workspace_package_metadata_from_exact() {
+        let workspace_root = assert_fs::TempDir::new().unwrap();
+        workspace_root
+            .child(WORKSPACE_TOML)
+            .write_str(
+                r#"
+[workspace]
+members = ["glob:members/*"]
+
+[workspace.package]
+license = "MIT"
+"#,
+            )
+            .unwrap();
+        let project_toml = r#"
+package = "foo"
+version = "1.0.0"
+lua = ">=5.1"
+
+[description]
+license.workspace = true
+"#;
+        workspace_root
+            .child("members/foo")
+            .child(PROJECT_TOML)
+            .write_str(project_toml)
+            .unwrap();
+        workspace_root
+            .child("other/foo")
+            .child(PROJECT_TOML)
+            .write_str(project_toml)
+            .unwrap();
+
+        let member = Project::from_exact(workspace_root.child("members/foo"))
+            .unwrap()
+            .unwrap();
+        let local = member.toml().into_local().unwrap();
+        assert_eq!(local.description().license, Some("MIT".into()));
+
+        let non_member = Project::from_exact(workspace_root.child("other/foo"))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            non_member.toml().into_local(),
+            Err(LocalProjectTomlValidationError::MissingWorkspaceField(field)) if field == "description.license"
+        ));
+    }
+
+    #[tokio::test]
+    async fn inherit_missing_workspace_package_field() {
+        let workspace_root = assert_fs::TempDir::new().unwrap();
+        workspace_root
+            .child(WORKSPACE_TOML)
+            .write_str(
+                r#"
+[workspace]
+members = ["foo"]
+"#,
+            )
+            .unwrap();
+        workspace_root
+            .child("foo")
+            .child(PROJECT_TOML)
+            .write_str(
+                r#"
+package = "foo"
+version = "1.0.0"
+lua = ">=5.1"
+
+[description]
+license.workspace = true
+"#,
+            )
+            .unwrap();
+
+        let workspace = Workspace::from(&workspace_root).unwrap().unwrap();
+        let foo = workspace.select_member(&"foo".into()).unwrap();
+        assert!(matches!(
+            foo.toml().into_local(),
+            Err(LocalProjectTomlValidationError::MissingWorkspaceField(field)) if field == "description.license"
+        ));
+    }
+
+    #[tokio::test]
+    async fn select_members_matching_globs() {
+        let sample_workspace = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/test/sample-projects/multi-project/");
+        let workspace_root = assert_fs::TempDir::new().unwrap();
+        workspace_root
+            .copy_from(&sample_workspace, &["**"])
+            .unwrap();
+        let workspace = Workspace::from(&workspace_root).unwrap().unwrap();
+        let package_names = |projects: Vec<&Project>| {
+            projects
+                .into_iter()
+                .map(|project| project.toml().package().to_string())
+                .sorted()
+                .collect_vec()
+        };
+        assert_eq!(
+            package_names(workspace.select_members_matching(&["f*".into()]).unwrap()),
+            vec!["foo"]
+        );
+        assert_eq!(
+            package_names(
+                workspace
+                    .select_members_matching(&["foo".into(), "ba?".into()])
+                    .unwrap()
+            ),
+            vec!["bar", "foo"]
+        );
+        assert!(workspace
+            .select_members_matching(&["baz".into()])
+            .unwrap()
+            .is_empty());
+        assert!(workspace.select_members_matching(&["[".into()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn select_members_changed_since() {
+        let workspace_root = assert_fs::TempDir::new().unwrap();
+        workspace_root
+            .child(WORKSPACE_TOML)
+            .write_str(
+                r#"
+[workspace]
+members = ["foo", "bar", "baz"]
+"#,
+            )
+            .unwrap();
+        for (name, dependencies) in [("foo", ""), ("bar", "foo = \"1.0.0\""), ("baz", "")] {
+            let project_dir = workspace_root.child(name);
+            project_dir
+                .child(PROJECT_TOML)
+                .write_str(&format!(
+                    r#"
+package = "{name}"
+version = "1.0.0"
+lua = ">=5.1"
+
+[dependencies]
+{dependencies}
+"#
+                ))
+                .unwrap();
+            project_dir
+                .child("src")
+                .child("main.lua")
+                .write_str("return true")
+                .unwrap();
+        }
+        let repo = git2::Repository::init(&workspace_root).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("lux", "lux@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+
+        let workspace = Workspace::from(&workspace_root).unwrap().unwrap();
+        assert!(workspace
+            .select_members_changed_since("HEAD")
+            .unwrap()
+            .is_empty());
+
+        workspace_root
+            .child("foo")
+            .child("src")
+            .child("main.lua")
+            .write_str("return false")
+            .unwrap();
+        let changed = workspace
+            .select_members_changed_since("HEAD")
+            .unwrap()
+            .into_iter()
+            .map(|project| project.toml().package().to_string())
+            .sorted()
+            .collect_vec();
+        assert_eq!(changed, vec!["bar", "foo"]);
+    }
+
     #[tokio::test]
     async fn test_no_find_workspace_upwards() {
         let work_dir = assert_fs::TempDir::new().unwrap();
@@ -1,8 +1,16 @@
 use std::path::PathBuf;
 
 use serde::{de, Deserialize};
+use url::Url;
 
-use crate::project::{parse_toml, TomlDeError};
+use crate::{
+    package::PackageVersionReq,
+    project::{
+        gen::RockSourceTemplate, parse_toml, project_toml::parse_map_to_dependency_vec_opt,
+        TomlDeError,
+    },
+    rockspec::lua_dependency::LuaDependencySpec,
+};
 
 /// The `lux.toml` file for a workspace.
 /// Used to deserialize a workspace with multiple projects.
@@ -21,6 +29,32 @@ impl WorkspaceToml {
 #[derive(Clone, Debug, Deserialize)]
 pub(super) struct WorkspaceSpec {
     pub members: Vec<WorkspaceMemberSpec>,
+    #[serde(default)]
+    pub package: Option<WorkspacePackage>,
+    /// Forced replacements for (transitive) dependencies of all members,
+    /// declared in the `[workspace.overrides]` table.
+    #[serde(default, deserialize_with = "parse_map_to_dependency_vec_opt")]
+    pub overrides: Option<Vec<LuaDependencySpec>>,
+}
+
+/// Shared package metadata, declared in a workspace's `[workspace.package]` table.
+/// Members can inherit each field with `field.workspace = true`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub(crate) struct WorkspacePackage {
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub maintainer: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<Url>,
+    #[serde(default)]
+    pub issues_url: Option<Url>,
+    #[serde(default)]
+    pub labels: Option<Vec<String>>,
+    #[serde(default)]
+    pub lua: Option<PackageVersionReq>,
+    #[serde(default, rename = "source")]
+    pub source_template: Option<RockSourceTemplate>,
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -71,5 +105,56 @@ mod tests {
                 WorkspaceMemberSpec::RelativeProjectGlob("projects/baz/*".into()),
             ]
         );
+        assert!(workspace_toml.workspace.package.is_none());
+        assert!(workspace_toml.workspace.overrides.is_none());
+    }
+
+    #[tokio::test]
+    async fn parse_workspace_toml_with_overrides() {
+        let toml_content = r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.overrides]
+            luasocket = "3.1.0"
+            lpeg = { version = "1.1.0", git = "https://github.com/roberto-ieru/lpeg", rev = "fix" }
+        "#;
+        let workspace_toml = WorkspaceToml::new("lux.toml", toml_content).unwrap();
+        let overrides = workspace_toml.workspace.overrides.unwrap();
+        assert_eq!(overrides.len(), 2);
+        let lpeg = overrides
+            .iter()
+            .find(|dep| dep.name().to_string() == "lpeg")
+            .unwrap();
+        assert!(lpeg.source().is_some());
+    }
+
+    #[tokio::test]
+    async fn parse_workspace_toml_with_package() {
+        let toml_content = r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.package]
+            license = "MIT"
+            maintainer = "lumen-labs"
+            homepage = "https://github.com/lumen-oss/lux"
+            labels = ["lua", "neovim"]
+            lua = ">=5.1"
+
+            [workspace.package.source]
+            url = "https://github.com/lumen-oss/lux/archive/refs/tags/$(PACKAGE)-$(VERSION).tar.gz"
+        "#;
+        let workspace_toml = WorkspaceToml::new("lux.toml", toml_content).unwrap();
+        let package = workspace_toml.workspace.package.unwrap();
+        assert_eq!(package.license, Some("MIT".into()));
+        assert_eq!(package.maintainer, Some("lumen-labs".into()));
+        assert_eq!(
+            package.labels,
+            Some(vec!["lua".to_string(), "neovim".to_string()])
+        );
+        assert!(package.lua.is_some());
+        assert!(package.issues_url.is_none());
+        assert!(package.source_template.is_some());
     }
 }
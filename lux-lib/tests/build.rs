@@ -99,6 +99,48 @@ async fn make_build() {
         .unwrap();
 }
 
+#[cfg(not(target_env = "msvc"))]
+#[flaky_test(tokio, times = 5)]
+async fn autotools_build() {
+    let dir = TempDir::new().unwrap();
+
+    let content = String::from_utf8(
+        std::fs::read(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("resources/test/autotools-project/autotools-project-scm-1.rockspec"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let rockspec = RemoteLuaRockspec::new(&content).unwrap();
+
+    let lua_version = detect_installed_lua_version().or(Some(LuaVersion::Lua51));
+
+    let config = ConfigBuilder::new()
+        .unwrap()
+        .user_tree(Some(dir.to_path_buf()))
+        .lua_version(lua_version)
+        .build()
+        .unwrap();
+
+    let lua = LuaInstallation::new_from_config(&config).await.unwrap();
+
+    let tree = config.user_tree(lua.version.clone()).unwrap();
+
+    let package = Build::new()
+        .rockspec(&rockspec)
+        .lua(&lua)
+        .tree(&tree)
+        .entry_type(tree::EntryType::Entrypoint)
+        .config(&config)
+        .behaviour(Force)
+        .build()
+        .await
+        .unwrap();
+    let layout = tree.installed_rock_layout(&package).unwrap();
+    assert!(layout.etc.join("autotools-project.txt").is_file());
+}
+
 #[flaky_test(tokio, times = 5)]
 async fn cmake_build() {
     let rockspec =
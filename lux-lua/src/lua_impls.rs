@@ -25,12 +25,12 @@ use lux_lib::{
         OptState, PinnedState, ReadOnly, ReadWrite,
     },
     lua_rockspec::{
-        BuildBackendSpec, BuildSpec, BuiltinBuildSpec, BustedTestSpec, CMakeBuildSpec,
-        CommandBuildSpec, CommandTestSpec, ExternalDependencySpec, InstallSpec, LocalLuaRockspec,
-        LuaModule, LuaScriptTestSpec, MakeBuildSpec, ModulePaths, ModuleSpec, PartialLuaRockspec,
-        PartialOverride, PerPlatform, PlatformIdentifier, PlatformOverridable, PlatformSupport,
-        RemoteLuaRockspec, RemoteRockSource, RockDescription, RockSourceSpec, RockspecFormat,
-        RustMluaBuildSpec, TestSpec, TreesitterParserBuildSpec,
+        AutotoolsBuildSpec, BuildBackendSpec, BuildSpec, BuiltinBuildSpec, BustedTestSpec,
+        CMakeBuildSpec, CommandBuildSpec, CommandTestSpec, ExternalDependencySpec, InstallSpec,
+        LocalLuaRockspec, LuaModule, LuaScriptTestSpec, MakeBuildSpec, MesonBuildSpec, ModulePaths,
+        ModuleSpec, PartialLuaRockspec, PartialOverride, PerPlatform, PlatformIdentifier,
+        PlatformOverridable, PlatformSupport, RemoteLuaRockspec, RemoteRockSource, RockDescription,
+        RockSourceSpec, RockspecFormat, RustMluaBuildSpec, TestSpec, TreesitterParserBuildSpec,
     },
     lua_version::LuaVersion,
     operations::{DownloadedRockspec, PackageInstallSpec, SyncReport},
@@ -683,6 +683,8 @@ impl IntoLua for BuildBackendSpecLua {
             BuildBackendSpec::Builtin(spec) => BuiltinBuildSpecLua(spec).into_lua(lua),
             BuildBackendSpec::Make(spec) => MakeBuildSpecLua(spec).into_lua(lua),
             BuildBackendSpec::CMake(spec) => CMakeBuildSpecLua(spec).into_lua(lua),
+            BuildBackendSpec::Meson(spec) => MesonBuildSpecLua(spec).into_lua(lua),
+            BuildBackendSpec::Autotools(spec) => AutotoolsBuildSpecLua(spec).into_lua(lua),
             BuildBackendSpec::Command(spec) => CommandBuildSpecLua(spec).into_lua(lua),
             BuildBackendSpec::LuaRock(s) => s.into_lua(lua),
             BuildBackendSpec::RustMlua(spec) => RustMluaBuildSpecLua(spec).into_lua(lua),
@@ -1425,6 +1427,19 @@ Does not affect existing install trees or dependency rock layouts."#,
             Ok(ConfigBuilderLua(this.0.clone().generate_luarc(generate)))
         });
 
+        methods.document(
+            "Whether to generate a `compile_commands.json` when building a project with C sources.",
+        );
+        methods.param("generate", "Default: true");
+        methods.add_method(
+            "generate_compile_commands",
+            |_, this, generate: Option<bool>| {
+                Ok(ConfigBuilderLua(
+                    this.0.clone().generate_compile_commands(generate),
+                ))
+            },
+        );
+
         methods.document(
             r#"Whether to wrap installed Lua bin scripts to be executed with
 the detected or configured Lua installation.
@@ -1910,6 +1925,84 @@ impl mlua::UserData for CMakeBuildSpecLua {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct AutotoolsBuildSpecLua(pub AutotoolsBuildSpec);
+
+impl Typed for AutotoolsBuildSpecLua {
+    fn ty() -> Type {
+        Type::named("AutotoolsBuildSpec")
+    }
+}
+
+impl TypedUserData for AutotoolsBuildSpecLua {
+    fn add_methods<M: TypedDataMethods<Self>>(methods: &mut M) {
+        methods.document("Extra arguments to pass to the `configure` script");
+        methods.add_method("configure_args", |_, this, ()| {
+            Ok(this.0.configure_args.clone())
+        });
+
+        methods.document("Whether to configure and build in a separate build directory");
+        methods.add_method("out_of_tree", |_, this, ()| Ok(this.0.out_of_tree));
+
+        methods.document("Whether to perform a build pass");
+        methods.add_method("build_pass", |_, this, ()| Ok(this.0.build_pass));
+
+        methods.document("Whether to perform an install pass");
+        methods.add_method("install_pass", |_, this, ()| Ok(this.0.install_pass));
+    }
+    fn add_documentation<F: mlua_extras::typed::TypedDataDocumentation<Self>>(docs: &mut F) {
+        docs.add("Specification for building a rock with the `autotools` build backend");
+    }
+}
+
+impl mlua::UserData for AutotoolsBuildSpecLua {
+    fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        let mut wrapper = mlua_extras::typed::WrappedBuilder::new(fields);
+        <Self as TypedUserData>::add_fields(&mut wrapper);
+    }
+
+    fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
+        let mut wrapper = mlua_extras::typed::WrappedBuilder::new(methods);
+        <Self as TypedUserData>::add_methods(&mut wrapper);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MesonBuildSpecLua(pub MesonBuildSpec);
+
+impl Typed for MesonBuildSpecLua {
+    fn ty() -> Type {
+        Type::named("MesonBuildSpec")
+    }
+}
+
+impl TypedUserData for MesonBuildSpecLua {
+    fn add_methods<M: TypedDataMethods<Self>>(methods: &mut M) {
+        methods.document("Whether to perform a build pass");
+        methods.add_method("build_pass", |_, this, ()| Ok(this.0.build_pass));
+
+        methods.document("Whether to perform an install pass");
+        methods.add_method("install_pass", |_, this, ()| Ok(this.0.install_pass));
+
+        methods.add_method("variables", |_, this, ()| Ok(this.0.variables.clone()));
+    }
+    fn add_documentation<F: mlua_extras::typed::TypedDataDocumentation<Self>>(docs: &mut F) {
+        docs.add("Specification for building a rock with the `meson` build backend");
+    }
+}
+
+impl mlua::UserData for MesonBuildSpecLua {
+    fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        let mut wrapper = mlua_extras::typed::WrappedBuilder::new(fields);
+        <Self as TypedUserData>::add_fields(&mut wrapper);
+    }
+
+    fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
+        let mut wrapper = mlua_extras::typed::WrappedBuilder::new(methods);
+        <Self as TypedUserData>::add_methods(&mut wrapper);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MakeBuildSpecLua(pub MakeBuildSpec);
 
@@ -3200,15 +3293,16 @@ mod definitions_registry {
     use mlua_extras::typed::{Type, TypedClassBuilder};
 
     use super::{
-        BuildSpecLua, BustedTestSpecLua, CMakeBuildSpecLua, CommandBuildSpecLua,
-        CommandTestSpecLua, ConfigBuilderLua, ConfigLua, DownloadedRockspecLua, GitSourceLua,
-        InstallSpecLua, LocalLuaRockspecLua, LocalPackageHashesLua, LocalPackageLua,
-        LocalProjectTomlLua, LockfileGuardLua, LockfileReadOnlyLua, LockfileReadWriteLua,
-        LuaDependencySpecLua, LuaScriptTestSpecLua, MakeBuildSpecLua, ModulePathsLua,
-        PackageReqLua, PackageSpecLua, PartialLuaRockspecLua, PartialProjectTomlLua,
-        PlatformSupportLua, ProjectLua, RemoteLuaRockspecLua, RemotePackageDBLua,
-        RemoteProjectTomlLua, RemoteRockSourceLua, RockDescriptionLua, RockLayoutConfigLua,
-        RockLayoutLua, RustMluaBuildSpecLua, TreeLua, TreesitterParserBuildSpecLua, WorkspaceLua,
+        AutotoolsBuildSpecLua, BuildSpecLua, BustedTestSpecLua, CMakeBuildSpecLua,
+        CommandBuildSpecLua, CommandTestSpecLua, ConfigBuilderLua, ConfigLua,
+        DownloadedRockspecLua, GitSourceLua, InstallSpecLua, LocalLuaRockspecLua,
+        LocalPackageHashesLua, LocalPackageLua, LocalProjectTomlLua, LockfileGuardLua,
+        LockfileReadOnlyLua, LockfileReadWriteLua, LuaDependencySpecLua, LuaScriptTestSpecLua,
+        MakeBuildSpecLua, MesonBuildSpecLua, ModulePathsLua, PackageReqLua, PackageSpecLua,
+        PartialLuaRockspecLua, PartialProjectTomlLua, PlatformSupportLua, ProjectLua,
+        RemoteLuaRockspecLua, RemotePackageDBLua, RemoteProjectTomlLua, RemoteRockSourceLua,
+        RockDescriptionLua, RockLayoutConfigLua, RockLayoutLua, RustMluaBuildSpecLua, TreeLua,
+        TreesitterParserBuildSpecLua, WorkspaceLua,
     };
     use crate::definitions::LuxDefinition;
 
@@ -3246,6 +3340,8 @@ mod definitions_registry {
         "ModulePaths" => ModulePathsLua,
         "CMakeBuildSpec" => CMakeBuildSpecLua,
         "MakeBuildSpec" => MakeBuildSpecLua,
+        "MesonBuildSpec" => MesonBuildSpecLua,
+        "AutotoolsBuildSpec" => AutotoolsBuildSpecLua,
         "TreesitterParserBuildSpec" => TreesitterParserBuildSpecLua,
         "RustMluaBuildSpec" => RustMluaBuildSpecLua,
         "CommandBuildSpec" => CommandBuildSpecLua,